@@ -7,7 +7,8 @@ use super::{
     DebugError, DebugRegisters, StackFrame, VariableCache,
 };
 use crate::{
-    registers, stack_frame::StackFrameInfo, unit_info::RangeExt, SourceLocation, VerifiedBreakpoint,
+    extract_byte_size, registers, stack_frame::StackFrameInfo, unit_info::RangeExt, SourceLocation,
+    VerifiedBreakpoint,
 };
 use gimli::{
     BaseAddresses, DebugFrame, DebugInfoOffset, RunTimeEndian, UnwindContext, UnwindSection,
@@ -952,6 +953,102 @@ impl DebugInfo {
         }
     }
 
+    /// Returns the size of the static variable located at `address`, as described by the DWARF
+    /// type of the variable.
+    ///
+    /// Returns `None` if no variable is located at `address`, or the size of its type is unknown.
+    pub fn static_variable_size(&self, address: u64) -> Option<u64> {
+        for unit_info in &self.unit_infos {
+            let mut entries = unit_info.unit.entries();
+            while let Ok(Some((_, entry))) = entries.next_dfs() {
+                if entry.tag() != gimli::DW_TAG_variable
+                    || self.static_variable_address(unit_info, entry) != Some(address)
+                {
+                    continue;
+                }
+
+                if let Some(size) = self.type_size(unit_info, entry) {
+                    return Some(size);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the address of a variable whose location is a plain address.
+    fn static_variable_address(&self, unit_info: &UnitInfo, variable: &Die) -> Option<u64> {
+        let gimli::AttributeValue::Exprloc(expression) =
+            variable.attr_value(gimli::DW_AT_location).ok()??
+        else {
+            return None;
+        };
+
+        let mut operations = expression.operations(unit_info.unit.encoding());
+        let address = match operations.next().ok()?? {
+            gimli::Operation::Address { address } => address,
+            gimli::Operation::AddressIndex { index } => {
+                self.dwarf.address(&unit_info.unit, index).ok()?
+            }
+            _ => return None,
+        };
+
+        // Expressions which compute the location are not static.
+        operations.next().ok()?.is_none().then_some(address)
+    }
+
+    /// Returns the size of the type of `die`, following typedefs, qualifiers and arrays.
+    fn type_size(&self, unit_info: &UnitInfo, die: &Die) -> Option<u64> {
+        if die.tag() != gimli::DW_TAG_variable {
+            if let Some(size) = extract_byte_size(die) {
+                return Some(size);
+            }
+        }
+
+        let inner = self.resolve_die_reference(gimli::DW_AT_type, die, unit_info)?;
+        match die.tag() {
+            gimli::DW_TAG_variable
+            | gimli::DW_TAG_typedef
+            | gimli::DW_TAG_const_type
+            | gimli::DW_TAG_volatile_type
+            | gimli::DW_TAG_atomic_type => self.type_size(unit_info, &inner),
+            gimli::DW_TAG_array_type => {
+                let element_size = self.type_size(unit_info, &inner)?;
+
+                let mut tree = unit_info.unit.entries_tree(Some(die.offset())).ok()?;
+                let root = tree.root().ok()?;
+                let mut children = root.children();
+                let mut count = 1;
+                while let Ok(Some(child)) = children.next() {
+                    let subrange = child.entry();
+                    if subrange.tag() != gimli::DW_TAG_subrange_type {
+                        continue;
+                    }
+
+                    let udata = |attribute| {
+                        subrange
+                            .attr_value(attribute)
+                            .ok()
+                            .flatten()
+                            .and_then(|value| value.udata_value())
+                    };
+                    let length = match udata(gimli::DW_AT_count) {
+                        Some(count) => count,
+                        // The DWARF upper bound is inclusive.
+                        None => {
+                            udata(gimli::DW_AT_upper_bound)? + 1
+                                - udata(gimli::DW_AT_lower_bound).unwrap_or(0)
+                        }
+                    };
+                    count *= length;
+                }
+
+                Some(element_size * count)
+            }
+            _ => None,
+        }
+    }
+
     /// The program binary's (and core's) endianness.
     pub fn endianness(&self) -> RunTimeEndian {
         self.endianness
@@ -1427,6 +1524,17 @@ mod test {
             .unwrap_or_else(|err| panic!("Failed to open file {}: {:?}", path.display(), err))
     }
 
+    #[test]
+    fn static_variable_sizes() {
+        let debug_info = load_test_elf_as_debug_info("debug-unwind-tests/RP2040_full_unwind.elf");
+
+        // BOOT2_FIRMWARE: [u8; 256]
+        assert_eq!(debug_info.static_variable_size(0x1000_0000), Some(256));
+        // _SEGGER_RTT
+        assert_eq!(debug_info.static_variable_size(0x2000_007c), Some(0x48));
+        assert_eq!(debug_info.static_variable_size(0x1000_0001), None);
+    }
+
     #[test]
     fn unwinding_first_instruction_after_exception() {
         let debug_info = load_test_elf_as_debug_info("exceptions");
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use super::memory::MemoryRegion;
-use crate::{serialize::hex_option, CoreType};
+use crate::{serialize::hex_option, ConfigurationRegion, CoreType, SequenceDescription};
 use serde::{Deserialize, Serialize};
 
 /// Represents a DAP scan chain element.
@@ -101,6 +101,19 @@ pub struct Chip {
     // TODO: rename to default_platform
     #[serde(default)]
     pub default_binary_format: Option<String>,
+    /// Debug access sequences of the chip, usually imported from a CMSIS-Pack.
+    ///
+    /// These replace the built-in ARM debug sequences with the same name, unless probe-rs
+    /// already has a hand-written sequence for the chip.
+    #[serde(default)]
+    pub debug_sequences: Vec<SequenceDescription>,
+    /// Declarations of the global variables used by the debug sequences, written in the
+    /// sequence language, as described by the `<debugvars>` element of a CMSIS-Pack.
+    #[serde(default)]
+    pub debug_vars: Option<String>,
+    /// Vendor specific configuration memory of the chip, like option bytes or the UICR.
+    #[serde(default)]
+    pub configuration_regions: Vec<ConfigurationRegion>,
 }
 
 impl Chip {
@@ -124,6 +137,9 @@ impl Chip {
             rtt_scan_ranges: None,
             jtag: None,
             default_binary_format: None,
+            debug_sequences: vec![],
+            debug_vars: None,
+            configuration_regions: vec![],
         }
     }
 
@@ -204,6 +220,18 @@ pub struct ArmCoreAccessOptions {
 
     /// The JTAG TAP index of the core's debug module
     pub jtag_tap: Option<usize>,
+
+    /// The debug port ID of the core, as given by `__dp` in a CMSIS-Pack.
+    ///
+    /// This is the value of `__dp` when running debug sequences for the core.
+    #[serde(default)]
+    pub dp_id: Option<u8>,
+    /// The sequence used to reset the core, e.g. `ResetProcessor`, as given by the
+    /// `defaultResetSequence` of a CMSIS-Pack.
+    ///
+    /// This is run instead of `ResetSystem` if it is one of the chip's debug sequences.
+    #[serde(default)]
+    pub default_reset_sequence: Option<String>,
 }
 
 /// The data required to access a Risc-V core
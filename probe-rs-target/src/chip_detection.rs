@@ -1,10 +1,11 @@
 //! Chip detection information.
 
 use indexmap::IndexMap;
+use jep106::JEP106Code;
 use serde::{Deserialize, Serialize};
 use serde_with::rust::maps_duplicate_key_is_error;
 
-use crate::serialize::{hex_keys_indexmap, hex_u_int};
+use crate::serialize::{hex_jep106_option, hex_keys_indexmap, hex_option, hex_u_int};
 
 /// Vendor-specific chip detection information.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +25,9 @@ pub enum ChipDetectionMethod {
 
     /// Infineon SCU chip detection information.
     InfineonScu(InfinionScuDetection),
+
+    /// Vendor independent chip detection by matching register values.
+    RegisterMatch(RegisterMatchDetection),
 }
 
 impl ChipDetectionMethod {
@@ -71,6 +75,15 @@ impl ChipDetectionMethod {
             None
         }
     }
+
+    /// Returns the register match detection information if available.
+    pub fn as_register_match(&self) -> Option<&RegisterMatchDetection> {
+        if let Self::RegisterMatch(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
 }
 
 /// Microchip ATSAM chip detection information when the device contains a DSU.
@@ -159,3 +172,115 @@ pub struct InfinionScuDetection {
     #[serde(deserialize_with = "maps_duplicate_key_is_error::deserialize")]
     pub variants: IndexMap<u32, String>,
 }
+
+/// Chip detection by reading ARM debug registers and comparing them to known values.
+///
+/// A target is detected if all `registers` and all registers of the variant match.
+/// Variants are tried in order, the first matching one is used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisterMatchDetection {
+    /// The JEP106 code of the manufacturer in the ROM table. If set, only chips of this
+    /// manufacturer are considered, which avoids reading the registers on unrelated chips.
+    #[serde(default, serialize_with = "hex_jep106_option")]
+    pub manufacturer: Option<JEP106Code>,
+
+    /// The TARGETSEL value of the debug port, for targets that use SWD multi-drop.
+    #[serde(default, serialize_with = "hex_option")]
+    pub targetsel: Option<u32>,
+
+    /// The index of the MEM-AP used for memory reads.
+    #[serde(default)]
+    pub ap: u8,
+
+    /// The registers that have to match for any of the variants, e.g. a device ID.
+    #[serde(default)]
+    pub registers: Vec<RegisterMatch>,
+
+    /// The variants, with the registers that identify them.
+    pub variants: Vec<VariantMatch>,
+}
+
+/// A target variant identified by register values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VariantMatch {
+    /// The target name.
+    pub name: String,
+
+    /// The registers that have to match, e.g. the flash size.
+    #[serde(default)]
+    pub registers: Vec<RegisterMatch>,
+}
+
+/// A register value to compare against.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisterMatch {
+    /// Where the register is read from.
+    #[serde(default)]
+    pub source: RegisterSource,
+
+    /// The memory address, or the address of the access port register.
+    #[serde(serialize_with = "hex_u_int")]
+    pub address: u64,
+
+    /// The width of the register in bits, 8, 16 or 32. Access port registers are always 32 bits wide.
+    #[serde(default = "default_register_width")]
+    pub width: u8,
+
+    /// The bits of the register that are compared.
+    #[serde(default = "default_register_mask", serialize_with = "hex_u_int")]
+    pub mask: u32,
+
+    /// The expected value of the masked register.
+    #[serde(serialize_with = "hex_u_int")]
+    pub value: u32,
+}
+
+impl RegisterMatch {
+    /// Returns whether the register value `read` matches.
+    pub fn matches(&self, read: u32) -> bool {
+        read & self.mask == self.value & self.mask
+    }
+}
+
+fn default_register_width() -> u8 {
+    32
+}
+
+fn default_register_mask() -> u32 {
+    u32::MAX
+}
+
+/// The source of a [`RegisterMatch`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterSource {
+    /// The target memory, read through the MEM-AP of the detection method.
+    #[default]
+    Memory,
+
+    /// A register of the MEM-AP of the detection method.
+    AccessPort,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn register_match_compares_masked_bits() {
+        let register = RegisterMatch {
+            source: RegisterSource::Memory,
+            address: 0xE004_2000,
+            width: 32,
+            mask: 0xFFF,
+            value: 0x431,
+        };
+
+        assert!(register.matches(0x431));
+        assert!(register.matches(0x1000_6431));
+        assert!(!register.matches(0x433));
+    }
+}
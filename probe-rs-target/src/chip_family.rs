@@ -1,6 +1,9 @@
 use crate::memory::RegionMergeIterator as _;
 use crate::serialize::hex_jep106_option;
-use crate::{chip_detection::ChipDetectionMethod, CoreAccessOptions};
+use crate::{
+    chip_detection::{ChipDetectionMethod, RegisterSource},
+    CoreAccessOptions,
+};
 use crate::{MemoryRange, MemoryRegion};
 
 use super::chip::Chip;
@@ -224,6 +227,8 @@ impl ChipFamily {
         self.reject_incorrect_core_access_options()?;
         self.validate_memory_regions()?;
         self.validate_rtt_scan_regions()?;
+        self.validate_register_match_detection()?;
+        self.validate_configuration_regions()?;
 
         Ok(())
     }
@@ -402,6 +407,99 @@ impl ChipFamily {
 
         Ok(())
     }
+
+    /// Makes sure the register match detection only names variants of this family, and only
+    /// reads registers of a supported width.
+    fn validate_register_match_detection(&self) -> Result<(), String> {
+        let detections = self
+            .chip_detection
+            .iter()
+            .filter_map(ChipDetectionMethod::as_register_match);
+
+        for detection in detections {
+            for variant in &detection.variants {
+                if !self.variants.iter().any(|chip| chip.name == variant.name) {
+                    return Err(format!(
+                        "chip detection refers to unknown variant `{}`",
+                        variant.name
+                    ));
+                }
+
+                let registers = detection.registers.iter().chain(&variant.registers);
+                for register in registers {
+                    let valid = match register.source {
+                        RegisterSource::Memory => [8, 16, 32].contains(&register.width),
+                        RegisterSource::AccessPort => register.width == 32,
+                    };
+                    if !valid {
+                        return Err(format!(
+                            "chip detection of `{}` reads the {:?} register at {:#x} with an unsupported width of {} bits",
+                            variant.name, register.source, register.address, register.width
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that the fields of configuration regions have a valid width, lie within their
+    /// region and have unique names.
+    fn validate_configuration_regions(&self) -> Result<(), String> {
+        use std::collections::HashSet;
+
+        for variant in &self.variants {
+            let mut region_names = HashSet::new();
+            for region in &variant.configuration_regions {
+                if !region_names.insert(region.name.to_ascii_lowercase()) {
+                    return Err(format!(
+                        "Variant {} has multiple configuration regions named {}",
+                        variant.name, region.name
+                    ));
+                }
+
+                let mut field_names = HashSet::new();
+                for field in &region.fields {
+                    if !field_names.insert(field.name.to_ascii_lowercase()) {
+                        return Err(format!(
+                            "Configuration region {} of {} has multiple fields named {}",
+                            region.name, variant.name, field.name
+                        ));
+                    }
+
+                    if !(1..=64).contains(&field.bit_width) || field.bit_offset >= 8 {
+                        return Err(format!(
+                            "Field {} of configuration region {} of {} must have a width of 1 to 64 bits and a bit offset below 8",
+                            field.name, region.name, variant.name
+                        ));
+                    }
+
+                    if field.offset + field.byte_len() as u64
+                        > region.range.end.saturating_sub(region.range.start)
+                    {
+                        return Err(format!(
+                            "Field {} of configuration region {} of {} does not fit into the region",
+                            field.name, region.name, variant.name
+                        ));
+                    }
+
+                    if let Some(value) = field
+                        .values
+                        .iter()
+                        .find(|value| value.value & !field.mask() != 0)
+                    {
+                        return Err(format!(
+                            "Value {} of field {} of configuration region {} of {} does not fit into the field",
+                            value.name, field.name, region.name, variant.name
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl ChipFamily {
@@ -450,3 +548,70 @@ impl ChipFamily {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chip_detection::{RegisterMatch, RegisterMatchDetection, VariantMatch};
+
+    fn register(source: RegisterSource, width: u8) -> RegisterMatch {
+        RegisterMatch {
+            source,
+            address: 0xE004_2000,
+            width,
+            mask: 0xFFF,
+            value: 0x431,
+        }
+    }
+
+    fn family(variant: &str, registers: Vec<RegisterMatch>) -> ChipFamily {
+        ChipFamily {
+            name: "Test".to_owned(),
+            manufacturer: None,
+            chip_detection: vec![ChipDetectionMethod::RegisterMatch(RegisterMatchDetection {
+                manufacturer: None,
+                targetsel: None,
+                ap: 0,
+                registers: vec![],
+                variants: vec![VariantMatch {
+                    name: variant.to_owned(),
+                    registers,
+                }],
+            })],
+            generated_from_pack: false,
+            pack_file_release: None,
+            variants: vec![Chip::generic_arm("Chip", CoreType::Armv7em)],
+            flash_algorithms: vec![],
+            source: TargetDescriptionSource::Generic,
+        }
+    }
+
+    #[test]
+    fn register_match_detection_is_valid() {
+        let family = family(
+            "Chip",
+            vec![
+                register(RegisterSource::Memory, 16),
+                register(RegisterSource::AccessPort, 32),
+            ],
+        );
+
+        assert_eq!(family.validate_register_match_detection(), Ok(()));
+    }
+
+    #[test]
+    fn register_match_detection_of_unknown_variant() {
+        let family = family("Other", vec![register(RegisterSource::Memory, 32)]);
+
+        assert!(family.validate_register_match_detection().is_err());
+    }
+
+    #[test]
+    fn register_match_detection_with_invalid_width() {
+        let memory = family("Chip", vec![register(RegisterSource::Memory, 24)]);
+        let access_port = family("Chip", vec![register(RegisterSource::AccessPort, 16)]);
+
+        assert!(memory.validate_register_match_detection().is_err());
+        assert!(access_port.validate_register_match_detection().is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::serialize::{hex_range, hex_u_int};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// A region of vendor specific configuration memory.
+///
+/// Examples are the option bytes of STM32 devices, the UICR of nRF devices, the user row of
+/// SAM devices or OTP memory. The region is read like normal memory and written using the
+/// flash algorithm covering its address range.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigurationRegion {
+    /// The name of the region, e.g. `UICR`.
+    pub name: String,
+    /// A short description of the region.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Address range of the region.
+    #[serde(serialize_with = "hex_range")]
+    pub range: Range<u64>,
+    /// The named fields of the region.
+    #[serde(default)]
+    pub fields: Vec<ConfigurationField>,
+}
+
+impl ConfigurationRegion {
+    /// Returns the field with the given name, ignoring case.
+    pub fn field(&self, name: &str) -> Option<&ConfigurationField> {
+        self.fields
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A named field of a [`ConfigurationRegion`].
+///
+/// The field consists of `bit_width` bits, starting at bit `bit_offset` of the little endian
+/// value stored at `offset` bytes from the start of the region.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigurationField {
+    /// The name of the field, e.g. `RDP`.
+    pub name: String,
+    /// A short description of the field.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Offset of the field in bytes, relative to the start of the region.
+    #[serde(serialize_with = "hex_u_int")]
+    pub offset: u64,
+    /// Offset of the least significant bit of the field.
+    #[serde(default)]
+    pub bit_offset: u8,
+    /// Width of the field in bits, at most 64.
+    pub bit_width: u8,
+    /// Writing any value to this field can not be undone, e.g. because it blows a fuse.
+    #[serde(default)]
+    pub irreversible: bool,
+    /// Named values of the field.
+    #[serde(default)]
+    pub values: Vec<ConfigurationValue>,
+}
+
+impl ConfigurationField {
+    /// Returns the number of bytes, starting at `offset`, which contain the field.
+    pub fn byte_len(&self) -> usize {
+        (self.bit_offset as usize + self.bit_width as usize).div_ceil(8)
+    }
+
+    /// Returns the mask of valid field values.
+    pub fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.bit_width.clamp(1, 64))
+    }
+
+    /// Extracts the value of the field from the contents of its region.
+    ///
+    /// Returns `None` if `region` is too short to contain the field.
+    pub fn extract(&self, region: &[u8]) -> Option<u64> {
+        let bytes = region.get(self.offset as usize..)?.get(..self.byte_len())?;
+        let raw = bytes
+            .iter()
+            .rev()
+            .fold(0u128, |acc, byte| (acc << 8) | *byte as u128);
+
+        Some((raw >> self.bit_offset) as u64 & self.mask())
+    }
+
+    /// Replaces the value of the field in the contents of its region.
+    ///
+    /// Returns `None` if `region` is too short to contain the field, or `value` does not fit
+    /// into the field.
+    pub fn insert(&self, region: &mut [u8], value: u64) -> Option<()> {
+        if value & !self.mask() != 0 {
+            return None;
+        }
+
+        let bytes = region
+            .get_mut(self.offset as usize..)?
+            .get_mut(..self.byte_len())?;
+        let mask = (self.mask() as u128) << self.bit_offset;
+        let value = (value as u128) << self.bit_offset;
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            let shift = index * 8;
+            let byte_mask = (mask >> shift) as u8;
+            *byte = (*byte & !byte_mask) | ((value >> shift) as u8 & byte_mask);
+        }
+
+        Some(())
+    }
+
+    /// Returns the named value with the given name, ignoring case.
+    pub fn value_by_name(&self, name: &str) -> Option<&ConfigurationValue> {
+        self.values
+            .iter()
+            .find(|value| value.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the named value matching `value`, if there is one.
+    pub fn value_by_raw(&self, value: u64) -> Option<&ConfigurationValue> {
+        self.values.iter().find(|named| named.value == value)
+    }
+
+    /// Returns whether writing `value` to this field can not be undone.
+    pub fn is_irreversible(&self, value: u64) -> bool {
+        self.irreversible
+            || self
+                .value_by_raw(value)
+                .is_some_and(|named| named.irreversible)
+    }
+}
+
+/// A named value of a [`ConfigurationField`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigurationValue {
+    /// The name of the value, e.g. `Level2`.
+    pub name: String,
+    /// A short description of the value.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The raw value of the field.
+    #[serde(serialize_with = "hex_u_int")]
+    pub value: u64,
+    /// Writing this value can not be undone, e.g. a permanent read protection level.
+    #[serde(default)]
+    pub irreversible: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn field(offset: u64, bit_offset: u8, bit_width: u8) -> ConfigurationField {
+        ConfigurationField {
+            name: "FIELD".to_string(),
+            description: None,
+            offset,
+            bit_offset,
+            bit_width,
+            irreversible: false,
+            values: vec![],
+        }
+    }
+
+    #[test]
+    fn extract_and_insert_field() {
+        let mut region = [0xff; 8];
+
+        // A field crossing a byte boundary.
+        let field = field(1, 6, 4);
+        assert_eq!(field.byte_len(), 2);
+        assert_eq!(field.extract(&region), Some(0xf));
+
+        field.insert(&mut region, 0b1010).unwrap();
+        assert_eq!(
+            region,
+            [0xff, 0b1011_1111, 0b1111_1110, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(field.extract(&region), Some(0b1010));
+
+        assert_eq!(field.insert(&mut region, 0x10), None);
+    }
+
+    #[test]
+    fn full_width_field() {
+        let mut region = [0; 8];
+        let field = field(0, 0, 64);
+
+        field.insert(&mut region, 0x0123_4567_89ab_cdef).unwrap();
+        assert_eq!(region, 0x0123_4567_89ab_cdef_u64.to_le_bytes());
+        assert_eq!(field.extract(&region), Some(0x0123_4567_89ab_cdef));
+        assert_eq!(field.extract(&region[..7]), None);
+    }
+}
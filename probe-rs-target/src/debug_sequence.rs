@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// A debug access sequence, as described by a `<sequence>` element of a CMSIS-Pack.
+///
+/// Sequences overwrite the debugger's built-in behavior for the sequence with the same name,
+/// e.g. `ResetSystem` or `DebugPortStart`.
+///
+/// ref: `<https://open-cmsis-pack.github.io/Open-CMSIS-Pack-Spec/main/html/pdsc_family_pg.html#element_sequence>`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SequenceDescription {
+    /// The name of the sequence, e.g. `ResetSystem`.
+    pub name: String,
+    /// The name of the core this sequence applies to. If not set, the sequence applies to all cores.
+    #[serde(default)]
+    pub pname: Option<String>,
+    /// Disables the sequence, which then does nothing instead of running the built-in behavior.
+    #[serde(default)]
+    pub disable: bool,
+    /// A description of the sequence.
+    #[serde(default)]
+    pub info: Option<String>,
+    /// The blocks and control elements of the sequence, in execution order.
+    #[serde(default)]
+    pub elements: Vec<SequenceElement>,
+}
+
+/// A part of a debug access sequence.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SequenceElement {
+    /// A block of statements.
+    #[serde(rename = "block")]
+    Block(SequenceBlock),
+    /// A conditional or a loop around further elements.
+    #[serde(rename = "control")]
+    Control(SequenceControl),
+}
+
+/// A block of statements in a debug access sequence, as described by a `<block>` element.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SequenceBlock {
+    /// Whether the debug accesses of the block must be executed without interruption.
+    #[serde(default)]
+    pub atomic: bool,
+    /// A description of the block.
+    #[serde(default)]
+    pub info: Option<String>,
+    /// The statements of the block.
+    pub code: String,
+}
+
+/// A conditional or a loop in a debug access sequence, as described by a `<control>` element.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SequenceControl {
+    /// The condition for executing the elements at all.
+    #[serde(default, rename = "if")]
+    pub if_expr: Option<String>,
+    /// The condition for repeating the elements.
+    #[serde(default, rename = "while")]
+    pub while_expr: Option<String>,
+    /// The time in microseconds after which a `while` loop is ended. `0` means no timeout.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// A description of the control element.
+    #[serde(default)]
+    pub info: Option<String>,
+    /// The nested blocks and control elements.
+    #[serde(default)]
+    pub elements: Vec<SequenceElement>,
+}
@@ -13,6 +13,8 @@
 mod chip;
 pub mod chip_detection;
 mod chip_family;
+mod configuration;
+mod debug_sequence;
 mod flash_algorithm;
 mod flash_properties;
 mod memory;
@@ -25,6 +27,8 @@ pub use chip::{
 pub use chip_family::{
     Architecture, ChipFamily, CoreType, InstructionSet, TargetDescriptionSource,
 };
+pub use configuration::{ConfigurationField, ConfigurationRegion, ConfigurationValue};
+pub use debug_sequence::{SequenceBlock, SequenceControl, SequenceDescription, SequenceElement};
 pub use flash_algorithm::{RawFlashAlgorithm, TransferEncoding};
 pub use flash_properties::FlashProperties;
 pub use memory::{
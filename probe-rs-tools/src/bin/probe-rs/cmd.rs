@@ -1,20 +1,28 @@
+pub mod arm;
 pub mod attach;
 pub mod benchmark;
 pub mod cargo_embed;
 pub mod cargo_flash;
 pub mod chip;
 pub mod complete;
+pub mod config_bytes;
+pub mod coresight;
 pub mod dap_server;
 pub mod debug;
 pub mod download;
+pub mod dump;
 pub mod erase;
+pub mod gang;
 pub mod gdb;
 pub mod info;
 pub mod itm;
+pub mod jtag;
 pub mod list;
 pub mod mi;
 pub mod profile;
+pub mod protect;
 pub mod read;
+pub mod reg;
 pub mod reset;
 pub mod run;
 pub mod trace;
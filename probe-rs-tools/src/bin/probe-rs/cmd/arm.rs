@@ -0,0 +1,509 @@
+use anyhow::{anyhow, Context, Result};
+use probe_rs::{
+    architecture::arm::{
+        dp::{DpAddress, DpRegisterAddress},
+        memory::ArmMemoryInterface,
+        sequences::DefaultArmSequence,
+        ApAddress, ApV2Address, ArmProbeInterface, FullyQualifiedApAddress,
+    },
+    probe::list::Lister,
+};
+
+use super::info::parse_hex;
+use crate::util::common_options::{ProbeOptions, ReadWriteBitWidth, ReadWriteOptions};
+use crate::util::{parse_u32, parse_u64};
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+/// Raw access to the debug ports, access ports and MEM-APs of an ARM target
+///
+/// No core is attached, so these commands also work when the cores are not
+/// accessible, e.g. to talk to the CTRL-AP of a locked nRF device.
+#[derive(clap::Subcommand)]
+#[clap(verbatim_doc_comment)]
+enum Subcommand {
+    /// Reads a debug port register
+    ///
+    /// e.g. probe-rs arm dp-read 0x4
+    ///      Reads CTRL/STAT of the default debug port
+    #[clap(name = "dp-read", verbatim_doc_comment)]
+    DpRead {
+        #[clap(flatten)]
+        register: DpRegisterOptions,
+
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+
+    /// Writes a debug port register
+    ///
+    /// e.g. probe-rs arm dp-write 0x8 0x01000000
+    ///      Writes SELECT of the default debug port
+    #[clap(name = "dp-write", verbatim_doc_comment)]
+    DpWrite {
+        #[clap(flatten)]
+        register: DpRegisterOptions,
+
+        /// The value to write
+        #[clap(value_parser = parse_u32)]
+        value: u32,
+
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+
+    /// Reads an access port register
+    ///
+    /// e.g. probe-rs arm ap-read --ap 1 0xFC
+    ///      Reads the IDR of access port 1
+    #[clap(name = "ap-read", verbatim_doc_comment)]
+    ApRead {
+        #[clap(flatten)]
+        access_port: ApOptions,
+
+        /// The address of the register within the access port
+        #[clap(value_parser = parse_u32)]
+        register: u32,
+
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+
+    /// Writes an access port register
+    ///
+    /// e.g. probe-rs arm ap-write --ap 1 0x04 0x1
+    ///      Writes 1 to register 0x04 of access port 1
+    #[clap(name = "ap-write", verbatim_doc_comment)]
+    ApWrite {
+        #[clap(flatten)]
+        access_port: ApOptions,
+
+        /// The address of the register within the access port
+        #[clap(value_parser = parse_u32)]
+        register: u32,
+
+        /// The value to write
+        #[clap(value_parser = parse_u32)]
+        value: u32,
+
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+
+    /// Reads memory through a MEM-AP
+    ///
+    /// e.g. probe-rs arm mem-read --ap 0 b32 0xE000ED00 1
+    ///      Reads CPUID through access port 0
+    ///
+    /// Output is a space separated list of hex values padded to the read word width.
+    #[clap(name = "mem-read", verbatim_doc_comment)]
+    MemRead {
+        #[clap(flatten)]
+        access_port: ApOptions,
+
+        #[clap(flatten)]
+        read_write_options: ReadWriteOptions,
+
+        /// Number of words to read from the target
+        words: u64,
+
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+
+    /// Writes memory through a MEM-AP
+    ///
+    /// e.g. probe-rs arm mem-write --ap 0 b32 0x20000000 0xDEADBEEF
+    ///      Writes 0xDEADBEEF to address 0x20000000 through access port 0
+    #[clap(name = "mem-write", verbatim_doc_comment)]
+    MemWrite {
+        #[clap(flatten)]
+        access_port: ApOptions,
+
+        #[clap(flatten)]
+        read_write_options: ReadWriteOptions,
+
+        /// Values to write to the target.
+        /// Takes a list of integer values and can be specified in decimal (16), hexadecimal (0x10) or octal (0o20) format.
+        #[clap(value_parser = parse_u64)]
+        values: Vec<u64>,
+
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+}
+
+/// Selects a debug port.
+#[derive(clap::Args)]
+struct DpOptions {
+    /// SWD Multidrop target selection value of the debug port. By default, the default debug port is used.
+    #[arg(long, value_parser = parse_hex)]
+    target_sel: Option<u32>,
+}
+
+impl DpOptions {
+    fn address(&self) -> DpAddress {
+        match self.target_sel {
+            Some(target_sel) => DpAddress::Multidrop(target_sel),
+            None => DpAddress::Default,
+        }
+    }
+}
+
+/// Selects a debug port register.
+#[derive(clap::Args)]
+struct DpRegisterOptions {
+    #[clap(flatten)]
+    dp: DpOptions,
+
+    /// The bank of the register, written to DPBANKSEL before the access
+    #[arg(long)]
+    bank: Option<u8>,
+
+    /// The address of the register (0x0, 0x4, 0x8 or 0xC)
+    #[clap(value_parser = parse_u32)]
+    register: u32,
+}
+
+impl DpRegisterOptions {
+    fn address(&self) -> Result<DpRegisterAddress> {
+        anyhow::ensure!(
+            self.register <= 0xC && self.register % 4 == 0,
+            "{:#x} is not a debug port register address",
+            self.register
+        );
+        if let Some(bank) = self.bank {
+            anyhow::ensure!(bank <= 0xF, "{bank} is not a debug port register bank");
+        }
+
+        Ok(DpRegisterAddress {
+            address: self.register as u8,
+            bank: self.bank,
+        })
+    }
+}
+
+/// Selects an access port.
+#[derive(clap::Args)]
+struct ApOptions {
+    #[clap(flatten)]
+    dp: DpOptions,
+
+    /// The index of an ADIv5 access port
+    #[arg(long, required_unless_present = "ap_address")]
+    ap: Option<u8>,
+
+    /// The base address of an ADIv6 access port, in the memory space of the debug port
+    #[arg(long, conflicts_with = "ap", value_parser = parse_u64)]
+    ap_address: Option<u64>,
+}
+
+impl ApOptions {
+    fn address(&self) -> FullyQualifiedApAddress {
+        let dp = self.dp.address();
+        match (self.ap, self.ap_address) {
+            (_, Some(address)) => {
+                FullyQualifiedApAddress::v2_with_dp(dp, ApV2Address::new_with_tip(address))
+            }
+            (Some(ap), None) => FullyQualifiedApAddress::v1_with_dp(dp, ap),
+            // Enforced by the argument constraints.
+            (None, None) => unreachable!("no access port was selected"),
+        }
+    }
+
+    /// Checks that `register` is a register address within the selected access port.
+    fn register(&self, register: u32) -> Result<u32> {
+        // ADIv6 access ports have a 4 KiB register space, ADIv5 ones only 256 bytes.
+        let size = if self.ap_address.is_some() {
+            0x1000
+        } else {
+            0x100
+        };
+        anyhow::ensure!(
+            register < size && register % 4 == 0,
+            "{register:#x} is not an access port register address"
+        );
+
+        Ok(register)
+    }
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> Result<()> {
+        match self.subcommand {
+            Subcommand::DpRead { register, common } => {
+                let address = register.address()?;
+                let dp = register.dp.address();
+                with_interface(common, lister, dp, |interface| {
+                    let value = interface.read_raw_dp_register(dp, address)?;
+                    println!("{value:#010x}");
+                    Ok(())
+                })
+            }
+            Subcommand::DpWrite {
+                register,
+                value,
+                common,
+            } => {
+                let address = register.address()?;
+                let dp = register.dp.address();
+                with_interface(common, lister, dp, |interface| {
+                    interface.write_raw_dp_register(dp, address, value)?;
+                    interface.flush()?;
+                    Ok(())
+                })
+            }
+            Subcommand::ApRead {
+                access_port,
+                register,
+                common,
+            } => {
+                let register = access_port.register(register)?;
+                let ap = access_port.address();
+                with_interface(common, lister, ap.dp(), |interface| {
+                    let value = read_ap_register(interface, &ap, register)?;
+                    println!("{value:#010x}");
+                    Ok(())
+                })
+            }
+            Subcommand::ApWrite {
+                access_port,
+                register,
+                value,
+                common,
+            } => {
+                let register = access_port.register(register)?;
+                let ap = access_port.address();
+                with_interface(common, lister, ap.dp(), |interface| {
+                    write_ap_register(interface, &ap, register, value)
+                })
+            }
+            Subcommand::MemRead {
+                access_port,
+                read_write_options,
+                words,
+                common,
+            } => {
+                let ap = access_port.address();
+                with_interface(common, lister, ap.dp(), |interface| {
+                    let mut memory = interface.memory_interface(&ap)?;
+                    read_memory(&mut *memory, &read_write_options, words as usize)
+                })
+            }
+            Subcommand::MemWrite {
+                access_port,
+                read_write_options,
+                values,
+                common,
+            } => {
+                let ap = access_port.address();
+                with_interface(common, lister, ap.dp(), |interface| {
+                    let mut memory = interface.memory_interface(&ap)?;
+                    write_memory(&mut *memory, &read_write_options, &values)?;
+                    memory.flush()?;
+                    Ok(())
+                })
+            }
+        }
+    }
+}
+
+/// Reads an access port register. The registers of an ADIv6 access port are
+/// accessed through the root memory interface of its debug port.
+fn read_ap_register(
+    interface: &mut dyn ArmProbeInterface,
+    ap: &FullyQualifiedApAddress,
+    register: u32,
+) -> Result<u32> {
+    match ap.ap() {
+        ApAddress::V1(_) => Ok(interface.read_raw_ap_register(ap, register as u8)?),
+        ApAddress::V2(address) => {
+            let root = FullyQualifiedApAddress::v2_with_dp(ap.dp(), ApV2Address::root());
+            let base = address.as_slice()[0];
+            Ok(interface
+                .memory_interface(&root)?
+                .read_word_32(base + u64::from(register))?)
+        }
+    }
+}
+
+/// Writes an access port register, see [`read_ap_register`].
+fn write_ap_register(
+    interface: &mut dyn ArmProbeInterface,
+    ap: &FullyQualifiedApAddress,
+    register: u32,
+    value: u32,
+) -> Result<()> {
+    match ap.ap() {
+        ApAddress::V1(_) => {
+            interface.write_raw_ap_register(ap, register as u8, value)?;
+            interface.flush()?;
+        }
+        ApAddress::V2(address) => {
+            let root = FullyQualifiedApAddress::v2_with_dp(ap.dp(), ApV2Address::root());
+            let base = address.as_slice()[0];
+            let mut memory = interface.memory_interface(&root)?;
+            memory.write_word_32(base + u64::from(register), value)?;
+            memory.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Attaches to the debug port `dp` without attaching to a core, and runs `f` on the ARM interface.
+fn with_interface(
+    common: ProbeOptions,
+    lister: &Lister,
+    dp: DpAddress,
+    f: impl FnOnce(&mut dyn ArmProbeInterface) -> Result<()>,
+) -> Result<()> {
+    let probe_options = common.load()?;
+    let mut probe = probe_options.attach_probe(lister)?;
+
+    if probe_options.connect_under_reset() {
+        probe.attach_to_unspecified_under_reset()?;
+    } else {
+        probe.attach_to_unspecified()?;
+    }
+
+    let interface = probe
+        .try_into_arm_interface()
+        .map_err(|(_probe, error)| anyhow!(error))?;
+    let mut interface = interface
+        .initialize(DefaultArmSequence::create(), dp)
+        .map_err(|(_interface, error)| anyhow!(error))
+        .context("Failed to connect to the debug port")?;
+
+    let result = f(&mut *interface);
+
+    interface.close().detach()?;
+
+    result
+}
+
+fn read_memory(
+    memory: &mut dyn ArmMemoryInterface,
+    options: &ReadWriteOptions,
+    words: usize,
+) -> Result<()> {
+    let address = options.resolve_address()?;
+    match options.width {
+        ReadWriteBitWidth::B8 => {
+            let mut values = vec![0; words];
+            memory.read_8(address, &mut values)?;
+            for val in values {
+                print!("{:02x} ", val);
+            }
+        }
+        ReadWriteBitWidth::B32 => {
+            let mut values = vec![0; words];
+            memory.read_32(address, &mut values)?;
+            for val in values {
+                print!("{:08x} ", val);
+            }
+        }
+        ReadWriteBitWidth::B64 => {
+            let mut values = vec![0; words];
+            memory.read_64(address, &mut values)?;
+            for val in values {
+                print!("{:016x} ", val);
+            }
+        }
+    }
+    println!();
+
+    Ok(())
+}
+
+fn write_memory(
+    memory: &mut dyn ArmMemoryInterface,
+    options: &ReadWriteOptions,
+    values: &[u64],
+) -> Result<()> {
+    let address = options.resolve_address()?;
+    match options.width {
+        ReadWriteBitWidth::B8 => {
+            let values = narrow::<u8>(values, 8)?;
+            memory.write_8(address, &values)?;
+        }
+        ReadWriteBitWidth::B32 => {
+            let values = narrow::<u32>(values, 32)?;
+            memory.write_32(address, &values)?;
+        }
+        ReadWriteBitWidth::B64 => memory.write_64(address, values)?,
+    }
+
+    Ok(())
+}
+
+/// Converts `values` to a narrower word type, failing if any value does not fit.
+fn narrow<T: TryFrom<u64>>(values: &[u64], bits: u32) -> Result<Vec<T>> {
+    values
+        .iter()
+        .map(|&value| {
+            T::try_from(value)
+                .map_err(|_| anyhow!("{value} in {values:?} is too large for a {bits} bit write."))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dp_register_address() {
+        let register = DpRegisterOptions {
+            dp: DpOptions { target_sel: None },
+            bank: Some(3),
+            register: 0x4,
+        };
+        assert_eq!(
+            register.address().unwrap(),
+            DpRegisterAddress {
+                address: 0x4,
+                bank: Some(3)
+            }
+        );
+
+        let register = DpRegisterOptions {
+            register: 0x10,
+            ..register
+        };
+        assert!(register.address().is_err());
+    }
+
+    #[test]
+    fn access_port_address() {
+        let dp = DpAddress::Multidrop(0x0100_2927);
+
+        let v1 = ApOptions {
+            dp: DpOptions {
+                target_sel: Some(0x0100_2927),
+            },
+            ap: Some(1),
+            ap_address: None,
+        };
+        assert_eq!(v1.address(), FullyQualifiedApAddress::v1_with_dp(dp, 1));
+        assert_eq!(v1.register(0xFC).unwrap(), 0xFC);
+        assert!(v1.register(0xD00).is_err());
+        assert!(v1.register(0x02).is_err());
+
+        let v2 = ApOptions {
+            ap: None,
+            ap_address: Some(0x2000),
+            ..v1
+        };
+        assert_eq!(
+            v2.address(),
+            FullyQualifiedApAddress::v2_with_dp(dp, ApV2Address::new_with_tip(0x2000))
+        );
+        assert_eq!(v2.register(0xD00).unwrap(), 0xD00);
+        assert!(v2.register(0x1000).is_err());
+    }
+}
@@ -52,6 +52,17 @@ pub struct Flashing {
     pub disable_double_buffering: bool,
     pub preverify: bool,
     pub verify: bool,
+    pub bundle: Option<String>,
+    pub preserve: Vec<PreservedRange>,
+}
+
+/// A memory range whose contents are kept when the flash around it is erased.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PreservedRange {
+    pub name: String,
+    pub start: u64,
+    pub end: u64,
 }
 
 /// The reset config struct holding all the possible reset options.
@@ -257,6 +268,16 @@ impl Configs {
                         defined_profiles.join(", ")
                     );
                 }
+                for preserved in &config.flashing.preserve {
+                    if preserved.start >= preserved.end {
+                        bail!(
+                            "the preserved range \"{}\" ({:#010x}..{:#010x}) is empty, its start has to be below its end",
+                            preserved.name,
+                            preserved.start,
+                            preserved.end
+                        );
+                    }
+                }
                 Ok(config)
             }
         }
@@ -280,6 +301,7 @@ impl Configs {
 #[cfg(test)]
 mod test {
     use super::Configs;
+    use figment::providers::{Format, Toml};
 
     #[test]
     fn default_profile() {
@@ -300,6 +322,21 @@ mod test {
         let _superfluous: anyhow::Error = configs.select_defined("default").unwrap_err();
     }
     #[test]
+    fn empty_preserved_range_fails() {
+        let mut configs = Configs::new(std::env::current_dir().unwrap());
+        configs.figment = configs.figment.merge(
+            Toml::string(
+                r#"
+            [default.flashing]
+               preserve = [{ name = "calibration", start = 0x1100, end = 0x1000 }]
+               "#,
+            )
+            .nested(),
+        );
+
+        let _empty: anyhow::Error = configs.select_defined("default").unwrap_err();
+    }
+    #[test]
     fn file_name_patterns() {
         // Existence of files is not tested here, so it is fine to use a file that does not exist
         Configs::new(std::env::current_dir().unwrap())
@@ -6,7 +6,7 @@ use anyhow::{anyhow, Context, Result};
 use clap::Parser;
 use colored::Colorize;
 use parking_lot::FairMutex;
-use probe_rs::flashing::{BootInfo, FormatKind};
+use probe_rs::flashing::{BootInfo, FormatKind, PreservedRange};
 use probe_rs::gdb_server::GdbInstanceConfiguration;
 use probe_rs::probe::list::Lister;
 use probe_rs::rtt::ScanRegion;
@@ -25,6 +25,7 @@ use std::{
 };
 use time::{OffsetDateTime, UtcOffset};
 
+use crate::util::bundle;
 use crate::util::cargo::target_instruction_set;
 use crate::util::common_options::{BinaryDownloadOptions, OperationError, ProbeOptions};
 use crate::util::flash::{build_loader, run_flash_download};
@@ -244,7 +245,7 @@ fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
         Err(e) => return Err(e.into()),
     };
 
-    let format = FormatOptions::default().to_format_kind(session.target());
+    let format = FormatOptions::default().to_format_kind(session.target(), &path);
     let elf = if matches!(format, FormatKind::Elf | FormatKind::Idf) {
         Some(fs::read(&path)?)
     } else {
@@ -270,9 +271,21 @@ fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
             flash_layout_output_path: None,
             preverify: config.flashing.preverify,
             verify: config.flashing.verify,
+            preserved_ranges: config
+                .flashing
+                .preserve
+                .iter()
+                .map(|preserved| PreservedRange {
+                    name: preserved.name.clone(),
+                    range: preserved.start..preserved.end,
+                })
+                .collect(),
         };
         let format_options = FormatOptions::default();
-        let loader = build_loader(&mut session, &path, format_options, image_instr_set)?;
+        let mut loader = build_loader(&mut session, &path, format_options, image_instr_set)?;
+        if let Some(manifest) = &config.flashing.bundle {
+            bundle::add_bundle(&mut session, &mut loader, Path::new(manifest))?;
+        }
 
         // When using RTT with a program in flash, the RTT header will be moved to RAM on
         // startup, so clearing it before startup is ok. However, if we're downloading to the
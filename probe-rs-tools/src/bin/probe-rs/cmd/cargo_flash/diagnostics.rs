@@ -64,6 +64,30 @@ pub(crate) fn render_diagnostics(error: OperationError) {
                 ],
             ),
         },
+        OperationError::FailedToLoadBundle(e) => (
+            e.to_string(),
+            vec![
+                "Image paths in a bundle manifest are relative to the directory of the manifest.".into()
+            ],
+        ),
+        OperationError::FailedToLoadFlashAlgorithm { .. } => (
+            error.to_string(),
+            vec![
+                "The ELF file has to contain a `FlashDevice` symbol and a `PrgCode` section, like the `.FLM` files of CMSIS-Packs.".into()
+            ],
+        ),
+        OperationError::FlashAlgorithmWithoutChip => (
+            error.to_string(),
+            vec![
+                "You can list all the available chips by running `probe-rs chip list`.".into(),
+            ],
+        ),
+        OperationError::FlashAlgorithmRangeMismatch { .. } => (
+            error.to_string(),
+            vec![
+                "Use `--flash-algorithm-range` to program a range covering whole flash regions of the target.".into(),
+            ],
+        ),
         OperationError::FailedToOpenProbe(_e) => (
             error.to_string(),
             vec![
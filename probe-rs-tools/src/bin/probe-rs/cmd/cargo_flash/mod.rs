@@ -7,9 +7,10 @@ use probe_rs::probe::list::Lister;
 use std::ffi::OsString;
 use std::{path::PathBuf, process};
 
+use crate::util::bundle;
 use crate::util::cargo::target_instruction_set;
 use crate::util::common_options::{
-    BinaryDownloadOptions, CargoOptions, OperationError, ProbeOptions,
+    BinaryDownloadOptions, CargoOptions, FlashAlgorithmOptions, OperationError, ProbeOptions,
 };
 use crate::util::flash;
 use crate::util::logging::{setup_logging, LevelFilter};
@@ -35,8 +36,13 @@ struct CliOptions {
     #[arg(value_name = "level", long)]
     pub log: Option<LevelFilter>,
     /// The path to the file to be flashed. Setting this will ignore the cargo options.
+    ///
+    /// A `.toml` file is read as a bundle manifest listing several images.
     #[arg(value_name = "path", long)]
     pub path: Option<PathBuf>,
+    /// A bundle manifest listing additional images, which are flashed together with the binary.
+    #[arg(value_name = "manifest", long)]
+    pub bundle: Option<PathBuf>,
     /// The work directory from which cargo-flash should operate from.
     #[arg(value_name = "directory", long)]
     pub work_dir: Option<PathBuf>,
@@ -50,6 +56,9 @@ struct CliOptions {
     #[command(flatten)]
     /// Argument relating to probe/chip selection/configuration.
     pub download_options: BinaryDownloadOptions,
+    #[command(flatten)]
+    /// Arguments to flash with a flash algorithm from an ELF file.
+    pub flash_algorithm_options: FlashAlgorithmOptions,
 
     #[command(flatten)]
     pub format_options: crate::FormatOptions,
@@ -126,11 +135,21 @@ fn main_try(args: &[OsString]) -> Result<(), OperationError> {
     let lister = Lister::new();
 
     // Attach to specified probe
-    let (mut session, probe_options) = opt.probe_options.simple_attach(&lister)?;
+    let (mut session, probe_options) = opt
+        .probe_options
+        .simple_attach_with_flash_algorithm(&lister, &opt.flash_algorithm_options)?;
 
     // Flash the binary
-    let loader =
-        flash::build_loader(&mut session, &path, opt.format_options, image_instr_set).unwrap();
+    let mut loader = if bundle::is_manifest(&path) {
+        flash::build_bundle_loader(&mut session, &path)
+            .map_err(OperationError::FailedToLoadBundle)?
+    } else {
+        flash::build_loader(&mut session, &path, opt.format_options, image_instr_set).unwrap()
+    };
+    if let Some(manifest) = &opt.bundle {
+        bundle::add_bundle(&mut session, &mut loader, manifest)
+            .map_err(OperationError::FailedToLoadBundle)?;
+    }
     flash::run_flash_download(
         &mut session,
         &path,
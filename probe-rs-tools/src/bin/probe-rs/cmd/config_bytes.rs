@@ -0,0 +1,305 @@
+use anyhow::{bail, Context, Result};
+use probe_rs::config::{ConfigurationField, ConfigurationRegion, Target};
+use probe_rs::{probe::list::Lister, Session};
+
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+/// Reads and writes configuration memory of the target, like option bytes, fuses or the UICR
+///
+/// Fields are named like `REGION.FIELD`, e.g. `UICR.REGOUT0`.
+#[derive(clap::Subcommand)]
+#[clap(verbatim_doc_comment)]
+enum Subcommand {
+    /// Reads and decodes configuration regions or fields
+    ///
+    /// e.g. probe-rs config-bytes read --chip nRF52840_xxAA UICR
+    ///      probe-rs config-bytes read --chip nRF52840_xxAA UICR.APPROTECT
+    ///
+    /// Reads all configuration regions of the target if none are given.
+    #[clap(verbatim_doc_comment)]
+    Read {
+        /// Names of the regions or fields to read
+        names: Vec<String>,
+
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+    },
+
+    /// Writes configuration fields
+    ///
+    /// e.g. probe-rs config-bytes write --allow-configuration-write --chip nRF52840_xxAA UICR.REGOUT0=3V3 UICR.NFCPINS=Disabled
+    ///
+    /// Fields can be set to a number or to the name of a value. Other fields of
+    /// the region keep their value. The target usually has to be reset for the
+    /// new configuration to take effect.
+    #[clap(verbatim_doc_comment)]
+    Write {
+        /// The assignments, `REGION.FIELD=VALUE`
+        #[clap(required = true)]
+        assignments: Vec<String>,
+
+        /// Confirm that the configuration of the target may be changed
+        #[arg(long)]
+        allow_configuration_write: bool,
+
+        /// Allow writing values which can not be undone, like permanent read protection
+        #[arg(long)]
+        allow_irreversible: bool,
+
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+    },
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> Result<()> {
+        match self.subcommand {
+            Subcommand::Read {
+                names,
+                probe_options,
+            } => {
+                let (mut session, _probe_options) = probe_options.simple_attach(lister)?;
+
+                let selections = if names.is_empty() {
+                    session
+                        .target()
+                        .configuration_regions
+                        .iter()
+                        .map(|region| (region.clone(), None))
+                        .collect()
+                } else {
+                    names
+                        .iter()
+                        .map(|name| find(session.target(), name))
+                        .collect::<Result<Vec<_>>>()?
+                };
+
+                if selections.is_empty() {
+                    println!("{} has no configuration regions", session.target().name);
+                }
+
+                for (region, field) in selections {
+                    print_region(&mut session, &region, field.as_ref())?;
+                }
+
+                session.resume_all_cores()?;
+
+                Ok(())
+            }
+            Subcommand::Write {
+                assignments,
+                allow_configuration_write,
+                allow_irreversible,
+                probe_options,
+            } => {
+                if !allow_configuration_write {
+                    bail!("Writing configuration regions changes how the target starts up, use `--allow-configuration-write` to write them");
+                }
+
+                let probe_options = probe_options.load()?;
+                let target = probe_options.get_target_selector()?;
+                let probe = probe_options.attach_probe(lister)?;
+                let permissions = probe_options.permissions().allow_configuration_write();
+                let mut session =
+                    probe_options.attach_session_with_permissions(probe, target, permissions)?;
+
+                let mut writes =
+                    Vec::<(ConfigurationRegion, Vec<(ConfigurationField, u64)>)>::new();
+                for assignment in &assignments {
+                    let Some((name, value)) = assignment.split_once('=') else {
+                        bail!("Expected an assignment like `UICR.REGOUT0=3V3`, got `{assignment}`");
+                    };
+
+                    let (region, Some(field)) = find(session.target(), name)? else {
+                        bail!(
+                            "`{}` is a region, expected a field like `REGION.FIELD`",
+                            name.trim()
+                        );
+                    };
+                    let value = parse_value(&field, value)?;
+
+                    if field.is_irreversible(value) && !allow_irreversible {
+                        bail!(
+                            "Writing {value:#x} to {}.{} can not be undone, use `--allow-irreversible` to write it anyway",
+                            region.name,
+                            field.name
+                        );
+                    }
+
+                    match writes
+                        .iter_mut()
+                        .find(|(existing, _)| existing.name == region.name)
+                    {
+                        Some((_, fields)) => fields.push((field, value)),
+                        None => writes.push((region, vec![(field, value)])),
+                    }
+                }
+
+                for (region, fields) in writes {
+                    let values = fields
+                        .iter()
+                        .map(|(field, value)| (field.name.as_str(), *value))
+                        .collect::<Vec<_>>();
+                    session.write_configuration_fields(&region.name, &values, None)?;
+
+                    for (field, _) in &fields {
+                        print_region(&mut session, &region, Some(field))?;
+                    }
+                }
+
+                session.resume_all_cores()?;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Looks up a region, or a field if the name is of the form `REGION.FIELD`.
+fn find(target: &Target, name: &str) -> Result<(ConfigurationRegion, Option<ConfigurationField>)> {
+    let name = name.trim();
+    let (region_name, field_name) = match name.split_once('.') {
+        Some((region, field)) => (region, Some(field)),
+        None => (name, None),
+    };
+
+    let Some(region) = target.configuration_region(region_name) else {
+        bail!(
+            "{} has no configuration region named `{region_name}`",
+            target.name
+        );
+    };
+
+    let field = field_name
+        .map(|field_name| {
+            region.field(field_name).cloned().with_context(|| {
+                format!(
+                    "The configuration region {} has no field named `{field_name}`",
+                    region.name
+                )
+            })
+        })
+        .transpose()?;
+
+    Ok((region.clone(), field))
+}
+
+/// Parses a number or the name of a value of `field`.
+fn parse_value(field: &ConfigurationField, value: &str) -> Result<u64> {
+    let value = value.trim();
+    if let Some(named) = field.value_by_name(value) {
+        return Ok(named.value);
+    }
+
+    let Ok(raw) = parse_int::parse::<u64>(value) else {
+        let names = field
+            .values
+            .iter()
+            .map(|named| named.name.as_str())
+            .collect::<Vec<_>>();
+        bail!(
+            "Invalid value `{value}` for field {}, expected a number or one of: {}",
+            field.name,
+            names.join(", ")
+        );
+    };
+
+    if raw & !field.mask() != 0 {
+        bail!(
+            "The value {raw:#x} does not fit into the {} bits of field {}",
+            field.bit_width,
+            field.name
+        );
+    }
+
+    Ok(raw)
+}
+
+/// Reads `region` and prints all its fields, or only `field` if given.
+fn print_region(
+    session: &mut Session,
+    region: &ConfigurationRegion,
+    field: Option<&ConfigurationField>,
+) -> Result<()> {
+    let contents = session.read_configuration_region(&region.name)?;
+
+    match field {
+        Some(field) => println!("{}.{}", region.name, format_field(field, &contents)),
+        None => {
+            println!("{} @ {:#010x?}", region.name, region.range);
+            if let Some(description) = &region.description {
+                println!("    {description}");
+            }
+            for field in &region.fields {
+                println!("    {}", format_field(field, &contents));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn format_field(field: &ConfigurationField, contents: &[u8]) -> String {
+    let Some(value) = field.extract(contents) else {
+        return format!("{} = <out of range>", field.name);
+    };
+
+    let mut formatted = format!(
+        "{} = {value:#0width$x}",
+        field.name,
+        width = field.bit_width.div_ceil(4) as usize + 2
+    );
+    if let Some(named) = field.value_by_raw(value) {
+        formatted.push_str(&format!(" ({})", named.name));
+    }
+
+    formatted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use probe_rs::config::ConfigurationValue;
+
+    fn field() -> ConfigurationField {
+        ConfigurationField {
+            name: "REGOUT0".to_string(),
+            description: None,
+            offset: 1,
+            bit_offset: 0,
+            bit_width: 3,
+            irreversible: false,
+            values: vec![ConfigurationValue {
+                name: "3V3".to_string(),
+                description: None,
+                value: 5,
+                irreversible: false,
+            }],
+        }
+    }
+
+    #[test]
+    fn parse_field_value() {
+        let field = field();
+
+        assert_eq!(parse_value(&field, "3v3").unwrap(), 5);
+        assert_eq!(parse_value(&field, " 0x2").unwrap(), 2);
+        assert!(parse_value(&field, "8").is_err());
+        assert!(parse_value(&field, "1V8").is_err());
+    }
+
+    #[test]
+    fn format_field_value() {
+        let field = field();
+
+        assert_eq!(format_field(&field, &[0xff, 0xfd]), "REGOUT0 = 0x5 (3V3)");
+        assert_eq!(format_field(&field, &[0xff, 0x01]), "REGOUT0 = 0x1");
+        assert_eq!(format_field(&field, &[0xff]), "REGOUT0 = <out of range>");
+    }
+}
@@ -0,0 +1,581 @@
+use anyhow::{anyhow, Result};
+use jep106::JEP106Code;
+use probe_rs::{
+    architecture::arm::{
+        ap_v1::{ApClass, Register, IDR},
+        dp::{DebugPortId, DebugPortVersion, DpAddress, DpRegister, DLPIDR, DPIDR, TARGETID},
+        memory::{romtable::RomTable, ArmMemoryInterface, Component, ComponentId, PeripheralType},
+        sequences::DefaultArmSequence,
+        ApAddress, ApV2Address, ArmProbeInterface, FullyQualifiedApAddress,
+    },
+    probe::list::Lister,
+};
+use serde::Serialize;
+use termtree::Tree;
+
+use super::info::parse_hex;
+use crate::util::common_options::ProbeOptions;
+
+/// Offset of the first identification register (PIDR4) of a CoreSight component.
+const IDENTIFICATION_REGISTERS: u64 = 0xFD0;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// SWD Multidrop target selection value of a debug port to explore
+    ///
+    /// Can be given multiple times to walk several debug ports. By default,
+    /// only the default debug port is explored.
+    #[arg(long, value_parser = parse_hex)]
+    target_sel: Vec<u32>,
+
+    /// Prints the topology as JSON instead of a tree
+    #[arg(long)]
+    json: bool,
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> Result<()> {
+        let probe_options = self.common.load()?;
+        let mut probe = probe_options.attach_probe(lister)?;
+
+        if probe_options.connect_under_reset() {
+            probe.attach_to_unspecified_under_reset()?;
+        } else {
+            probe.attach_to_unspecified()?;
+        }
+
+        let debug_ports = if self.target_sel.is_empty() {
+            vec![DpAddress::Default]
+        } else {
+            self.target_sel
+                .iter()
+                .map(|&target_sel| DpAddress::Multidrop(target_sel))
+                .collect()
+        };
+
+        let interface = probe
+            .try_into_arm_interface()
+            .map_err(|(_probe, error)| anyhow!(error))?;
+        let mut interface = interface
+            .initialize(DefaultArmSequence::create(), debug_ports[0])
+            .map_err(|(_interface, error)| anyhow!(error))?;
+
+        let topology = debug_ports
+            .iter()
+            .map(|&dp| {
+                walk_debug_port(&mut *interface, dp).unwrap_or_else(|error| DebugPortNode {
+                    address: dp_name(dp),
+                    error: Some(format!("{error:#}")),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        interface.close().detach()?;
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&topology)?);
+        } else {
+            for debug_port in &topology {
+                println!("{}", debug_port_tree(debug_port));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A debug port, and the access ports behind it.
+#[derive(Debug, Default, Serialize)]
+struct DebugPortNode {
+    address: String,
+    version: Option<String>,
+    designer: Option<String>,
+    dpidr: Option<u32>,
+    targetid: Option<u32>,
+    instance: Option<u8>,
+    access_ports: Vec<AccessPortNode>,
+    error: Option<String>,
+}
+
+/// An access port, and the components it gives access to.
+#[derive(Debug, Default, Serialize)]
+struct AccessPortNode {
+    address: String,
+    /// The IDR register of an ADIv5 access port.
+    idr: Option<u32>,
+    description: String,
+    /// Whether memory accesses through a MEM-AP are enabled.
+    enabled: Option<bool>,
+    components: Vec<ComponentNode>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Jep106 {
+    continuation: u8,
+    id: u8,
+}
+
+/// A CoreSight component, decoded from its identification registers.
+#[derive(Debug, Serialize)]
+struct ComponentNode {
+    address: u64,
+    class: &'static str,
+    /// The CIDR0-3 registers, combined into a single value.
+    cidr: Option<u32>,
+    /// The PIDR0-7 registers, combined into a single value.
+    pidr: Option<u64>,
+    designer: Option<&'static str>,
+    jep106: Option<Jep106>,
+    part: u16,
+    revision: u8,
+    devtype: u8,
+    devarch: u16,
+    name: Option<&'static str>,
+    peripheral_type: Option<String>,
+    children: Vec<ComponentNode>,
+    /// The access port described by an ADIv6 MEM-AP component.
+    access_port: Option<Box<AccessPortNode>>,
+}
+
+fn dp_name(dp: DpAddress) -> String {
+    match dp {
+        DpAddress::Default => String::from("Default"),
+        DpAddress::Multidrop(target_sel) => format!("Multidrop {target_sel:#010x}"),
+    }
+}
+
+fn walk_debug_port(interface: &mut dyn ArmProbeInterface, dp: DpAddress) -> Result<DebugPortNode> {
+    let dpidr = interface.read_raw_dp_register(dp, DPIDR::ADDRESS)?;
+    let dp_info = DebugPortId::from(DPIDR(dpidr));
+
+    let mut node = DebugPortNode {
+        address: dp_name(dp),
+        version: Some(dp_info.version.to_string()),
+        designer: dp_info.designer.get().map(String::from),
+        dpidr: Some(dpidr),
+        ..Default::default()
+    };
+
+    if matches!(
+        dp_info.version,
+        DebugPortVersion::DPv2 | DebugPortVersion::DPv3
+    ) {
+        let target_id = interface.read_raw_dp_register(dp, TARGETID::ADDRESS)?;
+        let dlpidr = DLPIDR(interface.read_raw_dp_register(dp, DLPIDR::ADDRESS)?);
+
+        node.targetid = Some(target_id);
+        node.instance = Some(dlpidr.tinstance());
+    }
+
+    if dp_info.version == DebugPortVersion::DPv3 {
+        let root = FullyQualifiedApAddress::v2_with_dp(dp, ApV2Address::root());
+        node.access_ports.push(walk_memory_ap(
+            interface,
+            &root,
+            String::from("Root memory interface"),
+            None,
+        ));
+    } else {
+        for ap_address in interface.access_ports(dp)? {
+            node.access_ports.push(walk_ap_v1(interface, &ap_address));
+        }
+    }
+
+    Ok(node)
+}
+
+fn walk_ap_v1(
+    interface: &mut dyn ArmProbeInterface,
+    ap_address: &FullyQualifiedApAddress,
+) -> AccessPortNode {
+    let address = ap_address.ap().to_string();
+    let idr = match interface.read_raw_ap_register(ap_address, IDR::ADDRESS) {
+        Ok(idr) => idr,
+        Err(error) => {
+            return AccessPortNode {
+                address,
+                error: Some(format!("{:#}", anyhow!(error))),
+                ..Default::default()
+            }
+        }
+    };
+
+    match IDR::try_from(idr) {
+        Ok(decoded) if decoded.CLASS == ApClass::MemAp => {
+            let mut node = walk_memory_ap(
+                interface,
+                ap_address,
+                format!("MEM-AP ({:?})", decoded.TYPE),
+                Some(decoded.DESIGNER),
+            );
+            node.idr = Some(idr);
+            node
+        }
+        Ok(decoded) => AccessPortNode {
+            address,
+            idr: Some(idr),
+            description: format!(
+                "{:?} AP, Designer: {}, Type: {:#x}, Variant: {:#x}, Revision: {:#x}",
+                decoded.CLASS,
+                decoded.DESIGNER.get().unwrap_or("<unknown>"),
+                decoded.TYPE as u8,
+                decoded.VARIANT,
+                decoded.REVISION
+            ),
+            ..Default::default()
+        },
+        Err(error) => AccessPortNode {
+            address,
+            idr: Some(idr),
+            error: Some(error.to_string()),
+            ..Default::default()
+        },
+    }
+}
+
+/// Walks the components behind a MEM-AP, starting at its base address.
+fn walk_memory_ap(
+    interface: &mut dyn ArmProbeInterface,
+    ap_address: &FullyQualifiedApAddress,
+    description: String,
+    designer: Option<JEP106Code>,
+) -> AccessPortNode {
+    let description = match designer.and_then(|designer| designer.get()) {
+        Some(designer) => format!("{description}, Designer: {designer}"),
+        None => description,
+    };
+    let mut node = AccessPortNode {
+        address: ap_address.ap().to_string(),
+        description,
+        ..Default::default()
+    };
+
+    let root = (|| -> Result<Option<Component>> {
+        let mut memory = interface.memory_interface(ap_address)?;
+        // The root memory interface of an ADIv6 debug port has no CSW to check.
+        if ap_address.ap() != &ApAddress::V2(ApV2Address::root()) {
+            let enabled = memory.generic_status()?.enabled();
+            node.enabled = Some(enabled);
+            if !enabled {
+                return Ok(None);
+            }
+        }
+
+        let base_address = memory.base_address()?;
+        Ok(Some(Component::try_parse(&mut *memory, base_address)?))
+    })();
+
+    match root {
+        Ok(Some(component)) => match walk_component(interface, &component, ap_address) {
+            Ok(component) => node.components.push(component),
+            Err(error) => node.error = Some(format!("{error:#}")),
+        },
+        Ok(None) => {}
+        Err(error) => node.error = Some(format!("{error:#}")),
+    }
+
+    node
+}
+
+fn walk_component(
+    interface: &mut dyn ArmProbeInterface,
+    component: &Component,
+    ap_address: &FullyQualifiedApAddress,
+) -> Result<ComponentNode> {
+    let id = component.id();
+    let mut node = decode_component(interface, component, ap_address);
+
+    match component {
+        Component::Class1RomTable(_, table) => {
+            for entry in table.entries() {
+                node.children
+                    .push(walk_component(interface, entry.component(), ap_address)?);
+            }
+        }
+        _ if id.peripheral_id().is_of_type(PeripheralType::Rom) => {
+            let table = {
+                let mut memory = interface.memory_interface(ap_address)?;
+                RomTable::try_parse(
+                    memory.as_mut() as &mut dyn ArmMemoryInterface,
+                    id.component_address(),
+                )?
+            };
+            for entry in table.entries() {
+                node.children
+                    .push(walk_component(interface, entry.component(), ap_address)?);
+            }
+        }
+        _ if id.peripheral_id().is_of_type(PeripheralType::MemAp) => {
+            if let ApAddress::V2(parent) = ap_address.ap() {
+                let nested = FullyQualifiedApAddress::v2_with_dp(
+                    ap_address.dp(),
+                    parent.clone().append(id.component_address()),
+                );
+                node.access_port = Some(Box::new(walk_memory_ap(
+                    interface,
+                    &nested,
+                    String::from("MEM-AP"),
+                    None,
+                )));
+            }
+        }
+        _ => {}
+    }
+
+    Ok(node)
+}
+
+/// Decodes the identification of a component, and reads its raw CIDR and PIDR values.
+fn decode_component(
+    interface: &mut dyn ArmProbeInterface,
+    component: &Component,
+    ap_address: &FullyQualifiedApAddress,
+) -> ComponentNode {
+    let id = component.id();
+    let (pidr, cidr) = match read_identification(interface, id, ap_address) {
+        Ok((pidr, cidr)) => (Some(pidr), Some(cidr)),
+        Err(error) => {
+            tracing::warn!(
+                "Failed to read the identification registers at {:#010x}: {error:#}",
+                id.component_address()
+            );
+            (None, None)
+        }
+    };
+
+    let peripheral_id = id.peripheral_id();
+    let part = peripheral_id.determine_part();
+
+    ComponentNode {
+        address: id.component_address(),
+        class: component_class(component),
+        cidr,
+        pidr,
+        designer: peripheral_id.designer(),
+        jep106: peripheral_id.jep106().map(|jep106| Jep106 {
+            continuation: jep106.cc,
+            id: jep106.id,
+        }),
+        part: peripheral_id.part(),
+        revision: peripheral_id.revision(),
+        devtype: peripheral_id.dev_type(),
+        devarch: peripheral_id.arch_id(),
+        name: part.map(|part| part.name()),
+        peripheral_type: part.map(|part| part.peripheral_type().to_string()),
+        children: Vec::new(),
+        access_port: None,
+    }
+}
+
+/// Reads the PIDR0-7 and CIDR0-3 registers of a component.
+fn read_identification(
+    interface: &mut dyn ArmProbeInterface,
+    id: &ComponentId,
+    ap_address: &FullyQualifiedApAddress,
+) -> Result<(u64, u32)> {
+    // PIDR4-7, PIDR0-3 and CIDR0-3, with the identification value in the low byte of each.
+    let mut registers = [0u32; 12];
+    interface.memory_interface(ap_address)?.read_32(
+        id.component_address() + IDENTIFICATION_REGISTERS,
+        &mut registers,
+    )?;
+
+    let bytes = registers.map(|register| register as u8);
+    let pidr = u64::from_le_bytes([
+        bytes[4], bytes[5], bytes[6], bytes[7], bytes[0], bytes[1], bytes[2], bytes[3],
+    ]);
+    let cidr = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+
+    Ok((pidr, cidr))
+}
+
+fn component_class(component: &Component) -> &'static str {
+    match component {
+        Component::GenericVerificationComponent(_) => "Generic verification component",
+        Component::Class1RomTable(..) => "ROM Table (Class 0x1)",
+        Component::CoresightComponent(id) if id.peripheral_id().is_of_type(PeripheralType::Rom) => {
+            "ROM Table (Class 0x9)"
+        }
+        Component::CoresightComponent(_) => "CoreSight component",
+        Component::PeripheralTestBlock(_) => "Peripheral test block",
+        Component::GenericIPComponent(_) => "Generic IP component",
+        Component::CoreLinkOrPrimeCellOrSystemComponent(_) => {
+            "CoreLink / PrimeCell / System component"
+        }
+    }
+}
+
+fn debug_port_tree(debug_port: &DebugPortNode) -> Tree<String> {
+    let mut root = format!("Debug Port {}", debug_port.address);
+    if let Some(version) = &debug_port.version {
+        root.push_str(&format!(": {version}"));
+    }
+    if let Some(designer) = &debug_port.designer {
+        root.push_str(&format!(", Designer: {designer}"));
+    }
+    if let Some(dpidr) = debug_port.dpidr {
+        root.push_str(&format!(", DPIDR: {dpidr:#010x}"));
+    }
+    if let Some(targetid) = debug_port.targetid {
+        root.push_str(&format!(", TARGETID: {targetid:#010x}"));
+    }
+    if let Some(instance) = debug_port.instance {
+        root.push_str(&format!(", Instance: {instance:#04x}"));
+    }
+
+    let mut tree = Tree::new(root);
+    if let Some(error) = &debug_port.error {
+        tree.push(format!("Error: {error}"));
+    }
+    for access_port in &debug_port.access_ports {
+        tree.push(access_port_tree(access_port));
+    }
+
+    tree
+}
+
+fn access_port_tree(access_port: &AccessPortNode) -> Tree<String> {
+    let mut root = format!("AP {} {}", access_port.address, access_port.description);
+    if let Some(idr) = access_port.idr {
+        root.push_str(&format!(", IDR: {idr:#010x}"));
+    }
+
+    let mut tree = Tree::new(root);
+    if access_port.enabled == Some(false) {
+        tree.push(String::from(
+            "Memory accesses are disabled, DeviceEn is not set",
+        ));
+    }
+    if let Some(error) = &access_port.error {
+        tree.push(format!("Error: {error}"));
+    }
+    for component in &access_port.components {
+        tree.push(component_tree(component));
+    }
+
+    tree
+}
+
+fn component_tree(component: &ComponentNode) -> Tree<String> {
+    let name = match (component.name, &component.peripheral_type) {
+        (Some(name), Some(peripheral_type)) => format!("{name}, {peripheral_type}"),
+        _ => String::from("<unknown part>"),
+    };
+    let mut tree = Tree::new(format!(
+        "{:#010x} {} ({})",
+        component.address, name, component.class
+    ));
+
+    let designer = match (component.designer, &component.jep106) {
+        (designer, Some(jep106)) => format!(
+            "{} (JEP106 bank {}, id {:#04x})",
+            designer.unwrap_or("<unknown>"),
+            jep106.continuation + 1,
+            jep106.id
+        ),
+        (_, None) => String::from("<legacy>"),
+    };
+    tree.push(format!(
+        "Designer: {designer}, Part: {:#05x}, Revision: {}",
+        component.part, component.revision
+    ));
+    tree.push(format!(
+        "DEVTYPE: {:#04x}, DEVARCH: {:#06x}",
+        component.devtype, component.devarch
+    ));
+    if let (Some(pidr), Some(cidr)) = (component.pidr, component.cidr) {
+        tree.push(format!("PIDR: {pidr:#018x}, CIDR: {cidr:#010x}"));
+    }
+
+    if let Some(access_port) = &component.access_port {
+        tree.push(access_port_tree(access_port));
+    }
+    for child in &component.children {
+        tree.push(component_tree(child));
+    }
+
+    tree
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn component(address: u64, name: Option<&'static str>, part: u16) -> ComponentNode {
+        ComponentNode {
+            address,
+            class: "CoreSight component",
+            cidr: Some(0xB105_900D),
+            pidr: Some(0x04_000B_B000 | part as u64),
+            designer: Some("ARM Ltd"),
+            jep106: Some(Jep106 {
+                continuation: 4,
+                id: 0x3b,
+            }),
+            part,
+            revision: 0,
+            devtype: 0,
+            devarch: 0,
+            name,
+            peripheral_type: name.map(|_| String::from("SCS (System Control Space)")),
+            children: Vec::new(),
+            access_port: None,
+        }
+    }
+
+    fn topology() -> Vec<DebugPortNode> {
+        let mut rom_table = component(0xE00F_F000, Some("Cortex-M4 ROM"), 0x4C4);
+        rom_table.class = "ROM Table (Class 0x1)";
+        rom_table.cidr = Some(0xB105_100D);
+        rom_table.peripheral_type = Some(String::from("ROM"));
+        rom_table.children = vec![
+            component(0xE000_E000, Some("Cortex-M4 SCS"), 0x00C),
+            component(0xE004_2000, None, 0x9A8),
+        ];
+
+        vec![DebugPortNode {
+            address: dp_name(DpAddress::Default),
+            version: Some(String::from("DPv1")),
+            designer: Some(String::from("ARM Ltd")),
+            dpidr: Some(0x2BA0_1477),
+            access_ports: vec![
+                AccessPortNode {
+                    address: String::from("V1(0)"),
+                    idr: Some(0x2477_0011),
+                    description: String::from("MEM-AP (AmbaAhb3), Designer: ARM Ltd"),
+                    enabled: Some(true),
+                    components: vec![rom_table],
+                    ..Default::default()
+                },
+                AccessPortNode {
+                    address: String::from("V1(1)"),
+                    idr: Some(0x2477_0011),
+                    description: String::from("MEM-AP (AmbaAhb3), Designer: ARM Ltd"),
+                    enabled: Some(false),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }]
+    }
+
+    #[test]
+    fn render_topology_tree() {
+        let output = topology()
+            .iter()
+            .map(|debug_port| debug_port_tree(debug_port).to_string())
+            .collect::<String>();
+
+        insta::assert_snapshot!(output);
+    }
+
+    #[test]
+    fn render_topology_json() {
+        insta::assert_snapshot!(serde_json::to_string_pretty(&topology()).unwrap());
+    }
+}
@@ -566,7 +566,43 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         let parent_key: ObjectRef = arguments.variables_reference.into();
         let new_value = &arguments.value;
 
-        //TODO: Check for, and prevent SVD Peripheral/Register/Field values from being updated, until such time as we can do it safely.
+        // SVD registers and fields are written directly to the target.
+        if let Some(svd_cache) = target_core.core_data.core_peripherals.as_mut() {
+            if svd_cache
+                .svd_variable_cache
+                .get_variable_by_key(parent_key)
+                .is_some()
+            {
+                let result = svd_cache
+                    .set_value(&mut target_core.core, parent_key, &arguments.name, new_value)
+                    .and_then(|variable_key| {
+                        svd_cache
+                            .svd_variable_cache
+                            .get_variable_by_key(variable_key)
+                            .ok_or_else(|| {
+                                DebuggerError::Other(anyhow!(
+                                    "Written SVD variable {} not found. Please report this as a bug.",
+                                    arguments.name
+                                ))
+                            })
+                    });
+                let variable = match result {
+                    Ok(variable) => variable,
+                    Err(error) => {
+                        return self.send_response::<SetVariableResponseBody>(request, Err(&error))
+                    }
+                };
+
+                let (variables_reference, named_child_variables_cnt) =
+                    get_svd_variable_reference(variable, &svd_cache.svd_variable_cache);
+                response_body.variables_reference = Some(variables_reference.into());
+                response_body.named_variables = Some(named_child_variables_cnt);
+                response_body.type_ = variable.type_name();
+                response_body.value = variable.get_value(&mut target_core.core);
+
+                return self.send_response(request, Ok(Some(response_body)));
+            }
+        }
 
         match target_core
             .core_data
@@ -690,6 +726,9 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                     Err(&DebuggerError::Other(anyhow!("{}", error))),
                 );
             }
+            if let Some(svd_cache) = target_core.core_data.core_peripherals.as_mut() {
+                svd_cache.reset_written_once();
+            }
 
             // Ensure ebreak enters debug mode, this is necessary for soft breakpoints to work on architectures like RISC-V.
             target_core.core.debug_on_sw_breakpoint(true)?;
@@ -759,6 +798,9 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                     return self.show_error_message(&DebuggerError::Other(anyhow!("{}", error)));
                 }
             };
+            if let Some(svd_cache) = target_core.core_data.core_peripherals.as_mut() {
+                svd_cache.reset_written_once();
+            }
 
             // Ensure ebreak enters debug mode, this is necessary for soft breakpoints to work on architectures like RISC-V.
             target_core.core.debug_on_sw_breakpoint(true)?;
@@ -1382,7 +1424,14 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                             memory_reference: variable.memory_reference(),
                             indexed_variables: None,
                             named_variables: Some(named_child_variables_cnt),
-                            presentation_hint: None,
+                            presentation_hint: (!variable.is_writable()).then(|| {
+                                VariablePresentationHint {
+                                    attributes: Some(vec!["readOnly".to_string()]),
+                                    kind: None,
+                                    lazy: None,
+                                    visibility: None,
+                                }
+                            }),
                             type_: variable.type_name(),
                             value: {
                                 // The SVD cache is not automatically refreshed on every stack trace, and we only need to refresh the field values.
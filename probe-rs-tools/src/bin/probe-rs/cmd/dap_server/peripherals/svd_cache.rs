@@ -1,8 +1,10 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, sync::Arc};
 
 use probe_rs::MemoryInterface;
 use probe_rs_debug::{get_object_reference, DebugError, ObjectRef};
 
+use crate::util::svd::Register;
+
 /// VariableCache stores available `Variable`s, and provides methods to create and navigate the parent-child relationships of the Variables.
 #[derive(Debug, Clone, PartialEq)]
 pub struct SvdVariableCache {
@@ -166,22 +168,25 @@ impl Variable {
 
     /// Memory reference, compatible with DAP
     pub fn memory_reference(&self) -> Option<String> {
-        match self.variable_kind {
-            SvdVariable::SvdRegister {
-                address,
-                restricted_read: false,
-                ..
-            } => Some(format!("{:#010X}", address)),
-
-            SvdVariable::SvdField {
-                address,
-                restricted_read: false,
-                ..
-            } => Some(format!("{:#010X}", address)),
+        match &self.variable_kind {
+            SvdVariable::SvdRegister(register) | SvdVariable::SvdField { register, .. }
+                if !register.restricted_read =>
+            {
+                Some(format!("{:#010X}", register.address))
+            }
             _ => None,
         }
     }
 
+    /// `true` if the value of the variable can be changed, i.e. it is a writable register or field.
+    pub fn is_writable(&self) -> bool {
+        match &self.variable_kind {
+            SvdVariable::SvdRegister(register) => register.writable,
+            SvdVariable::SvdField { register, field } => register.fields[*field].writable,
+            _ => false,
+        }
+    }
+
     pub fn type_name(&self) -> Option<String> {
         self.variable_kind.type_name()
     }
@@ -197,27 +202,12 @@ impl Variable {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SvdVariable {
     Root,
-    /// Register with address
-    SvdRegister {
-        address: u64,
-        /// true if the register is write-only or  if a ride has side effects
-        restricted_read: bool,
-
-        /// Description of the register, used as type in DAP
-        description: Option<String>,
-
-        /// Size in bits of the register
-        size: u32,
-    },
-    /// Field with address
+    /// Register of a peripheral
+    SvdRegister(Arc<Register>),
+    /// Field of a register, `field` is the index of the field in [`Register::fields`]
     SvdField {
-        address: u64,
-        /// true if the register is write-only or  if a ride has side effects
-        restricted_read: bool,
-        bit_range_lower_bound: u32,
-        bit_range_upper_bound: u32,
-
-        description: Option<String>,
+        register: Arc<Register>,
+        field: usize,
     },
     /// Peripheral with peripheral base address
     SvdPeripheral {
@@ -239,84 +229,52 @@ impl SvdVariable {
                 description.as_ref().cloned().unwrap_or_default()
             }
 
-            SvdVariable::SvdRegister {
-                address,
-                restricted_read,
-                size,
-                ..
-            } => {
-                if *restricted_read {
+            SvdVariable::SvdRegister(register) => {
+                if register.restricted_read {
                     format!(
                         "Register cannot be read without side effects @ {:#010X}",
-                        address
+                        register.address
                     )
                 } else {
-                    let size_bytes = (*size / 8).max(1);
-                    let bits_alignment_offset = (*address % size_bytes as u64) as u32 * 8;
-                    let value = match *size {
-                        0..=8 => memory
-                            .read_word_8(*address)
-                            .map(|val| format!("{val:#04X}")),
-                        9..=16 => {
-                            let aligned_address = *address & !0b1;
-                            memory
-                                .read_word_16(aligned_address)
-                                .map(|val| format!("{:#06X}", val >> bits_alignment_offset))
-                        }
-                        _ => {
-                            let aligned_address = *address & !0b11;
-                            memory
-                                .read_word_32(aligned_address)
-                                .map(|val| format!("{:#010X}", val >> bits_alignment_offset))
-                        }
-                    };
-
-                    match value {
-                        Ok(value) => value,
+                    match register.read(memory) {
+                        Ok(value) => format!(
+                            "{value:#0width$X}",
+                            width = register.size.div_ceil(4) as usize + 2
+                        ),
                         Err(error) => format!(
                             "Unable to read peripheral register value @ {:#010X} : {:?}",
-                            address, error
+                            register.address, error
                         ),
                     }
                 }
             }
-            SvdVariable::SvdField {
-                address,
-                restricted_read,
-                bit_range_lower_bound,
-                bit_range_upper_bound,
-                ..
-            } => {
-                if *restricted_read {
+            SvdVariable::SvdField { register, field } => {
+                let field = &register.fields[*field];
+                if field.restricted_read || register.restricted_read {
                     format!(
                         "Field cannot be read without side effects @ {:#010X}",
-                        address
+                        register.address
                     )
                 } else {
-                    let value = match *bit_range_upper_bound {
-                        0..8 => memory.read_word_8(*address).map(u32::from),
-                        8..16 => memory.read_word_16(*address & !0b1).map(u32::from),
-                        _ => memory.read_word_32(*address & !0b11),
-                    };
-
-                    // In this special case, we extract just the bits we need from the stored value of the register.
-                    match value {
-                        Ok(register_u32_value) => {
-                            let mut bit_value: u32 = register_u32_value;
-                            bit_value <<= 32 - bit_range_upper_bound;
-                            bit_value >>= 32 - (bit_range_upper_bound - bit_range_lower_bound);
-                            format!(
+                    match register.read(memory) {
+                        Ok(register_value) => {
+                            let bit_value = field.extract(register_value);
+                            let mut value = format!(
                                 "{:0width$b} @ {:#010X}:{}..{}",
                                 bit_value,
-                                address,
-                                bit_range_lower_bound,
-                                bit_range_upper_bound,
-                                width = (*bit_range_upper_bound - *bit_range_lower_bound) as usize
-                            )
+                                register.address,
+                                field.bit_offset,
+                                field.bit_offset + field.bit_width,
+                                width = field.bit_width as usize
+                            );
+                            if let Some(enumerated_value) = field.enumerated_value(bit_value) {
+                                value.push_str(&format!(" ({})", enumerated_value.name));
+                            }
+                            value
                         }
                         Err(error) => format!(
                             "Unable to read peripheral register field value @ {:#010X} : {:?}",
-                            address, error
+                            register.address, error
                         ),
                     }
                 }
@@ -326,8 +284,10 @@ impl SvdVariable {
 
     fn type_name(&self) -> Option<String> {
         match &self {
-            SvdVariable::SvdRegister { description, .. }
-            | SvdVariable::SvdField { description, .. } => description.clone(),
+            SvdVariable::SvdRegister(register) => register.description.clone(),
+            SvdVariable::SvdField { register, field } => {
+                register.fields[*field].description.clone()
+            }
             SvdVariable::SvdPeripheral { .. } => Some("Peripheral".to_string()),
             SvdVariable::SvdPeripheralGroup { .. } => Some("Peripheral Group".to_string()),
             SvdVariable::Root => None,
@@ -1,9 +1,14 @@
-use crate::cmd::dap_server::{
-    debug_adapter::{dap::adapter::DebugAdapter, protocol::ProtocolAdapter},
-    DebuggerError,
+use crate::{
+    cmd::dap_server::{
+        debug_adapter::{dap::adapter::DebugAdapter, protocol::ProtocolAdapter},
+        DebuggerError,
+    },
+    util::svd::SvdDevice,
 };
-use std::{fmt::Debug, fs::File, io::Read, path::Path};
-use svd_parser::Config;
+use anyhow::anyhow;
+use probe_rs::MemoryInterface;
+use probe_rs_debug::ObjectRef;
+use std::{collections::HashSet, fmt::Debug, path::Path, sync::Arc};
 
 use super::svd_cache::{SvdVariable, SvdVariableCache};
 
@@ -15,6 +20,8 @@ pub struct SvdCache {
     /// After that, only the SVD fields values change values, and the data for these will be re-read
     /// every time they are queried by the debugger.
     pub(crate) svd_variable_cache: SvdVariableCache,
+    /// Addresses of the write-once registers written since the last reset.
+    written_once: HashSet<u64>,
 }
 
 impl SvdCache {
@@ -24,21 +31,12 @@ impl SvdCache {
         debug_adapter: &mut DebugAdapter<P>,
         dap_request_id: i64,
     ) -> Result<Self, DebuggerError> {
-        let svd_xml = &mut String::new();
-
-        let mut svd_opened_file = File::open(svd_file)?;
-
         let progress_id = debug_adapter.start_progress(
             format!("Loading SVD file: {}", svd_file.display()).as_str(),
             Some(dap_request_id),
         )?;
 
-        let _ = svd_opened_file.read_to_string(svd_xml)?;
-
-        let svd_cache = match svd_parser::parse_with_config(
-            svd_xml,
-            &Config::default().expand(true).ignore_enums(true),
-        ) {
+        let svd_cache = match SvdDevice::load(svd_file) {
             Ok(peripheral_device) => {
                 debug_adapter
                     .update_progress(
@@ -54,32 +52,99 @@ impl SvdCache {
                         debug_adapter,
                         progress_id,
                     )?,
+                    written_once: HashSet::new(),
                 })
             }
-            Err(error) => Err(DebuggerError::Other(anyhow::anyhow!(
-                "Unable to parse CMSIS-SVD file: {:?}. {:?}",
-                svd_file,
-                error,
-            ))),
+            Err(error) => Err(DebuggerError::Other(error)),
         };
         debug_adapter.end_progress(progress_id)?;
 
         svd_cache
     }
+
+    /// Forgets the writes to write-once registers, which can be written again after the target
+    /// has been reset.
+    pub(crate) fn reset_written_once(&mut self) {
+        self.written_once.clear();
+    }
+
+    /// Writes `value` to the register or field variable named `name` (the last part of the
+    /// variable name) below `parent_key`, and returns the key of the variable.
+    ///
+    /// Fields are written with a read-modify-write of their register. Registers which contain
+    /// write-once registers or fields are only written once per reset.
+    pub(crate) fn set_value(
+        &mut self,
+        memory: &mut dyn MemoryInterface,
+        parent_key: ObjectRef,
+        name: &str,
+        value: &str,
+    ) -> Result<ObjectRef, DebuggerError> {
+        let variable = self
+            .svd_variable_cache
+            .get_children(parent_key)
+            .into_iter()
+            .find(|variable| variable.name().rsplit('.').next() == Some(name))
+            .ok_or_else(|| anyhow!("No SVD register or field named {name}"))?;
+
+        let value = value.trim();
+        let register = match &variable.variable_kind {
+            SvdVariable::SvdRegister(register) => register,
+            SvdVariable::SvdField { register, .. } => register,
+            _ => {
+                return Err(DebuggerError::UserMessage(format!(
+                    "{} is not a register or field, only registers and fields can be changed.",
+                    variable.name()
+                )))
+            }
+        };
+
+        if !variable.is_writable() {
+            return Err(DebuggerError::UserMessage(format!(
+                "{} is read-only.",
+                variable.name()
+            )));
+        }
+
+        let write_once = register.write_once || register.fields.iter().any(|f| f.write_once);
+        if write_once && self.written_once.contains(&register.address) {
+            return Err(DebuggerError::UserMessage(format!(
+                "{} can only be written once after reset, and has already been written since the last reset.",
+                variable.name()
+            )));
+        }
+
+        match &variable.variable_kind {
+            SvdVariable::SvdField { register, field } => {
+                let field = &register.fields[*field];
+                let value = field.parse_value(value)?;
+                register.write_field(memory, field, value)?;
+            }
+            _ => {
+                let value = parse_int::parse::<u64>(value)
+                    .map_err(|error| anyhow!("Invalid register value {value:?}: {error}"))?;
+                register.write(memory, value)?;
+            }
+        }
+
+        if write_once {
+            self.written_once.insert(register.address);
+        }
+
+        Ok(variable.variable_key())
+    }
 }
 
 /// Create a [`SvdVariableCache`] from a Device that was parsed from a CMSIS-SVD file.
 #[tracing::instrument(skip_all)]
 pub(crate) fn variable_cache_from_svd<P: ProtocolAdapter>(
-    peripheral_device: svd_parser::svd::Device,
+    peripheral_device: SvdDevice,
     debug_adapter: &mut DebugAdapter<P>,
     progress_id: i64,
 ) -> Result<SvdVariableCache, DebuggerError> {
     let mut svd_cache = SvdVariableCache::new_svd_cache();
     let device_root_variable_key = svd_cache.root_variable_key();
 
-    let device_default_access = peripheral_device.default_register_properties.access;
-
     for peripheral in &peripheral_device.peripherals {
         let current_peripheral_group_name = peripheral.group_name.as_ref();
 
@@ -134,61 +199,25 @@ pub(crate) fn variable_cache_from_svd<P: ProtocolAdapter>(
             },
         )?;
 
-        for register in peripheral.all_registers() {
-            let register_address = peripheral.base_address + register.address_offset as u64;
-
-            let mut register_has_restricted_read = register.read_action.is_some()
-                || register
-                    .properties
-                    .access
-                    .map(|a| !a.can_read())
-                    .or_else(|| device_default_access.map(|a| !a.can_read()))
-                    .unwrap_or(true);
-
+        for register in &peripheral.registers {
             let register_name = format!("{}.{}", &peripheral_name, register.name);
-
-            let mut field_variables = Vec::new();
-
-            for field in register.fields() {
-                let field_has_restricted_read = register_has_restricted_read
-                    || field.read_action.is_some()
-                    || field
-                        .access
-                        .map(|a| !a.can_read())
-                        .or_else(|| device_default_access.map(|a| !a.can_read()))
-                        .unwrap_or(register_has_restricted_read);
-
-                let field_variable = (
-                    format!("{}.{}", register_name, field.name),
-                    SvdVariable::SvdField {
-                        address: register_address,
-                        restricted_read: field_has_restricted_read,
-                        bit_range_lower_bound: field.bit_offset(),
-                        bit_range_upper_bound: (field.bit_offset() + field.bit_width()),
-                        description: field.description.clone(),
-                    },
-                );
-
-                // If any of the fields in the register have restricted read, then the register has restricted read.
-                register_has_restricted_read |= field_has_restricted_read;
-
-                field_variables.push(field_variable);
-            }
+            let register = Arc::new(register.clone());
 
             let register_variable_key = svd_cache.add_variable(
                 peripheral_key,
-                format!("{}.{}", &peripheral_name, register.name),
-                SvdVariable::SvdRegister {
-                    address: register_address,
-                    restricted_read: register_has_restricted_read,
-                    description: register.description.clone(),
-                    size: register.properties.size.unwrap_or(32),
-                },
+                register_name.clone(),
+                SvdVariable::SvdRegister(register.clone()),
             )?;
 
-            for (variable_name, variable) in field_variables {
-                // TODO: Extend the Variable definition, so that we can resolve the EnumeratedValues for fields.
-                svd_cache.add_variable(register_variable_key, variable_name, variable)?;
+            for (field_index, field) in register.fields.iter().enumerate() {
+                svd_cache.add_variable(
+                    register_variable_key,
+                    format!("{}.{}", register_name, field.name),
+                    SvdVariable::SvdField {
+                        register: register.clone(),
+                        field: field_index,
+                    },
+                )?;
             }
         }
     }
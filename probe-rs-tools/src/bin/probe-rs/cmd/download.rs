@@ -2,10 +2,12 @@ use std::path::PathBuf;
 
 use probe_rs::probe::list::Lister;
 
+use crate::util::bundle;
 use crate::util::common_options::BinaryDownloadOptions;
-use crate::util::common_options::ProbeOptions;
-use crate::util::flash::build_loader;
+use crate::util::common_options::{FlashAlgorithmOptions, ProbeOptions};
 use crate::util::flash::run_flash_download;
+use crate::util::flash::{build_bundle_loader, build_loader};
+use crate::util::patch::PatchOptions;
 use crate::FormatOptions;
 
 #[derive(clap::Parser)]
@@ -14,6 +16,9 @@ pub struct Cmd {
     pub probe_options: ProbeOptions,
 
     /// The path to the file to be downloaded to the flash
+    ///
+    /// A `.toml` file is read as a bundle manifest listing several images,
+    /// which are all flashed in one pass.
     pub path: PathBuf,
 
     /// Whether to erase the entire chip before downloading
@@ -25,13 +30,31 @@ pub struct Cmd {
 
     #[clap(flatten)]
     pub format_options: FormatOptions,
+
+    #[clap(flatten)]
+    pub patch_options: PatchOptions,
+
+    #[clap(flatten)]
+    pub flash_algorithm_options: FlashAlgorithmOptions,
 }
 
 impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
-        let (mut session, probe_options) = self.probe_options.simple_attach(lister)?;
+        let (mut session, probe_options) = self
+            .probe_options
+            .simple_attach_with_flash_algorithm(lister, &self.flash_algorithm_options)?;
+
+        let is_bundle = bundle::is_manifest(&self.path);
+        let patches = self
+            .patch_options
+            .resolve((!is_bundle).then_some(self.path.as_path()))?;
 
-        let loader = build_loader(&mut session, &self.path, self.format_options, None)?;
+        let mut loader = if is_bundle {
+            build_bundle_loader(&mut session, &self.path)?
+        } else {
+            build_loader(&mut session, &self.path, self.format_options, None)?
+        };
+        patches.apply(&mut loader)?;
         run_flash_download(
             &mut session,
             &self.path,
@@ -41,6 +64,10 @@ impl Cmd {
             self.chip_erase,
         )?;
 
+        if !probe_options.dry_run() {
+            patches.increment_counters()?;
+        }
+
         Ok(())
     }
 }
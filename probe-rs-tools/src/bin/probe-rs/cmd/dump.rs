@@ -0,0 +1,242 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use probe_rs::config::MemoryRegion;
+use probe_rs::{probe::list::Lister, MemoryInterface, Session};
+
+use crate::util::common_options::ProbeOptions;
+use crate::util::image::{self, Segment};
+use crate::util::parse_range;
+use crate::CoreOptions;
+
+/// Memory is read in blocks of this size.
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Read target memory into an image file
+///
+/// e.g. probe-rs dump --chip nRF52840_xxAA backup.hex
+///      Reads all non-volatile memory regions into an Intel HEX file
+///
+/// e.g. probe-rs dump --chip STM32F401RETx --range 0x08000000..0x08004000 boot.bin
+///      Reads the first 16 KiB of flash into a raw binary
+///
+/// ELF images contain one loadable segment per region and can be flashed
+/// with `probe-rs download`, e.g. to clone a device.
+#[derive(clap::Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    probe_options: ProbeOptions,
+
+    /// The image file to write
+    output: PathBuf,
+
+    /// The image format, guessed from the file extension by default
+    #[clap(long, value_enum)]
+    format: Option<DumpFormat>,
+
+    /// Names of memory regions to read, defaults to all non-volatile memory regions
+    #[clap(long = "region")]
+    regions: Vec<String>,
+
+    /// Address ranges to read, like `0x08000000..0x08004000`
+    #[clap(long = "range", value_parser = parse_range)]
+    ranges: Vec<Range<u64>>,
+
+    /// Leave erased (0xFF) memory out of the image, in 16 byte blocks
+    ///
+    /// Not supported by the binary format, which fills gaps with 0xFF.
+    #[clap(long)]
+    skip_erased: bool,
+}
+
+/// Image formats of the dump command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+enum DumpFormat {
+    /// Intel HEX
+    Hex,
+    /// Raw bytes, starting at the lowest address
+    Bin,
+    /// ELF file with one loadable segment per region
+    Elf,
+}
+
+impl DumpFormat {
+    fn from_extension(path: &std::path::Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "hex" | "ihex" => Some(Self::Hex),
+            "bin" => Some(Self::Bin),
+            "elf" | "axf" | "out" => Some(Self::Elf),
+            _ => None,
+        }
+    }
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> Result<()> {
+        let Some(format) = self
+            .format
+            .or_else(|| DumpFormat::from_extension(&self.output))
+        else {
+            bail!(
+                "Unable to determine the image format of {}, use `--format`",
+                self.output.display()
+            );
+        };
+        if format == DumpFormat::Bin && self.skip_erased {
+            bail!("`--skip-erased` is not supported by the binary format");
+        }
+
+        let (mut session, _probe_options) = self.probe_options.simple_attach(lister)?;
+
+        let regions = select_regions(&session, &self.regions, &self.ranges)?;
+        let mut segments = Vec::new();
+        for (name, range) in regions {
+            println!(
+                "Reading {name} ({:#010x}..{:#010x})",
+                range.start, range.end
+            );
+            let segment = read_segment(&mut session, self.shared.core, name, range)?;
+
+            if self.skip_erased {
+                segments.extend(segment.without_erased());
+            } else {
+                segments.push(segment);
+            }
+        }
+        segments.sort_by_key(|segment| segment.address);
+
+        let core_type = session.target().cores[0].core_type;
+        session.resume_all_cores()?;
+
+        let output = match format {
+            DumpFormat::Hex => image::ihex(&segments)?.into_bytes(),
+            DumpFormat::Bin => image::binary(&segments)?,
+            DumpFormat::Elf => image::elf(&segments, core_type)?,
+        };
+        std::fs::write(&self.output, &output)
+            .with_context(|| format!("Failed to write {}", self.output.display()))?;
+
+        let size = segments
+            .iter()
+            .map(|segment| segment.data.len())
+            .sum::<usize>();
+        println!("Wrote {size} bytes to {}", self.output.display());
+
+        Ok(())
+    }
+}
+
+/// Returns the names and address ranges to read.
+fn select_regions(
+    session: &Session,
+    names: &[String],
+    ranges: &[Range<u64>],
+) -> Result<Vec<(String, Range<u64>)>> {
+    let memory_map = &session.target().memory_map;
+
+    if names.is_empty() && ranges.is_empty() {
+        let regions = memory_map
+            .iter()
+            .filter_map(MemoryRegion::as_nvm_region)
+            .filter(|region| !region.is_alias && region.is_readable())
+            .map(|region| {
+                (
+                    region_name(region.name.as_deref(), &region.range),
+                    region.range.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if regions.is_empty() {
+            bail!(
+                "{} has no non-volatile memory regions",
+                session.target().name
+            );
+        }
+
+        return Ok(regions);
+    }
+
+    let mut selected = Vec::new();
+    for name in names {
+        let region = memory_map.iter().find(|region| {
+            let region_name = match region {
+                MemoryRegion::Ram(region) => region.name.as_deref(),
+                MemoryRegion::Generic(region) => region.name.as_deref(),
+                MemoryRegion::Nvm(region) => region.name.as_deref(),
+            };
+            region_name.is_some_and(|region_name| region_name.eq_ignore_ascii_case(name))
+        });
+
+        let Some(region) = region else {
+            bail!(
+                "{} has no memory region named `{name}`",
+                session.target().name
+            );
+        };
+        selected.push((name.clone(), region.address_range()));
+    }
+
+    for range in ranges {
+        selected.push((region_name(None, range), range.clone()));
+    }
+
+    Ok(selected)
+}
+
+fn region_name(name: Option<&str>, range: &Range<u64>) -> String {
+    match name {
+        Some(name) => name.to_string(),
+        None => format!("{:#010x}", range.start),
+    }
+}
+
+fn read_segment(
+    session: &mut Session,
+    default_core: usize,
+    name: String,
+    range: Range<u64>,
+) -> Result<Segment> {
+    let core_index = session
+        .target()
+        .core_index_by_address(range.start)
+        .unwrap_or(default_core);
+    let mut core = session.core(core_index)?;
+
+    let mut data = vec![0; (range.end - range.start) as usize];
+    for (index, chunk) in data.chunks_mut(READ_CHUNK_SIZE).enumerate() {
+        let address = range.start + (index * READ_CHUNK_SIZE) as u64;
+        core.read(address, chunk)
+            .with_context(|| format!("Failed to read memory at {address:#010x}"))?;
+    }
+
+    Ok(Segment {
+        name,
+        address: range.start,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(
+            DumpFormat::from_extension("backup.HEX".as_ref()),
+            Some(DumpFormat::Hex)
+        );
+        assert_eq!(
+            DumpFormat::from_extension("clone.elf".as_ref()),
+            Some(DumpFormat::Elf)
+        );
+        assert_eq!(DumpFormat::from_extension("dump".as_ref()), None);
+    }
+}
@@ -0,0 +1,368 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use probe_rs::flashing::{DownloadOptions, FlashProgress, ProgressEvent};
+use probe_rs::probe::{list::Lister, DebugProbeInfo, DebugProbeSelector};
+use serde::Serialize;
+
+use crate::util::common_options::{LoadedProbeOptions, ProbeOptions};
+use crate::util::flash::build_loader;
+use crate::FormatOptions;
+
+/// Flash and verify the same image through several probes in parallel
+///
+/// e.g. probe-rs gang --chip STM32F401RETx --probe 0483:374b firmware.elf
+///      Flashes the targets of all connected ST-Link/V2-1 probes
+///
+/// e.g. probe-rs gang --chip nRF52840_xxAA --serial 000683 --serial 000684 firmware.elf
+///      Flashes the targets of the probes with the given serial numbers
+///
+/// Without `--probe` and `--serial`, all connected probes are used.
+#[derive(clap::Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct Cmd {
+    #[clap(flatten)]
+    probe_options: ProbeOptions,
+
+    /// The path to the file to be downloaded to the flash
+    pub path: PathBuf,
+
+    /// Serial numbers of the probes to use
+    #[clap(long = "serial")]
+    serials: Vec<String>,
+
+    /// Whether to erase the entire chip before downloading
+    #[clap(long)]
+    chip_erase: bool,
+
+    /// Writes the results as JSON to the given file
+    #[clap(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    #[clap(flatten)]
+    format_options: FormatOptions,
+}
+
+/// The result of flashing through one probe.
+#[derive(Debug, Serialize)]
+struct ProbeReport {
+    probe: String,
+    serial_number: Option<String>,
+    passed: bool,
+    /// The duration in seconds.
+    duration: f64,
+    error: Option<String>,
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> Result<()> {
+        let probe_options = self.probe_options.load()?;
+        let target = probe_options.get_target_selector()?;
+
+        let probes = select_probes(
+            lister.list_all(),
+            probe_options.as_ref().probe.as_ref(),
+            &self.serials,
+        )?;
+
+        let multi_progress = MultiProgress::new();
+        let reports = std::thread::scope(|scope| {
+            let threads = probes
+                .iter()
+                .map(|info| {
+                    let progress = multi_progress.add(ProgressBar::new(0));
+                    progress.set_style(progress_style());
+                    progress.set_prefix(probe_name(info));
+                    progress.set_message("Attaching");
+
+                    let target = target.clone();
+                    let probe_options = &probe_options;
+                    let format_options = self.format_options.clone();
+                    let path = &self.path;
+                    let chip_erase = self.chip_erase;
+
+                    scope.spawn(move || {
+                        let start = Instant::now();
+                        let result = flash(
+                            info,
+                            probe_options,
+                            target,
+                            path,
+                            format_options,
+                            chip_erase,
+                            &progress,
+                        );
+
+                        match &result {
+                            Ok(()) => progress.finish_with_message("Passed".green().to_string()),
+                            Err(_) => progress.abandon_with_message("Failed".red().to_string()),
+                        }
+
+                        report(info, start.elapsed(), result)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            threads
+                .into_iter()
+                .zip(&probes)
+                .map(|(thread, info)| {
+                    thread.join().unwrap_or_else(|_| {
+                        report(
+                            info,
+                            Duration::ZERO,
+                            Err(anyhow::anyhow!("The flashing thread panicked")),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        println!();
+        print!("{}", format_table(&reports));
+
+        if let Some(path) = &self.report {
+            std::fs::write(path, serde_json::to_string_pretty(&reports)?)
+                .with_context(|| format!("Failed to write the report to {}", path.display()))?;
+        }
+
+        let failed = reports.iter().filter(|report| !report.passed).count();
+        if failed > 0 {
+            bail!("Flashing failed on {failed} of {} probes", reports.len());
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the probes matching the selector and serial numbers, or all probes if neither is given.
+fn select_probes(
+    probes: Vec<DebugProbeInfo>,
+    selector: Option<&DebugProbeSelector>,
+    serials: &[String],
+) -> Result<Vec<DebugProbeInfo>> {
+    for serial in serials {
+        if !probes
+            .iter()
+            .any(|probe| probe.serial_number.as_ref() == Some(serial))
+        {
+            bail!("No probe with the serial number {serial} was found");
+        }
+    }
+
+    let selected = probes
+        .into_iter()
+        .filter(|probe| selector.map_or(true, |selector| selector.matches(probe)))
+        .filter(|probe| {
+            serials.is_empty()
+                || probe
+                    .serial_number
+                    .as_ref()
+                    .is_some_and(|serial| serials.contains(serial))
+        })
+        .collect::<Vec<_>>();
+
+    if selected.is_empty() {
+        bail!("No matching probes were found");
+    }
+
+    Ok(selected)
+}
+
+fn flash(
+    info: &DebugProbeInfo,
+    probe_options: &LoadedProbeOptions,
+    target: probe_rs::config::TargetSelector,
+    path: &Path,
+    format_options: FormatOptions,
+    chip_erase: bool,
+    progress: &ProgressBar,
+) -> Result<()> {
+    let mut probe = info.open()?;
+    probe_options.configure_probe(&mut probe)?;
+    let mut session = probe_options.attach_session(probe, target)?;
+
+    let loader = build_loader(&mut session, path, format_options, None)?;
+
+    let mut options = DownloadOptions::default();
+    options.dry_run = probe_options.dry_run();
+    options.do_chip_erase = chip_erase;
+    options.verify = true;
+
+    let progress = progress.clone();
+    options.progress = Some(FlashProgress::new(move |event| match event {
+        ProgressEvent::Initialized {
+            chip_erase, phases, ..
+        } => {
+            if !chip_erase {
+                let length = phases
+                    .iter()
+                    .flat_map(|phase| phase.sectors())
+                    .map(|sector| sector.size())
+                    .sum();
+                progress.set_length(length);
+            }
+        }
+        ProgressEvent::StartedErasing => progress.set_message("Erasing"),
+        ProgressEvent::SectorErased { size, .. } => progress.inc(size),
+        ProgressEvent::StartedProgramming { length } => {
+            progress.set_message("Programming");
+            progress.set_position(0);
+            progress.set_length(length);
+        }
+        ProgressEvent::PageProgrammed { size, .. } => progress.inc(size as u64),
+        ProgressEvent::FinishedProgramming => progress.set_message("Verifying"),
+        _ => {}
+    }));
+
+    loader.commit(&mut session, options)?;
+
+    Ok(())
+}
+
+fn report(info: &DebugProbeInfo, duration: Duration, result: Result<()>) -> ProbeReport {
+    ProbeReport {
+        probe: probe_name(info),
+        serial_number: info.serial_number.clone(),
+        passed: result.is_ok(),
+        duration: duration.as_secs_f64(),
+        error: result.err().map(|error| format!("{error:#}")),
+    }
+}
+
+fn probe_name(info: &DebugProbeInfo) -> String {
+    format!(
+        "{} ({})",
+        info.identifier,
+        info.serial_number.as_deref().unwrap_or("no serial number")
+    )
+}
+
+fn progress_style() -> ProgressStyle {
+    ProgressStyle::with_template("{prefix:.bold} {msg:12} [{bar:30}] {percent:>3}%")
+        .expect("Error in progress bar creation. This is a bug, please report it.")
+        .progress_chars("##-")
+}
+
+/// Formats the results as a table with one row per probe.
+fn format_table(reports: &[ProbeReport]) -> String {
+    let width = reports
+        .iter()
+        .map(|report| report.probe.len())
+        .chain(["Probe".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut table = format!("{:width$}  Result  Time\n", "Probe");
+    for report in reports {
+        let result = if report.passed { "passed" } else { "failed" };
+        table.push_str(&format!(
+            "{:width$}  {result:6}  {:.2}s",
+            report.probe, report.duration
+        ));
+        if let Some(error) = &report.error {
+            table.push_str(&format!("  {error}"));
+        }
+        table.push('\n');
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs::probe::stlink::StLinkFactory;
+
+    use super::*;
+
+    fn probe(product_id: u16, serial: &str) -> DebugProbeInfo {
+        DebugProbeInfo::new(
+            "STLink",
+            0x0483,
+            product_id,
+            Some(serial.to_string()),
+            &StLinkFactory,
+            None,
+        )
+    }
+
+    fn probes() -> Vec<DebugProbeInfo> {
+        vec![
+            probe(0x374b, "0670FF"),
+            probe(0x374b, "066DFF"),
+            probe(0x3748, "0A1B2C"),
+        ]
+    }
+
+    fn serials(probes: &[DebugProbeInfo]) -> Vec<&str> {
+        probes
+            .iter()
+            .filter_map(|probe| probe.serial_number.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn select_all_probes() {
+        let selected = select_probes(probes(), None, &[]).unwrap();
+
+        assert_eq!(serials(&selected), ["0670FF", "066DFF", "0A1B2C"]);
+    }
+
+    #[test]
+    fn select_probes_by_selector() {
+        let selector = DebugProbeSelector::try_from("0483:374b").unwrap();
+
+        let selected = select_probes(probes(), Some(&selector), &[]).unwrap();
+
+        assert_eq!(serials(&selected), ["0670FF", "066DFF"]);
+    }
+
+    #[test]
+    fn select_probes_by_duplicate_serials() {
+        let serial_numbers = ["066DFF".to_string(), "066DFF".to_string()];
+
+        let selected = select_probes(probes(), None, &serial_numbers).unwrap();
+
+        assert_eq!(serials(&selected), ["066DFF"]);
+    }
+
+    #[test]
+    fn select_missing_probes() {
+        let missing = ["123456".to_string()];
+        assert!(select_probes(probes(), None, &missing).is_err());
+
+        let selector = DebugProbeSelector::try_from("1366:0101").unwrap();
+        assert!(select_probes(probes(), Some(&selector), &[]).is_err());
+
+        assert!(select_probes(vec![], None, &[]).is_err());
+    }
+
+    fn report(probe: &str, error: Option<&str>) -> ProbeReport {
+        ProbeReport {
+            probe: probe.to_string(),
+            serial_number: None,
+            passed: error.is_none(),
+            duration: 1.5,
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn result_table() {
+        assert_eq!(
+            format_table(&[
+                report("STLink V2-1 (0670FF)", None),
+                report(
+                    "STLink V2-1 (066DFF)",
+                    Some("Connecting to the chip was unsuccessful.")
+                ),
+            ]),
+            "Probe                 Result  Time\n\
+             STLink V2-1 (0670FF)  passed  1.50s\n\
+             STLink V2-1 (066DFF)  failed  1.50s  Connecting to the chip was unsuccessful.\n"
+        );
+    }
+}
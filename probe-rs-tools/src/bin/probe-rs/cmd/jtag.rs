@@ -0,0 +1,287 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use jep106::JEP106Code;
+use probe_rs::probe::{
+    boundary_scan::{BoundaryScan, Bsdl, PinState},
+    list::Lister,
+    svf::{play_svf, play_xsvf},
+    IdCode, JtagChainItem, Probe, WireProtocol,
+};
+
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+#[derive(clap::Subcommand)]
+/// Low-level access to the JTAG scan chain
+enum Subcommand {
+    /// Discovers the TAPs on the JTAG scan chain, with their IDCODEs and IR lengths
+    #[clap(name = "scan")]
+    Scan {
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+
+    /// Plays an SVF or XSVF file on the JTAG scan chain, e.g. to program a CPLD or FPGA
+    #[clap(name = "svf")]
+    Svf {
+        /// The file to play. Files with the `.xsvf` extension are played as XSVF.
+        file: PathBuf,
+
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+
+    /// Reads and drives the pins of a device with boundary scan, described by its BSDL file
+    ///
+    /// Without `--drive`, the device keeps running and its pins are sampled. Driving a pin
+    /// switches the device to EXTEST, where all other outputs take their safe values.
+    #[clap(name = "boundary", verbatim_doc_comment)]
+    Boundary {
+        /// The BSDL file of the device.
+        bsdl: PathBuf,
+
+        /// The TAP of the device. By default, the TAP is found by the IDCODE in the BSDL file.
+        #[clap(long)]
+        tap: Option<usize>,
+
+        /// Drives a pin, by port name or package pin, to 0, 1 or z (high impedance).
+        #[clap(long, value_name = "PIN=STATE", value_parser = parse_drive)]
+        drive: Vec<(String, PinState)>,
+
+        /// Reads a pin, by port name or package pin. By default, all readable pins are read.
+        #[clap(long, value_name = "PIN")]
+        read: Vec<String>,
+
+        /// Keeps driving the pins after the command exits, instead of resetting the TAPs.
+        #[clap(long)]
+        hold: bool,
+
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        match self.subcommand {
+            Subcommand::Scan { common } => scan(common, lister),
+            Subcommand::Svf { file, common } => svf(&file, common, lister),
+            Subcommand::Boundary {
+                bsdl,
+                tap,
+                drive,
+                read,
+                hold,
+                common,
+            } => boundary(&bsdl, tap, &drive, &read, hold, common, lister),
+        }
+    }
+}
+
+/// Opens the probe, and attaches to the JTAG chain without selecting a target.
+fn attach_jtag(common: ProbeOptions, lister: &Lister) -> anyhow::Result<Probe> {
+    let probe_options = common.load()?;
+    if probe_options.protocol() == Some(WireProtocol::Swd) {
+        bail!("Accessing the JTAG chain requires the JTAG protocol");
+    }
+
+    let mut probe = probe_options.attach_probe(lister)?;
+    probe
+        .select_protocol(WireProtocol::Jtag)
+        .context("The probe does not support JTAG")?;
+    probe.attach_to_unspecified()?;
+
+    Ok(probe)
+}
+
+fn scan(common: ProbeOptions, lister: &Lister) -> anyhow::Result<()> {
+    let mut probe = attach_jtag(common, lister)?;
+
+    let chain = probe
+        .discover_jtag_chain()
+        .context("Failed to scan the JTAG chain")?;
+
+    print_chain(std::io::stdout().lock(), &chain)
+}
+
+fn svf(file: &Path, common: ProbeOptions, lister: &Lister) -> anyhow::Result<()> {
+    let contents =
+        std::fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let is_xsvf = file
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("xsvf"));
+
+    let mut probe = attach_jtag(common, lister)?;
+    let jtag = probe
+        .try_as_jtag_access()
+        .context("The probe does not offer low-level JTAG access")?;
+
+    let summary = if is_xsvf {
+        play_xsvf(jtag, &contents)
+    } else {
+        let source = String::from_utf8(contents).context("The SVF file is not valid UTF-8")?;
+        play_svf(jtag, &source)
+    }
+    .with_context(|| format!("Failed to play {}", file.display()))?;
+
+    println!(
+        "Played {} scans, {} TDO checks passed",
+        summary.scans, summary.tdo_checks
+    );
+
+    Ok(())
+}
+
+fn parse_drive(argument: &str) -> Result<(String, PinState), String> {
+    let Some((pin, state)) = argument.split_once('=') else {
+        return Err(String::from("expected PIN=STATE"));
+    };
+
+    let state = match state.trim() {
+        "0" => PinState::Low,
+        "1" => PinState::High,
+        "z" | "Z" => PinState::HighZ,
+        state => return Err(format!("invalid state '{state}', expected 0, 1 or z")),
+    };
+
+    Ok((pin.trim().to_string(), state))
+}
+
+fn boundary(
+    bsdl: &Path,
+    tap: Option<usize>,
+    drive: &[(String, PinState)],
+    read: &[String],
+    hold: bool,
+    common: ProbeOptions,
+    lister: &Lister,
+) -> anyhow::Result<()> {
+    let source = std::fs::read_to_string(bsdl)
+        .with_context(|| format!("Failed to read {}", bsdl.display()))?;
+    let bsdl =
+        Bsdl::parse(&source).with_context(|| format!("Failed to parse {}", bsdl.display()))?;
+
+    let mut probe = attach_jtag(common, lister)?;
+    let jtag = probe
+        .try_as_jtag_access()
+        .context("The probe does not offer low-level JTAG access")?;
+    let mut boundary_scan = BoundaryScan::new(jtag, &bsdl, tap)?;
+
+    for (pin, state) in drive {
+        boundary_scan
+            .drive_pin(pin, *state)
+            .with_context(|| format!("Failed to drive {pin}"))?;
+    }
+
+    let levels = if read.is_empty() {
+        boundary_scan.read_pins()?
+    } else {
+        read.iter()
+            .map(|pin| Ok((pin.clone(), boundary_scan.read_pin(pin)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+
+    for (pin, level) in levels {
+        let package_pin = bsdl
+            .package_pin(&pin)
+            .map(|package_pin| format!(" (pin {package_pin})"))
+            .unwrap_or_default();
+        println!("{pin}{package_pin}: {}", if level { "high" } else { "low" });
+    }
+
+    if !hold {
+        boundary_scan.release()?;
+    }
+
+    Ok(())
+}
+
+/// Prints the TAPs of a scan chain, starting with the TAP closest to TDO.
+fn print_chain(mut output: impl Write, chain: &[JtagChainItem]) -> anyhow::Result<()> {
+    writeln!(
+        output,
+        "Found {} TAP{} on the JTAG scan chain, starting at TDO:",
+        chain.len(),
+        if chain.len() == 1 { "" } else { "s" }
+    )?;
+
+    for (index, tap) in chain.iter().enumerate() {
+        writeln!(output)?;
+        writeln!(output, "TAP {index}:")?;
+        match tap.idcode {
+            Some(idcode) => print_idcode(&mut output, idcode)?,
+            None => writeln!(
+                output,
+                "    IDCODE:       none, the TAP is in BYPASS after reset"
+            )?,
+        }
+        writeln!(output, "    IR length:    {} bits", tap.irlen)?;
+    }
+
+    Ok(())
+}
+
+fn print_idcode(mut output: impl Write, idcode: IdCode) -> anyhow::Result<()> {
+    let manufacturer = JEP106Code::new(
+        idcode.manufacturer_continuation(),
+        idcode.manufacturer_identity(),
+    );
+
+    writeln!(output, "    IDCODE:       {:#010x}", idcode.0)?;
+    writeln!(
+        output,
+        "    Manufacturer: {} (JEP106 bank {}, id {:#04x})",
+        manufacturer.get().unwrap_or("<unknown>"),
+        manufacturer.cc + 1,
+        manufacturer.id
+    )?;
+    writeln!(output, "    Part number:  {:#06x}", idcode.part_number())?;
+    writeln!(output, "    Version:      {}", idcode.version())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn print_scan_chain() {
+        let chain = [
+            JtagChainItem {
+                idcode: Some(IdCode(0x4BA00477)),
+                irlen: 4,
+            },
+            JtagChainItem {
+                idcode: None,
+                irlen: 5,
+            },
+        ];
+
+        let mut output = Vec::new();
+        print_chain(&mut output, &chain).unwrap();
+
+        insta::assert_snapshot!(String::from_utf8(output).unwrap());
+    }
+
+    #[test]
+    fn parse_drive_argument() {
+        assert_eq!(
+            parse_drive("PA0=1"),
+            Ok((String::from("PA0"), PinState::High))
+        );
+        assert_eq!(
+            parse_drive("14 = z"),
+            Ok((String::from("14"), PinState::HighZ))
+        );
+        assert!(parse_drive("PA0").is_err());
+        assert!(parse_drive("PA0=2").is_err());
+    }
+}
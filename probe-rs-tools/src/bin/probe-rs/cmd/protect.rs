@@ -0,0 +1,234 @@
+use anyhow::{bail, Result};
+use probe_rs::probe::list::Lister;
+use probe_rs::protection::{ProtectionLevel, ProtectionState, ProtectionStatus, ReadoutProtection};
+
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+/// Manages the readout protection of the target, like STM32 RDP or nRF APPROTECT
+///
+/// The protection is accessed without halting the cores, so the status of a
+/// protected target can be read without erasing it.
+#[derive(clap::Subcommand)]
+#[clap(verbatim_doc_comment)]
+enum Subcommand {
+    /// Prints the state of every readout protection mechanism of the target
+    ///
+    /// e.g. probe-rs protect status --chip nRF52840_xxAA --expect enabled
+    ///
+    /// With `--expect`, the command fails unless all mechanisms are in the
+    /// expected state, which is useful for end-of-line tests.
+    #[clap(verbatim_doc_comment)]
+    Status {
+        /// Fail unless all mechanisms are in this state
+        #[arg(long, value_enum)]
+        expect: Option<ExpectedState>,
+
+        /// Prints the status as JSON
+        #[arg(long)]
+        json: bool,
+
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+    },
+
+    /// Enables readout protection
+    ///
+    /// The target usually has to be reset or power cycled for the protection
+    /// to take effect.
+    Enable {
+        /// Enable the permanent protection level, which can never be removed
+        #[arg(long, requires = "allow_permanent_protection")]
+        permanent: bool,
+
+        /// Confirm that the permanent protection level can never be removed
+        #[arg(long)]
+        allow_permanent_protection: bool,
+
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+    },
+
+    /// Removes readout protection, which erases all memory on most targets
+    ///
+    /// This requires `--allow-erase-all`.
+    Disable {
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+    },
+}
+
+/// The protection state expected by `protect status --expect`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+enum ExpectedState {
+    /// The memory of the target can be read
+    Disabled,
+    /// The memory can not be read, regardless of whether the protection can be removed
+    Enabled,
+    /// The protection can not be removed anymore
+    Permanent,
+}
+
+impl ExpectedState {
+    fn matches(self, state: ProtectionState) -> bool {
+        match self {
+            Self::Disabled => state == ProtectionState::Disabled,
+            Self::Enabled => matches!(state, ProtectionState::Enabled | ProtectionState::Permanent),
+            Self::Permanent => state == ProtectionState::Permanent,
+        }
+    }
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> Result<()> {
+        match self.subcommand {
+            Subcommand::Status {
+                expect,
+                json,
+                probe_options,
+            } => {
+                let mut protection = attach(lister, probe_options, |permissions| permissions)?;
+                let status = protection.status()?;
+
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&status)?);
+                } else {
+                    for status in &status {
+                        println!("{}", format_status(status));
+                    }
+                }
+
+                if let Some(expect) = expect {
+                    check_expected(&status, expect)?;
+                }
+
+                Ok(())
+            }
+            Subcommand::Enable {
+                permanent,
+                allow_permanent_protection,
+                probe_options,
+            } => {
+                let level = if permanent {
+                    ProtectionLevel::Permanent
+                } else {
+                    ProtectionLevel::Enabled
+                };
+
+                let mut protection = attach(lister, probe_options, |permissions| {
+                    let permissions = permissions.allow_enable_protection();
+                    if allow_permanent_protection {
+                        permissions.allow_permanent_protection()
+                    } else {
+                        permissions
+                    }
+                })?;
+                protection.enable(level)?;
+
+                println!("Readout protection enabled, reset the target for it to take effect");
+
+                Ok(())
+            }
+            Subcommand::Disable { probe_options } => {
+                let mut protection = attach(lister, probe_options, |permissions| permissions)?;
+                protection.disable()?;
+
+                println!("Readout protection removed");
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn attach(
+    lister: &Lister,
+    probe_options: ProbeOptions,
+    permissions: impl FnOnce(probe_rs::Permissions) -> probe_rs::Permissions,
+) -> Result<ReadoutProtection> {
+    let probe_options = probe_options.load()?;
+    let target = probe_options.get_target_selector()?;
+    let probe = probe_options.attach_probe(lister)?;
+    let permissions = permissions(probe_options.permissions());
+
+    Ok(ReadoutProtection::attach(probe, target, permissions)?)
+}
+
+fn format_status(status: &ProtectionStatus) -> String {
+    let state = match status.state {
+        ProtectionState::Disabled => "disabled",
+        ProtectionState::Enabled => "enabled",
+        ProtectionState::Permanent => "permanent",
+        ProtectionState::Unknown => "unknown",
+    };
+
+    format!("{}: {state} ({})", status.mechanism, status.details)
+}
+
+fn check_expected(status: &[ProtectionStatus], expect: ExpectedState) -> Result<()> {
+    if status.is_empty() {
+        bail!("The target did not report any readout protection mechanism");
+    }
+
+    let mismatches = status
+        .iter()
+        .filter(|status| !expect.matches(status.state))
+        .map(|status| status.mechanism.as_str())
+        .collect::<Vec<_>>();
+
+    if !mismatches.is_empty() {
+        bail!(
+            "Expected the readout protection to be {expect:?}, but {} is not",
+            mismatches.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn status(mechanism: &str, state: ProtectionState) -> ProtectionStatus {
+        ProtectionStatus {
+            mechanism: mechanism.to_string(),
+            state,
+            details: "level 1".to_string(),
+        }
+    }
+
+    #[test]
+    fn expected_state() {
+        let status = [
+            status("APPROTECT (core 0)", ProtectionState::Enabled),
+            status("APPROTECT (core 1)", ProtectionState::Permanent),
+        ];
+
+        assert!(check_expected(&status, ExpectedState::Enabled).is_ok());
+        assert!(check_expected(&status, ExpectedState::Permanent).is_err());
+        assert!(check_expected(&status, ExpectedState::Disabled).is_err());
+        assert!(check_expected(&[], ExpectedState::Disabled).is_err());
+    }
+
+    #[test]
+    fn unknown_state_is_never_expected() {
+        let status = [status("CRP", ProtectionState::Unknown)];
+
+        assert!(check_expected(&status, ExpectedState::Enabled).is_err());
+        assert!(check_expected(&status, ExpectedState::Disabled).is_err());
+    }
+
+    #[test]
+    fn format_protection_status() {
+        assert_eq!(
+            format_status(&status("RDP", ProtectionState::Enabled)),
+            "RDP: enabled (level 1)"
+        );
+    }
+}
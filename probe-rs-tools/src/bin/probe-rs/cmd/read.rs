@@ -1,14 +1,34 @@
-use probe_rs::{probe::list::Lister, MemoryInterface};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use probe_rs::{probe::list::Lister, Core, MemoryInterface};
+use probe_rs_debug::{
+    debug_info::DebugInfo, registers::DebugRegisters, stack_frame::StackFrameInfo,
+    variable::Variable, variable_cache::VariableCache, VariableName,
+};
 
 use crate::util::common_options::{ProbeOptions, ReadWriteBitWidth, ReadWriteOptions};
+use crate::util::elf_symbols::ElfSymbol;
+use crate::util::image::{self, Segment};
 use crate::CoreOptions;
 
+/// How deep the members of a variable are expanded when pretty printing it.
+const PRETTY_PRINT_DEPTH: usize = 6;
+
 /// Read from target memory address
 ///
 /// e.g. probe-rs read b32 0x400E1490 2
 ///      Reads 2 32-bit words from address 0x400E1490
 ///
-/// Output is a space separated list of hex values padded to the read word width.
+/// e.g. probe-rs read --elf firmware.elf b8 SOME_STATIC
+///      Reads all bytes of the static SOME_STATIC
+///
+/// e.g. probe-rs read --elf firmware.elf --pretty b8 SOME_STATIC
+///      Reads SOME_STATIC and prints it using its type from the debug information
+///
+/// By default, output is a space separated list of hex values padded to the read word width.
 /// e.g. 2 words
 ///     00 00 (8-bit)
 ///     00000000 00000000 (32-bit)
@@ -27,47 +47,283 @@ pub struct Cmd {
     #[clap(flatten)]
     read_write_options: ReadWriteOptions,
 
-    /// Number of words to read from the target
-    words: u64,
+    /// Number of words to read from the target.
+    /// Defaults to the size of the symbol if the address is a symbol name.
+    words: Option<u64>,
+
+    /// The output format
+    #[clap(long, value_enum, default_value_t = ReadFormat::Words)]
+    format: ReadFormat,
+
+    /// Write the output to this file instead of stdout
+    #[clap(long)]
+    output: Option<PathBuf>,
+
+    /// Print the symbol using its type from the debug information of the ELF file
+    #[clap(long, requires = "elf", conflicts_with_all = ["format", "output"])]
+    pretty: bool,
+}
+
+/// Output formats of the read command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+enum ReadFormat {
+    /// Space separated hex values of the read width
+    Words,
+    /// Hex and ASCII dump with addresses
+    Hexdump,
+    /// Raw bytes, requires `--output`
+    Binary,
+    /// Intel HEX
+    Ihex,
 }
 
 impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        let (address, symbol) = self.read_write_options.resolve()?;
+        let width = self.read_write_options.width as u64 / 8;
+
+        if self.format == ReadFormat::Binary && self.output.is_none() {
+            bail!("The binary format requires `--output`");
+        }
+
+        if self.pretty {
+            let Some(symbol) = symbol else {
+                bail!("`--pretty` requires the address to be a symbol name");
+            };
+            let Some(elf) = &self.read_write_options.elf else {
+                bail!("`--pretty` requires `--elf`");
+            };
+            let debug_info = DebugInfo::from_file(elf)
+                .with_context(|| format!("Failed to load debug info from {}", elf.display()))?;
+
+            let (mut session, _probe_options) = self.probe_options.simple_attach(lister)?;
+            let mut core = session.core(self.shared.core)?;
+            let result = print_symbol(&mut core, &debug_info, &symbol);
+            std::mem::drop(core);
+
+            session.resume_all_cores()?;
+
+            return result;
+        }
+
+        let words = match (self.words, &symbol) {
+            (Some(words), _) => words,
+            (None, Some(symbol)) if symbol.size > 0 => symbol.size.div_ceil(width),
+            (None, Some(symbol)) => bail!(
+                "The size of symbol {} is unknown, the number of words to read is required",
+                symbol.demangled
+            ),
+            (None, None) => bail!("The number of words to read is required"),
+        } as usize;
+
         let (mut session, _probe_options) = self.probe_options.simple_attach(lister)?;
 
         let mut core = session.core(self.shared.core)?;
-        let words = self.words as usize;
 
-        match self.read_write_options.width {
+        let (text, bytes) = match self.read_write_options.width {
             ReadWriteBitWidth::B8 => {
                 let mut values = vec![0; words];
-                core.read_8(self.read_write_options.address, &mut values)?;
-                for val in values {
-                    print!("{:02x} ", val);
-                }
-                println!();
+                core.read_8(address, &mut values)?;
+                let text = values
+                    .iter()
+                    .map(|val| format!("{:02x} ", val))
+                    .collect::<Vec<_>>()
+                    .concat();
+                (text, values)
             }
             ReadWriteBitWidth::B32 => {
                 let mut values = vec![0; words];
-                core.read_32(self.read_write_options.address, &mut values)?;
-                for val in values {
-                    print!("{:08x} ", val);
-                }
-                println!();
+                core.read_32(address, &mut values)?;
+                let text = values
+                    .iter()
+                    .map(|val| format!("{:08x} ", val))
+                    .collect::<Vec<_>>()
+                    .concat();
+                (
+                    text,
+                    values.iter().flat_map(|val| val.to_le_bytes()).collect(),
+                )
             }
             ReadWriteBitWidth::B64 => {
                 let mut values = vec![0; words];
-                core.read_64(self.read_write_options.address, &mut values)?;
-                for val in values {
-                    print!("{:016x} ", val);
-                }
-                println!();
+                core.read_64(address, &mut values)?;
+                let text = values
+                    .iter()
+                    .map(|val| format!("{:016x} ", val))
+                    .collect::<Vec<_>>()
+                    .concat();
+                (
+                    text,
+                    values.iter().flat_map(|val| val.to_le_bytes()).collect(),
+                )
             }
-        }
+        };
         std::mem::drop(core);
 
         session.resume_all_cores()?;
 
-        Ok(())
+        let output = match self.format {
+            ReadFormat::Words => format!("{text}\n").into_bytes(),
+            ReadFormat::Hexdump => hexdump(address, &bytes).into_bytes(),
+            ReadFormat::Binary => bytes,
+            ReadFormat::Ihex => image::ihex(&[Segment {
+                name: "read".to_string(),
+                address,
+                data: bytes,
+            }])?
+            .into_bytes(),
+        };
+
+        write_output(self.output.as_deref(), &output)
+    }
+}
+
+fn write_output(path: Option<&Path>, output: &[u8]) -> anyhow::Result<()> {
+    match path {
+        Some(path) => std::fs::write(path, output)
+            .with_context(|| format!("Failed to write {}", path.display())),
+        None => Ok(std::io::stdout().write_all(output)?),
+    }
+}
+
+/// Formats `bytes` as lines of 16 bytes, with their address and ASCII representation.
+fn hexdump(address: u64, bytes: &[u8]) -> String {
+    let mut output = String::new();
+    for (index, line) in bytes.chunks(16).enumerate() {
+        let hex = line
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+
+        output.push_str(&format!(
+            "{:08x}  {hex:<47}  |{ascii}|\n",
+            address + index as u64 * 16
+        ));
+    }
+
+    output
+}
+
+/// Prints the static variable at the address of `symbol`, using its type from the debug info.
+fn print_symbol(
+    core: &mut Core<'_>,
+    debug_info: &DebugInfo,
+    symbol: &ElfSymbol,
+) -> anyhow::Result<()> {
+    let registers = DebugRegisters::from_core(core);
+    let frame_info = StackFrameInfo {
+        registers: &registers,
+        frame_base: None,
+        canonical_frame_address: None,
+    };
+
+    let mut cache = debug_info.create_static_scope_cache();
+    let path = symbol.demangled.split("::").collect::<Vec<_>>();
+    let Some((name, namespaces)) = path.split_last() else {
+        bail!("Symbol {} has no name", symbol.demangled);
+    };
+
+    // Search the namespaces of the symbol path for the variable located at the symbol.
+    let mut variable = None;
+    let mut queue = VecDeque::from([cache.root_variable().clone()]);
+    while let Some(mut parent) = queue.pop_front() {
+        debug_info.cache_deferred_variables(&mut cache, core, &mut parent, frame_info)?;
+
+        for child in cache.get_children(parent.variable_key()) {
+            match &child.name {
+                VariableName::Named(child_name)
+                    if child_name == name
+                        && child.memory_location.memory_address().ok() == Some(symbol.address) =>
+                {
+                    variable = Some(child.clone());
+                    break;
+                }
+                VariableName::Namespace(namespace) if namespaces.contains(&namespace.as_str()) => {
+                    queue.push_back(child.clone())
+                }
+                VariableName::AnonymousNamespace => queue.push_back(child.clone()),
+                _ => {}
+            }
+        }
+
+        if variable.is_some() {
+            break;
+        }
+    }
+
+    let Some(mut variable) = variable else {
+        bail!(
+            "Symbol {} was not found in the debug information",
+            symbol.demangled
+        );
+    };
+
+    expand(
+        &mut cache,
+        debug_info,
+        core,
+        &mut variable,
+        frame_info,
+        PRETTY_PRINT_DEPTH,
+    );
+
+    println!(
+        "{}: {} = {}",
+        symbol.demangled,
+        variable.type_name(),
+        variable.to_string(&cache)
+    );
+
+    Ok(())
+}
+
+/// Loads the members of `variable` into the cache, up to `depth` levels deep.
+fn expand(
+    cache: &mut VariableCache,
+    debug_info: &DebugInfo,
+    memory: &mut dyn MemoryInterface,
+    variable: &mut Variable,
+    frame_info: StackFrameInfo<'_>,
+    depth: usize,
+) {
+    if depth == 0
+        || debug_info
+            .cache_deferred_variables(cache, memory, variable, frame_info)
+            .is_err()
+    {
+        return;
+    }
+
+    let children = cache
+        .get_children(variable.variable_key())
+        .cloned()
+        .collect::<Vec<_>>();
+    for mut child in children {
+        expand(cache, debug_info, memory, &mut child, frame_info, depth - 1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_hexdump() {
+        assert_eq!(
+            hexdump(0x2000_0000, b"Hello, world!\0\x01\x02\x03"),
+            "20000000  48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 00 01 02  |Hello, world!...|\n\
+             20000010  03                                               |.|\n"
+        );
     }
 }
@@ -0,0 +1,309 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use probe_rs::{probe::list::Lister, MemoryInterface};
+
+use crate::util::common_options::ProbeOptions;
+use crate::util::svd::{Field, Peripheral, Register, SvdDevice, SvdElement};
+use crate::CoreOptions;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+/// Reads and writes peripheral registers by name, as described by a CMSIS-SVD file
+///
+/// Registers are named like `PERIPHERAL.REGISTER`, fields like `PERIPHERAL.REGISTER.FIELD`.
+/// Registers inside clusters are named `PERIPHERAL.CLUSTER_REGISTER`.
+#[derive(clap::Subcommand)]
+#[clap(verbatim_doc_comment)]
+enum Subcommand {
+    /// Reads and decodes peripherals, registers or fields
+    ///
+    /// e.g. probe-rs reg read --svd STM32F411.svd GPIOA.MODER RCC.CR.HSERDY
+    ///
+    /// Registers are printed with the value of each field, followed by the
+    /// name of the matching enumerated value.
+    #[clap(verbatim_doc_comment)]
+    Read {
+        /// Names of the peripherals, registers or fields to read
+        #[clap(required = true)]
+        names: Vec<String>,
+
+        #[clap(flatten)]
+        common: SvdOptions,
+    },
+
+    /// Writes a register or a field
+    ///
+    /// e.g. probe-rs reg write --svd STM32F411.svd GPIOA.ODR.ODR5 = 1
+    ///      probe-rs reg write --svd STM32F411.svd GPIOA.MODER.MODER5=Output
+    ///      probe-rs reg write --svd STM32F411.svd GPIOA.ODR = 0x20
+    ///
+    /// Fields can be set to a number or to the name of an enumerated value.
+    /// The other fields of the register keep their value.
+    #[clap(verbatim_doc_comment)]
+    Write {
+        /// The assignment, `NAME = VALUE`
+        #[clap(required = true, num_args = 1..)]
+        assignment: Vec<String>,
+
+        #[clap(flatten)]
+        common: SvdOptions,
+    },
+
+    /// Reads and decodes all registers of peripherals
+    ///
+    /// e.g. probe-rs reg dump --svd STM32F411.svd GPIOA GPIOB
+    ///
+    /// Dumps all peripherals of the device if none are given.
+    #[clap(verbatim_doc_comment)]
+    Dump {
+        /// Names of the peripherals to dump
+        peripherals: Vec<String>,
+
+        #[clap(flatten)]
+        common: SvdOptions,
+    },
+}
+
+#[derive(clap::Args)]
+struct SvdOptions {
+    /// The CMSIS-SVD file describing the peripherals of the target
+    #[arg(long, env = "PROBE_RS_SVD")]
+    svd: PathBuf,
+
+    /// Also read registers which can not be read without side effects, like clearing status flags
+    #[arg(long)]
+    force: bool,
+
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    probe_options: ProbeOptions,
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> Result<()> {
+        match self.subcommand {
+            Subcommand::Read { names, common } => {
+                let device = SvdDevice::load(&common.svd)?;
+                let elements = names
+                    .iter()
+                    .map(|name| find(&device, name))
+                    .collect::<Result<Vec<_>>>()?;
+
+                with_memory(common, lister, |memory, force| {
+                    for element in elements {
+                        match element {
+                            SvdElement::Peripheral(peripheral) => {
+                                print_peripheral(memory, peripheral, force)
+                            }
+                            SvdElement::Register(peripheral, register) => {
+                                print_register(memory, peripheral, register, force)
+                            }
+                            SvdElement::Field(peripheral, register, field) => {
+                                print_field(memory, peripheral, register, field, force)
+                            }
+                        }
+                    }
+                    Ok(())
+                })
+            }
+            Subcommand::Write { assignment, common } => {
+                let assignment = assignment.concat();
+                let Some((name, value)) = assignment.split_once('=') else {
+                    bail!("Expected an assignment like `GPIOA.ODR.ODR5 = 1`, got `{assignment}`");
+                };
+
+                let device = SvdDevice::load(&common.svd)?;
+                let element = find(&device, name)?;
+
+                with_memory(common, lister, |memory, force| match element {
+                    SvdElement::Peripheral(peripheral) => {
+                        bail!("{} is a peripheral, not a register", peripheral.name)
+                    }
+                    SvdElement::Register(peripheral, register) => {
+                        if !register.writable {
+                            bail!(
+                                "Register {}.{} is read-only",
+                                peripheral.name,
+                                register.name
+                            );
+                        }
+                        let value = parse_int::parse::<u64>(value)
+                            .with_context(|| format!("Invalid register value `{value}`"))?;
+
+                        register.write(memory, value)?;
+                        print_register(memory, peripheral, register, force);
+                        Ok(())
+                    }
+                    SvdElement::Field(peripheral, register, field) => {
+                        if !field.writable {
+                            bail!(
+                                "Field {}.{}.{} is read-only",
+                                peripheral.name,
+                                register.name,
+                                field.name
+                            );
+                        }
+                        let value = field.parse_value(value)?;
+
+                        register.write_field(memory, field, value)?;
+                        print_register(memory, peripheral, register, force);
+                        Ok(())
+                    }
+                })
+            }
+            Subcommand::Dump {
+                peripherals,
+                common,
+            } => {
+                let device = SvdDevice::load(&common.svd)?;
+                let peripherals = if peripherals.is_empty() {
+                    device.peripherals.iter().collect()
+                } else {
+                    peripherals
+                        .iter()
+                        .map(|name| match find(&device, name)? {
+                            SvdElement::Peripheral(peripheral) => Ok(peripheral),
+                            _ => bail!("{name} is not a peripheral"),
+                        })
+                        .collect::<Result<Vec<_>>>()?
+                };
+
+                with_memory(common, lister, |memory, force| {
+                    for peripheral in peripherals {
+                        print_peripheral(memory, peripheral, force);
+                    }
+                    Ok(())
+                })
+            }
+        }
+    }
+}
+
+fn find<'a>(device: &'a SvdDevice, name: &str) -> Result<SvdElement<'a>> {
+    device.find(name.trim()).with_context(|| {
+        format!(
+            "No peripheral, register or field named `{}` in the SVD file",
+            name.trim()
+        )
+    })
+}
+
+fn with_memory(
+    options: SvdOptions,
+    lister: &Lister,
+    f: impl FnOnce(&mut dyn MemoryInterface, bool) -> Result<()>,
+) -> Result<()> {
+    let (mut session, _probe_options) = options.probe_options.simple_attach(lister)?;
+
+    let mut core = session.core(options.shared.core)?;
+    f(&mut core, options.force)?;
+    std::mem::drop(core);
+
+    session.resume_all_cores()?;
+
+    Ok(())
+}
+
+fn print_peripheral(memory: &mut dyn MemoryInterface, peripheral: &Peripheral, force: bool) {
+    println!("{} @ {:#010x}", peripheral.name, peripheral.base_address);
+    if let Some(description) = &peripheral.description {
+        println!("    {}", description.trim());
+    }
+
+    for register in &peripheral.registers {
+        print_register(memory, peripheral, register, force);
+    }
+}
+
+fn print_register(
+    memory: &mut dyn MemoryInterface,
+    peripheral: &Peripheral,
+    register: &Register,
+    force: bool,
+) {
+    let name = format!("{}.{}", peripheral.name, register.name);
+    let Some(value) = read(memory, register, force, &name) else {
+        return;
+    };
+
+    println!(
+        "{name} @ {:#010x} = {value:#0width$x}",
+        register.address,
+        width = register.size.div_ceil(4) as usize + 2
+    );
+    for field in &register.fields {
+        println!("    {}", format_field(field, value));
+    }
+}
+
+fn print_field(
+    memory: &mut dyn MemoryInterface,
+    peripheral: &Peripheral,
+    register: &Register,
+    field: &Field,
+    force: bool,
+) {
+    let name = format!("{}.{}.{}", peripheral.name, register.name, field.name);
+    if let Some(value) = read(memory, register, force, &name) {
+        println!(
+            "{}.{} @ {:#010x}: {}",
+            peripheral.name,
+            register.name,
+            register.address,
+            format_field(field, value)
+        );
+    }
+}
+
+/// Reads `register`, printing why it was not read on failure.
+///
+/// Registers which can not be read without side effects are only read if `force` is set.
+fn read(
+    memory: &mut dyn MemoryInterface,
+    register: &Register,
+    force: bool,
+    name: &str,
+) -> Option<u64> {
+    let address = register.address;
+    if register.restricted_read && !force {
+        println!(
+            "{name} @ {address:#010x}: not read, the register is write-only or reading it may have side effects"
+        );
+        return None;
+    }
+
+    match register.read(memory) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            println!("{name} @ {address:#010x}: failed to read: {error}");
+            None
+        }
+    }
+}
+
+fn format_field(field: &Field, register_value: u64) -> String {
+    let bits = if field.bit_width == 1 {
+        format!("[{}]", field.bit_offset)
+    } else {
+        format!(
+            "[{}:{}]",
+            field.bit_offset + field.bit_width - 1,
+            field.bit_offset
+        )
+    };
+
+    let value = field.extract(register_value);
+    let mut formatted = format!("{} {bits} = {value:#x}", field.name);
+    if let Some(enumerated_value) = field.enumerated_value(value) {
+        formatted.push_str(&format!(" ({})", enumerated_value.name));
+    }
+
+    formatted
+}
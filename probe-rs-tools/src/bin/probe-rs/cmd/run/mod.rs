@@ -24,7 +24,7 @@ use probe_rs_debug::{exception_handler_for_core, DebugInfo, DebugRegisters};
 use signal_hook::consts::signal;
 use time::UtcOffset;
 
-use crate::util::common_options::{BinaryDownloadOptions, ProbeOptions};
+use crate::util::common_options::{BinaryDownloadOptions, FlashAlgorithmOptions, ProbeOptions};
 use crate::util::flash::{build_loader, run_flash_download};
 use crate::util::rtt::client::RttClient;
 use crate::util::rtt::{ChannelDataCallbacks, RttChannelConfig, RttConfig};
@@ -53,6 +53,9 @@ pub struct SharedOptions {
     #[clap(flatten)]
     pub(crate) download_options: BinaryDownloadOptions,
 
+    #[clap(flatten)]
+    pub(crate) flash_algorithm_options: FlashAlgorithmOptions,
+
     /// The path to the ELF file to flash and run.
     #[clap(
         index = 1,
@@ -97,8 +100,13 @@ impl Cmd {
     ) -> Result<()> {
         let run_mode = detect_run_mode(&self)?;
 
-        let (mut session, probe_options) =
-            self.shared_options.probe_options.simple_attach(lister)?;
+        let (mut session, probe_options) = self
+            .shared_options
+            .probe_options
+            .simple_attach_with_flash_algorithm(
+                lister,
+                &self.shared_options.flash_algorithm_options,
+            )?;
 
         if !run_download {
             // If we don't have to flash, resume cores now to prevent halting while processing elf
@@ -121,7 +129,7 @@ impl Cmd {
         let format = self
             .shared_options
             .format_options
-            .to_format_kind(session.target());
+            .to_format_kind(session.target(), &self.shared_options.path);
         let elf = if matches!(format, FormatKind::Elf | FormatKind::Idf) {
             Some(fs::read(&self.shared_options.path)?)
         } else {
@@ -9,6 +9,9 @@ use crate::CoreOptions;
 /// e.g. probe-rs write b32 0x400E1490 0xDEADBEEF 0xCAFEF00D
 ///      Writes 0xDEADBEEF to address 0x400E1490 and 0xCAFEF00D to address 0x400E1494
 ///
+/// e.g. probe-rs write --elf firmware.elf b8 CONFIG+4 0x01
+///      Writes 0x01 to the fifth byte of the static CONFIG
+///
 /// NOTE: Only supports RAM addresses
 #[derive(clap::Parser)]
 #[clap(verbatim_doc_comment)]
@@ -30,6 +33,20 @@ pub struct Cmd {
 
 impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        let (address, symbol) = self.read_write_options.resolve()?;
+        if let Some(symbol) = symbol.filter(|symbol| symbol.size > 0) {
+            let width = self.read_write_options.width as u64 / 8;
+            let end = address + self.values.len() as u64 * width;
+            if address < symbol.address || end > symbol.address + symbol.size {
+                tracing::warn!(
+                    "The write to {address:#x}..{end:#x} is outside of the symbol {} at {:#x}..{:#x}",
+                    symbol.demangled,
+                    symbol.address,
+                    symbol.address + symbol.size
+                );
+            }
+        }
+
         let (mut session, _probe_options) = self.probe_options.simple_attach(lister)?;
         let mut core = session.core(self.shared.core)?;
 
@@ -46,7 +63,7 @@ impl Cmd {
                     }
                     bvalues.push(*val as u8);
                 }
-                core.write_8(self.read_write_options.address, &bvalues)?;
+                core.write_8(address, &bvalues)?;
             }
             ReadWriteBitWidth::B32 => {
                 let mut bvalues = Vec::new();
@@ -60,10 +77,10 @@ impl Cmd {
                     }
                     bvalues.push(*val as u32);
                 }
-                core.write_32(self.read_write_options.address, &bvalues)?;
+                core.write_32(address, &bvalues)?;
             }
             ReadWriteBitWidth::B64 => {
-                core.write_64(self.read_write_options.address, &self.values)?;
+                core.write_64(address, &self.values)?;
             }
         }
 
@@ -64,6 +64,10 @@ enum Subcommand {
     List(cmd::list::Cmd),
     /// Gets info about the selected debug probe and connected target
     Info(cmd::info::Cmd),
+    /// Explores the CoreSight topology of the target, listing every debug port, access port and component
+    Coresight(cmd::coresight::Cmd),
+    /// Reads and writes debug port and access port registers, and memory through a MEM-AP, without attaching to a core
+    Arm(cmd::arm::Cmd),
     /// Resets the target attached to the selected debug probe
     Reset(cmd::reset::Cmd),
     /// Run a GDB server
@@ -72,6 +76,8 @@ enum Subcommand {
     Debug(cmd::debug::Cmd),
     /// Download memory to attached target
     Download(cmd::download::Cmd),
+    /// Download memory to the targets of several probes in parallel
+    Gang(cmd::gang::Cmd),
     /// Compare memory to attached target
     Verify(cmd::verify::Cmd),
     /// Erase all nonvolatile memory of attached target
@@ -89,11 +95,19 @@ enum Subcommand {
     #[clap(name = "itm")]
     Itm(cmd::itm::Cmd),
     Chip(cmd::chip::Cmd),
+    Jtag(cmd::jtag::Cmd),
     /// Measure the throughput of the selected debug probe
     Benchmark(cmd::benchmark::Cmd),
     /// Profile on-target runtime performance of target ELF program
     Profile(cmd::profile::ProfileCmd),
     Read(cmd::read::Cmd),
+    Dump(cmd::dump::Cmd),
+    /// Read and write peripheral registers described by a CMSIS-SVD file
+    Reg(cmd::reg::Cmd),
+    /// Read and write configuration memory like option bytes, fuses or the UICR
+    ConfigBytes(cmd::config_bytes::Cmd),
+    /// Read, enable or remove the readout protection of the target
+    Protect(cmd::protect::Cmd),
     Write(cmd::write::Cmd),
     Complete(cmd::complete::Cmd),
     Mi(cmd::mi::Cmd),
@@ -154,20 +168,24 @@ pub struct FormatOptions {
 
 impl FormatOptions {
     /// If a format is provided, use it.
+    /// If the format of the image at `path` can be detected, we use that.
     /// If a target has a preferred format, we use that.
-    /// Finally, if neither of the above cases are true, we default to [`Format::default()`].
-    pub fn to_format_kind(&self, target: &Target) -> FormatKind {
-        self.binary_format.unwrap_or_else(|| {
-            FormatKind::from_optional(target.default_format.as_deref())
-                .expect("Failed to parse a default binary format. This shouldn't happen.")
-        })
+    /// Finally, if none of the above cases are true, we default to [`Format::default()`].
+    pub fn to_format_kind(&self, target: &Target, path: &Path) -> FormatKind {
+        self.binary_format
+            .or_else(|| FormatKind::detect(path))
+            .unwrap_or_else(|| {
+                FormatKind::from_optional(target.default_format.as_deref())
+                    .expect("Failed to parse a default binary format. This shouldn't happen.")
+            })
     }
 
     /// If a format is provided, use it.
+    /// If the format of the image at `path` can be detected, we use that.
     /// If a target has a preferred format, we use that.
-    /// Finally, if neither of the above cases are true, we default to [`Format::default()`].
-    pub fn into_format(self, target: &Target) -> Format {
-        match self.to_format_kind(target) {
+    /// Finally, if none of the above cases are true, we default to [`Format::default()`].
+    pub fn into_format(self, target: &Target, path: &Path) -> Format {
+        match self.to_format_kind(target, path) {
             FormatKind::Bin => Format::Bin(BinOptions {
                 base_address: self.bin_options.base_address,
                 skip: self.bin_options.skip,
@@ -175,6 +193,8 @@ impl FormatOptions {
             FormatKind::Hex => Format::Hex,
             FormatKind::Elf => Format::Elf,
             FormatKind::Uf2 => Format::Uf2,
+            FormatKind::SRecord => Format::SRecord,
+            FormatKind::TiTxt => Format::TiTxt,
             FormatKind::Idf => Format::Idf(IdfOptions {
                 bootloader: self.idf_options.idf_bootloader,
                 partition_table: self.idf_options.idf_partition_table,
@@ -319,6 +339,8 @@ fn main() -> Result<()> {
         Subcommand::DapServer { .. } => unreachable!(), // handled above.
         Subcommand::List(cmd) => cmd.run(&lister),
         Subcommand::Info(cmd) => cmd.run(&lister),
+        Subcommand::Coresight(cmd) => cmd.run(&lister),
+        Subcommand::Arm(cmd) => cmd.run(&lister),
         Subcommand::Gdb(cmd) => cmd.run(&lister),
         Subcommand::Reset(cmd) => cmd.run(&lister),
         Subcommand::Debug(cmd) => cmd.run(&lister),
@@ -334,6 +356,10 @@ fn main() -> Result<()> {
             elf = Some(cmd.run.shared_options.path.clone());
             cmd.run(&lister, utc_offset)
         }
+        Subcommand::Gang(cmd) => {
+            elf = Some(cmd.path.clone());
+            cmd.run(&lister)
+        }
         Subcommand::Verify(cmd) => {
             elf = Some(cmd.path.clone());
             cmd.run(&lister)
@@ -342,9 +368,14 @@ fn main() -> Result<()> {
         Subcommand::Trace(cmd) => cmd.run(&lister),
         Subcommand::Itm(cmd) => cmd.run(&lister),
         Subcommand::Chip(cmd) => cmd.run(),
+        Subcommand::Jtag(cmd) => cmd.run(&lister),
         Subcommand::Benchmark(cmd) => cmd.run(&lister),
         Subcommand::Profile(cmd) => cmd.run(&lister),
         Subcommand::Read(cmd) => cmd.run(&lister),
+        Subcommand::Dump(cmd) => cmd.run(&lister),
+        Subcommand::Reg(cmd) => cmd.run(&lister),
+        Subcommand::ConfigBytes(cmd) => cmd.run(&lister),
+        Subcommand::Protect(cmd) => cmd.run(&lister),
         Subcommand::Write(cmd) => cmd.run(&lister),
         Subcommand::Complete(cmd) => cmd.run(&lister),
         Subcommand::Mi(cmd) => cmd.run(),
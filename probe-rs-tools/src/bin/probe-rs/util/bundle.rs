@@ -0,0 +1,418 @@
+//! Flash bundles, which flash several images in a single erase and program pass.
+//!
+//! A bundle is described by a TOML manifest listing the images:
+//!
+//! ```toml
+//! [[images]]
+//! path = "bootloader.elf"
+//!
+//! [[images]]
+//! path = "app.bin"
+//! format = "bin"
+//! base_address = 0x0801_0000
+//!
+//! [[images]]
+//! path = "calibration.hex"
+//! only_if_changed = true
+//! ```
+//!
+//! Relative image paths are resolved relative to the directory of the manifest.
+
+use std::fs::File;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use figment::providers::{Format as _, Toml};
+use figment::Figment;
+use probe_rs::flashing::{
+    BinOptions, FileDownloadError, FlashError, FlashLoader, Format, FormatKind,
+};
+use probe_rs::{Session, Target};
+use serde::Deserialize;
+
+use crate::util::logging;
+use crate::FormatOptions;
+
+/// The manifest of a flash bundle.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The images of the bundle, in the order they are added to the flash loader.
+    pub images: Vec<BundleImage>,
+}
+
+/// An image of a flash bundle.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BundleImage {
+    /// The path of the image file.
+    pub path: PathBuf,
+    /// The format of the image, detected from the file like for single images if not given.
+    pub format: Option<String>,
+    /// The address at which a binary image is placed.
+    pub base_address: Option<u64>,
+    /// The number of bytes to skip at the start of a binary image.
+    #[serde(default)]
+    pub skip: u32,
+    /// Only flash the image if the flash does not already contain it.
+    #[serde(default)]
+    pub only_if_changed: bool,
+}
+
+/// Errors which can occur while loading a flash bundle.
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("Failed to read the bundle manifest '{path}'.")]
+    Manifest {
+        path: PathBuf,
+        #[source]
+        source: Box<figment::Error>,
+    },
+    #[error("The bundle manifest '{0}' does not list any images.")]
+    Empty(PathBuf),
+    #[error("Invalid format of the image '{path}': {reason}")]
+    Format { path: PathBuf, reason: String },
+    #[error("Failed to load the image '{path}'.")]
+    Image {
+        path: PathBuf,
+        #[source]
+        source: FileDownloadError,
+    },
+    #[error("Failed to compare the image '{path}' with the flash contents.")]
+    Compare {
+        path: PathBuf,
+        #[source]
+        source: FlashError,
+    },
+}
+
+/// Returns whether `path` is a bundle manifest, which is decided by its `.toml` extension.
+pub fn is_manifest(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+}
+
+impl Manifest {
+    /// Reads the manifest at `path` and resolves the image paths.
+    pub fn load(path: &Path) -> Result<Self, BundleError> {
+        let mut manifest: Manifest =
+            Figment::from(Toml::file_exact(path))
+                .extract()
+                .map_err(|source| BundleError::Manifest {
+                    path: path.to_path_buf(),
+                    source: Box::new(source),
+                })?;
+
+        if manifest.images.is_empty() {
+            return Err(BundleError::Empty(path.to_path_buf()));
+        }
+
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for image in &mut manifest.images {
+            image.path = directory.join(&image.path);
+        }
+
+        Ok(manifest)
+    }
+}
+
+impl BundleImage {
+    fn format(&self, session: &Session) -> Result<Format, BundleError> {
+        let kind = match &self.format {
+            Some(format) => FormatKind::from_str(format).map_err(|reason| BundleError::Format {
+                path: self.path.clone(),
+                reason,
+            })?,
+            None => FormatOptions::default().to_format_kind(session.target(), &self.path),
+        };
+
+        if kind != FormatKind::Bin && (self.base_address.is_some() || self.skip != 0) {
+            return Err(BundleError::Format {
+                path: self.path.clone(),
+                reason: "`base_address` and `skip` are only supported for binary images".into(),
+            });
+        }
+
+        Ok(match kind {
+            FormatKind::Bin => Format::Bin(BinOptions {
+                base_address: self.base_address,
+                skip: self.skip,
+            }),
+            kind => Format::from(kind),
+        })
+    }
+
+    fn load(
+        &self,
+        session: &mut Session,
+        loader: &mut FlashLoader,
+        format: Format,
+    ) -> Result<(), BundleError> {
+        let image_error = |source| BundleError::Image {
+            path: self.path.clone(),
+            source,
+        };
+
+        let mut file = File::open(&self.path)
+            .map_err(FileDownloadError::IO)
+            .map_err(image_error)?;
+        loader
+            .load_image(session, &mut file, format, None)
+            .map_err(image_error)
+    }
+
+    /// Loads the image on its own, to find out where it is placed and whether the flash already
+    /// contains it.
+    fn extent(&self, session: &mut Session, format: Format) -> Result<ImageExtent, BundleError> {
+        let mut loader = session.target().flash_loader();
+        self.load(session, &mut loader, format)?;
+
+        let data = loader
+            .data()
+            .map(|(address, data)| address..address + data.len() as u64)
+            .collect::<Vec<_>>();
+        let sectors = data
+            .iter()
+            .map(|range| sector_range(session.target(), range))
+            .collect();
+
+        let up_to_date = self.only_if_changed
+            && match loader.verify(session) {
+                Ok(()) => true,
+                Err(FlashError::Verify) => false,
+                Err(source) => {
+                    return Err(BundleError::Compare {
+                        path: self.path.clone(),
+                        source,
+                    })
+                }
+            };
+
+        Ok(ImageExtent {
+            up_to_date,
+            data,
+            sectors,
+        })
+    }
+}
+
+/// The location of an image in memory.
+#[derive(Debug)]
+struct ImageExtent {
+    /// Whether the image is only flashed if changed, and the flash already contains it.
+    up_to_date: bool,
+    /// The address ranges of the image data.
+    data: Vec<Range<u64>>,
+    /// The address ranges of the data, extended to the flash sectors containing it.
+    sectors: Vec<Range<u64>>,
+}
+
+/// Extends `range` to the boundaries of the flash sectors containing its start and end.
+fn sector_range(target: &Target, range: &Range<u64>) -> Range<u64> {
+    let sector = |address: u64| {
+        target.flash_algorithms.iter().find_map(|algorithm| {
+            let properties = &algorithm.flash_properties;
+            if !properties.address_range.contains(&address) {
+                return None;
+            }
+
+            let offset = address - properties.address_range.start;
+            let sector = properties
+                .sectors
+                .iter()
+                .rfind(|sector| sector.address <= offset)?;
+            let start = address - (offset - sector.address) % sector.size;
+            Some(start..start + sector.size)
+        })
+    };
+
+    let start = sector(range.start).map_or(range.start, |sector| sector.start);
+    let end = sector(range.end.saturating_sub(1)).map_or(range.end, |sector| sector.end);
+    start..end
+}
+
+/// Decides which images have to be flashed.
+///
+/// An image which is up to date is skipped, unless it shares a flash sector with an image that
+/// is flashed, or with the data in `staged` which is flashed anyway. The sector is erased in that
+/// case, so the image has to be written again.
+fn images_to_flash(staged: &[Range<u64>], images: &[ImageExtent]) -> Vec<bool> {
+    let mut flash = images
+        .iter()
+        .map(|image| !image.up_to_date)
+        .collect::<Vec<_>>();
+
+    // Flashing a skipped image erases its sectors as well, so repeat until nothing changes.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, image) in images.iter().enumerate() {
+            if flash[index] {
+                continue;
+            }
+
+            let shares_sector = images
+                .iter()
+                .zip(&flash)
+                .filter(|(_, flashed)| **flashed)
+                .flat_map(|(other, _)| &other.data)
+                .chain(staged)
+                .any(|data| {
+                    image
+                        .sectors
+                        .iter()
+                        .any(|sector| data.start < sector.end && sector.start < data.end)
+                });
+
+            if shares_sector {
+                flash[index] = true;
+                changed = true;
+            }
+        }
+    }
+
+    flash
+}
+
+/// Adds all images of the bundle manifest at `path` to `loader`.
+///
+/// Images marked with `only_if_changed` are left out if the flash already contains them, and
+/// none of their flash sectors is erased for another image or for the data already staged in
+/// `loader`.
+pub fn add_bundle(
+    session: &mut Session,
+    loader: &mut FlashLoader,
+    path: &Path,
+) -> Result<(), BundleError> {
+    let manifest = Manifest::load(path)?;
+
+    let mut formats = Vec::with_capacity(manifest.images.len());
+    let mut extents = Vec::with_capacity(manifest.images.len());
+    for image in &manifest.images {
+        let format = image.format(session)?;
+        extents.push(image.extent(session, format.clone())?);
+        formats.push(format);
+    }
+
+    let staged = loader
+        .data()
+        .map(|(address, data)| address..address + data.len() as u64)
+        .collect::<Vec<_>>();
+    let flash = images_to_flash(&staged, &extents);
+    for ((image, format), flash) in manifest.images.iter().zip(formats).zip(flash) {
+        if !flash {
+            logging::eprintln(format!(
+                "     Skipping {}, the flash is up to date",
+                image.path.display()
+            ));
+            continue;
+        }
+
+        image.load(session, loader, format)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn load_manifest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = temp_dir.path();
+        let path = directory.join("bundle.toml");
+        std::fs::write(
+            &path,
+            r#"
+            [[images]]
+            path = "bootloader.elf"
+
+            [[images]]
+            path = "app.bin"
+            format = "bin"
+            base_address = 0x0801_0000
+            only_if_changed = true
+            "#,
+        )
+        .unwrap();
+
+        let manifest = Manifest::load(&path).unwrap();
+        assert_eq!(
+            manifest,
+            Manifest {
+                images: vec![
+                    BundleImage {
+                        path: directory.join("bootloader.elf"),
+                        format: None,
+                        base_address: None,
+                        skip: 0,
+                        only_if_changed: false,
+                    },
+                    BundleImage {
+                        path: directory.join("app.bin"),
+                        format: Some("bin".to_string()),
+                        base_address: Some(0x0801_0000),
+                        skip: 0,
+                        only_if_changed: true,
+                    },
+                ]
+            }
+        );
+
+        std::fs::write(&path, "images = []").unwrap();
+        assert!(matches!(Manifest::load(&path), Err(BundleError::Empty(_))));
+    }
+
+    #[test]
+    fn manifest_extension() {
+        assert!(is_manifest(Path::new("firmware/Bundle.TOML")));
+        assert!(!is_manifest(Path::new("firmware.elf")));
+    }
+
+    fn extent(up_to_date: bool, data: Range<u64>, sectors: Range<u64>) -> ImageExtent {
+        ImageExtent {
+            up_to_date,
+            data: vec![data],
+            sectors: vec![sectors],
+        }
+    }
+
+    #[test]
+    fn up_to_date_image_in_own_sectors_is_skipped() {
+        let images = [
+            extent(false, 0x0000..0x1800, 0x0000..0x2000),
+            extent(true, 0x2000..0x2100, 0x2000..0x3000),
+        ];
+
+        assert_eq!(images_to_flash(&[], &images), [true, false]);
+    }
+
+    #[test]
+    fn up_to_date_image_in_shared_sector_is_flashed() {
+        let images = [
+            extent(false, 0x0000..0x0800, 0x0000..0x1000),
+            extent(true, 0x0F00..0x1100, 0x0000..0x2000),
+            // Shares a sector with the previous image only, which has to be flashed as well.
+            extent(true, 0x1800..0x1900, 0x1000..0x2000),
+            extent(true, 0x3000..0x3100, 0x3000..0x4000),
+        ];
+
+        assert_eq!(images_to_flash(&[], &images), [true, true, true, false]);
+    }
+
+    #[test]
+    fn up_to_date_image_in_sector_of_staged_data_is_flashed() {
+        let images = [
+            extent(true, 0x1800..0x1900, 0x1000..0x2000),
+            extent(true, 0x3000..0x3100, 0x3000..0x4000),
+        ];
+
+        assert_eq!(
+            images_to_flash(&[0x0000..0x0800, 0x0F00..0x1100], &images),
+            [true, false]
+        );
+    }
+}
@@ -1,14 +1,20 @@
 use std::{
     fs::File,
     io::Write,
+    ops::Range,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
+use super::bundle::BundleError;
 use super::cargo::ArtifactError;
-use crate::util::parse_u64;
+use super::elf_symbols::{ElfSymbol, ElfSymbols};
+use crate::util::{parse_range, parse_u64};
 use probe_rs::{
-    config::{RegistryError, TargetSelector},
-    flashing::{FileDownloadError, FlashError},
+    config::{
+        MemoryRange, MemoryRegion, NvmRegion, RawFlashAlgorithm, RegistryError, TargetSelector,
+    },
+    flashing::{ElfAlgorithmError, FileDownloadError, FlashError, PreservedRange},
     integration::FakeProbe,
     probe::{
         list::Lister, DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe, WireProtocol,
@@ -42,6 +48,148 @@ pub struct BinaryDownloadOptions {
     /// After flashing, read back all the flashed data to verify it has been written correctly.
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub verify: bool,
+    /// Keeps the contents of a memory range when the flash around it is erased, even with a chip
+    /// erase, like `calibration=0x0801F800..0x08020000`.
+    #[arg(
+        long = "preserve",
+        value_name = "NAME=START..END",
+        value_parser = parse_preserved_range,
+        help_heading = "DOWNLOAD CONFIGURATION"
+    )]
+    pub preserved_ranges: Vec<PreservedRange>,
+}
+
+/// Parses a preserved range like `calibration=0x0801F800..0x08020000`, where the name is optional.
+fn parse_preserved_range(input: &str) -> anyhow::Result<PreservedRange> {
+    let (name, range) = match input.split_once('=') {
+        Some((name, range)) => (name.trim().to_string(), range),
+        None => (input.trim().to_string(), input),
+    };
+
+    Ok(PreservedRange {
+        name,
+        range: parse_range(range)?,
+    })
+}
+
+/// Options to flash with a flash algorithm from an ELF file instead of the ones of the target
+/// description.
+#[derive(Debug, Default, clap::Parser)]
+pub struct FlashAlgorithmOptions {
+    /// Flashes with the flash algorithm in this ELF file, like a CMSIS-Pack `.FLM` file or a
+    /// binary built with the `flash-algorithm` crate. Requires `--chip`.
+    #[arg(long, value_name = "ELF", help_heading = "DOWNLOAD CONFIGURATION")]
+    pub flash_algorithm: Option<PathBuf>,
+    /// The RAM address the flash algorithm is loaded to, instead of the start of the RAM.
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        value_parser = parse_u64,
+        requires = "flash_algorithm",
+        help_heading = "DOWNLOAD CONFIGURATION"
+    )]
+    pub flash_algorithm_address: Option<u64>,
+    /// The memory range programmed with the flash algorithm, instead of the range of its
+    /// `FlashDevice` description.
+    #[arg(
+        long,
+        value_name = "START..END",
+        value_parser = parse_range,
+        requires = "flash_algorithm",
+        help_heading = "DOWNLOAD CONFIGURATION"
+    )]
+    pub flash_algorithm_range: Option<Range<u64>>,
+}
+
+impl FlashAlgorithmOptions {
+    /// Adds the flash algorithm to the selected target, if one was given.
+    pub fn apply(&self, target: TargetSelector) -> Result<TargetSelector, OperationError> {
+        let Some(path) = &self.flash_algorithm else {
+            return Ok(target);
+        };
+        let TargetSelector::Specified(mut target) = target else {
+            return Err(OperationError::FlashAlgorithmWithoutChip);
+        };
+
+        let elf = std::fs::read(path).map_err(|source| OperationError::FailedToOpenElf {
+            source,
+            path: path.clone(),
+        })?;
+        let mut algorithm =
+            probe_rs::flashing::extract_flash_algorithm(&elf, false).map_err(|source| {
+                OperationError::FailedToLoadFlashAlgorithm {
+                    source,
+                    path: path.clone(),
+                }
+            })?;
+
+        algorithm.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        algorithm.load_address = self.flash_algorithm_address;
+        if let Some(range) = &self.flash_algorithm_range {
+            algorithm.flash_properties.address_range = range.clone();
+        }
+
+        add_flash_algorithm(&mut target, algorithm)?;
+
+        Ok(TargetSelector::Specified(target))
+    }
+}
+
+/// Makes `algorithm` the only flash algorithm of `target` for its address range, adding a flash
+/// region for the range if the memory map has none.
+fn add_flash_algorithm(
+    target: &mut Target,
+    mut algorithm: RawFlashAlgorithm,
+) -> Result<(), OperationError> {
+    let range = algorithm.flash_properties.address_range.clone();
+    let cores = target
+        .cores
+        .iter()
+        .map(|core| core.name.clone())
+        .collect::<Vec<_>>();
+
+    let mut has_region = false;
+    for region in &target.memory_map {
+        let MemoryRegion::Nvm(region) = region else {
+            continue;
+        };
+        if region.is_alias || !region.range.intersects_range(&range) {
+            continue;
+        }
+        if !range.contains_range(&region.range) {
+            return Err(OperationError::FlashAlgorithmRangeMismatch {
+                range,
+                region: region.range.clone(),
+            });
+        }
+        has_region = true;
+    }
+
+    if !has_region {
+        target.memory_map.push(MemoryRegion::Nvm(NvmRegion {
+            name: Some(algorithm.name.clone()),
+            range: range.clone(),
+            cores: cores.clone(),
+            is_alias: false,
+            access: None,
+        }));
+    }
+
+    target.flash_algorithms.retain(|existing| {
+        !existing
+            .flash_properties
+            .address_range
+            .intersects_range(&range)
+    });
+
+    algorithm.default = true;
+    algorithm.cores = cores;
+    target.flash_algorithms.push(algorithm);
+
+    Ok(())
 }
 
 /// Supported bit-widths for read/write commands (not every device may support each width).
@@ -63,8 +211,81 @@ pub struct ReadWriteOptions {
     pub width: ReadWriteBitWidth,
     /// The address to start from.
     /// Takes an integer as an argument, and can be specified in decimal (16), hexadecimal (0x10) or octal (0o20) format.
-    #[clap(value_parser = parse_u64)]
-    pub address: u64,
+    /// With `--elf`, it can also be a symbol name with an optional offset, e.g. `SOME_STATIC+4`.
+    pub address: AddressExpression,
+    /// ELF file used to resolve symbol names in the address.
+    #[clap(long)]
+    pub elf: Option<PathBuf>,
+}
+
+impl ReadWriteOptions {
+    /// Resolves the address, looking up the symbol in the ELF file if necessary.
+    pub fn resolve_address(&self) -> anyhow::Result<u64> {
+        Ok(self.resolve()?.0)
+    }
+
+    /// Resolves the address, and returns the symbol it is based on, if any.
+    pub fn resolve(&self) -> anyhow::Result<(u64, Option<ElfSymbol>)> {
+        match &self.address {
+            AddressExpression::Numeric(address) => Ok((*address, None)),
+            AddressExpression::Symbol { name, offset } => {
+                let Some(elf) = &self.elf else {
+                    anyhow::bail!("The address `{name}` is a symbol name, which requires `--elf`");
+                };
+
+                let symbol = ElfSymbols::load(elf)?.find(name)?.clone();
+                let address = symbol.address.checked_add_signed(*offset).ok_or_else(|| {
+                    anyhow::anyhow!("Offset {offset} is out of range for symbol `{name}`")
+                })?;
+
+                Ok((address, Some(symbol)))
+            }
+        }
+    }
+}
+
+/// A memory address given on the command line, either a number or a symbol name with an
+/// optional offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressExpression {
+    Numeric(u64),
+    Symbol { name: String, offset: i64 },
+}
+
+impl FromStr for AddressExpression {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        if let Ok(address) = parse_u64(input) {
+            return Ok(Self::Numeric(address));
+        }
+
+        let (name, offset) = match input.rfind(['+', '-']) {
+            Some(index) if index > 0 => {
+                let offset = parse_u64(input[index + 1..].trim())
+                    .ok()
+                    .and_then(|offset| i64::try_from(offset).ok())
+                    .ok_or_else(|| format!("Invalid offset in address `{input}`"))?;
+                let offset = if input[index..].starts_with('-') {
+                    -offset
+                } else {
+                    offset
+                };
+                (input[..index].trim(), offset)
+            }
+            _ => (input, 0),
+        };
+
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '_' | '.' | '$')) {
+            return Err(format!("`{input}` is neither an address nor a symbol name"));
+        }
+
+        Ok(Self::Symbol {
+            name: name.to_string(),
+            offset,
+        })
+    }
 }
 
 /// Common options and logic when interfacing with a [Probe].
@@ -131,10 +352,20 @@ impl ProbeOptions {
     pub fn simple_attach(
         self,
         lister: &Lister,
+    ) -> Result<(Session, LoadedProbeOptions), OperationError> {
+        self.simple_attach_with_flash_algorithm(lister, &FlashAlgorithmOptions::default())
+    }
+
+    /// Like [Self::simple_attach], but adds the flash algorithm given by `flash_algorithm` to the
+    /// target.
+    pub fn simple_attach_with_flash_algorithm(
+        self,
+        lister: &Lister,
+        flash_algorithm: &FlashAlgorithmOptions,
     ) -> Result<(Session, LoadedProbeOptions), OperationError> {
         let common_options = self.load()?;
 
-        let target = common_options.get_target_selector()?;
+        let target = flash_algorithm.apply(common_options.get_target_selector()?)?;
         let probe = common_options.attach_probe(lister)?;
         let session = common_options.attach_session(probe, target)?;
 
@@ -151,6 +382,8 @@ impl LoadedProbeOptions {
     /// and returns a newtype that ensures initialization.
     pub(crate) fn new(probe_options: ProbeOptions) -> Result<Self, OperationError> {
         let options = Self(probe_options);
+        // Pick up changes to the user target descriptions since the last session.
+        probe_rs::config::reload_user_targets();
         // Load the target description, if given in the cli parameters.
         options.maybe_load_chip_desc()?;
         Ok(options)
@@ -250,6 +483,13 @@ impl LoadedProbeOptions {
             }
         };
 
+        self.configure_probe(&mut probe)?;
+
+        Ok(probe)
+    }
+
+    /// Selects the protocol and speed given by the command line options.
+    pub fn configure_probe(&self, probe: &mut Probe) -> Result<(), OperationError> {
         if let Some(protocol) = self.0.protocol {
             // Select protocol and speed
             probe.select_protocol(protocol).map_err(|error| {
@@ -284,7 +524,17 @@ impl LoadedProbeOptions {
             tracing::info!("Protocol speed {} kHz", protocol_speed);
         }
 
-        Ok(probe)
+        Ok(())
+    }
+
+    /// The permissions granted to the session by the command line options.
+    pub fn permissions(&self) -> Permissions {
+        let mut permissions = Permissions::new();
+        if self.0.allow_erase_all {
+            permissions = permissions.allow_erase_all();
+        }
+
+        permissions
     }
 
     /// Attaches to target device session. Attaches under reset if
@@ -294,11 +544,17 @@ impl LoadedProbeOptions {
         probe: Probe,
         target: TargetSelector,
     ) -> Result<Session, OperationError> {
-        let mut permissions = Permissions::new();
-        if self.0.allow_erase_all {
-            permissions = permissions.allow_erase_all();
-        }
+        self.attach_session_with_permissions(probe, target, self.permissions())
+    }
 
+    /// Like [Self::attach_session], but grants the given permissions instead of the ones
+    /// selected by the command line options.
+    pub fn attach_session_with_permissions(
+        &self,
+        probe: Probe,
+        target: TargetSelector,
+        permissions: Permissions,
+    ) -> Result<Session, OperationError> {
         let session = if self.0.connect_under_reset {
             probe.attach_under_reset(target, permissions)
         } else {
@@ -454,7 +710,6 @@ pub enum OperationError {
     NoProbesFound,
 
     #[error("Failed to open the ELF file '{path}' for flashing.")]
-    #[allow(dead_code)]
     FailedToOpenElf {
         #[source]
         source: std::io::Error,
@@ -465,6 +720,26 @@ pub enum OperationError {
     #[allow(dead_code)]
     FailedToLoadElfData(#[source] FileDownloadError),
 
+    #[error("Failed to load the flash bundle.")]
+    FailedToLoadBundle(#[source] BundleError),
+
+    #[error("Failed to load the flash algorithm '{path}'.")]
+    FailedToLoadFlashAlgorithm {
+        source: ElfAlgorithmError,
+        path: PathBuf,
+    },
+
+    #[error("A flash algorithm can only be given together with `--chip`.")]
+    FlashAlgorithmWithoutChip,
+
+    #[error(
+        "The flash algorithm range {range:#010x?} only covers part of the flash region {region:#010x?}."
+    )]
+    FlashAlgorithmRangeMismatch {
+        range: Range<u64>,
+        region: Range<u64>,
+    },
+
     #[error("Failed to open the debug probe.")]
     FailedToOpenProbe(#[from] DebugProbeError),
 
@@ -599,4 +874,83 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn parse_address_expression() {
+        let symbol = |name: &str, offset| AddressExpression::Symbol {
+            name: name.to_string(),
+            offset,
+        };
+
+        assert_eq!(
+            "0x2000_0000".parse(),
+            Ok(AddressExpression::Numeric(0x2000_0000))
+        );
+        assert_eq!("16".parse(), Ok(AddressExpression::Numeric(16)));
+        assert_eq!("SOME_STATIC".parse(), Ok(symbol("SOME_STATIC", 0)));
+        assert_eq!("SOME_STATIC+4".parse(), Ok(symbol("SOME_STATIC", 4)));
+        assert_eq!("app::STATE - 0x10".parse(), Ok(symbol("app::STATE", -16)));
+        assert!("SOME_STATIC+x".parse::<AddressExpression>().is_err());
+    }
+
+    #[test]
+    fn parse_preserved_ranges() {
+        assert_eq!(
+            parse_preserved_range("calibration=0x0801F800..0x08020000").unwrap(),
+            PreservedRange {
+                name: "calibration".to_string(),
+                range: 0x0801_F800..0x0802_0000,
+            }
+        );
+        assert_eq!(
+            parse_preserved_range("0x1000..0x1100").unwrap().name,
+            "0x1000..0x1100"
+        );
+        assert!(parse_preserved_range("settings=0x1000").is_err());
+    }
+
+    #[test]
+    fn add_flash_algorithms() {
+        let target = probe_rs::config::get_target_by_name("nRF52840_xxAA").unwrap();
+        let algorithm = |range: Range<u64>| RawFlashAlgorithm {
+            name: "custom".to_string(),
+            flash_properties: probe_rs::config::FlashProperties {
+                address_range: range,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // A range outside of the memory map gets its own flash region.
+        let mut external = target.clone();
+        add_flash_algorithm(&mut external, algorithm(0x1200_0000..0x1300_0000)).unwrap();
+        assert_eq!(external.memory_map.len(), target.memory_map.len() + 1);
+        assert!(matches!(
+            external.memory_map.last(),
+            Some(MemoryRegion::Nvm(region)) if region.range == (0x1200_0000..0x1300_0000)
+        ));
+        assert_eq!(
+            external.flash_algorithms.len(),
+            target.flash_algorithms.len() + 1
+        );
+
+        // The algorithm replaces the ones of the target for the internal flash.
+        let mut internal = target.clone();
+        add_flash_algorithm(&mut internal, algorithm(0..0x10_0000)).unwrap();
+        assert_eq!(internal.memory_map.len(), target.memory_map.len());
+        let algorithms = internal
+            .flash_algorithms
+            .iter()
+            .filter(|algorithm| algorithm.flash_properties.address_range.contains(&0))
+            .collect::<Vec<_>>();
+        assert_eq!(algorithms.len(), 1);
+        assert_eq!(algorithms[0].name, "custom");
+        assert!(algorithms[0].default);
+
+        let mut partial = target.clone();
+        assert!(matches!(
+            add_flash_algorithm(&mut partial, algorithm(0..0x1000)),
+            Err(OperationError::FlashAlgorithmRangeMismatch { .. })
+        ));
+    }
 }
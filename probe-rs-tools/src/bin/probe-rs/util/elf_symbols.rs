@@ -0,0 +1,133 @@
+//! Lookup of data symbols in an ELF file by name.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use goblin::elf::{
+    sym::{STT_NOTYPE, STT_OBJECT},
+    Elf,
+};
+
+/// A symbol of an ELF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfSymbol {
+    /// The name of the symbol, as stored in the ELF file.
+    pub name: String,
+    /// The demangled name of the symbol, without the trailing hash of Rust symbols.
+    pub demangled: String,
+    pub address: u64,
+    /// The size of the symbol in bytes, `0` if unknown.
+    pub size: u64,
+}
+
+/// The symbol table of an ELF file.
+#[derive(Debug)]
+pub struct ElfSymbols {
+    symbols: Vec<ElfSymbol>,
+}
+
+impl ElfSymbols {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let buffer = std::fs::read(path)
+            .with_context(|| format!("Failed to read ELF file {}", path.display()))?;
+
+        Self::parse(&buffer).with_context(|| format!("Failed to parse ELF file {}", path.display()))
+    }
+
+    pub fn parse(buffer: &[u8]) -> anyhow::Result<Self> {
+        let elf = Elf::parse(buffer)?;
+
+        let symbols = elf
+            .syms
+            .iter()
+            // Data objects, and untyped symbols like the ones defined in linker scripts.
+            .filter(|sym| matches!(sym.st_type(), STT_OBJECT | STT_NOTYPE))
+            .filter_map(|sym| {
+                let name = elf.strtab.get_at(sym.st_name)?;
+                if name.is_empty() {
+                    return None;
+                }
+
+                Some(ElfSymbol {
+                    name: name.to_string(),
+                    demangled: format!("{:#}", rustc_demangle::demangle(name)),
+                    address: sym.st_value,
+                    size: sym.st_size,
+                })
+            })
+            .collect();
+
+        Ok(Self { symbols })
+    }
+
+    /// Finds a symbol by name.
+    ///
+    /// `name` is either the name as stored in the ELF file, the full demangled path
+    /// (`app::module::STATIC`), or its trailing part (`module::STATIC` or `STATIC`), as long as it
+    /// is unique.
+    pub fn find(&self, name: &str) -> anyhow::Result<&ElfSymbol> {
+        if let Some(symbol) = self
+            .symbols
+            .iter()
+            .find(|symbol| symbol.name == name || symbol.demangled == name)
+        {
+            return Ok(symbol);
+        }
+
+        let suffix = format!("::{name}");
+        let candidates = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.demangled.ends_with(&suffix))
+            .collect::<Vec<_>>();
+
+        match candidates.as_slice() {
+            [] => Err(anyhow!("Symbol `{name}` not found in the ELF file")),
+            [symbol] => Ok(symbol),
+            _ => bail!(
+                "Symbol `{name}` is ambiguous, it matches {}",
+                candidates
+                    .iter()
+                    .map(|symbol| format!("`{}`", symbol.demangled))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn symbols() -> ElfSymbols {
+        let symbol = |name: &str, address| ElfSymbol {
+            name: name.to_string(),
+            demangled: format!("{:#}", rustc_demangle::demangle(name)),
+            address,
+            size: 4,
+        };
+
+        ElfSymbols {
+            symbols: vec![
+                symbol("COUNTER", 0x2000_0000),
+                symbol("_ZN3app5state5STATE17h0123456789abcdefE", 0x2000_0004),
+                symbol("_ZN3app5other5STATE17h0123456789abcdefE", 0x2000_0008),
+            ],
+        }
+    }
+
+    #[test]
+    fn find_symbols() {
+        let symbols = symbols();
+
+        assert_eq!(symbols.find("COUNTER").unwrap().address, 0x2000_0000);
+        assert_eq!(
+            symbols.find("app::state::STATE").unwrap().address,
+            0x2000_0004
+        );
+        assert_eq!(symbols.find("other::STATE").unwrap().address, 0x2000_0008);
+        assert!(symbols.find("STATE").is_err());
+        assert!(symbols.find("MISSING").is_err());
+    }
+}
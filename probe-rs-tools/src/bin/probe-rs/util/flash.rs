@@ -1,5 +1,6 @@
 use crate::FormatOptions;
 
+use super::bundle::{self, BundleError};
 use super::common_options::{BinaryDownloadOptions, LoadedProbeOptions, OperationError};
 use super::logging;
 
@@ -33,6 +34,7 @@ pub fn run_flash_download(
     options.disable_double_buffering = download_options.disable_double_buffering;
     options.verify = download_options.verify;
     options.preverify = download_options.preverify;
+    options.preserved_ranges = download_options.preserved_ranges.clone();
 
     if !download_options.disable_progressbars {
         // Create progress bars.
@@ -166,11 +168,22 @@ pub fn build_loader(
     format_options: FormatOptions,
     image_instruction_set: Option<InstructionSet>,
 ) -> Result<FlashLoader, FileDownloadError> {
-    let format = format_options.into_format(session.target());
+    let format = format_options.into_format(session.target(), path.as_ref());
 
     probe_rs::flashing::build_loader(session, path, format, image_instruction_set)
 }
 
+/// Builds a new flash loader for the images of the bundle manifest at `path`.
+pub fn build_bundle_loader(
+    session: &mut Session,
+    path: impl AsRef<Path>,
+) -> Result<FlashLoader, BundleError> {
+    let mut loader = session.target().flash_loader();
+    bundle::add_bundle(session, &mut loader, path.as_ref())?;
+
+    Ok(loader)
+}
+
 struct ProgressBars {
     erase: ProgressBarGroup,
     fill: ProgressBarGroup,
@@ -0,0 +1,328 @@
+//! Writing memory contents to image files.
+
+use anyhow::bail;
+use object::{
+    elf,
+    write::elf::{FileHeader, ProgramHeader, SectionHeader, Writer},
+    Endianness,
+};
+use probe_rs::CoreType;
+
+/// The value of erased flash memory.
+const ERASED: u8 = 0xFF;
+
+/// Erased data is skipped in chunks of this many bytes, the length of an Intel HEX data record.
+const ERASED_CHUNK_SIZE: usize = 16;
+
+/// The maximum size of a raw binary image, to avoid filling large gaps between regions.
+const MAX_BINARY_SIZE: u64 = 256 * 1024 * 1024;
+
+/// A contiguous block of memory contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// The name of the memory region the data was read from.
+    pub name: String,
+    /// The address of the first byte.
+    pub address: u64,
+    /// The memory contents.
+    pub data: Vec<u8>,
+}
+
+impl Segment {
+    fn end(&self) -> u64 {
+        self.address + self.data.len() as u64
+    }
+
+    /// Splits the segment at erased chunks, which are dropped.
+    ///
+    /// Chunks are aligned to [`ERASED_CHUNK_SIZE`] relative to the start of the segment.
+    pub fn without_erased(self) -> Vec<Segment> {
+        let mut segments = Vec::<Segment>::new();
+        let mut offset = 0;
+        for chunk in self.data.chunks(ERASED_CHUNK_SIZE) {
+            let address = self.address + offset;
+            offset += chunk.len() as u64;
+
+            if chunk.iter().all(|&byte| byte == ERASED) {
+                continue;
+            }
+
+            match segments.last_mut() {
+                Some(last) if last.end() == address => last.data.extend_from_slice(chunk),
+                _ => segments.push(Segment {
+                    name: self.name.clone(),
+                    address,
+                    data: chunk.to_vec(),
+                }),
+            }
+        }
+
+        segments
+    }
+}
+
+/// Formats the segments as an Intel HEX file.
+pub fn ihex(segments: &[Segment]) -> anyhow::Result<String> {
+    let mut records = Vec::new();
+    let mut upper = None;
+    for segment in segments {
+        if segment.end() > 1 << 32 {
+            bail!(
+                "Intel HEX only supports 32-bit addresses, the data ends at {:#x}",
+                segment.end()
+            );
+        }
+
+        let bytes = &segment.data;
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let current = segment.address + offset as u64;
+            if upper != Some(current >> 16) {
+                upper = Some(current >> 16);
+                records.push(ihex::Record::ExtendedLinearAddress((current >> 16) as u16));
+            }
+
+            // Records must not cross a 64 KiB boundary.
+            let len = (bytes.len() - offset)
+                .min(16)
+                .min((0x1_0000 - (current & 0xffff)) as usize);
+            records.push(ihex::Record::Data {
+                offset: current as u16,
+                value: bytes[offset..offset + len].to_vec(),
+            });
+            offset += len;
+        }
+    }
+    records.push(ihex::Record::EndOfFile);
+
+    Ok(ihex::create_object_file_representation(&records)?)
+}
+
+/// Concatenates the segments into a raw binary, filling gaps with erased bytes.
+///
+/// The binary starts at the address of the first segment.
+pub fn binary(segments: &[Segment]) -> anyhow::Result<Vec<u8>> {
+    let Some(start) = segments.iter().map(|segment| segment.address).min() else {
+        return Ok(Vec::new());
+    };
+    let end = segments.iter().map(Segment::end).max().unwrap_or(start);
+
+    if end - start > MAX_BINARY_SIZE {
+        bail!(
+            "The binary image would span {:#x}..{end:#x}, select fewer regions or use the HEX or ELF format",
+            start
+        );
+    }
+
+    let mut image = vec![ERASED; (end - start) as usize];
+    for segment in segments {
+        let offset = (segment.address - start) as usize;
+        image[offset..][..segment.data.len()].copy_from_slice(&segment.data);
+    }
+
+    Ok(image)
+}
+
+/// Creates an ELF file with one `PT_LOAD` segment and one section per memory segment.
+///
+/// The sections are named after the memory regions, so the image can be flashed again.
+pub fn elf(segments: &[Segment], core_type: CoreType) -> anyhow::Result<Vec<u8>> {
+    let machine = match core_type {
+        CoreType::Armv6m
+        | CoreType::Armv7a
+        | CoreType::Armv7m
+        | CoreType::Armv7em
+        | CoreType::Armv8m => elf::EM_ARM,
+        CoreType::Armv8a => elf::EM_AARCH64,
+        CoreType::Riscv => elf::EM_RISCV,
+        CoreType::Xtensa => elf::EM_XTENSA,
+    };
+    let is_64 =
+        machine == elf::EM_AARCH64 || segments.iter().any(|segment| segment.end() > 1 << 32);
+    if is_64 && machine == elf::EM_ARM {
+        bail!("32-bit ARM ELF files can not contain data above 4 GiB");
+    }
+    // The program header count has to fit into `e_phnum`.
+    if segments.len() >= usize::from(elf::PN_XNUM) {
+        bail!("Too many segments for an ELF file: {}", segments.len());
+    }
+
+    let names = segments
+        .iter()
+        .map(|segment| format!(".{}", segment.name))
+        .collect::<Vec<_>>();
+
+    let mut image = Vec::new();
+    let mut writer = Writer::new(Endianness::Little, is_64, &mut image);
+
+    writer.reserve_file_header();
+    writer.reserve_program_headers(segments.len() as u32);
+    let offsets = segments
+        .iter()
+        .map(|segment| writer.reserve(segment.data.len(), 1) as u64)
+        .collect::<Vec<_>>();
+
+    writer.reserve_null_section_index();
+    let sections = names
+        .iter()
+        .map(|name| {
+            writer.reserve_section_index();
+            writer.add_section_name(name.as_bytes())
+        })
+        .collect::<Vec<_>>();
+    writer.reserve_shstrtab_section_index();
+    writer.reserve_shstrtab();
+    writer.reserve_section_headers();
+
+    writer.write_file_header(&FileHeader {
+        os_abi: elf::ELFOSABI_NONE,
+        abi_version: 0,
+        e_type: elf::ET_EXEC,
+        e_machine: machine,
+        e_entry: 0,
+        e_flags: 0,
+    })?;
+
+    writer.write_align_program_headers();
+    for (segment, &offset) in segments.iter().zip(&offsets) {
+        let size = segment.data.len() as u64;
+        writer.write_program_header(&ProgramHeader {
+            p_type: elf::PT_LOAD,
+            p_flags: elf::PF_R,
+            p_offset: offset,
+            p_vaddr: segment.address,
+            p_paddr: segment.address,
+            p_filesz: size,
+            p_memsz: size,
+            p_align: 1,
+        });
+    }
+
+    for segment in segments {
+        writer.write(&segment.data);
+    }
+    writer.write_shstrtab();
+
+    writer.write_null_section_header();
+    for ((segment, &offset), name) in segments.iter().zip(&offsets).zip(sections) {
+        writer.write_section_header(&SectionHeader {
+            name: Some(name),
+            sh_type: elf::SHT_PROGBITS,
+            sh_flags: elf::SHF_ALLOC.into(),
+            sh_addr: segment.address,
+            sh_offset: offset,
+            sh_size: segment.data.len() as u64,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: 1,
+            sh_entsize: 0,
+        });
+    }
+    writer.write_shstrtab_section_header();
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn segment(address: u64, data: &[u8]) -> Segment {
+        Segment {
+            name: "flash".to_string(),
+            address,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn skip_erased_chunks() {
+        let mut data = vec![ERASED; 64];
+        data[3] = 0;
+        data[40] = 1;
+        data[47] = 2;
+
+        let segments = segment(0x1000, &data).without_erased();
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| (segment.address, segment.data.len()))
+                .collect::<Vec<_>>(),
+            [(0x1000, 16), (0x1020, 16)]
+        );
+        assert_eq!(segments[1].data[8], 1);
+    }
+
+    #[test]
+    fn sparse_ihex() {
+        assert_eq!(
+            ihex(&[
+                segment(0x2000_fffe, &[1, 2, 3, 4]),
+                segment(0x2001_0010, &[5])
+            ])
+            .unwrap(),
+            ":020000042000DA\n\
+             :02FFFE000102FE\n\
+             :020000042001D9\n\
+             :020000000304F7\n\
+             :0100100005EA\n\
+             :00000001FF\n"
+        );
+    }
+
+    #[test]
+    fn binary_fills_gaps() {
+        assert_eq!(
+            binary(&[segment(0x10, &[1, 2]), segment(0x14, &[3])]).unwrap(),
+            [1, 2, ERASED, ERASED, 3]
+        );
+        assert!(binary(&[segment(0, &[1]), segment(0x1000_0000, &[2])]).is_err());
+    }
+
+    #[test]
+    fn elf_segments() {
+        use object::{Object, ObjectSection, ObjectSegment};
+
+        let segments = [segment(0x0800_0000, &[1, 2, 3]), segment(0x1fff_7800, &[4])];
+        let image = elf(&segments, CoreType::Armv7em).unwrap();
+
+        let file = object::File::parse(&*image).unwrap();
+        assert_eq!(file.architecture(), object::Architecture::Arm);
+        assert!(!file.is_64());
+
+        let program_segments = file
+            .segments()
+            .map(|segment| (segment.address(), segment.data().unwrap().to_vec()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            program_segments,
+            [(0x0800_0000, vec![1, 2, 3]), (0x1fff_7800, vec![4])]
+        );
+
+        let sections = file
+            .sections()
+            .map(|section| (section.name().unwrap().to_string(), section.address()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            [
+                (".flash".to_string(), 0x0800_0000),
+                (".flash".to_string(), 0x1fff_7800),
+                (".shstrtab".to_string(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn elf64_above_4gib() {
+        use object::{Object, ObjectSegment};
+
+        let image = elf(&[segment(0x1_0000_0000, &[1])], CoreType::Riscv).unwrap();
+
+        let file = object::File::parse(&*image).unwrap();
+        assert!(file.is_64());
+        assert_eq!(file.segments().next().unwrap().address(), 0x1_0000_0000);
+
+        assert!(elf(&[segment(0x1_0000_0000, &[1])], CoreType::Armv7em).is_err());
+    }
+}
@@ -1,11 +1,19 @@
+pub mod bundle;
 pub mod cargo;
 pub mod common_options;
+pub mod elf_symbols;
 pub mod flash;
+pub mod image;
 pub mod logging;
 pub mod meta;
+pub mod patch;
 pub mod rtt;
+pub mod svd;
 
 use std::num::ParseIntError;
+use std::ops::Range;
+
+use anyhow::{bail, Context};
 
 pub fn parse_u32(input: &str) -> Result<u32, ParseIntError> {
     parse_int::parse(input)
@@ -14,3 +22,35 @@ pub fn parse_u32(input: &str) -> Result<u32, ParseIntError> {
 pub fn parse_u64(input: &str) -> Result<u64, ParseIntError> {
     parse_int::parse(input)
 }
+
+/// Parses a range like `0x08000000..0x08004000`.
+pub fn parse_range(input: &str) -> anyhow::Result<Range<u64>> {
+    let Some((start, end)) = input.split_once("..") else {
+        bail!("Expected a range like `0x08000000..0x08004000`, got `{input}`");
+    };
+
+    let start = parse_int::parse::<u64>(start.trim())
+        .with_context(|| format!("Invalid start address `{start}`"))?;
+    let end = parse_int::parse::<u64>(end.trim())
+        .with_context(|| format!("Invalid end address `{end}`"))?;
+    if end <= start {
+        bail!("The range `{input}` is empty");
+    }
+
+    Ok(start..end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_address_range() {
+        assert_eq!(
+            parse_range("0x08000000..0x08004000").unwrap(),
+            0x0800_0000..0x0800_4000
+        );
+        assert!(parse_range("0x1000").is_err());
+        assert!(parse_range("0x1000..0x1000").is_err());
+    }
+}
@@ -0,0 +1,338 @@
+//! Per-device data, like serial numbers or calibration data, which is written over the image at
+//! flash time.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use probe_rs::flashing::{DataPatch, FlashLoader};
+use probe_rs_debug::DebugInfo;
+
+use super::elf_symbols::ElfSymbols;
+
+/// The width of integers written to absolute addresses.
+const DEFAULT_INTEGER_SIZE: u64 = 4;
+
+/// Options to replace parts of the image with per-device data.
+#[derive(Debug, Default, clap::Parser)]
+pub struct PatchOptions {
+    /// Replaces the bytes of an ELF symbol or an absolute address, like `SERIAL_NUMBER=1234`
+    ///
+    /// Values are integers, which fill the symbol or 32 bits at absolute addresses,
+    /// `hex:0011AABB` for raw bytes, `str:TEXT` for UTF-8 text, `file:PATH` for the
+    /// contents of a file, or `counter:PATH` for an integer read from a file, which
+    /// is incremented after flashing.
+    #[arg(
+        long = "patch",
+        value_name = "TARGET=VALUE",
+        help_heading = "DOWNLOAD CONFIGURATION"
+    )]
+    pub patches: Vec<String>,
+
+    /// Reads patches from a JSON object mapping symbols or addresses to values
+    ///
+    /// Values are strings like for `--patch`, integers, or arrays of bytes.
+    #[arg(long, value_name = "FILE", help_heading = "DOWNLOAD CONFIGURATION")]
+    pub patch_json: Option<PathBuf>,
+}
+
+/// Where a patch is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatchTarget {
+    Symbol(String),
+    Address(u64),
+}
+
+/// What a patch writes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatchValue {
+    Integer(u64),
+    Bytes(Vec<u8>),
+    Counter(PathBuf),
+}
+
+/// Patches resolved to addresses.
+#[derive(Debug, Default)]
+pub struct Patches {
+    patches: Vec<DataPatch>,
+    /// Counter files and the values which were written to the device.
+    counters: Vec<(PathBuf, u64)>,
+}
+
+impl PatchOptions {
+    /// Resolves the patches, looking up symbols in the ELF file `elf`.
+    pub fn resolve(&self, elf: Option<&Path>) -> anyhow::Result<Patches> {
+        let mut requests = self
+            .patches
+            .iter()
+            .map(|patch| parse_patch(patch))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if let Some(path) = &self.patch_json {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            requests.extend(
+                parse_json(&json)
+                    .with_context(|| format!("Failed to parse patches from {}", path.display()))?,
+            );
+        }
+
+        let has_symbols = requests
+            .iter()
+            .any(|(target, _)| matches!(target, PatchTarget::Symbol(_)));
+        let symbols = match elf {
+            Some(elf) if has_symbols => {
+                Some((ElfSymbols::load(elf)?, DebugInfo::from_file(elf).ok()))
+            }
+            _ => None,
+        };
+
+        let mut patches = Patches::default();
+        for (target, value) in requests {
+            let (address, size) = match &target {
+                PatchTarget::Address(address) => (*address, None),
+                PatchTarget::Symbol(name) => {
+                    let Some((symbols, debug_info)) = &symbols else {
+                        bail!("Patching the symbol `{name}` requires an ELF image");
+                    };
+
+                    let symbol = symbols.find(name)?;
+                    let size = debug_info
+                        .as_ref()
+                        .and_then(|debug_info| debug_info.static_variable_size(symbol.address))
+                        .or((symbol.size != 0).then_some(symbol.size));
+                    let Some(size) = size else {
+                        bail!("The size of the symbol `{name}` is unknown");
+                    };
+
+                    (symbol.address, Some(size))
+                }
+            };
+
+            if let PatchValue::Counter(path) = &value {
+                patches.counters.push((path.clone(), read_counter(path)?));
+            }
+
+            let data = patch_data(&value, size, &patches.counters)
+                .with_context(|| format!("Invalid patch for {}", describe(&target)))?;
+            patches.patches.push(DataPatch { address, data });
+        }
+
+        Ok(patches)
+    }
+}
+
+impl Patches {
+    /// Writes the patches over the data staged in `loader`.
+    pub fn apply(&self, loader: &mut FlashLoader) -> anyhow::Result<()> {
+        for patch in &self.patches {
+            loader
+                .patch_data(patch.address, &patch.data)
+                .with_context(|| format!("Failed to patch the data at {:#010x}", patch.address))?;
+        }
+
+        Ok(())
+    }
+
+    /// Increments the counters, which should be done once the device was flashed.
+    pub fn increment_counters(&self) -> anyhow::Result<()> {
+        for (path, value) in &self.counters {
+            std::fs::write(path, format!("{}\n", value + 1))
+                .with_context(|| format!("Failed to update the counter {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn describe(target: &PatchTarget) -> String {
+    match target {
+        PatchTarget::Symbol(name) => format!("`{name}`"),
+        PatchTarget::Address(address) => format!("{address:#010x}"),
+    }
+}
+
+/// Parses a patch like `SERIAL_NUMBER=1234`.
+fn parse_patch(patch: &str) -> anyhow::Result<(PatchTarget, PatchValue)> {
+    let Some((target, value)) = patch.split_once('=') else {
+        bail!("Expected a patch like `SYMBOL=VALUE`, got `{patch}`");
+    };
+
+    Ok((parse_target(target.trim()), parse_value(value.trim())?))
+}
+
+fn parse_target(target: &str) -> PatchTarget {
+    match parse_int::parse::<u64>(target) {
+        Ok(address) => PatchTarget::Address(address),
+        Err(_) => PatchTarget::Symbol(target.to_string()),
+    }
+}
+
+fn parse_value(value: &str) -> anyhow::Result<PatchValue> {
+    if let Some(hex) = value.strip_prefix("hex:") {
+        let hex = hex.replace([' ', ':', '-'], "");
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            bail!("Invalid hex data `{hex}`");
+        }
+
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Invalid hex data `{hex}`"))?;
+        Ok(PatchValue::Bytes(bytes))
+    } else if let Some(text) = value.strip_prefix("str:") {
+        Ok(PatchValue::Bytes(text.as_bytes().to_vec()))
+    } else if let Some(path) = value.strip_prefix("file:") {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read the file {path}"))?;
+        Ok(PatchValue::Bytes(bytes))
+    } else if let Some(path) = value.strip_prefix("counter:") {
+        Ok(PatchValue::Counter(PathBuf::from(path)))
+    } else {
+        let integer =
+            parse_int::parse::<u64>(value).with_context(|| format!("Invalid value `{value}`"))?;
+        Ok(PatchValue::Integer(integer))
+    }
+}
+
+/// Parses a JSON object like `{ "SERIAL_NUMBER": 1234, "MAC_ADDRESS": [0, 1, 2, 3, 4, 5] }`.
+fn parse_json(json: &str) -> anyhow::Result<Vec<(PatchTarget, PatchValue)>> {
+    let serde_json::Value::Object(object) = serde_json::from_str(json)? else {
+        bail!("Expected an object mapping symbols or addresses to values");
+    };
+
+    object
+        .into_iter()
+        .map(|(target, value)| {
+            let value = match value {
+                serde_json::Value::String(value) => parse_value(&value)?,
+                serde_json::Value::Number(number) => match number.as_u64() {
+                    Some(integer) => PatchValue::Integer(integer),
+                    None => bail!("Invalid value {number} for `{target}`"),
+                },
+                serde_json::Value::Array(bytes) => PatchValue::Bytes(
+                    bytes
+                        .iter()
+                        .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                        .collect::<Option<Vec<_>>>()
+                        .with_context(|| format!("Invalid byte array for `{target}`"))?,
+                ),
+                other => bail!("Invalid value {other} for `{target}`"),
+            };
+
+            Ok((parse_target(&target), value))
+        })
+        .collect()
+}
+
+fn read_counter(path: &Path) -> anyhow::Result<u64> {
+    let counter = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the counter {}", path.display()))?;
+
+    parse_int::parse::<u64>(counter.trim())
+        .with_context(|| format!("Invalid counter value in {}", path.display()))
+}
+
+/// Returns the bytes to write for `value`, checking that they fit into `size` bytes.
+fn patch_data(
+    value: &PatchValue,
+    size: Option<u64>,
+    counters: &[(PathBuf, u64)],
+) -> anyhow::Result<Vec<u8>> {
+    let integer = match value {
+        PatchValue::Bytes(bytes) => {
+            if let Some(size) = size {
+                if bytes.len() as u64 > size {
+                    bail!("{} bytes do not fit into {size} bytes", bytes.len());
+                }
+            }
+            return Ok(bytes.clone());
+        }
+        PatchValue::Integer(integer) => *integer,
+        PatchValue::Counter(path) => counters
+            .iter()
+            .find(|(counter, _)| counter == path)
+            .map(|(_, value)| *value)
+            .unwrap_or_default(),
+    };
+
+    let size = size.unwrap_or(DEFAULT_INTEGER_SIZE);
+    if size > 8 {
+        bail!("Integers can only be written to symbols of up to 8 bytes, not {size} bytes");
+    }
+    if size < 8 && integer >> (size * 8) != 0 {
+        bail!("{integer} does not fit into {size} bytes");
+    }
+
+    Ok(integer.to_le_bytes()[..size as usize].to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_patches() {
+        assert_eq!(
+            parse_patch("SERIAL_NUMBER=0x1234").unwrap(),
+            (
+                PatchTarget::Symbol("SERIAL_NUMBER".to_string()),
+                PatchValue::Integer(0x1234)
+            )
+        );
+        assert_eq!(
+            parse_patch("0x0800F000=hex:00:11:aa:BB").unwrap(),
+            (
+                PatchTarget::Address(0x0800_F000),
+                PatchValue::Bytes(vec![0x00, 0x11, 0xAA, 0xBB])
+            )
+        );
+        assert_eq!(
+            parse_patch("NAME=str:a=b").unwrap().1,
+            PatchValue::Bytes(b"a=b".to_vec())
+        );
+        assert!(parse_patch("SERIAL_NUMBER").is_err());
+        assert!(parse_patch("MAC=hex:123").is_err());
+    }
+
+    #[test]
+    fn parse_json_patches() {
+        let patches = parse_json(r#"{ "MAC": [0, 1, 255], "0x100": 7, "NAME": "str:x" }"#)
+            .unwrap()
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+        assert!(patches.contains(&PatchValue::Bytes(vec![0, 1, 255])));
+        assert!(patches.contains(&PatchValue::Integer(7)));
+        assert!(patches.contains(&PatchValue::Bytes(b"x".to_vec())));
+
+        assert!(parse_json(r#"{ "MAC": [256] }"#).is_err());
+        assert!(parse_json("[]").is_err());
+    }
+
+    #[test]
+    fn patch_sizes() {
+        assert_eq!(
+            patch_data(&PatchValue::Integer(0x1234), Some(2), &[]).unwrap(),
+            [0x34, 0x12]
+        );
+        assert_eq!(
+            patch_data(&PatchValue::Integer(1), None, &[]).unwrap(),
+            [1, 0, 0, 0]
+        );
+        assert!(patch_data(&PatchValue::Integer(0x1_0000), Some(2), &[]).is_err());
+        assert!(patch_data(&PatchValue::Integer(1), Some(16), &[]).is_err());
+        assert!(patch_data(&PatchValue::Bytes(vec![0; 7]), Some(6), &[]).is_err());
+
+        let counters = [(PathBuf::from("serial.txt"), 41)];
+        assert_eq!(
+            patch_data(
+                &PatchValue::Counter(PathBuf::from("serial.txt")),
+                Some(1),
+                &counters
+            )
+            .unwrap(),
+            [41]
+        );
+    }
+}
@@ -0,0 +1,594 @@
+//! Peripheral descriptions loaded from CMSIS-SVD files.
+//!
+//! The SVD file is parsed into a flat model of peripherals, registers and fields, with
+//! derived elements, arrays and inherited register properties already resolved. The model
+//! is used by the `reg` command and by the debugger to read, decode and write registers.
+
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Context};
+use probe_rs::MemoryInterface;
+use svd_parser::{
+    svd::{self, Access, ModifiedWriteValues, Usage},
+    Config,
+};
+
+/// A device described by an SVD file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SvdDevice {
+    pub name: String,
+    pub peripherals: Vec<Peripheral>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peripheral {
+    pub name: String,
+    pub group_name: Option<String>,
+    pub base_address: u64,
+    pub description: Option<String>,
+    pub registers: Vec<Register>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    /// The name of the register, prefixed by the name of the cluster containing it.
+    pub name: String,
+    pub address: u64,
+    /// Size of the register in bits.
+    pub size: u32,
+    pub reset_value: u64,
+    /// `true` if the register is write-only, or if reading it has or may have side effects.
+    pub restricted_read: bool,
+    pub writable: bool,
+    /// `true` if the register can only be written once after reset.
+    pub write_once: bool,
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub bit_offset: u32,
+    pub bit_width: u32,
+    /// `true` if the field is write-only, or if reading it has or may have side effects.
+    pub restricted_read: bool,
+    pub writable: bool,
+    /// `true` if the field can only be written once after reset.
+    pub write_once: bool,
+    /// How writing the field changes its value, e.g. [`ModifiedWriteValues::OneToClear`] for
+    /// status flags which are cleared by writing 1.
+    pub modified_write_values: ModifiedWriteValues,
+    pub description: Option<String>,
+    pub enumerated_values: Vec<EnumeratedValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumeratedValue {
+    pub name: String,
+    /// The value, or `None` for the default value which covers all values not listed.
+    pub value: Option<u64>,
+    pub usage: Usage,
+    pub description: Option<String>,
+}
+
+/// An element of an [`SvdDevice`], found by its name.
+#[derive(Debug, Clone, Copy)]
+pub enum SvdElement<'a> {
+    Peripheral(&'a Peripheral),
+    Register(&'a Peripheral, &'a Register),
+    Field(&'a Peripheral, &'a Register, &'a Field),
+}
+
+impl SvdDevice {
+    /// Loads and parses an SVD file.
+    pub fn load(svd_file: &Path) -> anyhow::Result<Self> {
+        let svd_xml = fs::read_to_string(svd_file)
+            .with_context(|| format!("Failed to read SVD file {}", svd_file.display()))?;
+
+        Self::parse(&svd_xml)
+            .with_context(|| format!("Unable to parse CMSIS-SVD file {}", svd_file.display()))
+    }
+
+    /// Parses the contents of an SVD file.
+    pub fn parse(svd_xml: &str) -> anyhow::Result<Self> {
+        let device = svd_parser::parse_with_config(
+            svd_xml,
+            &Config::default().expand(true).expand_properties(true),
+        )?;
+
+        Ok(Self::from(&device))
+    }
+
+    /// Finds a peripheral, register or field by its name, e.g. `GPIOA`, `GPIOA.ODR` or
+    /// `GPIOA.ODR.ODR5`. Names are compared case-insensitively.
+    pub fn find(&self, name: &str) -> Option<SvdElement<'_>> {
+        for peripheral in &self.peripherals {
+            let Some(rest) = strip_name(name, &peripheral.name) else {
+                continue;
+            };
+            if rest.is_empty() {
+                return Some(SvdElement::Peripheral(peripheral));
+            }
+
+            for register in &peripheral.registers {
+                let Some(rest) = strip_name(rest, &register.name) else {
+                    continue;
+                };
+                if rest.is_empty() {
+                    return Some(SvdElement::Register(peripheral, register));
+                }
+
+                if let Some(field) = register
+                    .fields
+                    .iter()
+                    .find(|field| field.name.eq_ignore_ascii_case(rest))
+                {
+                    return Some(SvdElement::Field(peripheral, register, field));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Strips `element_name` and the following `.` separator from the start of `name`.
+fn strip_name<'a>(name: &'a str, element_name: &str) -> Option<&'a str> {
+    let prefix = name.get(..element_name.len())?;
+    if !prefix.eq_ignore_ascii_case(element_name) {
+        return None;
+    }
+
+    match &name[element_name.len()..] {
+        "" => Some(""),
+        rest => rest.strip_prefix('.'),
+    }
+}
+
+impl From<&svd::Device> for SvdDevice {
+    fn from(device: &svd::Device) -> Self {
+        let peripherals = device
+            .peripherals
+            .iter()
+            .map(|peripheral| {
+                // Clusters have been flattened into registers named `CLUSTER_REGISTER` by expanding
+                // the device.
+                let registers = peripheral
+                    .all_registers()
+                    .map(|register| Register::new(register, peripheral.base_address))
+                    .collect();
+
+                Peripheral {
+                    name: peripheral.name.clone(),
+                    group_name: peripheral.group_name.clone(),
+                    base_address: peripheral.base_address,
+                    description: peripheral.description.clone(),
+                    registers,
+                }
+            })
+            .collect();
+
+        SvdDevice {
+            name: device.name.clone(),
+            peripherals,
+        }
+    }
+}
+
+impl Register {
+    fn new(register: &svd::RegisterInfo, base_address: u64) -> Self {
+        // Registers without an access attribute are not read, as reading them might have side
+        // effects. The SVD specification defines read-write as the default access for writing.
+        let access = register.properties.access;
+
+        let fields = register
+            .fields()
+            .map(|field| Field::new(field, register, access))
+            .collect::<Vec<_>>();
+
+        // Reading the register reads all of its fields.
+        let restricted_read = register.read_action.is_some()
+            || !access.is_some_and(Access::can_read)
+            || fields.iter().any(|field| field.restricted_read);
+        let access = access.unwrap_or(Access::ReadWrite);
+
+        Register {
+            name: register.name.clone(),
+            address: base_address + register.address_offset as u64,
+            size: register.properties.size.unwrap_or(32),
+            reset_value: register.properties.reset_value.unwrap_or(0),
+            restricted_read,
+            writable: access.can_write(),
+            write_once: is_write_once(access),
+            description: register.description.clone(),
+            fields,
+        }
+    }
+
+    /// Reads the value of the register from the target.
+    pub fn read(&self, memory: &mut dyn MemoryInterface) -> anyhow::Result<u64> {
+        let value = match self.size {
+            0..=8 => memory.read_word_8(self.address)?.into(),
+            9..=16 => memory.read_word_16(self.address)?.into(),
+            17..=32 => memory.read_word_32(self.address)?.into(),
+            _ => memory.read_word_64(self.address)?,
+        };
+
+        Ok(value)
+    }
+
+    /// Writes `value` to the register on the target.
+    pub fn write(&self, memory: &mut dyn MemoryInterface, value: u64) -> anyhow::Result<()> {
+        if value & !mask(self.size) != 0 {
+            bail!(
+                "Value {value:#x} does not fit into the {} bit register {}",
+                self.size,
+                self.name
+            );
+        }
+
+        match self.size {
+            0..=8 => memory.write_word_8(self.address, value as u8)?,
+            9..=16 => memory.write_word_16(self.address, value as u16)?,
+            17..=32 => memory.write_word_32(self.address, value as u32)?,
+            _ => memory.write_word_64(self.address, value)?,
+        }
+
+        Ok(())
+    }
+
+    /// Writes `value` to `field`, keeping the values of the other fields of the register.
+    ///
+    /// Other fields which are modified by writing 1 or 0, like status flags cleared by writing 1,
+    /// are written with the value that leaves them unchanged. Fails if the register can not be
+    /// read without side effects, as the values of the other fields are unknown. Such registers
+    /// have to be written as a whole.
+    pub fn write_field(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        field: &Field,
+        value: u64,
+    ) -> anyhow::Result<()> {
+        if self.restricted_read {
+            bail!(
+                "Can not write the field {} on its own, as the register {} can not be read. Write the whole register instead.",
+                field.name,
+                self.name
+            );
+        }
+
+        let current_value = self.read(memory)?;
+
+        self.write(memory, self.field_write_value(current_value, field, value)?)
+    }
+
+    /// Returns the register value to write to set `field` to `value`, based on the current
+    /// value of the register.
+    fn field_write_value(
+        &self,
+        current_value: u64,
+        field: &Field,
+        value: u64,
+    ) -> anyhow::Result<u64> {
+        let mut register_value = current_value;
+        for other_field in self.fields.iter().filter(|other| *other != field) {
+            let neutral_value = match other_field.modified_write_values {
+                ModifiedWriteValues::OneToClear
+                | ModifiedWriteValues::OneToSet
+                | ModifiedWriteValues::OneToToggle => 0,
+                ModifiedWriteValues::ZeroToClear
+                | ModifiedWriteValues::ZeroToSet
+                | ModifiedWriteValues::ZeroToToggle => mask(other_field.bit_width),
+                ModifiedWriteValues::Clear
+                | ModifiedWriteValues::Set
+                | ModifiedWriteValues::Modify => continue,
+            };
+            register_value = other_field.insert(register_value, neutral_value)?;
+        }
+
+        field.insert(register_value, value)
+    }
+}
+
+impl Field {
+    fn new(
+        field: &svd::FieldInfo,
+        register: &svd::RegisterInfo,
+        register_access: Option<Access>,
+    ) -> Self {
+        let restricted_read = register.read_action.is_some()
+            || field.read_action.is_some()
+            || register_access.is_none()
+            || !field
+                .access
+                .or(register_access)
+                .is_some_and(Access::can_read);
+        let access = field
+            .access
+            .or(register_access)
+            .unwrap_or(Access::ReadWrite);
+
+        let enumerated_values = field
+            .enumerated_values
+            .iter()
+            .flat_map(|values| {
+                values.values.iter().map(|value| EnumeratedValue {
+                    name: value.name.clone(),
+                    value: if value.is_default() {
+                        None
+                    } else {
+                        value.value
+                    },
+                    usage: values.usage().unwrap_or_default(),
+                    description: value.description.clone(),
+                })
+            })
+            .collect();
+
+        Field {
+            name: field.name.clone(),
+            bit_offset: field.bit_offset(),
+            bit_width: field.bit_width(),
+            restricted_read,
+            writable: access.can_write(),
+            write_once: is_write_once(access),
+            modified_write_values: field
+                .modified_write_values
+                .or(register.modified_write_values)
+                .unwrap_or_default(),
+            description: field.description.clone(),
+            enumerated_values,
+        }
+    }
+
+    /// Extracts the value of the field from the value of its register.
+    pub fn extract(&self, register_value: u64) -> u64 {
+        (register_value >> self.bit_offset) & mask(self.bit_width)
+    }
+
+    /// Replaces the value of the field in the value of its register.
+    pub fn insert(&self, register_value: u64, value: u64) -> anyhow::Result<u64> {
+        let mask = mask(self.bit_width);
+        if value & !mask != 0 {
+            bail!(
+                "Value {value:#x} does not fit into the {} bit field {}",
+                self.bit_width,
+                self.name
+            );
+        }
+
+        Ok(register_value & !(mask << self.bit_offset) | value << self.bit_offset)
+    }
+
+    /// Returns the enumerated value describing the field value `value` when read.
+    pub fn enumerated_value(&self, value: u64) -> Option<&EnumeratedValue> {
+        let values = || {
+            self.enumerated_values
+                .iter()
+                .filter(|enumerated_value| enumerated_value.usage != Usage::Write)
+        };
+
+        values()
+            .find(|enumerated_value| enumerated_value.value == Some(value))
+            .or_else(|| values().find(|enumerated_value| enumerated_value.value.is_none()))
+    }
+
+    /// Parses a value to write to the field, either a number or the name of an enumerated value.
+    pub fn parse_value(&self, value: &str) -> anyhow::Result<u64> {
+        if let Ok(value) = parse_int::parse::<u64>(value) {
+            return Ok(value);
+        }
+
+        self.enumerated_values
+            .iter()
+            .filter(|enumerated_value| enumerated_value.usage != Usage::Read)
+            .find(|enumerated_value| enumerated_value.name.eq_ignore_ascii_case(value))
+            .and_then(|enumerated_value| enumerated_value.value)
+            .ok_or_else(|| {
+                anyhow!(
+                    "'{value}' is neither a number nor an enumerated value of field {}",
+                    self.name
+                )
+            })
+    }
+}
+
+fn is_write_once(access: Access) -> bool {
+    matches!(access, Access::WriteOnce | Access::ReadWriteOnce)
+}
+
+fn mask(bits: u32) -> u64 {
+    u64::MAX.checked_shr(64 - bits.min(64)).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SVD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<device schemaVersion="1.3">
+  <name>TEST</name>
+  <version>1.0</version>
+  <description>Test device</description>
+  <addressUnitBits>8</addressUnitBits>
+  <width>32</width>
+  <size>32</size>
+  <access>read-write</access>
+  <resetValue>0</resetValue>
+  <resetMask>0xFFFFFFFF</resetMask>
+  <peripherals>
+    <peripheral>
+      <name>GPIOA</name>
+      <groupName>GPIO</groupName>
+      <baseAddress>0x48000000</baseAddress>
+      <registers>
+        <register>
+          <name>MODER</name>
+          <addressOffset>0x0</addressOffset>
+          <resetValue>0xA8000000</resetValue>
+          <fields>
+            <field>
+              <name>MODER0</name>
+              <bitOffset>0</bitOffset>
+              <bitWidth>2</bitWidth>
+              <enumeratedValues>
+                <enumeratedValue><name>Input</name><value>0</value></enumeratedValue>
+                <enumeratedValue><name>Output</name><value>1</value></enumeratedValue>
+                <enumeratedValue><name>Other</name><isDefault>true</isDefault></enumeratedValue>
+              </enumeratedValues>
+            </field>
+          </fields>
+        </register>
+        <register>
+          <name>BSRR</name>
+          <addressOffset>0x18</addressOffset>
+          <access>write-only</access>
+          <fields>
+            <field><name>BS5</name><bitOffset>5</bitOffset><bitWidth>1</bitWidth></field>
+          </fields>
+        </register>
+        <register>
+          <name>SR</name>
+          <addressOffset>0x10</addressOffset>
+          <fields>
+            <field><name>EN</name><bitOffset>0</bitOffset><bitWidth>1</bitWidth></field>
+            <field>
+              <name>FLAG</name>
+              <bitOffset>1</bitOffset>
+              <bitWidth>1</bitWidth>
+              <modifiedWriteValues>oneToClear</modifiedWriteValues>
+            </field>
+            <field>
+              <name>LOCK</name>
+              <bitOffset>2</bitOffset>
+              <bitWidth>1</bitWidth>
+              <access>read-writeOnce</access>
+            </field>
+          </fields>
+        </register>
+        <register>
+          <name>DR</name>
+          <addressOffset>0x14</addressOffset>
+          <readAction>clear</readAction>
+        </register>
+        <cluster>
+          <name>AF</name>
+          <addressOffset>0x20</addressOffset>
+          <register>
+            <name>AFRL</name>
+            <addressOffset>0x4</addressOffset>
+          </register>
+        </cluster>
+      </registers>
+    </peripheral>
+    <peripheral derivedFrom="GPIOA">
+      <name>GPIOB</name>
+      <baseAddress>0x48000400</baseAddress>
+    </peripheral>
+  </peripherals>
+</device>
+"#;
+
+    #[test]
+    fn find_elements() {
+        let device = SvdDevice::parse(SVD).unwrap();
+
+        assert!(matches!(
+            device.find("gpioa"),
+            Some(SvdElement::Peripheral(peripheral)) if peripheral.name == "GPIOA"
+        ));
+
+        let Some(SvdElement::Register(_, register)) = device.find("GPIOB.MODER") else {
+            panic!("register not found");
+        };
+        assert_eq!(register.address, 0x4800_0400);
+        assert_eq!(register.reset_value, 0xA800_0000);
+
+        let Some(SvdElement::Register(_, register)) = device.find("GPIOA.AF_AFRL") else {
+            panic!("register in cluster not found");
+        };
+        assert_eq!(register.address, 0x4800_0024);
+
+        let Some(SvdElement::Field(_, register, field)) = device.find("GPIOA.BSRR.BS5") else {
+            panic!("field not found");
+        };
+        assert!(register.restricted_read);
+        assert!(field.writable);
+
+        assert!(device.find("GPIOA.MODERX").is_none());
+        assert!(device.find("GPIOA.MODER.MODER1").is_none());
+    }
+
+    #[test]
+    fn field_values() {
+        let device = SvdDevice::parse(SVD).unwrap();
+        let Some(SvdElement::Field(_, _, field)) = device.find("GPIOA.MODER.MODER0") else {
+            panic!("field not found");
+        };
+
+        assert_eq!(field.extract(0xA800_0001), 1);
+        assert_eq!(field.insert(0xA800_0003, 1).unwrap(), 0xA800_0001);
+        assert!(field.insert(0, 4).is_err());
+
+        let name = |value| {
+            field
+                .enumerated_value(value)
+                .map(|value| value.name.as_str())
+        };
+        assert_eq!(name(1), Some("Output"));
+        assert_eq!(name(3), Some("Other"));
+
+        assert_eq!(field.parse_value("output").unwrap(), 1);
+        assert_eq!(field.parse_value("0b10").unwrap(), 2);
+        assert!(field.parse_value("Other").is_err());
+    }
+
+    #[test]
+    fn field_write_values() {
+        let device = SvdDevice::parse(SVD).unwrap();
+        let Some(SvdElement::Register(_, register)) = device.find("GPIOA.SR") else {
+            panic!("register not found");
+        };
+        let field = |name| register.fields.iter().find(|f| f.name == name).unwrap();
+
+        assert!(!register.restricted_read);
+        assert!(field("LOCK").write_once);
+        assert!(!field("EN").write_once);
+
+        // Writing EN must not clear the pending flag by writing back 1.
+        let value = register.field_write_value(0b110, field("EN"), 1).unwrap();
+        assert_eq!(value, 0b101);
+        let value = register.field_write_value(0b110, field("FLAG"), 1).unwrap();
+        assert_eq!(value, 0b110);
+
+        let Some(SvdElement::Register(_, register)) = device.find("GPIOA.DR") else {
+            panic!("register not found");
+        };
+        assert!(register.restricted_read);
+        assert!(register.writable);
+    }
+
+    #[test]
+    fn missing_access_restricts_reads() {
+        let svd = SVD.replace("  <access>read-write</access>\n", "").replace(
+            "<name>MODER</name>",
+            "<name>MODER</name><access>read-write</access>",
+        );
+        let device = SvdDevice::parse(&svd).unwrap();
+
+        let Some(SvdElement::Field(_, register, field)) = device.find("GPIOA.MODER.MODER0") else {
+            panic!("field not found");
+        };
+        assert!(!register.restricted_read);
+        assert!(!field.restricted_read);
+
+        let Some(SvdElement::Field(_, register, field)) = device.find("GPIOA.SR.EN") else {
+            panic!("field not found");
+        };
+        assert!(register.restricted_read);
+        assert!(field.restricted_read);
+        assert!(register.writable);
+        assert!(field.writable);
+    }
+}
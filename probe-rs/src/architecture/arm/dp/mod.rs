@@ -6,12 +6,15 @@ mod register_generation;
 use super::{ArmError, DapAccess, DapError, RegisterParseError};
 use bitfield::bitfield;
 use jep106::JEP106Code;
+use serde::{Deserialize, Serialize};
 
 use crate::probe::DebugProbeError;
 use std::fmt::Display;
 
 /// Debug port address.
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+#[derive(
+    Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Hash, Default, Serialize, Deserialize,
+)]
 pub enum DpAddress {
     /// Access the single DP on the bus, assuming there is only one.
     /// Will cause corruption if multiple are present.
@@ -23,7 +26,7 @@ pub enum DpAddress {
 }
 
 /// A Debug port register address and its bank.
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct DpRegisterAddress {
     /// The register address.
     pub address: u8,
@@ -44,7 +44,7 @@ impl From<ap_v2::registers::CSW> for Status {
 }
 
 /// An ArmMemoryInterface (ArmProbeInterface + MemoryAp)
-pub trait ArmMemoryInterface: ArmMemoryInterfaceShim {
+pub trait ArmMemoryInterface: ArmMemoryInterfaceShim + Send {
     /// The underlying MemoryAp address.
     fn fully_qualified_address(&self) -> FullyQualifiedApAddress;
 
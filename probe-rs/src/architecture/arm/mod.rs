@@ -9,6 +9,7 @@ pub mod component;
 pub mod core;
 pub mod dp;
 pub mod memory;
+pub mod pack_sequence;
 pub mod sequences;
 pub mod swo;
 mod traits;
@@ -0,0 +1,829 @@
+//! Interpreter for CMSIS-Pack debug access sequences.
+
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+use probe_rs_target::{SequenceControl, SequenceDescription, SequenceElement};
+
+use super::parser::{self, BinaryOp, Expr, ParseError, Statement, UnaryOp};
+use crate::architecture::arm::ArmError;
+
+/// The maximum nesting of `Sequence()` calls, to catch sequences that call themselves.
+const MAX_DEPTH: usize = 16;
+
+/// An error that occurred while running a debug access sequence.
+#[derive(thiserror::Error, Debug)]
+pub enum SequenceError {
+    /// The sequence code could not be parsed.
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    /// A variable was used without being declared.
+    #[error("Variable `{0}` is not declared")]
+    UndeclaredVariable(String),
+    /// The sequence called a function which is not supported.
+    #[error("Function `{0}` is not supported")]
+    UnknownFunction(String),
+    /// A function was called with the wrong number of arguments.
+    #[error("`{function}` takes {expected} arguments, but {actual} were given")]
+    ArgumentCount {
+        /// The called function.
+        function: String,
+        /// The number of arguments the function takes.
+        expected: usize,
+        /// The number of arguments given.
+        actual: usize,
+    },
+    /// A string literal was used where a number was expected.
+    #[error("String literals are only allowed as function arguments")]
+    UnexpectedString,
+    /// A number was used where a string literal was expected.
+    #[error("`{0}` expects a string literal argument")]
+    ExpectedString(String),
+    /// The sequence divided by zero.
+    #[error("Division by zero")]
+    DivisionByZero,
+    /// A sequence is neither defined by the pack, nor available as a built-in sequence.
+    #[error("Sequence `{0}` is not defined")]
+    UndefinedSequence(String),
+    /// `Sequence()` calls are nested too deeply.
+    #[error("Sequence calls are nested more than {MAX_DEPTH} levels deep")]
+    TooDeep,
+    /// The sequence reported an error with `Message(2, ...)`.
+    #[error("{0}")]
+    Message(String),
+    /// A debug access failed.
+    #[error(transparent)]
+    Access(#[from] ArmError),
+}
+
+/// The debug accesses available to a running sequence.
+///
+/// Depending on where a sequence is called from, only some accesses are available.
+/// Unavailable accesses return an error.
+pub(crate) trait SequenceHost {
+    /// Reads the debug port register at `address`.
+    fn read_dp(&mut self, address: u8) -> Result<u32, ArmError>;
+
+    /// Writes the debug port register at `address`.
+    fn write_dp(&mut self, address: u8, value: u32) -> Result<(), ArmError>;
+
+    /// Reads the register at `address` of the access port `ap`, as given by `__ap`.
+    fn read_ap(&mut self, ap: u64, address: u64) -> Result<u32, ArmError>;
+
+    /// Writes the register at `address` of the access port `ap`, as given by `__ap`.
+    fn write_ap(&mut self, ap: u64, address: u64, value: u32) -> Result<(), ArmError>;
+
+    /// Reads a `bits` wide value from memory through the access port `ap`.
+    fn read_memory(&mut self, ap: u64, bits: u8, address: u64) -> Result<u64, ArmError>;
+
+    /// Writes a `bits` wide value to memory through the access port `ap`.
+    fn write_memory(&mut self, ap: u64, bits: u8, address: u64, value: u64)
+        -> Result<(), ArmError>;
+
+    /// Drives the debug pins, see [`RawDapAccess::swj_pins`](crate::architecture::arm::RawDapAccess::swj_pins).
+    fn swj_pins(&mut self, pin_out: u32, pin_select: u32, pin_wait: u32) -> Result<u32, ArmError>;
+
+    /// Sends a sequence of bits on SWDIO/TMS.
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), ArmError>;
+
+    /// Sends a sequence of TDI bits with a constant TMS value.
+    fn jtag_sequence(&mut self, cycles: u8, tms: bool, tdi: u64) -> Result<(), ArmError>;
+
+    /// Sets the clock frequency of the debug interface.
+    fn swj_clock(&mut self, frequency: u32) -> Result<(), ArmError>;
+
+    /// Runs the built-in implementation of the sequence `name`.
+    ///
+    /// Returns `None` if the built-in sequence is not available from here.
+    fn builtin_sequence(&mut self, name: &str) -> Option<Result<(), ArmError>>;
+}
+
+/// Runs the sequences of a chip on a [`SequenceHost`].
+pub(crate) struct Interpreter<'a> {
+    sequences: &'a [SequenceDescription],
+    pname: Option<&'a str>,
+    globals: &'a mut HashMap<String, u64>,
+    host: &'a mut dyn SequenceHost,
+    depth: usize,
+}
+
+impl<'a> Interpreter<'a> {
+    /// Creates an interpreter for `sequences`. Sequences for the core `pname` are preferred
+    /// over sequences for all cores.
+    pub(crate) fn new(
+        sequences: &'a [SequenceDescription],
+        pname: Option<&'a str>,
+        globals: &'a mut HashMap<String, u64>,
+        host: &'a mut dyn SequenceHost,
+    ) -> Self {
+        Self {
+            sequences,
+            pname,
+            globals,
+            host,
+            depth: 0,
+        }
+    }
+
+    /// Runs `code` on the global scope, e.g. the `<debugvars>` declarations.
+    pub(crate) fn run_global(&mut self, code: &str) -> Result<(), SequenceError> {
+        for statement in parser::parse_block(code)? {
+            self.execute(&statement, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the sequence `name`, falling back to the built-in implementation if the
+    /// pack does not define it.
+    pub(crate) fn run(&mut self, name: &str) -> Result<(), SequenceError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(SequenceError::TooDeep);
+        }
+
+        let Some(sequence) = find_sequence(self.sequences, name, self.pname) else {
+            return match self.host.builtin_sequence(name) {
+                Some(result) => Ok(result?),
+                None => Err(SequenceError::UndefinedSequence(name.to_string())),
+            };
+        };
+
+        if sequence.disable {
+            tracing::debug!("Sequence {name} is disabled");
+            return Ok(());
+        }
+
+        tracing::debug!("Running sequence {name}");
+        self.depth += 1;
+        let mut locals = HashMap::new();
+        let result = self.run_elements(&sequence.elements, &mut locals);
+        self.depth -= 1;
+
+        result
+    }
+
+    fn run_elements(
+        &mut self,
+        elements: &[SequenceElement],
+        locals: &mut HashMap<String, u64>,
+    ) -> Result<(), SequenceError> {
+        for element in elements {
+            match element {
+                SequenceElement::Block(block) => {
+                    for statement in parser::parse_block(&block.code)? {
+                        self.execute(&statement, Some(locals))?;
+                    }
+                }
+                SequenceElement::Control(control) => self.run_control(control, locals)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_control(
+        &mut self,
+        control: &SequenceControl,
+        locals: &mut HashMap<String, u64>,
+    ) -> Result<(), SequenceError> {
+        if let Some(condition) = &control.if_expr {
+            let condition = parser::parse_expression(condition)?;
+            if self.eval(&condition, Some(locals))? == 0 {
+                return Ok(());
+            }
+        }
+
+        let Some(condition) = &control.while_expr else {
+            return self.run_elements(&control.elements, locals);
+        };
+
+        let condition = parser::parse_expression(condition)?;
+        let timeout = control
+            .timeout
+            .filter(|&timeout| timeout != 0)
+            .map(Duration::from_micros);
+        let start = Instant::now();
+
+        while self.eval(&condition, Some(locals))? != 0 {
+            self.run_elements(&control.elements, locals)?;
+
+            if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+                tracing::debug!("Loop `while {condition:?}` timed out");
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute(
+        &mut self,
+        statement: &Statement,
+        mut locals: Option<&mut HashMap<String, u64>>,
+    ) -> Result<(), SequenceError> {
+        match statement {
+            Statement::Declare { name, value } => {
+                let value = match value {
+                    Some(value) => self.eval(value, locals.as_deref_mut())?,
+                    None => 0,
+                };
+                match locals {
+                    Some(locals) => locals.insert(name.clone(), value),
+                    None => self.globals.insert(name.clone(), value),
+                };
+            }
+            Statement::Assign { name, op, value } => {
+                let mut value = self.eval(value, locals.as_deref_mut())?;
+                if let Some(op) = op {
+                    let current = self.variable(name, locals.as_deref())?;
+                    value = binary(*op, current, value)?;
+                }
+
+                let slot = match locals.and_then(|locals| locals.get_mut(name)) {
+                    Some(slot) => slot,
+                    None => self
+                        .globals
+                        .get_mut(name)
+                        .ok_or_else(|| SequenceError::UndeclaredVariable(name.clone()))?,
+                };
+                *slot = value;
+            }
+            Statement::Expr(expr) => {
+                self.eval(expr, locals)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn variable(
+        &self,
+        name: &str,
+        locals: Option<&HashMap<String, u64>>,
+    ) -> Result<u64, SequenceError> {
+        locals
+            .and_then(|locals| locals.get(name))
+            .or_else(|| self.globals.get(name))
+            .copied()
+            .ok_or_else(|| SequenceError::UndeclaredVariable(name.to_string()))
+    }
+
+    fn eval(
+        &mut self,
+        expr: &Expr,
+        mut locals: Option<&mut HashMap<String, u64>>,
+    ) -> Result<u64, SequenceError> {
+        Ok(match expr {
+            Expr::Number(value) => *value,
+            Expr::Str(_) => return Err(SequenceError::UnexpectedString),
+            Expr::Variable(name) => self.variable(name, locals.as_deref())?,
+            Expr::Call { function, args } => self.call(function, args, locals)?,
+            Expr::Unary(op, operand) => {
+                let operand = self.eval(operand, locals)?;
+                match op {
+                    UnaryOp::Not => u64::from(operand == 0),
+                    UnaryOp::BitNot => !operand,
+                    UnaryOp::Neg => operand.wrapping_neg(),
+                    UnaryOp::Plus => operand,
+                }
+            }
+            Expr::Binary(BinaryOp::And, lhs, rhs) => u64::from(
+                self.eval(lhs, locals.as_deref_mut())? != 0 && self.eval(rhs, locals)? != 0,
+            ),
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => u64::from(
+                self.eval(lhs, locals.as_deref_mut())? != 0 || self.eval(rhs, locals)? != 0,
+            ),
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs, locals.as_deref_mut())?;
+                let rhs = self.eval(rhs, locals)?;
+                binary(*op, lhs, rhs)?
+            }
+            Expr::Conditional(condition, then, otherwise) => {
+                if self.eval(condition, locals.as_deref_mut())? != 0 {
+                    self.eval(then, locals)?
+                } else {
+                    self.eval(otherwise, locals)?
+                }
+            }
+        })
+    }
+
+    fn call(
+        &mut self,
+        function: &str,
+        args: &[Expr],
+        mut locals: Option<&mut HashMap<String, u64>>,
+    ) -> Result<u64, SequenceError> {
+        let arity = |expected: usize| {
+            if args.len() == expected {
+                Ok(())
+            } else {
+                Err(SequenceError::ArgumentCount {
+                    function: function.to_string(),
+                    expected,
+                    actual: args.len(),
+                })
+            }
+        };
+        let string = |index: usize| match &args[index] {
+            Expr::Str(value) => Ok(value.as_str()),
+            _ => Err(SequenceError::ExpectedString(function.to_string())),
+        };
+
+        // Functions taking strings, which can't be evaluated like the other arguments.
+        match function {
+            "Sequence" => {
+                arity(1)?;
+                self.run(string(0)?)?;
+                return Ok(0);
+            }
+            "Message" => {
+                if args.len() < 2 {
+                    return Err(SequenceError::ArgumentCount {
+                        function: function.to_string(),
+                        expected: 2,
+                        actual: args.len(),
+                    });
+                }
+                let level = self.eval(&args[0], locals.as_deref_mut())?;
+                let format = string(1)?;
+                let mut values = Vec::with_capacity(args.len() - 2);
+                for arg in &args[2..] {
+                    values.push(self.eval(arg, locals.as_deref_mut())?);
+                }
+
+                let message = format_message(format, &values);
+                match level {
+                    0 => tracing::info!("{message}"),
+                    1 => tracing::warn!("{message}"),
+                    _ => return Err(SequenceError::Message(message)),
+                }
+                return Ok(0);
+            }
+            "Query" => {
+                // There is no user to ask, so the default answer is used.
+                arity(3)?;
+                tracing::warn!("Answering query `{}` with the default", string(1)?);
+                return self.eval(&args[2], locals);
+            }
+            "QueryValue" => {
+                arity(2)?;
+                tracing::warn!("Answering query `{}` with the default", string(0)?);
+                return self.eval(&args[1], locals);
+            }
+            "LoadDebugInfo" => {
+                arity(1)?;
+                tracing::warn!("Ignoring LoadDebugInfo({:?})", string(0)?);
+                return Ok(0);
+            }
+            _ => {}
+        }
+
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.eval(arg, locals.as_deref_mut())?);
+        }
+
+        let ap = self.globals.get("__ap").copied().unwrap_or(0);
+        let result = match function {
+            "Read8" | "Read16" | "Read32" | "Read64" => {
+                arity(1)?;
+                let bits = function[4..].parse().unwrap();
+                self.host.read_memory(ap, bits, values[0])
+            }
+            "Write8" | "Write16" | "Write32" | "Write64" => {
+                arity(2)?;
+                let bits = function[5..].parse().unwrap();
+                self.host
+                    .write_memory(ap, bits, values[0], values[1])
+                    .map(|_| 0)
+            }
+            "ReadAP" => {
+                arity(1)?;
+                self.host.read_ap(ap, values[0]).map(u64::from)
+            }
+            "WriteAP" => {
+                arity(2)?;
+                self.host
+                    .write_ap(ap, values[0], values[1] as u32)
+                    .map(|_| 0)
+            }
+            "ReadDP" => {
+                arity(1)?;
+                self.host.read_dp(values[0] as u8).map(u64::from)
+            }
+            "WriteDP" => {
+                arity(2)?;
+                self.host
+                    .write_dp(values[0] as u8, values[1] as u32)
+                    .map(|_| 0)
+            }
+            "DAP_WriteABORT" => {
+                arity(1)?;
+                self.host.write_dp(0x0, values[0] as u32).map(|_| 0)
+            }
+            "DAP_Delay" => {
+                arity(1)?;
+                thread::sleep(Duration::from_micros(values[0]));
+                Ok(0)
+            }
+            "DAP_SWJ_Pins" => {
+                arity(3)?;
+                self.host
+                    .swj_pins(values[0] as u32, values[1] as u32, values[2] as u32)
+                    .map(u64::from)
+            }
+            "DAP_SWJ_Clock" => {
+                arity(1)?;
+                self.host.swj_clock(values[0] as u32).map(|_| 0)
+            }
+            "DAP_SWJ_Sequence" => {
+                arity(2)?;
+                self.host
+                    .swj_sequence(values[0] as u8, values[1])
+                    .map(|_| 0)
+            }
+            "DAP_JTAG_Sequence" => {
+                // The TDO bits are not captured, so they always read as 0.
+                arity(3)?;
+                self.host
+                    .jtag_sequence(values[0] as u8, values[1] != 0, values[2])
+                    .map(|_| 0)
+            }
+            _ => return Err(SequenceError::UnknownFunction(function.to_string())),
+        };
+
+        match result {
+            Ok(value) => Ok(value),
+            // Bit 0 of `__errorcontrol` makes the sequence ignore failed debug accesses.
+            Err(error) if self.globals.get("__errorcontrol").copied().unwrap_or(0) & 1 != 0 => {
+                tracing::debug!("Ignoring error in {function}: {error}");
+                Ok(0)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+/// Finds the sequence `name`, preferring one specific to the core `pname`.
+pub(crate) fn find_sequence<'s>(
+    sequences: &'s [SequenceDescription],
+    name: &str,
+    pname: Option<&str>,
+) -> Option<&'s SequenceDescription> {
+    let mut candidates = sequences.iter().filter(|sequence| sequence.name == name);
+
+    candidates
+        .clone()
+        .find(|sequence| pname.is_some() && sequence.pname.as_deref() == pname)
+        .or_else(|| candidates.find(|sequence| sequence.pname.is_none()))
+}
+
+fn binary(op: BinaryOp, lhs: u64, rhs: u64) -> Result<u64, SequenceError> {
+    let shift = |rhs: u64| u32::try_from(rhs).unwrap_or(u32::MAX);
+
+    Ok(match op {
+        BinaryOp::Mul => lhs.wrapping_mul(rhs),
+        BinaryOp::Div => lhs.checked_div(rhs).ok_or(SequenceError::DivisionByZero)?,
+        BinaryOp::Rem => lhs.checked_rem(rhs).ok_or(SequenceError::DivisionByZero)?,
+        BinaryOp::Add => lhs.wrapping_add(rhs),
+        BinaryOp::Sub => lhs.wrapping_sub(rhs),
+        BinaryOp::Shl => lhs.checked_shl(shift(rhs)).unwrap_or(0),
+        BinaryOp::Shr => lhs.checked_shr(shift(rhs)).unwrap_or(0),
+        BinaryOp::Lt => u64::from(lhs < rhs),
+        BinaryOp::Le => u64::from(lhs <= rhs),
+        BinaryOp::Gt => u64::from(lhs > rhs),
+        BinaryOp::Ge => u64::from(lhs >= rhs),
+        BinaryOp::Eq => u64::from(lhs == rhs),
+        BinaryOp::Ne => u64::from(lhs != rhs),
+        BinaryOp::BitAnd => lhs & rhs,
+        BinaryOp::BitXor => lhs ^ rhs,
+        BinaryOp::BitOr => lhs | rhs,
+        BinaryOp::And => u64::from(lhs != 0 && rhs != 0),
+        BinaryOp::Or => u64::from(lhs != 0 || rhs != 0),
+    })
+}
+
+/// Formats a `Message()` with the `printf`-like conversions `%d`, `%u`, `%x` and `%X`,
+/// with optional zero padding and width.
+fn format_message(format: &str, values: &[u64]) -> String {
+    let mut values = values.iter();
+    let mut message = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            message.push(c);
+            continue;
+        }
+
+        let zero_pad = chars.next_if_eq(&'0').is_some();
+        let mut width = 0;
+        while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            width = width * 10 + digit as usize;
+            chars.next();
+        }
+        // Length modifiers don't matter, all values are 64 bit.
+        while chars.next_if(|c| matches!(c, 'l' | 'h')).is_some() {}
+
+        let formatted = match chars.next() {
+            Some('%') => String::from("%"),
+            Some(conversion @ ('d' | 'i' | 'u' | 'x' | 'X')) => {
+                let value = values.next().copied().unwrap_or(0);
+                match conversion {
+                    'x' => format!("{value:x}"),
+                    'X' => format!("{value:X}"),
+                    _ => value.to_string(),
+                }
+            }
+            Some(other) => format!("%{other}"),
+            None => String::from("%"),
+        };
+
+        let padding = width.saturating_sub(formatted.len());
+        message.extend(std::iter::repeat_n(
+            if zero_pad { '0' } else { ' ' },
+            padding,
+        ));
+        message.push_str(&formatted);
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs_target::SequenceBlock;
+
+    use super::*;
+
+    /// Records the debug accesses of a sequence, and serves memory reads from a map.
+    #[derive(Default)]
+    struct RecordingHost {
+        memory: HashMap<u64, u64>,
+        accesses: Vec<String>,
+    }
+
+    impl SequenceHost for RecordingHost {
+        fn read_dp(&mut self, address: u8) -> Result<u32, ArmError> {
+            self.accesses.push(format!("ReadDP({address:#x})"));
+            Ok(0xF000_0000)
+        }
+
+        fn write_dp(&mut self, address: u8, value: u32) -> Result<(), ArmError> {
+            self.accesses
+                .push(format!("WriteDP({address:#x}, {value:#x})"));
+            Ok(())
+        }
+
+        fn read_ap(&mut self, ap: u64, address: u64) -> Result<u32, ArmError> {
+            self.accesses.push(format!("ReadAP[{ap}]({address:#x})"));
+            Err(ArmError::Timeout)
+        }
+
+        fn write_ap(&mut self, ap: u64, address: u64, value: u32) -> Result<(), ArmError> {
+            self.accesses
+                .push(format!("WriteAP[{ap}]({address:#x}, {value:#x})"));
+            Ok(())
+        }
+
+        fn read_memory(&mut self, _ap: u64, bits: u8, address: u64) -> Result<u64, ArmError> {
+            self.accesses.push(format!("Read{bits}({address:#x})"));
+            Ok(self.memory.get(&address).copied().unwrap_or(0))
+        }
+
+        fn write_memory(
+            &mut self,
+            _ap: u64,
+            bits: u8,
+            address: u64,
+            value: u64,
+        ) -> Result<(), ArmError> {
+            self.accesses
+                .push(format!("Write{bits}({address:#x}, {value:#x})"));
+            self.memory.insert(address, value);
+            Ok(())
+        }
+
+        fn swj_pins(&mut self, _: u32, _: u32, _: u32) -> Result<u32, ArmError> {
+            Ok(0)
+        }
+
+        fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), ArmError> {
+            self.accesses
+                .push(format!("DAP_SWJ_Sequence({bit_len}, {bits:#x})"));
+            Ok(())
+        }
+
+        fn jtag_sequence(&mut self, _: u8, _: bool, _: u64) -> Result<(), ArmError> {
+            Ok(())
+        }
+
+        fn swj_clock(&mut self, _: u32) -> Result<(), ArmError> {
+            Ok(())
+        }
+
+        fn builtin_sequence(&mut self, name: &str) -> Option<Result<(), ArmError>> {
+            (name == "DebugPortSetup").then(|| {
+                self.accesses.push(format!("builtin {name}"));
+                Ok(())
+            })
+        }
+    }
+
+    fn block(code: &str) -> SequenceElement {
+        SequenceElement::Block(SequenceBlock {
+            atomic: false,
+            info: None,
+            code: code.to_string(),
+        })
+    }
+
+    fn sequence(name: &str, elements: Vec<SequenceElement>) -> SequenceDescription {
+        SequenceDescription {
+            name: name.to_string(),
+            pname: None,
+            disable: false,
+            info: None,
+            elements,
+        }
+    }
+
+    fn run(
+        sequences: &[SequenceDescription],
+        debug_vars: &str,
+        name: &str,
+        host: &mut RecordingHost,
+    ) -> Result<HashMap<String, u64>, SequenceError> {
+        let mut globals = HashMap::from([
+            (String::from("__ap"), 0),
+            (String::from("__errorcontrol"), 0),
+        ]);
+        let mut interpreter = Interpreter::new(sequences, None, &mut globals, host);
+        interpreter.run_global(debug_vars)?;
+        interpreter.run(name)?;
+
+        Ok(globals)
+    }
+
+    #[test]
+    fn reset_system() {
+        let sequences = [sequence(
+            "ResetSystem",
+            vec![
+                block(
+                    "__var SCB_AIRCR = 0xE000ED0C;\n\
+                     __var value = 0x05FA0000 | (1 << 2);\n\
+                     Write32(SCB_AIRCR, value);",
+                ),
+                SequenceElement::Control(SequenceControl {
+                    if_expr: None,
+                    while_expr: Some(String::from("(Read32(0xE000EDF0) & 0x02000000) == 0")),
+                    timeout: Some(500_000),
+                    info: None,
+                    elements: vec![block("Write32(0xE000EDF0, 0x02000000); Resets += 1;")],
+                }),
+            ],
+        )];
+
+        let mut host = RecordingHost::default();
+        let globals = run(&sequences, "__var Resets = 0;", "ResetSystem", &mut host).unwrap();
+
+        assert_eq!(
+            host.accesses,
+            [
+                "Write32(0xe000ed0c, 0x5fa0004)",
+                "Read32(0xe000edf0)",
+                "Write32(0xe000edf0, 0x2000000)",
+                "Read32(0xe000edf0)",
+            ]
+        );
+        assert_eq!(globals["Resets"], 1);
+    }
+
+    #[test]
+    fn nested_sequences_and_builtins() {
+        let sequences = [
+            sequence(
+                "DebugDeviceUnlock",
+                vec![block(
+                    "Sequence(\"DebugPortSetup\"); Sequence(\"CheckID\"); __ap = 1; WriteAP(0x04, 1);",
+                )],
+            ),
+            sequence(
+                "CheckID",
+                vec![SequenceElement::Control(SequenceControl {
+                    if_expr: Some(String::from("ReadDP(0x0) >> 28 == 0xF")),
+                    while_expr: None,
+                    timeout: None,
+                    info: None,
+                    elements: vec![block("DAP_SWJ_Sequence(16, 0xE79E);")],
+                })],
+            ),
+        ];
+
+        let mut host = RecordingHost::default();
+        run(&sequences, "", "DebugDeviceUnlock", &mut host).unwrap();
+
+        assert_eq!(
+            host.accesses,
+            [
+                "builtin DebugPortSetup",
+                "ReadDP(0x0)",
+                "DAP_SWJ_Sequence(16, 0xe79e)",
+                "WriteAP[1](0x4, 0x1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn locals_are_scoped_to_the_sequence() {
+        let sequences = [
+            sequence("Outer", vec![block("__var x = 1; Sequence(\"Inner\");")]),
+            sequence("Inner", vec![block("x = 2;")]),
+        ];
+
+        let mut host = RecordingHost::default();
+        assert!(matches!(
+            run(&sequences, "", "Outer", &mut host),
+            Err(SequenceError::UndeclaredVariable(name)) if name == "x"
+        ));
+    }
+
+    #[test]
+    fn error_control() {
+        let sequences = [sequence(
+            "DebugPortStart",
+            vec![block("__var id = ReadAP(0xFC);")],
+        )];
+
+        let mut host = RecordingHost::default();
+        assert!(matches!(
+            run(&sequences, "", "DebugPortStart", &mut host),
+            Err(SequenceError::Access(ArmError::Timeout))
+        ));
+
+        let mut host = RecordingHost::default();
+        run(
+            &sequences,
+            "__errorcontrol = 1;",
+            "DebugPortStart",
+            &mut host,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn disabled_and_undefined_sequences() {
+        let mut disabled = sequence("ResetSystem", vec![block("Write32(0, 0);")]);
+        disabled.disable = true;
+
+        let mut host = RecordingHost::default();
+        run(&[disabled], "", "ResetSystem", &mut host).unwrap();
+        assert!(host.accesses.is_empty());
+
+        assert!(matches!(
+            run(&[], "", "ResetSystem", &mut host),
+            Err(SequenceError::UndefinedSequence(_))
+        ));
+    }
+
+    #[test]
+    fn prefer_core_specific_sequences() {
+        let mut specific = sequence("ResetCatchSet", vec![]);
+        specific.pname = Some(String::from("cm33"));
+        let sequences = [sequence("ResetCatchSet", vec![]), specific];
+
+        assert_eq!(
+            find_sequence(&sequences, "ResetCatchSet", Some("cm33")),
+            Some(&sequences[1])
+        );
+        assert_eq!(
+            find_sequence(&sequences, "ResetCatchSet", Some("cm0")),
+            Some(&sequences[0])
+        );
+        assert_eq!(
+            find_sequence(&sequences, "ResetCatchSet", None),
+            Some(&sequences[0])
+        );
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            format_message("DPIDR: %08X, AP %d, %%", &[0x2BA01477, 3]),
+            "DPIDR: 2BA01477, AP 3, %"
+        );
+
+        let sequences = [sequence(
+            "DebugDeviceUnlock",
+            vec![block("Message(2, \"Device is locked: %x\", 0xCA);")],
+        )];
+        let mut host = RecordingHost::default();
+        let error = run(&sequences, "", "DebugDeviceUnlock", &mut host).unwrap_err();
+        assert_eq!(error.to_string(), "Device is locked: ca");
+    }
+}
@@ -0,0 +1,780 @@
+//! Debug sequences described by CMSIS-Packs.
+//!
+//! CMSIS-Packs describe the chip specific parts of connecting, resetting and unlocking
+//! as `<sequence>` elements, written in a small C-like language. [`PackDebugSequence`]
+//! runs these sequences, as carried by the target description, in place of the built-in
+//! ones of [`ArmDebugSequence`].
+
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::Mutex;
+use probe_rs_target::{Chip, CoreAccessOptions, CoreType, SequenceDescription};
+
+use super::{
+    communication_interface::{DapProbe, Initialized},
+    component::TraceSink,
+    dp::{DpAddress, DpRegisterAddress},
+    memory::{ArmMemoryInterface, CoresightComponent},
+    sequences::{ArmDebugSequence, ArmDebugSequenceError, DefaultArmSequence},
+    ApAddress, ApV2Address, ArmCommunicationInterface, ArmError, ArmProbeInterface,
+    FullyQualifiedApAddress, RegisterAddress,
+};
+use crate::{config::CoreExt, probe::WireProtocol};
+
+mod interpreter;
+mod parser;
+
+pub use interpreter::SequenceError;
+use interpreter::{find_sequence, Interpreter, SequenceHost};
+pub use parser::ParseError;
+
+/// Runs the debug access sequences of a CMSIS-Pack.
+///
+/// Sequences not defined by the pack use the built-in implementation of [`ArmDebugSequence`].
+#[derive(Debug)]
+pub struct PackDebugSequence {
+    sequences: Vec<SequenceDescription>,
+    debug_vars: Option<String>,
+    cores: Vec<PackCore>,
+    state: Mutex<State>,
+}
+
+/// The parts of a core used by the sequences.
+#[derive(Debug)]
+struct PackCore {
+    /// The memory AP, which identifies the core the sequences are run for.
+    ap: FullyQualifiedApAddress,
+    /// The name of the core, to pick the sequences specific to the core.
+    name: String,
+    /// The value of `__dp`.
+    dp_id: u8,
+    /// The sequence run instead of `ResetSystem`.
+    reset_sequence: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The global variables, once the `<debugvars>` have been run.
+    globals: Option<HashMap<String, u64>>,
+    /// The protocol last seen on the probe, for `__protocol`.
+    protocol: Option<WireProtocol>,
+}
+
+impl PackDebugSequence {
+    /// Creates the sequence for `chip`, or returns `None` if the chip has no debug sequences.
+    pub fn create(chip: &Chip) -> Option<Arc<dyn ArmDebugSequence>> {
+        if chip.debug_sequences.is_empty() {
+            return None;
+        }
+
+        let cores = chip
+            .cores
+            .iter()
+            .filter_map(|core| {
+                let CoreAccessOptions::Arm(options) = &core.core_access_options else {
+                    return None;
+                };
+
+                Some(PackCore {
+                    ap: core.memory_ap()?,
+                    name: core.name.clone(),
+                    dp_id: options.dp_id.unwrap_or(0),
+                    reset_sequence: options.default_reset_sequence.clone(),
+                })
+            })
+            .collect();
+
+        Some(Arc::new(Self {
+            sequences: chip.debug_sequences.clone(),
+            debug_vars: chip.debug_vars.clone(),
+            cores,
+            state: Mutex::new(State::default()),
+        }))
+    }
+
+    fn core(&self, ap: &FullyQualifiedApAddress) -> Option<&PackCore> {
+        self.cores.iter().find(|core| core.ap == *ap)
+    }
+
+    fn pname(&self, ap: &FullyQualifiedApAddress) -> Option<&str> {
+        self.core(ap).map(|core| core.name.as_str())
+    }
+
+    fn default_ap(&self) -> FullyQualifiedApAddress {
+        self.cores
+            .first()
+            .map(|core| core.ap.clone())
+            .unwrap_or_else(|| FullyQualifiedApAddress::v1_with_default_dp(0))
+    }
+
+    /// Runs the sequence `name` on `host`.
+    ///
+    /// The global variables are taken out of the state while the sequence runs, so that
+    /// sequences called by the probe while this one runs don't deadlock.
+    fn run(
+        &self,
+        name: &str,
+        ap: &FullyQualifiedApAddress,
+        host: &mut dyn SequenceHost,
+    ) -> Result<(), ArmError> {
+        let (globals, protocol) = {
+            let mut state = self.state.lock();
+            (state.globals.take(), state.protocol)
+        };
+
+        let initialized = globals.is_some();
+        let mut globals = globals.unwrap_or_default();
+        let dp_id = self.core(ap).map_or(0, |core| core.dp_id);
+        set_predefined_variables(&mut globals, ap, dp_id, protocol);
+
+        let mut interpreter = Interpreter::new(&self.sequences, self.pname(ap), &mut globals, host);
+        let mut result = Ok(());
+        if !initialized {
+            if let Some(debug_vars) = &self.debug_vars {
+                result = interpreter.run_global(debug_vars);
+            }
+        }
+        let result = result.and_then(|_| interpreter.run(name));
+
+        // Keep the debug variables, even if the sequence failed half way through.
+        self.state.lock().globals = Some(globals);
+
+        result.map_err(|error| match error {
+            SequenceError::Access(error) => error,
+            error => {
+                ArmDebugSequenceError::custom(format!("Sequence {name} failed: {error}")).into()
+            }
+        })
+    }
+
+    fn run_on_probe(
+        &self,
+        name: &str,
+        probe: &mut dyn DapProbe,
+        dp: DpAddress,
+    ) -> Result<(), ArmError> {
+        self.state.lock().protocol = probe.active_protocol();
+        let ap = self.default_ap();
+
+        self.run(name, &ap, &mut ProbeHost { probe, dp })
+    }
+}
+
+/// Sets the predefined variables of the sequence language.
+fn set_predefined_variables(
+    globals: &mut HashMap<String, u64>,
+    ap: &FullyQualifiedApAddress,
+    dp_id: u8,
+    protocol: Option<WireProtocol>,
+) {
+    // Bits 0..15 are the protocol, bit 16 flags an SWJ-DP which can switch between them.
+    let protocol = match protocol {
+        Some(WireProtocol::Jtag) => 1,
+        Some(WireProtocol::Swd) => 2,
+        None => 0,
+    } | 1 << 16;
+    let ap_index = match ap.ap() {
+        ApAddress::V1(index) => u64::from(*index),
+        ApAddress::V2(_) => 0,
+    };
+
+    globals.insert(String::from("__protocol"), protocol);
+    // A debug connection, not for flash programming.
+    globals.insert(String::from("__connection"), 1);
+    globals.insert(String::from("__dp"), dp_id.into());
+    globals.insert(String::from("__ap"), ap_index);
+    globals.insert(String::from("__apid"), 0);
+    globals.insert(String::from("__traceout"), 0);
+    globals.insert(String::from("__errorcontrol"), 0);
+    globals.insert(String::from("__Result"), 0);
+    for flash_variable in ["__FlashOp", "__FlashAddr", "__FlashLen", "__FlashArg"] {
+        globals.insert(String::from(flash_variable), 0);
+    }
+}
+
+fn unavailable(access: &str) -> ArmError {
+    ArmDebugSequenceError::custom(format!("{access} is not available in this sequence")).into()
+}
+
+/// Returns the access port selected by `__ap`, relative to the default access port `base`.
+///
+/// For ADIv6 access ports, `__ap` is not used.
+fn selected_ap(base: &FullyQualifiedApAddress, ap: u64) -> FullyQualifiedApAddress {
+    match base.ap() {
+        ApAddress::V1(_) => FullyQualifiedApAddress::v1_with_dp(base.dp(), ap as u8),
+        ApAddress::V2(_) => base.clone(),
+    }
+}
+
+/// Debug port and access port accesses through an initialized interface.
+struct InterfaceAccess {
+    ap: FullyQualifiedApAddress,
+    /// The DPBANKSEL value written by the sequence, which must be kept for DP reads.
+    dp_bank: u8,
+}
+
+impl InterfaceAccess {
+    fn new(ap: FullyQualifiedApAddress) -> Self {
+        Self { ap, dp_bank: 0 }
+    }
+
+    fn dp_address(&self, address: u8) -> DpRegisterAddress {
+        DpRegisterAddress {
+            address,
+            bank: Some(self.dp_bank),
+        }
+    }
+
+    fn read_dp(&self, interface: &mut dyn ArmProbeInterface, address: u8) -> Result<u32, ArmError> {
+        interface.read_raw_dp_register(self.ap.dp(), self.dp_address(address))
+    }
+
+    fn write_dp(
+        &mut self,
+        interface: &mut dyn ArmProbeInterface,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        interface.write_raw_dp_register(self.ap.dp(), self.dp_address(address), value)?;
+        if address == 0x8 {
+            self.dp_bank = (value & 0xF) as u8;
+        }
+
+        Ok(())
+    }
+
+    fn read_ap(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        ap: u64,
+        address: u64,
+    ) -> Result<u32, ArmError> {
+        let ap = selected_ap(&self.ap, ap);
+        match ap.ap() {
+            ApAddress::V1(_) => interface.read_raw_ap_register(&ap, address as u8),
+            // The registers of an ADIv6 access port are in the memory of its debug port.
+            ApAddress::V2(address_chain) => {
+                let root = FullyQualifiedApAddress::v2_with_dp(ap.dp(), ApV2Address::root());
+                let base = address_chain.as_slice().last().copied().unwrap_or(0);
+                interface
+                    .memory_interface(&root)?
+                    .read_word_32(base + address)
+            }
+        }
+    }
+
+    fn write_ap(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        ap: u64,
+        address: u64,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        let ap = selected_ap(&self.ap, ap);
+        match ap.ap() {
+            ApAddress::V1(_) => interface.write_raw_ap_register(&ap, address as u8, value),
+            ApAddress::V2(address_chain) => {
+                let root = FullyQualifiedApAddress::v2_with_dp(ap.dp(), ApV2Address::root());
+                let base = address_chain.as_slice().last().copied().unwrap_or(0);
+                interface
+                    .memory_interface(&root)?
+                    .write_word_32(base + address, value)
+            }
+        }
+    }
+}
+
+fn read_memory(
+    memory: &mut dyn ArmMemoryInterface,
+    bits: u8,
+    address: u64,
+) -> Result<u64, ArmError> {
+    Ok(match bits {
+        8 => memory.read_word_8(address)?.into(),
+        16 => memory.read_word_16(address)?.into(),
+        32 => memory.read_word_32(address)?.into(),
+        _ => memory.read_word_64(address)?,
+    })
+}
+
+fn write_memory(
+    memory: &mut dyn ArmMemoryInterface,
+    bits: u8,
+    address: u64,
+    value: u64,
+) -> Result<(), ArmError> {
+    match bits {
+        8 => memory.write_word_8(address, value as u8)?,
+        16 => memory.write_word_16(address, value as u16)?,
+        32 => memory.write_word_32(address, value as u32)?,
+        _ => memory.write_word_64(address, value)?,
+    }
+
+    // Writes may be buffered, but sequences rely on them having happened, e.g. before a delay.
+    memory.flush()
+}
+
+/// Runs sequences on the raw probe, before the debug port is powered up.
+struct ProbeHost<'a> {
+    probe: &'a mut dyn DapProbe,
+    dp: DpAddress,
+}
+
+impl SequenceHost for ProbeHost<'_> {
+    fn read_dp(&mut self, address: u8) -> Result<u32, ArmError> {
+        self.probe
+            .raw_read_register(RegisterAddress::DpRegister(DpRegisterAddress {
+                address,
+                bank: None,
+            }))
+    }
+
+    fn write_dp(&mut self, address: u8, value: u32) -> Result<(), ArmError> {
+        self.probe.raw_write_register(
+            RegisterAddress::DpRegister(DpRegisterAddress {
+                address,
+                bank: None,
+            }),
+            value,
+        )
+    }
+
+    fn read_ap(&mut self, ap: u64, address: u64) -> Result<u32, ArmError> {
+        // Select the access port and register bank with an ADIv5 SELECT value.
+        self.write_dp(0x8, ((ap as u32) << 24) | (address as u32 & 0xF0))?;
+        self.probe
+            .raw_read_register(RegisterAddress::ApRegister(address as u8))
+    }
+
+    fn write_ap(&mut self, ap: u64, address: u64, value: u32) -> Result<(), ArmError> {
+        self.write_dp(0x8, ((ap as u32) << 24) | (address as u32 & 0xF0))?;
+        self.probe
+            .raw_write_register(RegisterAddress::ApRegister(address as u8), value)
+    }
+
+    fn read_memory(&mut self, _ap: u64, _bits: u8, _address: u64) -> Result<u64, ArmError> {
+        Err(unavailable("Memory access"))
+    }
+
+    fn write_memory(&mut self, _: u64, _: u8, _: u64, _: u64) -> Result<(), ArmError> {
+        Err(unavailable("Memory access"))
+    }
+
+    fn swj_pins(&mut self, pin_out: u32, pin_select: u32, pin_wait: u32) -> Result<u32, ArmError> {
+        Ok(self.probe.swj_pins(pin_out, pin_select, pin_wait)?)
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), ArmError> {
+        Ok(self.probe.swj_sequence(bit_len, bits)?)
+    }
+
+    fn jtag_sequence(&mut self, cycles: u8, tms: bool, tdi: u64) -> Result<(), ArmError> {
+        Ok(self.probe.jtag_sequence(cycles, tms, tdi)?)
+    }
+
+    fn swj_clock(&mut self, frequency: u32) -> Result<(), ArmError> {
+        self.probe.set_speed((frequency / 1000).max(1))?;
+        Ok(())
+    }
+
+    fn builtin_sequence(&mut self, name: &str) -> Option<Result<(), ArmError>> {
+        let default = DefaultArmSequence(());
+        Some(match name {
+            "ResetHardwareAssert" => default.reset_hardware_assert(self.probe),
+            "DebugPortSetup" => default.debug_port_setup(self.probe, self.dp),
+            "DebugPortStop" => default.debug_port_stop(self.probe, self.dp),
+            _ => return None,
+        })
+    }
+}
+
+/// Runs sequences on an initialized interface, with a powered up debug port.
+struct InterfaceHost<'a> {
+    interface: &'a mut dyn ArmProbeInterface,
+    access: InterfaceAccess,
+    /// The name and built-in implementation of the sequence being run.
+    builtin: Option<BuiltinSequence<'a>>,
+}
+
+type BuiltinSequence<'a> = (
+    &'static str,
+    &'a dyn Fn(&mut dyn ArmProbeInterface) -> Result<(), ArmError>,
+);
+
+impl SequenceHost for InterfaceHost<'_> {
+    fn read_dp(&mut self, address: u8) -> Result<u32, ArmError> {
+        self.access.read_dp(self.interface, address)
+    }
+
+    fn write_dp(&mut self, address: u8, value: u32) -> Result<(), ArmError> {
+        self.access.write_dp(self.interface, address, value)
+    }
+
+    fn read_ap(&mut self, ap: u64, address: u64) -> Result<u32, ArmError> {
+        self.access.read_ap(self.interface, ap, address)
+    }
+
+    fn write_ap(&mut self, ap: u64, address: u64, value: u32) -> Result<(), ArmError> {
+        self.access.write_ap(self.interface, ap, address, value)
+    }
+
+    fn read_memory(&mut self, ap: u64, bits: u8, address: u64) -> Result<u64, ArmError> {
+        let ap = selected_ap(&self.access.ap, ap);
+        read_memory(&mut *self.interface.memory_interface(&ap)?, bits, address)
+    }
+
+    fn write_memory(
+        &mut self,
+        ap: u64,
+        bits: u8,
+        address: u64,
+        value: u64,
+    ) -> Result<(), ArmError> {
+        let ap = selected_ap(&self.access.ap, ap);
+        write_memory(
+            &mut *self.interface.memory_interface(&ap)?,
+            bits,
+            address,
+            value,
+        )
+    }
+
+    fn swj_pins(&mut self, pin_out: u32, pin_select: u32, pin_wait: u32) -> Result<u32, ArmError> {
+        Ok(self.interface.swj_pins(pin_out, pin_select, pin_wait)?)
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), ArmError> {
+        Ok(self.interface.swj_sequence(bit_len, bits)?)
+    }
+
+    fn jtag_sequence(&mut self, _cycles: u8, _tms: bool, _tdi: u64) -> Result<(), ArmError> {
+        Err(unavailable("DAP_JTAG_Sequence"))
+    }
+
+    fn swj_clock(&mut self, _frequency: u32) -> Result<(), ArmError> {
+        Err(unavailable("DAP_SWJ_Clock"))
+    }
+
+    fn builtin_sequence(&mut self, name: &str) -> Option<Result<(), ArmError>> {
+        if let Some((_, builtin)) = self.builtin.filter(|(builtin, _)| *builtin == name) {
+            Some(builtin(self.interface))
+        } else if name == "ResetHardwareDeassert" {
+            Some(DefaultArmSequence(()).reset_hardware_deassert(self.interface, &self.access.ap))
+        } else {
+            None
+        }
+    }
+}
+
+/// Runs sequences on the memory interface of a core.
+struct MemoryHost<'a> {
+    memory: &'a mut dyn ArmMemoryInterface,
+    access: InterfaceAccess,
+    core_type: CoreType,
+    debug_base: Option<u64>,
+}
+
+impl<'a> MemoryHost<'a> {
+    fn new(
+        memory: &'a mut dyn ArmMemoryInterface,
+        core_type: CoreType,
+        debug_base: Option<u64>,
+    ) -> Self {
+        let access = InterfaceAccess::new(memory.fully_qualified_address());
+        Self {
+            memory,
+            access,
+            core_type,
+            debug_base,
+        }
+    }
+}
+
+impl SequenceHost for MemoryHost<'_> {
+    fn read_dp(&mut self, address: u8) -> Result<u32, ArmError> {
+        let interface = self.memory.get_arm_probe_interface()?;
+        self.access.read_dp(interface, address)
+    }
+
+    fn write_dp(&mut self, address: u8, value: u32) -> Result<(), ArmError> {
+        let interface = self.memory.get_arm_probe_interface()?;
+        self.access.write_dp(interface, address, value)
+    }
+
+    fn read_ap(&mut self, ap: u64, address: u64) -> Result<u32, ArmError> {
+        let interface = self.memory.get_arm_probe_interface()?;
+        self.access.read_ap(interface, ap, address)
+    }
+
+    fn write_ap(&mut self, ap: u64, address: u64, value: u32) -> Result<(), ArmError> {
+        let interface = self.memory.get_arm_probe_interface()?;
+        self.access.write_ap(interface, ap, address, value)
+    }
+
+    fn read_memory(&mut self, ap: u64, bits: u8, address: u64) -> Result<u64, ArmError> {
+        let ap = selected_ap(&self.access.ap, ap);
+        if ap == self.access.ap {
+            read_memory(self.memory, bits, address)
+        } else {
+            let interface = self.memory.get_arm_probe_interface()?;
+            read_memory(&mut *interface.memory_interface(&ap)?, bits, address)
+        }
+    }
+
+    fn write_memory(
+        &mut self,
+        ap: u64,
+        bits: u8,
+        address: u64,
+        value: u64,
+    ) -> Result<(), ArmError> {
+        let ap = selected_ap(&self.access.ap, ap);
+        if ap == self.access.ap {
+            write_memory(self.memory, bits, address, value)
+        } else {
+            let interface = self.memory.get_arm_probe_interface()?;
+            write_memory(&mut *interface.memory_interface(&ap)?, bits, address, value)
+        }
+    }
+
+    fn swj_pins(&mut self, pin_out: u32, pin_select: u32, pin_wait: u32) -> Result<u32, ArmError> {
+        Ok(self
+            .memory
+            .get_swd_sequence()?
+            .swj_pins(pin_out, pin_select, pin_wait)?)
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), ArmError> {
+        Ok(self
+            .memory
+            .get_swd_sequence()?
+            .swj_sequence(bit_len, bits)?)
+    }
+
+    fn jtag_sequence(&mut self, _cycles: u8, _tms: bool, _tdi: u64) -> Result<(), ArmError> {
+        Err(unavailable("DAP_JTAG_Sequence"))
+    }
+
+    fn swj_clock(&mut self, _frequency: u32) -> Result<(), ArmError> {
+        Err(unavailable("DAP_SWJ_Clock"))
+    }
+
+    fn builtin_sequence(&mut self, name: &str) -> Option<Result<(), ArmError>> {
+        let default = DefaultArmSequence(());
+        let (memory, core_type, debug_base) = (&mut *self.memory, self.core_type, self.debug_base);
+        Some(match name {
+            "ResetSystem" => default.reset_system(memory, core_type, debug_base),
+            "ResetCatchSet" => default.reset_catch_set(memory, core_type, debug_base),
+            "ResetCatchClear" => default.reset_catch_clear(memory, core_type, debug_base),
+            "RecoverSupportStart" => default.recover_support_start(memory),
+            "DebugCoreStop" => default.debug_core_stop(memory, core_type),
+            _ => return None,
+        })
+    }
+}
+
+impl ArmDebugSequence for PackDebugSequence {
+    fn reset_hardware_assert(&self, interface: &mut dyn DapProbe) -> Result<(), ArmError> {
+        self.run_on_probe("ResetHardwareAssert", interface, DpAddress::Default)
+    }
+
+    fn reset_hardware_deassert(
+        &self,
+        probe: &mut dyn ArmProbeInterface,
+        default_ap: &FullyQualifiedApAddress,
+    ) -> Result<(), ArmError> {
+        let builtin = |probe: &mut dyn ArmProbeInterface| {
+            DefaultArmSequence(()).reset_hardware_deassert(probe, default_ap)
+        };
+        self.run(
+            "ResetHardwareDeassert",
+            default_ap,
+            &mut InterfaceHost {
+                interface: probe,
+                access: InterfaceAccess::new(default_ap.clone()),
+                builtin: Some(("ResetHardwareDeassert", &builtin)),
+            },
+        )
+    }
+
+    fn debug_port_setup(
+        &self,
+        interface: &mut dyn DapProbe,
+        dp: DpAddress,
+    ) -> Result<(), ArmError> {
+        self.run_on_probe("DebugPortSetup", interface, dp)
+    }
+
+    fn debug_port_start(
+        &self,
+        interface: &mut ArmCommunicationInterface<Initialized>,
+        dp: DpAddress,
+    ) -> Result<(), ArmError> {
+        self.state.lock().protocol = interface.probe_mut().active_protocol();
+
+        let default_ap = self.default_ap();
+        let ap = match default_ap.ap() {
+            ApAddress::V1(index) => FullyQualifiedApAddress::v1_with_dp(dp, *index),
+            ApAddress::V2(address) => FullyQualifiedApAddress::v2_with_dp(dp, address.clone()),
+        };
+        // The built-in sequence needs the communication interface itself, so it can't be
+        // called from the pack's sequences.
+        if find_sequence(&self.sequences, "DebugPortStart", self.pname(&ap)).is_none() {
+            return DefaultArmSequence(()).debug_port_start(interface, dp);
+        }
+
+        self.run(
+            "DebugPortStart",
+            &ap,
+            &mut InterfaceHost {
+                interface,
+                access: InterfaceAccess::new(ap.clone()),
+                builtin: None,
+            },
+        )
+    }
+
+    fn debug_core_start(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        core_ap: &FullyQualifiedApAddress,
+        core_type: CoreType,
+        debug_base: Option<u64>,
+        cti_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        let builtin = |interface: &mut dyn ArmProbeInterface| {
+            DefaultArmSequence(())
+                .debug_core_start(interface, core_ap, core_type, debug_base, cti_base)
+        };
+        self.run(
+            "DebugCoreStart",
+            core_ap,
+            &mut InterfaceHost {
+                interface,
+                access: InterfaceAccess::new(core_ap.clone()),
+                builtin: Some(("DebugCoreStart", &builtin)),
+            },
+        )
+    }
+
+    fn reset_catch_set(
+        &self,
+        core: &mut dyn ArmMemoryInterface,
+        core_type: CoreType,
+        debug_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        let ap = core.fully_qualified_address();
+        self.run(
+            "ResetCatchSet",
+            &ap,
+            &mut MemoryHost::new(core, core_type, debug_base),
+        )
+    }
+
+    fn reset_catch_clear(
+        &self,
+        core: &mut dyn ArmMemoryInterface,
+        core_type: CoreType,
+        debug_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        let ap = core.fully_qualified_address();
+        self.run(
+            "ResetCatchClear",
+            &ap,
+            &mut MemoryHost::new(core, core_type, debug_base),
+        )
+    }
+
+    fn trace_start(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        components: &[CoresightComponent],
+        sink: &TraceSink,
+    ) -> Result<(), ArmError> {
+        let ap = self.default_ap();
+        let builtin = |interface: &mut dyn ArmProbeInterface| {
+            DefaultArmSequence(()).trace_start(interface, components, sink)
+        };
+        self.run(
+            "TraceStart",
+            &ap,
+            &mut InterfaceHost {
+                interface,
+                access: InterfaceAccess::new(ap.clone()),
+                builtin: Some(("TraceStart", &builtin)),
+            },
+        )
+    }
+
+    fn reset_system(
+        &self,
+        interface: &mut dyn ArmMemoryInterface,
+        core_type: CoreType,
+        debug_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        let ap = interface.fully_qualified_address();
+        // The default reset sequence of the core is only used if the pack defines it, as
+        // there are no built-in `ResetProcessor` and `ResetHardware` sequences for a core.
+        let name = self
+            .core(&ap)
+            .and_then(|core| core.reset_sequence.as_deref())
+            .filter(|name| find_sequence(&self.sequences, name, self.pname(&ap)).is_some())
+            .unwrap_or("ResetSystem");
+
+        self.run(
+            name,
+            &ap,
+            &mut MemoryHost::new(interface, core_type, debug_base),
+        )
+    }
+
+    fn debug_device_unlock(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: &FullyQualifiedApAddress,
+        permissions: &crate::Permissions,
+    ) -> Result<(), ArmError> {
+        let builtin = |interface: &mut dyn ArmProbeInterface| {
+            DefaultArmSequence(()).debug_device_unlock(interface, default_ap, permissions)
+        };
+        self.run(
+            "DebugDeviceUnlock",
+            default_ap,
+            &mut InterfaceHost {
+                interface,
+                access: InterfaceAccess::new(default_ap.clone()),
+                builtin: Some(("DebugDeviceUnlock", &builtin)),
+            },
+        )
+    }
+
+    fn recover_support_start(
+        &self,
+        interface: &mut dyn ArmMemoryInterface,
+    ) -> Result<(), ArmError> {
+        let ap = interface.fully_qualified_address();
+        // The core type only matters for the built-in sequences, which don't use it here.
+        self.run(
+            "RecoverSupportStart",
+            &ap,
+            &mut MemoryHost::new(interface, CoreType::Armv7m, None),
+        )
+    }
+
+    fn debug_core_stop(
+        &self,
+        interface: &mut dyn ArmMemoryInterface,
+        core_type: CoreType,
+    ) -> Result<(), ArmError> {
+        let ap = interface.fully_qualified_address();
+        self.run(
+            "DebugCoreStop",
+            &ap,
+            &mut MemoryHost::new(interface, core_type, None),
+        )
+    }
+
+    fn debug_port_stop(&self, interface: &mut dyn DapProbe, dp: DpAddress) -> Result<(), ArmError> {
+        self.run_on_probe("DebugPortStop", interface, dp)
+    }
+}
@@ -0,0 +1,554 @@
+//! Parser for the C-like language of CMSIS-Pack debug access sequences.
+//!
+//! The language is described in the [Open-CMSIS-Pack specification].
+//!
+//! [Open-CMSIS-Pack specification]: https://open-cmsis-pack.github.io/Open-CMSIS-Pack-Spec/main/html/debug_description.html#debugAccessSequences
+
+/// An error found while parsing sequence code.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("{message} at offset {offset} of `{code}`")]
+pub struct ParseError {
+    message: String,
+    offset: usize,
+    code: String,
+}
+
+/// A statement of a `<block>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Statement {
+    /// `__var name = value;`
+    Declare { name: String, value: Option<Expr> },
+    /// `name = value;`, or a compound assignment like `name |= value;`.
+    Assign {
+        name: String,
+        op: Option<BinaryOp>,
+        value: Expr,
+    },
+    /// An expression evaluated for its side effects, usually a function call.
+    Expr(Expr),
+}
+
+/// An expression. All values are 64 bit unsigned integers, except for string literals,
+/// which are only allowed as function arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Expr {
+    Number(u64),
+    Str(String),
+    Variable(String),
+    Call { function: String, args: Vec<Expr> },
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnaryOp {
+    Not,
+    BitNot,
+    Neg,
+    Plus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    /// The binding strength of the operator, following C. Higher binds tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 10,
+            BinaryOp::Add | BinaryOp::Sub => 9,
+            BinaryOp::Shl | BinaryOp::Shr => 8,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 7,
+            BinaryOp::Eq | BinaryOp::Ne => 6,
+            BinaryOp::BitAnd => 5,
+            BinaryOp::BitXor => 4,
+            BinaryOp::BitOr => 3,
+            BinaryOp::And => 2,
+            BinaryOp::Or => 1,
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        Some(match token {
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            "%" => BinaryOp::Rem,
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "<<" => BinaryOp::Shl,
+            ">>" => BinaryOp::Shr,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::Le,
+            ">" => BinaryOp::Gt,
+            ">=" => BinaryOp::Ge,
+            "==" => BinaryOp::Eq,
+            "!=" => BinaryOp::Ne,
+            "&" => BinaryOp::BitAnd,
+            "^" => BinaryOp::BitXor,
+            "|" => BinaryOp::BitOr,
+            "&&" => BinaryOp::And,
+            "||" => BinaryOp::Or,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(u64),
+    Ident(String),
+    Str(String),
+    Punct(&'static str),
+}
+
+/// Punctuation and operators, longest first so that the lexer can take the first match.
+const PUNCTUATION: &[&str] = &[
+    "<<=", ">>=", "&&", "||", "==", "!=", "<=", ">=", "<<", ">>", "+=", "-=", "*=", "/=", "%=",
+    "&=", "|=", "^=", "+", "-", "*", "/", "%", "&", "|", "^", "!", "~", "<", ">", "=", "(", ")",
+    ",", ";", "?", ":",
+];
+
+/// Parses the statements of a `<block>`.
+pub(crate) fn parse_block(code: &str) -> Result<Vec<Statement>, ParseError> {
+    let mut parser = Parser::new(code)?;
+    let mut statements = Vec::new();
+    while !parser.at_end() {
+        // Empty statements are allowed.
+        if parser.eat(";") {
+            continue;
+        }
+        statements.push(parser.statement()?);
+    }
+
+    Ok(statements)
+}
+
+/// Parses a single expression, as used by the `if` and `while` attributes of `<control>`.
+pub(crate) fn parse_expression(code: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser::new(code)?;
+    let expr = parser.expression()?;
+    if !parser.at_end() {
+        return Err(parser.error("Unexpected trailing input"));
+    }
+
+    Ok(expr)
+}
+
+struct Parser<'a> {
+    code: &'a str,
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(code: &'a str) -> Result<Self, ParseError> {
+        Ok(Self {
+            code,
+            tokens: tokenize(code)?,
+            position: 0,
+        })
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        let offset = self
+            .tokens
+            .get(self.position)
+            .map_or(self.code.len(), |(offset, _)| *offset);
+
+        ParseError {
+            message: message.into(),
+            offset,
+            code: self.code.trim().to_string(),
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.position + n).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(_, token)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    /// Consumes the punctuation `punct` if it is next.
+    fn eat(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Punct(next)) if *next == punct) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), ParseError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(self.error(format!("Expected `{punct}`")))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok(name)
+            }
+            _ => Err(self.error("Expected an identifier")),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        if self.peek() == Some(&Token::Ident(String::from("__var"))) {
+            self.position += 1;
+            let name = self.identifier()?;
+            let value = if self.eat("=") {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.expect(";")?;
+            return Ok(Statement::Declare { name, value });
+        }
+
+        if let (Some(Token::Ident(name)), Some(Token::Punct(op))) = (self.peek(), self.peek_nth(1))
+        {
+            let op = match *op {
+                "=" => Some(None),
+                compound if compound.len() >= 2 && compound.ends_with('=') => {
+                    // `==`, `<=`, `>=` and `!=` are comparisons, not assignments.
+                    BinaryOp::from_token(&compound[..compound.len() - 1])
+                        .filter(|_| !matches!(compound, "==" | "<=" | ">=" | "!="))
+                        .map(Some)
+                }
+                _ => None,
+            };
+            if let Some(op) = op {
+                let name = name.clone();
+                self.position += 2;
+                let value = self.expression()?;
+                self.expect(";")?;
+                return Ok(Statement::Assign { name, op, value });
+            }
+        }
+
+        let expr = self.expression()?;
+        self.expect(";")?;
+
+        Ok(Statement::Expr(expr))
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        let condition = self.binary(0)?;
+        if self.eat("?") {
+            let then = self.expression()?;
+            self.expect(":")?;
+            let otherwise = self.expression()?;
+            return Ok(Expr::Conditional(
+                Box::new(condition),
+                Box::new(then),
+                Box::new(otherwise),
+            ));
+        }
+
+        Ok(condition)
+    }
+
+    /// Parses binary operators binding tighter than `min_precedence`, by precedence climbing.
+    fn binary(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+
+        while let Some(Token::Punct(punct)) = self.peek() {
+            let op = match BinaryOp::from_token(punct) {
+                Some(op) if op.precedence() > min_precedence => op,
+                _ => break,
+            };
+            self.position += 1;
+
+            let rhs = self.binary(op.precedence())?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        let op = match self.peek() {
+            Some(Token::Punct("!")) => UnaryOp::Not,
+            Some(Token::Punct("~")) => UnaryOp::BitNot,
+            Some(Token::Punct("-")) => UnaryOp::Neg,
+            Some(Token::Punct("+")) => UnaryOp::Plus,
+            _ => return self.primary(),
+        };
+        self.position += 1;
+
+        Ok(Expr::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Str(value)) => Ok(Expr::Str(value)),
+            Some(Token::Ident(name)) => {
+                if !self.eat("(") {
+                    return Ok(Expr::Variable(name));
+                }
+
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.expression()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+
+                Ok(Expr::Call {
+                    function: name,
+                    args,
+                })
+            }
+            Some(Token::Punct("(")) => {
+                let expr = self.expression()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            _ => {
+                self.position -= 1;
+                Err(self.error("Expected an expression"))
+            }
+        }
+    }
+}
+
+fn tokenize(code: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let error = |message: &str, offset: usize| ParseError {
+        message: message.to_string(),
+        offset,
+        code: code.trim().to_string(),
+    };
+
+    let bytes = code.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        let rest = &code[i..];
+
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            let end = rest
+                .find("*/")
+                .ok_or_else(|| error("Unterminated comment", i))?;
+            i += end + 2;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            let (radix, digits_start) = match rest.get(..2) {
+                Some("0x" | "0X") => (16, i + 2),
+                Some("0b" | "0B") => (2, i + 2),
+                _ => (10, i),
+            };
+            i = digits_start;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            // Integer suffixes like `U` or `UL` don't change the value.
+            let digits = code[digits_start..i].trim_end_matches(['u', 'U', 'l', 'L']);
+            let value =
+                u64::from_str_radix(digits, radix).map_err(|_| error("Invalid number", start))?;
+            tokens.push((start, Token::Number(value)));
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push((start, Token::Ident(code[start..i].to_string())));
+        } else if c == b'"' {
+            let start = i;
+            let mut value = String::new();
+            let mut chars = rest[1..].char_indices();
+            loop {
+                match chars.next() {
+                    Some((offset, '"')) => {
+                        i += offset + 2;
+                        break;
+                    }
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 't')) => value.push('\t'),
+                        Some((_, escaped)) => value.push(escaped),
+                        None => return Err(error("Unterminated string", start)),
+                    },
+                    Some((_, c)) => value.push(c),
+                    None => return Err(error("Unterminated string", start)),
+                }
+            }
+            tokens.push((start, Token::Str(value)));
+        } else if let Some(punct) = PUNCTUATION.iter().find(|punct| rest.starts_with(**punct)) {
+            tokens.push((i, Token::Punct(punct)));
+            i += punct.len();
+        } else {
+            return Err(error("Unexpected character", i));
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn var(name: &str) -> Box<Expr> {
+        Box::new(Expr::Variable(name.to_string()))
+    }
+
+    fn num(value: u64) -> Box<Expr> {
+        Box::new(Expr::Number(value))
+    }
+
+    #[test]
+    fn precedence_follows_c() {
+        assert_eq!(
+            parse_expression("a | b & 1 << 2 + 3").unwrap(),
+            Expr::Binary(
+                BinaryOp::BitOr,
+                var("a"),
+                Box::new(Expr::Binary(
+                    BinaryOp::BitAnd,
+                    var("b"),
+                    Box::new(Expr::Binary(
+                        BinaryOp::Shl,
+                        num(1),
+                        Box::new(Expr::Binary(BinaryOp::Add, num(2), num(3)))
+                    ))
+                ))
+            )
+        );
+
+        assert_eq!(
+            parse_expression("a - b - c").unwrap(),
+            Expr::Binary(
+                BinaryOp::Sub,
+                Box::new(Expr::Binary(BinaryOp::Sub, var("a"), var("b"))),
+                var("c")
+            )
+        );
+    }
+
+    #[test]
+    fn parse_statements() {
+        let code = r#"
+            __var isSWJ = ((__protocol & 0x00010000) != 0); // comment
+            /* block
+               comment */
+            __var value;
+            value |= 0x05FA0004UL;
+            Write32(0xE000ED0C, value);
+            Message(0, "Reset %x", value);
+        "#;
+
+        assert_eq!(
+            parse_block(code).unwrap(),
+            vec![
+                Statement::Declare {
+                    name: "isSWJ".to_string(),
+                    value: Some(Expr::Binary(
+                        BinaryOp::Ne,
+                        Box::new(Expr::Binary(
+                            BinaryOp::BitAnd,
+                            var("__protocol"),
+                            num(0x1_0000)
+                        )),
+                        num(0)
+                    )),
+                },
+                Statement::Declare {
+                    name: "value".to_string(),
+                    value: None,
+                },
+                Statement::Assign {
+                    name: "value".to_string(),
+                    op: Some(BinaryOp::BitOr),
+                    value: Expr::Number(0x05FA_0004),
+                },
+                Statement::Expr(Expr::Call {
+                    function: "Write32".to_string(),
+                    args: vec![
+                        Expr::Number(0xE000_ED0C),
+                        Expr::Variable("value".to_string())
+                    ],
+                }),
+                Statement::Expr(Expr::Call {
+                    function: "Message".to_string(),
+                    args: vec![
+                        Expr::Number(0),
+                        Expr::Str("Reset %x".to_string()),
+                        Expr::Variable("value".to_string())
+                    ],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn comparison_is_not_assignment() {
+        assert_eq!(
+            parse_block("a == 1;").unwrap(),
+            vec![Statement::Expr(Expr::Binary(
+                BinaryOp::Eq,
+                var("a"),
+                num(1)
+            ))]
+        );
+    }
+
+    #[test]
+    fn report_errors() {
+        assert!(parse_block("Write32(0x1000, 1)").is_err());
+        assert!(parse_block("__var = 1;").is_err());
+        assert!(parse_expression("1 +").is_err());
+        assert!(parse_expression("0xZZ").is_err());
+        assert!(parse_block("Message(0, \"unterminated);").is_err());
+    }
+}
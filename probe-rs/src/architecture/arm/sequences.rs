@@ -17,6 +17,7 @@ use crate::{
         ArmProbeInterface, RegisterAddress,
     },
     probe::{DebugProbeError, WireProtocol},
+    protection::{ProtectionLevel, ProtectionStatus},
     MemoryInterface, MemoryMappedRegister, Session,
 };
 
@@ -1069,6 +1070,41 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
     fn allowed_access_ports(&self) -> Vec<u8> {
         (0..=255).collect()
     }
+
+    /// Reads the state of the readout protection mechanisms of the device.
+    ///
+    /// This is called without unlocking the device or attaching to its cores, so it has to work
+    /// while the device is protected.
+    fn protection_status(
+        &self,
+        _interface: &mut dyn ArmProbeInterface,
+        _default_ap: &FullyQualifiedApAddress,
+    ) -> Result<Vec<ProtectionStatus>, ArmError> {
+        Err(ArmError::NotImplemented("protection_status"))
+    }
+
+    /// Enables the readout protection of the device at the given level.
+    ///
+    /// The required permissions have already been checked by the caller.
+    fn enable_protection(
+        &self,
+        _interface: &mut dyn ArmProbeInterface,
+        _default_ap: &FullyQualifiedApAddress,
+        _level: ProtectionLevel,
+    ) -> Result<(), ArmError> {
+        Err(ArmError::NotImplemented("enable_protection"))
+    }
+
+    /// Removes the readout protection of the device, usually by erasing all memory.
+    ///
+    /// The required permissions have already been checked by the caller.
+    fn disable_protection(
+        &self,
+        _interface: &mut dyn ArmProbeInterface,
+        _default_ap: &FullyQualifiedApAddress,
+    ) -> Result<(), ArmError> {
+        Err(ArmError::NotImplemented("disable_protection"))
+    }
 }
 
 /// Chip-Erase Handling via the Device's Debug Interface
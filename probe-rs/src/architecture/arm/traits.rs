@@ -7,6 +7,7 @@ use super::{
     dp::{DpAddress, DpRegisterAddress},
     ArmError,
 };
+use serde::{Deserialize, Serialize};
 
 /// Specifies the address of register to access in a debug or access port.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -93,7 +94,7 @@ bitfield::bitfield! {
 /// access. In this way, a fully-qualified route to a specific final address can be specified. All
 /// accesses route through the "root memory interface", which is the memory interface of the debug
 /// port (DP).
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
 pub struct ApV2Address(Vec<u64>);
 
 impl ApV2Address {
@@ -126,7 +127,7 @@ impl From<&[u64]> for ApV2Address {
     }
 }
 /// Access port address
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
 pub enum ApAddress {
     /// Access port v1 address
     V1(u8),
@@ -150,7 +151,7 @@ impl std::fmt::Display for ApAddress {
 }
 
 /// Access port address.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
 pub struct FullyQualifiedApAddress {
     /// The address of the debug port this access port belongs to.
     dp: DpAddress,
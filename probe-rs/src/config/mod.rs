@@ -19,20 +19,29 @@
 //! To add a target at runtime, the [add_target_from_yaml] function can
 //! be used to read targets from a YAML file.
 //!
+//! ## User target directories
+//!
+//! Target description files (`*.yaml` or `*.yml`) in the directories returned by
+//! [target_search_path] are loaded automatically. They replace built-in families with
+//! the same name, and targets added with [add_target_from_yaml] replace both.
+//! [reload_user_targets] picks up changes to these files.
+//!
 
 mod chip_info;
 pub(crate) mod registry;
 mod target;
 
 pub use probe_rs_target::{
-    Chip, ChipFamily, Core, CoreType, FlashProperties, GenericRegion, InstructionSet, MemoryAccess,
-    MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion, RawFlashAlgorithm, ScanChainElement,
-    SectorDescription, SectorInfo, TargetDescriptionSource,
+    Chip, ChipFamily, ConfigurationField, ConfigurationRegion, ConfigurationValue, Core, CoreType,
+    FlashProperties, GenericRegion, InstructionSet, MemoryAccess, MemoryRange, MemoryRegion,
+    NvmRegion, PageInfo, RamRegion, RawFlashAlgorithm, ScanChainElement, SectorDescription,
+    SectorInfo, TargetDescriptionSource,
 };
 
 pub use registry::{
     add_target_from_yaml, families, get_target_and_family_by_name, get_target_by_name,
-    get_targets_by_family_name, search_chips, RegistryError,
+    get_targets_by_family_name, reload_user_targets, search_chips, target_search_path,
+    RegistryError, TARGET_PATH_ENV,
 };
 pub use target::{DebugSequence, Target, TargetSelector};
 
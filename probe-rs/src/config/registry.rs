@@ -6,12 +6,24 @@ use parking_lot::{RwLock, RwLockReadGuard};
 use probe_rs_target::{CoreAccessOptions, RiscvCoreAccessOptions};
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fs::File;
 use std::io::Read;
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
+use std::time::SystemTime;
 
-static REGISTRY: LazyLock<RwLock<Registry>> =
-    LazyLock::new(|| RwLock::new(Registry::from_builtin_families()));
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(|| {
+    let mut registry = Registry::from_builtin_families();
+    registry.reload_user_targets(&target_search_path());
+    RwLock::new(registry)
+});
+
+/// Environment variable containing additional directories to load target descriptions from.
+///
+/// The directories are separated like the `PATH` variable of the platform, i.e. by `:` on
+/// Unix and by `;` on Windows.
+pub const TARGET_PATH_ENV: &str = "PROBE_RS_TARGET_PATH";
 
 /// Error type for all errors which occur when working
 /// with the internal registry of targets.
@@ -113,6 +125,9 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
                 rtt_scan_ranges: None,
                 jtag: None,
                 default_binary_format: None,
+                debug_sequences: vec![],
+                debug_vars: None,
+                configuration_regions: vec![],
             }],
             flash_algorithms: vec![],
             source: TargetDescriptionSource::Generic,
@@ -124,6 +139,12 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
 struct Registry {
     /// All the available chips.
     families: Vec<ChipFamily>,
+    /// The target description files loaded from the target search path, with their
+    /// modification time and size, used to detect changes.
+    user_targets: Vec<(PathBuf, Option<SystemTime>, u64)>,
+    /// Families added by [`add_target_from_yaml`], which take precedence over all other
+    /// families and have to be kept when the user targets are reloaded.
+    added_families: Vec<ChipFamily>,
 }
 
 #[cfg(feature = "builtin-targets")]
@@ -149,7 +170,69 @@ impl Registry {
         // Additionally, validation for existing targets is done in the tests `validate_generic_targets` and
         // `validate_builtin` as well, to ensure we do not ship broken target definitions.
 
-        Self { families }
+        Self {
+            families,
+            user_targets: vec![],
+            added_families: vec![],
+        }
+    }
+
+    /// Loads the target descriptions in the directories of `search_path`, replacing the
+    /// ones loaded before.
+    ///
+    /// Families in later directories replace families with the same name in earlier
+    /// directories and in the built-in targets. Returns `false` without changing the registry
+    /// if no target description file was added, removed or modified since the last call.
+    fn reload_user_targets(&mut self, search_path: &[PathBuf]) -> bool {
+        let user_targets = search_path
+            .iter()
+            .flat_map(|dir| target_files(dir))
+            .map(|path| {
+                let metadata = std::fs::metadata(&path).ok();
+                let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+                let len = metadata.map_or(0, |m| m.len());
+                (path, modified, len)
+            })
+            .collect::<Vec<_>>();
+
+        if user_targets == self.user_targets {
+            return false;
+        }
+
+        let added_families = std::mem::take(&mut self.added_families);
+        *self = Self::from_builtin_families();
+
+        for (path, _, _) in &user_targets {
+            let family = File::open(path)
+                .map_err(RegistryError::from)
+                .and_then(parse_family);
+            match family {
+                Ok(family) => {
+                    tracing::debug!("Loaded target description {}", path.display());
+                    self.insert_family(family);
+                }
+                Err(error) => {
+                    tracing::warn!("Skipping target description {}: {error}", path.display());
+                }
+            }
+        }
+
+        for family in &added_families {
+            self.insert_family(family.clone());
+        }
+
+        self.user_targets = user_targets;
+        self.added_families = added_families;
+
+        true
+    }
+
+    /// Adds `family`, replacing the family with the same name.
+    fn insert_family(&mut self, family: ChipFamily) {
+        self.families
+            .retain(|old_family| !old_family.name.eq_ignore_ascii_case(&family.name));
+
+        self.families.push(family);
     }
 
     fn get_target_by_name(&self, name: impl AsRef<str>) -> Result<Target, RegistryError> {
@@ -342,23 +425,89 @@ impl Registry {
     where
         R: Read,
     {
-        let family: ChipFamily = serde_yaml::from_reader(yaml_reader)?;
-
-        validate_family(&family).map_err(|error| {
-            RegistryError::InvalidChipFamilyDefinition(Box::new(family.clone()), error)
-        })?;
-
+        let family = parse_family(yaml_reader)?;
         let family_name = family.name.clone();
 
-        self.families
+        self.added_families
             .retain(|old_family| !old_family.name.eq_ignore_ascii_case(&family_name));
+        self.added_families.push(family.clone());
 
-        self.families.push(family);
+        self.insert_family(family);
 
         Ok(family_name)
     }
 }
 
+fn parse_family(yaml_reader: impl Read) -> Result<ChipFamily, RegistryError> {
+    let family: ChipFamily = serde_yaml::from_reader(yaml_reader)?;
+
+    validate_family(&family).map_err(|error| {
+        RegistryError::InvalidChipFamilyDefinition(Box::new(family.clone()), error)
+    })?;
+
+    Ok(family)
+}
+
+/// Returns the target description files in `dir`, sorted by name.
+fn target_files(dir: &Path) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) => {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("Failed to read target directory {}: {error}", dir.display());
+            }
+            return vec![];
+        }
+    };
+
+    let mut files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension == "yaml" || extension == "yml")
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+
+    files
+}
+
+/// Returns the directories target descriptions are loaded from, in increasing order of
+/// precedence.
+///
+/// These are the `targets` directory in the probe-rs configuration directory (e.g.
+/// `~/.config/probe-rs/targets` on Linux), followed by the directories listed in the
+/// [`TARGET_PATH_ENV`] environment variable.
+pub fn target_search_path() -> Vec<PathBuf> {
+    let mut search_path = vec![];
+
+    if let Some(dirs) = directories::ProjectDirs::from("rs", "probe-rs", "probe-rs") {
+        search_path.push(dirs.config_dir().join("targets"));
+    }
+
+    if let Some(paths) = std::env::var_os(TARGET_PATH_ENV) {
+        search_path
+            .extend(std::env::split_paths(&paths).filter(|path| !path.as_os_str().is_empty()));
+    }
+
+    search_path
+}
+
+/// Reloads the target descriptions from the [target search path](target_search_path) if
+/// any of them was added, removed or modified since they were last loaded.
+///
+/// Target descriptions are loaded automatically when the registry is first used. Long-running
+/// tools like the debug servers call this before each session to pick up changes.
+///
+/// Returns `true` if the targets were reloaded.
+pub fn reload_user_targets() -> bool {
+    let search_path = target_search_path();
+    REGISTRY.write().reload_user_targets(&search_path)
+}
+
 /// Get a target from the internal registry based on its name.
 pub fn get_target_by_name(name: impl AsRef<str>) -> Result<Target, RegistryError> {
     REGISTRY.read_recursive().get_target_by_name(name)
@@ -598,4 +747,37 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn reload_user_targets_from_search_path() -> TestResult {
+        let dir = std::env::temp_dir().join(format!("probe-rs-targets-{}", fastrand::u64(..)));
+        std::fs::create_dir_all(&dir)?;
+        let target_file = dir.join("scan_chain_test.yaml");
+
+        let mut registry = Registry::from_builtin_families();
+        let search_path = [dir.clone()];
+
+        // Nothing changed, nothing to reload.
+        assert!(!registry.reload_user_targets(&search_path));
+        assert!(registry.get_target_by_name("NO_JTAG_INFO").is_err());
+
+        std::fs::copy("tests/scan_chain_test.yaml", &target_file)?;
+        assert!(registry.reload_user_targets(&search_path));
+        assert!(registry.get_target_by_name("NO_JTAG_INFO").is_ok());
+        assert!(!registry.reload_user_targets(&search_path));
+
+        // Targets added explicitly are kept when reloading.
+        registry.add_target_from_yaml(File::open("tests/scan_chain_test.yaml")?)?;
+        std::fs::remove_file(&target_file)?;
+        assert!(registry.reload_user_targets(&search_path));
+        assert!(registry.get_target_by_name("NO_JTAG_INFO").is_ok());
+
+        // Invalid files are skipped.
+        std::fs::write(dir.join("invalid.yaml"), "name: [")?;
+        assert!(registry.reload_user_targets(&search_path));
+
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
 }
@@ -1,9 +1,10 @@
-use super::{Core, MemoryRegion, RawFlashAlgorithm, TargetDescriptionSource};
+use super::{ConfigurationRegion, Core, MemoryRegion, RawFlashAlgorithm, TargetDescriptionSource};
 use crate::flashing::FlashLoader;
 use crate::{
     architecture::{
         arm::{
             dp::DpAddress,
+            pack_sequence::PackDebugSequence,
             sequences::{ArmDebugSequence, DefaultArmSequence},
             FullyQualifiedApAddress,
         },
@@ -45,6 +46,8 @@ pub struct Target {
     pub jtag: Option<Jtag>,
     /// The default executable format for the target.
     pub default_format: Option<String>,
+    /// Vendor specific configuration memory, like option bytes or the UICR.
+    pub configuration_regions: Vec<ConfigurationRegion>,
 }
 
 impl std::fmt::Debug for Target {
@@ -113,7 +116,9 @@ impl Target {
             // Default to the architecture of the first core, which is okay if
             // there is no mixed architectures.
             match chip.cores[0].core_type.architecture() {
-                Architecture::Arm => DebugSequence::Arm(DefaultArmSequence::create()),
+                Architecture::Arm => DebugSequence::Arm(
+                    PackDebugSequence::create(chip).unwrap_or_else(DefaultArmSequence::create),
+                ),
                 Architecture::Riscv => DebugSequence::Riscv(DefaultRiscvSequence::create()),
                 Architecture::Xtensa => DebugSequence::Xtensa(DefaultXtensaSequence::create()),
             }
@@ -136,6 +141,7 @@ impl Target {
             rtt_scan_regions,
             jtag: chip.jtag.clone(),
             default_format: chip.default_binary_format.clone(),
+            configuration_regions: chip.configuration_regions.clone(),
         }
     }
 
@@ -175,6 +181,13 @@ impl Target {
         FlashLoader::new(self.memory_map.clone(), self.source.clone())
     }
 
+    /// Returns the [ConfigurationRegion] with the given name, ignoring case.
+    pub fn configuration_region(&self, name: &str) -> Option<&ConfigurationRegion> {
+        self.configuration_regions
+            .iter()
+            .find(|region| region.name.eq_ignore_ascii_case(name))
+    }
+
     /// Returns a [RawFlashAlgorithm] by name.
     pub(crate) fn flash_algorithm_by_name(&self, name: &str) -> Option<&RawFlashAlgorithm> {
         self.flash_algorithms.iter().find(|a| a.name == name)
@@ -202,6 +215,7 @@ impl Target {
 
 /// Selector for the debug target.
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum TargetSelector {
     /// Specify the name of a target, which will
     /// be used to search the internal list of
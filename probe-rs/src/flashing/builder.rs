@@ -115,6 +115,39 @@ impl FlashLayout {
         self.data_blocks.extend(other.data_blocks);
     }
 
+    /// Removes the sectors for which `keep` returns `false`, together with their pages and fills.
+    pub(super) fn retain_sectors(&mut self, keep: impl Fn(&FlashSector) -> bool) {
+        let (kept, removed): (Vec<_>, Vec<_>) = self.sectors.drain(..).partition(|s| keep(s));
+        self.sectors = kept;
+
+        let in_removed_sector = |address: u64| {
+            removed
+                .iter()
+                .any(|sector| (sector.address..sector.address + sector.size).contains(&address))
+        };
+
+        // Fills refer to their page by index, which changes when pages are removed.
+        let mut page_indices = Vec::with_capacity(self.pages.len());
+        let mut next_index = 0;
+        for page in &self.pages {
+            if in_removed_sector(page.address) {
+                page_indices.push(None);
+            } else {
+                page_indices.push(Some(next_index));
+                next_index += 1;
+            }
+        }
+
+        self.pages.retain(|page| !in_removed_sector(page.address));
+        self.fills = self
+            .fills
+            .drain(..)
+            .filter_map(|fill| {
+                page_indices[fill.page_index].map(|page_index| FlashFill { page_index, ..fill })
+            })
+            .collect();
+    }
+
     /// List of sectors which are erased during flashing.
     pub fn sectors(&self) -> &[FlashSector] {
         &self.sectors
@@ -166,7 +199,7 @@ impl FlashDataBlockSpan {
 }
 
 /// A helper structure to build a flash layout from a set of data blocks.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(super) struct FlashBuilder {
     pub(super) data: BTreeMap<u64, Vec<u8>>,
 }
@@ -222,6 +255,50 @@ impl FlashBuilder {
         Ok(())
     }
 
+    /// Overwrites the staged data in the range of the chunk, and stages the parts of the chunk
+    /// which do not overlap staged data.
+    pub(super) fn patch_data(&mut self, address: u64, data: &[u8]) -> Result<(), FlashError> {
+        let end = address + data.len() as u64;
+
+        // A chunk which starts before the patch may still overlap it.
+        let first = self
+            .data
+            .range(..=address)
+            .next_back()
+            .map_or(address, |(&chunk_address, _)| chunk_address);
+
+        let mut gaps = Vec::new();
+        let mut cursor = address;
+        for (&chunk_address, chunk) in self.data.range_mut(first..end) {
+            let chunk_end = chunk_address + chunk.len() as u64;
+            if chunk_end <= address {
+                continue;
+            }
+
+            if chunk_address > cursor {
+                gaps.push(cursor..chunk_address);
+            }
+
+            let start = chunk_address.max(address);
+            let stop = chunk_end.min(end);
+            chunk[(start - chunk_address) as usize..(stop - chunk_address) as usize]
+                .copy_from_slice(&data[(start - address) as usize..(stop - address) as usize]);
+            cursor = stop;
+        }
+        if cursor < end {
+            gaps.push(cursor..end);
+        }
+
+        for gap in gaps {
+            self.add_data(
+                gap.start,
+                &data[(gap.start - address) as usize..(gap.end - address) as usize],
+            )?;
+        }
+
+        Ok(())
+    }
+
     /// Check whether there is staged data for a given address range.
     pub(crate) fn has_data_in_range(&self, range: &Range<u64>) -> bool {
         self.data_in_range(range).next().is_some()
@@ -1147,4 +1224,73 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    fn patch_staged_data() {
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0x100, &[1; 4]).unwrap();
+        flash_builder.add_data(0x108, &[2; 4]).unwrap();
+
+        flash_builder.patch_data(0x102, &[9; 8]).unwrap();
+        flash_builder.patch_data(0x110, &[7; 2]).unwrap();
+
+        assert_eq!(
+            flash_builder.data.into_iter().collect::<Vec<_>>(),
+            [
+                (0x100, vec![1, 1, 9, 9, 9, 9, 9, 9]),
+                (0x108, vec![9, 9, 2, 2]),
+                (0x110, vec![7, 7]),
+            ]
+        );
+    }
+
+    #[test]
+    fn retain_sectors_reindexes_fills() {
+        let (region, flash_algorithm) = assemble_demo_flash1();
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0x0F00, &[42; 0x200]).unwrap();
+        let mut flash_layout = flash_builder
+            .build_sectors_and_pages(&region, &flash_algorithm, true)
+            .unwrap();
+
+        flash_layout.retain_sectors(|sector| sector.address() != 0x0000);
+
+        assert_eq!(
+            flash_layout.sectors(),
+            &[FlashSector {
+                address: 0x1000,
+                size: 0x1000,
+            }]
+        );
+        assert!(flash_layout
+            .pages()
+            .iter()
+            .all(|page| (0x1000..0x2000).contains(&page.address())));
+        assert_eq!(flash_layout.pages().len(), 4);
+        assert_eq!(
+            flash_layout.fills(),
+            &[
+                FlashFill {
+                    address: 0x1100,
+                    size: 0x0300,
+                    page_index: 0,
+                },
+                FlashFill {
+                    address: 0x1400,
+                    size: 0x0400,
+                    page_index: 1,
+                },
+                FlashFill {
+                    address: 0x1800,
+                    size: 0x0400,
+                    page_index: 2,
+                },
+                FlashFill {
+                    address: 0x1C00,
+                    size: 0x0400,
+                    page_index: 3,
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,235 @@
+//! Built-in routines to compute checksums of the flash contents on the target.
+//!
+//! The routines are appended to the flash algorithm when it is loaded into RAM. This allows
+//! comparing the flash contents with the image by transferring only a checksum instead of every
+//! byte, which is a lot faster on slow debug links.
+//!
+//! The code of each routine is assembled from the listing in its documentation with the command
+//! given there, and converted to little endian words with `od -An -tx4 -v crc32.bin`.
+
+use crate::core::Architecture;
+
+/// A position independent routine computing the CRC32 (IEEE 802.3) of a memory range.
+///
+/// The routine is called with the start address, the length in bytes and the CRC32 of the
+/// preceding data (`0` to start a new checksum) as arguments, and returns the updated CRC32.
+/// It does not use the stack.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ChecksumRoutine {
+    /// The code of the routine.
+    pub(super) code: &'static [u32],
+    /// Offset of the entry point from the start of the code.
+    pub(super) entry: u64,
+}
+
+/// ARMv6-M compatible Thumb code.
+///
+/// Assembled with `arm-none-eabi-as -mcpu=cortex-m0 -mthumb -o crc32.o crc32.s` and
+/// `arm-none-eabi-objcopy -O binary -j .text crc32.o crc32.bin`.
+///
+/// ```text
+///     mvns    r2, r2
+///     ldr     r3, poly
+///     mov     r12, r3
+///     cmp     r1, #0
+///     beq     done
+/// byte_loop:
+///     ldrb    r3, [r0]
+///     eors    r2, r3
+///     mov     r3, r12
+///     .rept 8
+///     lsrs    r2, r2, #1
+///     bcc     1f
+///     eors    r2, r3
+/// 1:
+///     .endr
+///     adds    r0, #1
+///     subs    r1, #1
+///     bne     byte_loop
+/// done:
+///     mvns    r0, r2
+///     bx      lr
+///     .p2align 2
+/// poly:
+///     .word   0xEDB88320
+/// ```
+const THUMB_CRC32: [u32; 20] = [
+    0x4b1243d2, 0x2900469c, 0x7803d01d, 0x4663405a, 0xd3000852, 0x0852405a, 0x405ad300, 0xd3000852,
+    0x0852405a, 0x405ad300, 0xd3000852, 0x0852405a, 0x405ad300, 0xd3000852, 0x0852405a, 0x405ad300,
+    0x39013001, 0x43d0d1e1, 0x46c04770, 0xedb88320,
+];
+
+/// RV32I code, without compressed instructions.
+///
+/// Assembled with `riscv64-unknown-elf-as -march=rv32i -mabi=ilp32 -mno-relax -o crc32.o crc32.s`
+/// and `riscv64-unknown-elf-objcopy -O binary -j .text crc32.o crc32.bin`.
+///
+/// ```text
+///     not     a2, a2
+///     lui     a3, 0xedb88
+///     addi    a3, a3, 0x320
+///     beqz    a1, done
+/// loop:
+///     lbu     a4, 0(a0)
+///     xor     a2, a2, a4
+///     li      a5, 8
+/// bit:
+///     andi    a4, a2, 1
+///     srli    a2, a2, 1
+///     beqz    a4, skip
+///     xor     a2, a2, a3
+/// skip:
+///     addi    a5, a5, -1
+///     bnez    a5, bit
+///     addi    a0, a0, 1
+///     addi    a1, a1, -1
+///     bnez    a1, loop
+/// done:
+///     not     a0, a2
+///     ret
+/// ```
+const RISCV_CRC32: [u32; 18] = [
+    0xfff64613, 0xedb886b7, 0x32068693, 0x02058a63, 0x00054703, 0x00e64633, 0x00800793, 0x00167713,
+    0x00165613, 0x00070463, 0x00d64633, 0xfff78793, 0xfe0796e3, 0x00150513, 0xfff58593, 0xfc059ae3,
+    0xfff64513, 0x00008067,
+];
+
+/// Xtensa code, using the CALL0 register assignment and no density instructions.
+///
+/// The literal is placed in front of the code, the entry point is at offset 4. Assembled with
+/// `xtensa-esp32-elf-as --no-transform -o crc32.o crc32.s` and
+/// `xtensa-esp32-elf-objcopy -O binary -j .text crc32.o crc32.bin`, the code is padded with zeros
+/// to a multiple of four bytes.
+///
+/// ```text
+/// poly:
+///     .word   0xEDB88320
+/// crc32:
+///     l32r    a6, poly
+///     movi    a5, -1
+///     xor     a4, a4, a5
+///     beqz    a3, done
+/// loop:
+///     l8ui    a5, a2, 0
+///     xor     a4, a4, a5
+///     movi    a7, 8
+/// bit:
+///     srli    a5, a4, 1
+///     bbci    a4, 0, skip
+///     xor     a5, a5, a6
+/// skip:
+///     mov     a4, a5
+///     addi    a7, a7, -1
+///     bnez    a7, bit
+///     addi    a2, a2, 1
+///     addi    a3, a3, -1
+///     bnez    a3, loop
+/// done:
+///     movi    a5, -1
+///     xor     a2, a4, a5
+///     ret
+/// ```
+const XTENSA_CRC32: [u32; 16] = [
+    0xedb88320, 0x52ffff61, 0x4450ffaf, 0x02331630, 0x50000252, 0xa0723044, 0x41514008, 0x60026407,
+    0x45503055, 0xffc77220, 0x22fed756, 0xc33201c2, 0xfdb356ff, 0x50ffaf52, 0x00803024, 0x00000000,
+];
+
+/// Returns the CRC32 routine for the given architecture.
+pub(super) fn crc32_routine(architecture: Architecture) -> ChecksumRoutine {
+    match architecture {
+        Architecture::Arm => ChecksumRoutine {
+            code: &THUMB_CRC32,
+            entry: 0,
+        },
+        Architecture::Riscv => ChecksumRoutine {
+            code: &RISCV_CRC32,
+            entry: 0,
+        },
+        Architecture::Xtensa => ChecksumRoutine {
+            code: &XTENSA_CRC32,
+            entry: 4,
+        },
+    }
+}
+
+/// Computes the CRC32 of `data` on the host, continuing the checksum `initial` of the preceding data.
+pub(super) fn crc32(initial: u32, data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new_with_initial(initial);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32, THUMB_CRC32};
+    use crate::{
+        architecture::arm::ArmError, probe::fake_probe::emulator::EmulatedCore, MemoryInterface,
+    };
+    use probe_rs_target::CoreType;
+
+    const DHCSR: u64 = 0xE000_EDF0;
+
+    /// Runs the Thumb routine on the emulator and returns the checksum left in `r0`.
+    fn run_thumb_crc32(initial: u32, data: &[u8]) -> Result<u32, ArmError> {
+        const CODE: u32 = 0x2000_0000;
+        const DATA: u32 = 0x2000_1000;
+        // The routine returns to a `bkpt` instruction, which halts the core.
+        const RETURN: u32 = 0x2000_0F00;
+
+        let mut core = EmulatedCore::new(CoreType::Armv6m);
+        let code = THUMB_CRC32
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+        core.load(CODE, &code);
+        core.load(RETURN, &0xBE00u16.to_le_bytes());
+        core.load(DATA, data);
+
+        core.set_register(0, DATA);
+        core.set_register(1, data.len() as u32);
+        core.set_register(2, initial);
+        core.set_register(14, RETURN | 1);
+        core.set_register(15, CODE);
+        core.set_register(16, 1 << 24);
+
+        let mut interface = &mut core;
+        // Clear C_HALT while keeping C_DEBUGEN set, so the breakpoint halts the core again.
+        interface.write_word_32(DHCSR, 0xA05F_0001)?;
+        loop {
+            let dhcsr = interface.read_word_32(DHCSR)?;
+            assert_eq!(dhcsr & (1 << 19), 0, "The emulated core locked up");
+            if dhcsr & (1 << 17) != 0 {
+                break;
+            }
+        }
+
+        Ok(core.register(0))
+    }
+
+    #[test]
+    fn crc32_continues_checksum() {
+        let data = b"123456789";
+
+        assert_eq!(crc32(0, data), 0xcbf43926);
+        assert_eq!(crc32(crc32(0, &data[..4]), &data[4..]), 0xcbf43926);
+    }
+
+    #[test]
+    fn thumb_crc32_matches_host() {
+        let data = (0..=255u8).cycle().take(1027).collect::<Vec<_>>();
+
+        for len in [0, 1, 3, 4, 7, 64, 1027] {
+            let data = &data[..len];
+            assert_eq!(
+                run_thumb_crc32(0, data).unwrap(),
+                crc32(0, data),
+                "length {len}"
+            );
+        }
+
+        assert_eq!(
+            run_thumb_crc32(crc32(0, b"1234"), b"56789").unwrap(),
+            0xcbf43926
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Access to vendor specific configuration memory, like option bytes, fuses or the UICR.
+
+use probe_rs_target::{ConfigurationRegion, MemoryRange};
+
+use crate::flashing::{DownloadOptions, FlashError, FlashProgress};
+use crate::{MemoryInterface, Session};
+
+/// Errors which can occur while accessing a configuration region.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigurationError {
+    /// The target has no configuration region with the given name.
+    #[error("The target has no configuration region named {0}.")]
+    RegionNotFound(String),
+    /// The configuration region has no field with the given name.
+    #[error("The configuration region {region} has no field named {field}.")]
+    FieldNotFound {
+        /// The name of the configuration region.
+        region: String,
+        /// The name of the field which was not found.
+        field: String,
+    },
+    /// The value is too large for the field.
+    #[error("The value {value:#x} does not fit into the field {field} of the configuration region {region}.")]
+    ValueOutOfRange {
+        /// The name of the configuration region.
+        region: String,
+        /// The name of the field.
+        field: String,
+        /// The value which was supposed to be written.
+        value: u64,
+    },
+    /// The configuration region can not be written, because no flash algorithm can program it.
+    #[error("Writing the configuration region {0} is not supported, no flash algorithm of the target can program it.")]
+    WriteNotSupported(String),
+    /// Writing configuration regions was not permitted.
+    #[error("An operation could not be performed because it lacked the permission to do so: {0}")]
+    MissingPermissions(String),
+    /// Reading the contents of the configuration region failed.
+    #[error("Failed to read the configuration region {region}.")]
+    Read {
+        /// The name of the configuration region.
+        region: String,
+        /// The source error of this error.
+        #[source]
+        source: crate::Error,
+    },
+    /// Programming the configuration region failed.
+    #[error("Failed to write the configuration region {region}.")]
+    Write {
+        /// The name of the configuration region.
+        region: String,
+        /// The source error of this error.
+        #[source]
+        source: FlashError,
+    },
+}
+
+/// Returns the configuration region with the given name.
+fn find_region(session: &Session, name: &str) -> Result<ConfigurationRegion, ConfigurationError> {
+    session
+        .target()
+        .configuration_region(name)
+        .cloned()
+        .ok_or_else(|| ConfigurationError::RegionNotFound(name.to_string()))
+}
+
+/// Reads the contents of the configuration region with the given name.
+pub(crate) fn read_region(
+    session: &mut Session,
+    name: &str,
+) -> Result<Vec<u8>, ConfigurationError> {
+    let region = find_region(session, name)?;
+
+    read_contents(session, &region).map_err(|source| ConfigurationError::Read {
+        region: region.name.clone(),
+        source,
+    })
+}
+
+fn read_contents(
+    session: &mut Session,
+    region: &ConfigurationRegion,
+) -> Result<Vec<u8>, crate::Error> {
+    let core_index = session
+        .target()
+        .core_index_by_address(region.range.start)
+        .unwrap_or(0);
+
+    let mut contents = vec![0; (region.range.end - region.range.start) as usize];
+    session
+        .core(core_index)?
+        .read(region.range.start, &mut contents)?;
+
+    Ok(contents)
+}
+
+/// Replaces the values of the given fields of a configuration region and programs the region.
+///
+/// Fields which are not mentioned keep their current value. The region is not programmed if all
+/// fields already have the requested values.
+///
+/// The region is programmed with the flash algorithms of the target, so only regions covered by
+/// one of them can be written.
+pub(crate) fn write_fields(
+    session: &mut Session,
+    name: &str,
+    values: &[(&str, u64)],
+    progress: Option<FlashProgress>,
+) -> Result<(), ConfigurationError> {
+    let region = find_region(session, name)?;
+    let writable = session.target().flash_algorithms.iter().any(|algorithm| {
+        algorithm
+            .flash_properties
+            .address_range
+            .contains_range(&region.range)
+    });
+    if !writable {
+        return Err(ConfigurationError::WriteNotSupported(region.name));
+    }
+
+    let current = read_region(session, &region.name)?;
+
+    let mut contents = current.clone();
+    for &(field_name, value) in values {
+        let Some(field) = region.field(field_name) else {
+            return Err(ConfigurationError::FieldNotFound {
+                region: region.name.clone(),
+                field: field_name.to_string(),
+            });
+        };
+
+        if field.insert(&mut contents, value).is_none() {
+            return Err(ConfigurationError::ValueOutOfRange {
+                region: region.name.clone(),
+                field: field.name.clone(),
+                value,
+            });
+        }
+
+        if field.is_irreversible(value) {
+            tracing::warn!(
+                "Writing {value:#x} to {}.{} can not be undone",
+                region.name,
+                field.name
+            );
+        }
+    }
+
+    if contents == current {
+        tracing::info!(
+            "Configuration region {} is already up to date, skipping",
+            region.name
+        );
+        return Ok(());
+    }
+
+    let write_error = |source| ConfigurationError::Write {
+        region: region.name.clone(),
+        source,
+    };
+
+    let mut loader = session.target().flash_loader();
+    loader
+        .add_data(region.range.start, &contents)
+        .map_err(write_error)?;
+
+    let options = DownloadOptions {
+        progress,
+        keep_unwritten_bytes: true,
+        verify: true,
+        ..Default::default()
+    };
+
+    loader.commit(session, options).map_err(write_error)
+}
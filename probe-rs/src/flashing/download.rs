@@ -51,6 +51,10 @@ pub enum FormatKind {
     Idf,
     /// Marks a file in the [UF2](https://github.com/microsoft/uf2) format.
     Uf2,
+    /// Marks a file in the [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) format (S19, S28 or S37).
+    SRecord,
+    /// Marks a file in the TI-TXT format, as produced by TI's MSP430 and MSPM0 toolchains.
+    TiTxt,
 }
 
 impl FormatKind {
@@ -63,6 +67,53 @@ impl FormatKind {
             None => Ok(Self::default()),
         }
     }
+
+    /// Detects the format of the image at `path` from its extension, or from its contents.
+    ///
+    /// Returns `None` if the image is an ELF file or the format can not be determined, in which
+    /// case the format preferred by the target should be used.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+
+        match extension.as_deref() {
+            Some("hex" | "ihex") => return Some(Self::Hex),
+            Some("s19" | "s28" | "s37" | "srec" | "mot") => return Some(Self::SRecord),
+            Some("uf2") => return Some(Self::Uf2),
+            Some("elf") => return None,
+            _ => {}
+        }
+
+        let mut header = [0; 16];
+        let mut file = File::open(path).ok()?;
+        let len = std::io::Read::read(&mut file, &mut header).ok()?;
+
+        Self::sniff(&header[..len])
+    }
+
+    /// Detects the format of an image from its first bytes.
+    fn sniff(header: &[u8]) -> Option<Self> {
+        let text = header.trim_ascii_start();
+
+        if header.starts_with(b"UF2\n") {
+            Some(Self::Uf2)
+        } else if text.first() == Some(&b':') && text.get(1).is_some_and(|c| c.is_ascii_hexdigit())
+        {
+            Some(Self::Hex)
+        } else if text.first() == Some(&b'S')
+            && text.get(1).is_some_and(|c| c.is_ascii_digit())
+            && text.get(2).is_some_and(|c| c.is_ascii_hexdigit())
+        {
+            Some(Self::SRecord)
+        } else if text.first() == Some(&b'@') && text.get(1).is_some_and(|c| c.is_ascii_hexdigit())
+        {
+            Some(Self::TiTxt)
+        } else {
+            None
+        }
+    }
 }
 
 impl FromStr for FormatKind {
@@ -75,6 +126,8 @@ impl FromStr for FormatKind {
             "elf" => Ok(Self::Elf),
             "uf2" => Ok(Self::Uf2),
             "idf" | "esp-idf" | "espidf" => Ok(Self::Idf),
+            "srec" | "srecord" | "s-record" | "s19" | "s28" | "s37" | "mot" => Ok(Self::SRecord),
+            "titxt" | "ti-txt" => Ok(Self::TiTxt),
             _ => Err(format!("Format '{s}' is unknown.")),
         }
     }
@@ -97,6 +150,10 @@ pub enum Format {
     Idf(IdfOptions),
     /// Marks a file in the [UF2](https://github.com/microsoft/uf2) format.
     Uf2,
+    /// Marks a file in the [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) format (S19, S28 or S37).
+    SRecord,
+    /// Marks a file in the TI-TXT format, as produced by TI's MSP430 and MSPM0 toolchains.
+    TiTxt,
 }
 
 impl From<FormatKind> for Format {
@@ -106,6 +163,8 @@ impl From<FormatKind> for Format {
             FormatKind::Hex => Format::Hex,
             FormatKind::Elf => Format::Elf,
             FormatKind::Uf2 => Format::Uf2,
+            FormatKind::SRecord => Format::SRecord,
+            FormatKind::TiTxt => Format::TiTxt,
             FormatKind::Idf => Format::Idf(IdfOptions::default()),
         }
     }
@@ -126,6 +185,16 @@ pub enum FileDownloadError {
     /// Failed to read or decode the IHEX file.
     IhexRead(#[from] ihex::ReaderError),
 
+    /// Failed to decode line {line} of the {format} file: {reason}
+    ImageDecode {
+        /// The name of the image format.
+        format: &'static str,
+        /// The line which could not be decoded, starting at 1.
+        line: usize,
+        /// Why the line could not be decoded.
+        reason: String,
+    },
+
     /// An IO error has occurred while reading the firmware file.
     IO(#[from] std::io::Error),
 
@@ -201,11 +270,47 @@ pub struct DownloadOptions {
     /// It may be useful for mass production.
     pub skip_erase: bool,
     /// Before flashing, read back the flash contents to skip up-to-date regions.
+    ///
+    /// If the flash algorithm fits into RAM together with the built-in checksum routine, the
+    /// contents are compared using CRC32 checksums computed on the target, and every sector which
+    /// is up to date is skipped.
     pub preverify: bool,
     /// After flashing, read back all the flashed data to verify it has been written correctly.
+    ///
+    /// Like `preverify`, this compares checksums computed on the target if possible.
     pub verify: bool,
     /// Disable double buffering when loading flash.
     pub disable_double_buffering: bool,
+    /// Data which replaces the contents of the image before flashing, like a serial number.
+    ///
+    /// See [`FlashLoader::patch_data`].
+    pub patches: Vec<DataPatch>,
+    /// Memory ranges whose contents are kept when the flash around them is erased, like
+    /// calibration data.
+    ///
+    /// The parts of the ranges which are located in sectors that will be erased, or all of them
+    /// if `do_chip_erase` is set, are read before erasing and written back afterwards. Unlike
+    /// `keep_unwritten_bytes`, the rest of the erased flash is not restored. The image must not
+    /// contain data in these ranges.
+    pub preserved_ranges: Vec<PreservedRange>,
+}
+
+/// A named memory range whose contents are kept while flashing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreservedRange {
+    /// The name of the range, like `calibration`, used in messages.
+    pub name: String,
+    /// The address range.
+    pub range: std::ops::Range<u64>,
+}
+
+/// A chunk of data which is written over the image at flash time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataPatch {
+    /// The address of the first byte.
+    pub address: u64,
+    /// The bytes to write.
+    pub data: Vec<u8>,
 }
 
 impl DownloadOptions {
@@ -395,6 +500,22 @@ mod tests {
 
     use super::FormatKind;
 
+    #[test]
+    fn sniff_format() {
+        assert_eq!(FormatKind::sniff(b":020000040800F2"), Some(FormatKind::Hex));
+        assert_eq!(
+            FormatKind::sniff(b"S00F000068656C6C"),
+            Some(FormatKind::SRecord)
+        );
+        assert_eq!(
+            FormatKind::sniff(b"\r\n@08000000\r\n"),
+            Some(FormatKind::TiTxt)
+        );
+        assert_eq!(FormatKind::sniff(b"UF2\nWQ]\x9e"), Some(FormatKind::Uf2));
+        assert_eq!(FormatKind::sniff(b"\x7fELF\x01\x01\x01"), None);
+        assert_eq!(FormatKind::sniff(b""), None);
+    }
+
     #[test]
     fn parse_format() {
         assert_eq!(FormatKind::from_str("hex"), Ok(FormatKind::Hex));
@@ -416,6 +537,11 @@ mod tests {
         assert_eq!(FormatKind::from_str("espidf"), Ok(FormatKind::Idf));
         assert_eq!(FormatKind::from_str("esp-idf"), Ok(FormatKind::Idf));
         assert_eq!(FormatKind::from_str("ESP-IDF"), Ok(FormatKind::Idf));
+        assert_eq!(FormatKind::from_str("srec"), Ok(FormatKind::SRecord));
+        assert_eq!(FormatKind::from_str("S19"), Ok(FormatKind::SRecord));
+        assert_eq!(FormatKind::from_str("s37"), Ok(FormatKind::SRecord));
+        assert_eq!(FormatKind::from_str("ti-txt"), Ok(FormatKind::TiTxt));
+        assert_eq!(FormatKind::from_str("TITXT"), Ok(FormatKind::TiTxt));
         assert_eq!(
             FormatKind::from_str("elfbin"),
             Err("Format 'elfbin' is unknown.".to_string())
@@ -1,10 +1,7 @@
-use goblin::{
-    elf::program_header::PT_LOAD,
-    elf64::section_header::{SHT_NOBITS, SHT_PROGBITS},
-};
+use object::elf::{PT_LOAD, SHT_NOBITS, SHT_PROGBITS};
 use probe_rs_target::MemoryRange;
 
-use anyhow::{anyhow, Result};
+use super::{Elf, ElfAlgorithmError};
 
 const CODE_SECTION_KEY: (&str, u32) = ("PrgCode", SHT_PROGBITS);
 const DATA_SECTION_KEY: (&str, u32) = ("PrgData", SHT_PROGBITS);
@@ -41,7 +38,7 @@ pub(crate) struct AlgorithmBinary {
 
 impl AlgorithmBinary {
     /// Extract a new flash algorithm binary blob from an ELF data blob.
-    pub(crate) fn new(elf: &goblin::elf::Elf<'_>, buffer: &[u8]) -> Result<Self> {
+    pub(crate) fn new(elf: &Elf<'_>) -> Result<Self, ElfAlgorithmError> {
         let mut code_section = None;
         let mut data_section = None;
         let mut bss_section = None;
@@ -49,34 +46,33 @@ impl AlgorithmBinary {
         let mut suspicious_sections = Vec::new();
 
         // Iterate all program headers and get sections.
-        for ph in &elf.program_headers {
+        for ph in &elf.segments {
             // Only regard sections that contain at least one byte.
             // And are marked loadable (this filters out debug symbols).
-            if ph.p_type == PT_LOAD && ph.p_memsz > 0 {
-                let sector = ph.p_offset..ph.p_offset + ph.p_memsz;
+            if ph.kind == PT_LOAD && ph.memory_size > 0 {
+                let Some(sector_end) = ph.offset.checked_add(ph.memory_size) else {
+                    continue;
+                };
+                let sector = ph.offset..sector_end;
 
-                log::debug!("Program header: LOAD to VMA {:#010x}", ph.p_vaddr);
+                tracing::debug!("Program header: LOAD to VMA {:#010x}", ph.virtual_address);
 
                 // Scan all sectors if they contain any part of the sections found.
-                for sh in &elf.section_headers {
-                    let range = sh.sh_offset..sh.sh_offset + sh.sh_size;
-                    if sector.contains_range(&range) {
+                for sh in &elf.sections {
+                    let Some(section_end) = sh.offset.checked_add(sh.size) else {
+                        continue;
+                    };
+                    if sector.contains_range(&(sh.offset..section_end)) {
                         // If we found a valid section, store its contents if any.
-                        let data = if sh.sh_type == SHT_NOBITS {
-                            Vec::new()
-                        } else {
-                            Vec::from(&buffer[sh.sh_offset as usize..][..sh.sh_size as usize])
-                        };
-
                         let section = Some(Section {
-                            start: sh.sh_addr as u32,
-                            length: sh.sh_size as u32,
-                            data,
-                            load_address: (ph.p_vaddr + sh.sh_offset - ph.p_offset) as u32,
+                            start: sh.address as u32,
+                            length: sh.size as u32,
+                            data: sh.data.to_vec(),
+                            load_address: (ph.virtual_address + sh.offset - ph.offset) as u32,
                         });
 
                         // Make sure we store the section contents under the right name.
-                        match (&elf.shdr_strtab[sh.sh_name], sh.sh_type) {
+                        match (sh.name.as_str(), sh.kind) {
                             CODE_SECTION_KEY => code_section = section,
                             DATA_SECTION_KEY => data_section = section,
                             BSS_SECTION_KEY => bss_section = section,
@@ -92,22 +88,18 @@ impl AlgorithmBinary {
         }
 
         if !suspicious_sections.is_empty() {
-            log::warn!("The ELF file contains some unexpected sections, which should not be part of a flash loader: ");
+            tracing::warn!("The ELF file contains some unexpected sections, which should not be part of a flash loader: ");
 
             for section in suspicious_sections {
-                log::warn!("\t{}", section);
+                tracing::warn!("\t{}", section);
             }
 
-            log::warn!("Code should be placed in the '{}' section, and data should be placed in the '{}' section.", CODE_SECTION_KEY.0, DATA_SECTION_KEY.0);
+            tracing::warn!("Code should be placed in the '{}' section, and data should be placed in the '{}' section.", CODE_SECTION_KEY.0, DATA_SECTION_KEY.0);
         }
 
         // Check all the sections for validity and return the binary blob if possible.
-        let code_section = code_section.ok_or_else(|| {
-            anyhow!(
-                "Section '{}' not found, which is required to be present.",
-                CODE_SECTION_KEY.0
-            )
-        })?;
+        let code_section =
+            code_section.ok_or(ElfAlgorithmError::MissingSection(CODE_SECTION_KEY.0))?;
 
         let data_section = data_section.unwrap_or_else(|| Section {
             start: code_section.start + code_section.length,
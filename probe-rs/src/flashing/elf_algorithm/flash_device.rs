@@ -1,6 +1,6 @@
 use scroll::Pread;
 
-use anyhow::{anyhow, Result};
+use super::{read_elf_bin_data, Elf, ElfAlgorithmError};
 
 /// A struct to describe one sector in Flash.
 #[derive(Clone, Debug)]
@@ -64,13 +64,12 @@ impl FlashDevice {
     const MAX_ID_STRING_LENGTH: usize = 128;
 
     /// Parses the `FlashDevice` struct from ELF binary data.
-    pub(crate) fn new(elf: &goblin::elf::Elf<'_>, buffer: &[u8], address: u32) -> Result<Self> {
+    pub(crate) fn new(elf: &Elf<'_>, address: u32) -> Result<Self, ElfAlgorithmError> {
         // Extract all the sector data from the ELF blob.
-        let sectors = Self::parse_sectors(elf, buffer, address);
+        let sectors = Self::parse_sectors(elf, address)?;
 
         // Get the rest of the data stored in the struct.
-        let data = crate::parser::read_elf_bin_data(elf, buffer, address, Self::INFO_SIZE)
-            .ok_or_else(|| anyhow!("Failed to read binary data for flash device. Read address: {:#010x}, size: {} bytes", address, Self::INFO_SIZE))?;
+        let data = read_elf_bin_data(elf, address, Self::INFO_SIZE)?;
 
         // Get the string length of the name
         let hypothetical_length = data[2..2 + Self::MAX_ID_STRING_LENGTH]
@@ -97,24 +96,29 @@ impl FlashDevice {
 
     /// Parse the sector infos in the device struct.
     pub(crate) fn parse_sectors(
-        elf: &goblin::elf::Elf<'_>,
-        buffer: &[u8],
+        elf: &Elf<'_>,
         address: u32,
-    ) -> Vec<SectorInfo> {
+    ) -> Result<Vec<SectorInfo>, ElfAlgorithmError> {
         let mut sectors = vec![];
         let mut offset = Self::INFO_SIZE;
         // As long as we find new sectors, keep em comming.
-        while let Some(data) =
-            crate::parser::read_elf_bin_data(elf, buffer, address + offset, Self::SECTOR_INFO_SIZE)
-        {
-            if let Some(sector) = SectorInfo::new(data) {
-                sectors.push(sector);
-                offset += 8;
-            } else {
+        loop {
+            let sector_address =
+                address
+                    .checked_add(offset)
+                    .ok_or(ElfAlgorithmError::FlashDeviceData {
+                        address,
+                        size: offset,
+                    })?;
+            let data = read_elf_bin_data(elf, sector_address, Self::SECTOR_INFO_SIZE)?;
+
+            let Some(sector) = SectorInfo::new(data) else {
                 break;
-            }
+            };
+            sectors.push(sector);
+            offset += Self::SECTOR_INFO_SIZE;
         }
 
-        sectors
+        Ok(sectors)
     }
 }
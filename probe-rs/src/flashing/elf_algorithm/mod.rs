@@ -0,0 +1,378 @@
+//! Extraction of flash algorithms from ELF files, like the `.FLM` files of CMSIS-Packs or the
+//! binaries built with the `flash-algorithm` crate.
+
+mod algorithm_binary;
+mod flash_device;
+
+use algorithm_binary::AlgorithmBinary;
+use flash_device::FlashDevice;
+use object::{
+    elf::{FileHeader32, FileHeader64, SHT_SYMTAB},
+    read::elf::{FileHeader, ProgramHeader, SectionHeader, Sym},
+    Endianness, FileKind,
+};
+use probe_rs_target::{FlashProperties, MemoryRange, RawFlashAlgorithm, SectorDescription};
+
+/// Errors which can occur while extracting a flash algorithm from an ELF file.
+#[derive(thiserror::Error, Debug)]
+pub enum ElfAlgorithmError {
+    /// The file is not a valid ELF file.
+    #[error("Failed to parse the ELF file.")]
+    Elf(#[from] object::read::Error),
+    /// The file is not an ELF file.
+    #[error("The file is not an ELF file.")]
+    NotElf,
+    /// The ELF file does not describe the flash with a `FlashDevice` structure.
+    #[error("Failed to find 'FlashDevice' symbol in ELF file.")]
+    MissingFlashDevice,
+    /// The `FlashDevice` structure could not be read.
+    #[error(
+        "Failed to read binary data for flash device. Read address: {address:#010x}, size: {size} bytes"
+    )]
+    FlashDeviceData {
+        /// The address of the data.
+        address: u32,
+        /// The size of the data.
+        size: u32,
+    },
+    /// The data of a segment extends past the end of the ELF file.
+    #[error(
+        "The data at address {address:#010x} ({size} bytes) is outside of the ELF file, which is probably truncated."
+    )]
+    Truncated {
+        /// The address of the data.
+        address: u32,
+        /// The size of the data.
+        size: u32,
+    },
+    /// A required section is missing.
+    #[error("Section '{0}' not found, which is required to be present.")]
+    MissingSection(&'static str),
+    /// A function of the algorithm is located before its code section.
+    #[error("The symbol '{0}' is located before the code section.")]
+    SymbolBeforeCode(String),
+    /// The sections of an algorithm with a fixed load address do not follow each other.
+    #[error(
+        "If the flash algorithm is not position independent, all sections have to follow each other in RAM. \
+        Please check your linkerscript."
+    )]
+    NotContinuousInRam,
+}
+
+/// A program header of the ELF file.
+#[derive(Debug, Clone)]
+struct Segment {
+    kind: u32,
+    offset: u64,
+    virtual_address: u64,
+    physical_address: u64,
+    file_size: u64,
+    memory_size: u64,
+}
+
+/// A section header of the ELF file, together with its data.
+#[derive(Debug, Clone)]
+struct ElfSection<'data> {
+    name: String,
+    kind: u32,
+    offset: u64,
+    size: u64,
+    address: u64,
+    data: &'data [u8],
+}
+
+/// The parts of an ELF file which describe a flash algorithm.
+#[derive(Debug)]
+pub(crate) struct Elf<'data> {
+    buffer: &'data [u8],
+    segments: Vec<Segment>,
+    sections: Vec<ElfSection<'data>>,
+    /// The names and values of the symbols.
+    symbols: Vec<(String, u64)>,
+}
+
+impl<'data> Elf<'data> {
+    fn parse(buffer: &'data [u8]) -> Result<Self, ElfAlgorithmError> {
+        match FileKind::parse(buffer)? {
+            FileKind::Elf32 => Self::parse_with::<FileHeader32<Endianness>>(buffer),
+            FileKind::Elf64 => Self::parse_with::<FileHeader64<Endianness>>(buffer),
+            _ => Err(ElfAlgorithmError::NotElf),
+        }
+    }
+
+    fn parse_with<T: FileHeader<Endian = Endianness>>(
+        buffer: &'data [u8],
+    ) -> Result<Self, ElfAlgorithmError> {
+        let header = T::parse(buffer)?;
+        let endian = header.endian()?;
+
+        let segments = header
+            .program_headers(endian, buffer)?
+            .iter()
+            .map(|ph| Segment {
+                kind: ph.p_type(endian),
+                offset: ph.p_offset(endian).into(),
+                virtual_address: ph.p_vaddr(endian).into(),
+                physical_address: ph.p_paddr(endian).into(),
+                file_size: ph.p_filesz(endian).into(),
+                memory_size: ph.p_memsz(endian).into(),
+            })
+            .collect();
+
+        let section_table = header.sections(endian, buffer)?;
+        let sections = section_table
+            .iter()
+            .map(|sh| {
+                Ok(ElfSection {
+                    name: String::from_utf8_lossy(section_table.section_name(endian, sh)?)
+                        .into_owned(),
+                    kind: sh.sh_type(endian),
+                    offset: sh.sh_offset(endian).into(),
+                    size: sh.sh_size(endian).into(),
+                    address: sh.sh_addr(endian).into(),
+                    data: sh.data(endian, buffer)?,
+                })
+            })
+            .collect::<Result<_, object::read::Error>>()?;
+
+        let symbol_table = section_table.symbols(endian, buffer, SHT_SYMTAB)?;
+        let symbols = symbol_table
+            .iter()
+            .map(|sym| {
+                let name = symbol_table.symbol_name(endian, sym)?;
+                Ok((
+                    String::from_utf8_lossy(name).into_owned(),
+                    sym.st_value(endian).into(),
+                ))
+            })
+            .collect::<Result<_, object::read::Error>>()?;
+
+        Ok(Self {
+            buffer,
+            segments,
+            sections,
+            symbols,
+        })
+    }
+}
+
+/// Extract a chunk of data from an ELF binary.
+///
+/// This does only return the data chunk if it is fully contained in one segment.
+/// If it is across two segments, [`ElfAlgorithmError::FlashDeviceData`] is returned.
+fn read_elf_bin_data<'data>(
+    elf: &Elf<'data>,
+    address: u32,
+    size: u32,
+) -> Result<&'data [u8], ElfAlgorithmError> {
+    tracing::debug!("Trying to read {} bytes from {:#010x}.", size, address);
+
+    let start = address as u64;
+    let end = start + size as u64;
+    let range_to_read = start..end;
+
+    // Iterate all segments.
+    for segment in &elf.segments {
+        let segment_address = segment.physical_address;
+        let segment_size = segment.memory_size.min(segment.file_size);
+
+        tracing::debug!("Segment address: {:#010x}", segment_address);
+        tracing::debug!("Segment size:    {} bytes", segment_size);
+
+        let Some(segment_end) = segment_address.checked_add(segment_size) else {
+            tracing::debug!("Skipping segment, its size is invalid.");
+            continue;
+        };
+        // If the requested data is not fully inside of the current segment, skip the segment.
+        if !(segment_address..segment_end).contains_range(&range_to_read) {
+            tracing::debug!("Skipping segment.");
+            continue;
+        }
+
+        return segment
+            .offset
+            .checked_add(start - segment_address)
+            .and_then(|start| usize::try_from(start).ok())
+            .and_then(|start| elf.buffer.get(start..))
+            .and_then(|data| data.get(..size as usize))
+            .ok_or(ElfAlgorithmError::Truncated { address, size });
+    }
+
+    Err(ElfAlgorithmError::FlashDeviceData { address, size })
+}
+
+fn extract_flash_device(elf: &Elf<'_>) -> Result<FlashDevice, ElfAlgorithmError> {
+    // Extract the flash device info.
+    for (name, value) in &elf.symbols {
+        if name == "FlashDevice" {
+            // This struct contains information about the FLM file structure.
+            let address = *value as u32;
+            return FlashDevice::new(elf, address);
+        }
+    }
+
+    // Failed to find flash device
+    Err(ElfAlgorithmError::MissingFlashDevice)
+}
+
+/// Extracts a flash algorithm from the provided ELF file.
+///
+/// The flash properties are taken from the `FlashDevice` structure of the ELF file. With
+/// `fixed_load_address`, the algorithm is loaded at the address it was linked to, otherwise it
+/// has to be position independent. The name of the returned algorithm is empty.
+pub fn extract_flash_algorithm(
+    buffer: &[u8],
+    fixed_load_address: bool,
+) -> Result<RawFlashAlgorithm, ElfAlgorithmError> {
+    let mut algo = RawFlashAlgorithm::default();
+
+    let elf = Elf::parse(buffer)?;
+
+    let flash_device = extract_flash_device(&elf)?;
+
+    // Extract binary blob.
+    let algorithm_binary = AlgorithmBinary::new(&elf)?;
+    algo.instructions = algorithm_binary.blob();
+
+    let code_section_offset = algorithm_binary.code_section.start as u64;
+
+    // Extract the function pointers,
+    // and check if a RTT symbol is present.
+    for (name, value) in &elf.symbols {
+        let offset = || {
+            value
+                .checked_sub(code_section_offset)
+                .ok_or_else(|| ElfAlgorithmError::SymbolBeforeCode(name.clone()))
+        };
+
+        match name.as_str() {
+            "Init" => algo.pc_init = Some(offset()?),
+            "UnInit" => algo.pc_uninit = Some(offset()?),
+            "EraseChip" => algo.pc_erase_all = Some(offset()?),
+            "EraseSector" => algo.pc_erase_sector = offset()?,
+            "ProgramPage" => algo.pc_program_page = offset()?,
+            "Verify" => algo.pc_verify = Some(offset()?),
+            "ReadFlash" => algo.pc_read = Some(offset()?),
+            "_SEGGER_RTT" => {
+                algo.rtt_location = Some(*value);
+                tracing::debug!("Found RTT control block at address {:#010x}", value);
+            }
+
+            _ => {}
+        }
+    }
+
+    if fixed_load_address {
+        tracing::debug!(
+            "Flash algorithm will be loaded at fixed address {:#010x}",
+            algorithm_binary.code_section.load_address
+        );
+
+        if !algorithm_binary.is_continuous_in_ram() {
+            return Err(ElfAlgorithmError::NotContinuousInRam);
+        }
+
+        algo.load_address = Some(algorithm_binary.code_section.load_address as u64);
+    }
+
+    algo.description.clone_from(&flash_device.name);
+    algo.data_section_offset = algorithm_binary.data_section.start as u64;
+    algo.flash_properties = FlashProperties::from(flash_device);
+
+    Ok(algo)
+}
+
+impl From<FlashDevice> for FlashProperties {
+    fn from(device: FlashDevice) -> Self {
+        let sectors = device
+            .sectors
+            .iter()
+            .map(|si| SectorDescription {
+                address: si.address.into(),
+                size: si.size.into(),
+            })
+            .collect();
+
+        FlashProperties {
+            address_range: device.start_address as u64
+                ..(device.start_address as u64 + device.device_size as u64),
+
+            page_size: device.page_size,
+            erased_byte_value: device.erased_default_value,
+
+            program_page_timeout: device.program_page_timeout,
+            erase_sector_timeout: device.erase_sector_timeout,
+
+            sectors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_elf_bin_data, Elf, ElfAlgorithmError};
+
+    /// Builds a little endian ELF32 file with a single segment of `size` bytes at `address`,
+    /// of which only `file_data` is present in the file.
+    fn elf_with_segment(address: u32, size: u32, file_data: &[u8]) -> Vec<u8> {
+        let mut elf = b"\x7fELF\x01\x01\x01\0\0\0\0\0\0\0\0\0".to_vec();
+        for half in [2u16, 40] {
+            elf.extend(half.to_le_bytes());
+        }
+        // e_version, e_entry, e_phoff, e_shoff, e_flags
+        for word in [1u32, 0, 52, 0, 0] {
+            elf.extend(word.to_le_bytes());
+        }
+        // e_ehsize, e_phentsize, e_phnum, e_shentsize, e_shnum, e_shstrndx
+        for half in [52u16, 32, 1, 40, 0, 0] {
+            elf.extend(half.to_le_bytes());
+        }
+        // p_type, p_offset, p_vaddr, p_paddr, p_filesz, p_memsz, p_flags, p_align
+        for word in [1u32, 84, address, address, size, size, 5, 4] {
+            elf.extend(word.to_le_bytes());
+        }
+        elf.extend(file_data);
+
+        elf
+    }
+
+    #[test]
+    fn read_data_from_segment() {
+        let buffer = elf_with_segment(0x2000_0000, 8, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        let elf = Elf::parse(&buffer).unwrap();
+
+        assert_eq!(
+            read_elf_bin_data(&elf, 0x2000_0002, 4).unwrap(),
+            &[3, 4, 5, 6]
+        );
+        assert!(matches!(
+            read_elf_bin_data(&elf, 0x2000_0004, 8),
+            Err(ElfAlgorithmError::FlashDeviceData { .. })
+        ));
+        assert!(matches!(
+            read_elf_bin_data(&elf, 0xFFFF_FFFC, 8),
+            Err(ElfAlgorithmError::FlashDeviceData { .. })
+        ));
+    }
+
+    #[test]
+    fn read_data_from_truncated_segment() {
+        let buffer = elf_with_segment(0x2000_0000, 0x100, &[0; 16]);
+        let elf = Elf::parse(&buffer).unwrap();
+
+        assert!(read_elf_bin_data(&elf, 0x2000_0000, 16).is_ok());
+        assert!(matches!(
+            read_elf_bin_data(&elf, 0x2000_0010, 8),
+            Err(ElfAlgorithmError::Truncated { .. })
+        ));
+        assert!(matches!(
+            super::extract_flash_algorithm(&buffer, false),
+            Err(ElfAlgorithmError::MissingFlashDevice)
+        ));
+    }
+
+    #[test]
+    fn reject_other_files() {
+        assert!(Elf::parse(b"not an elf file").is_err());
+    }
+}
@@ -177,6 +177,14 @@ pub enum FlashError {
         /// The address range that was already present.
         existing_addresses: Range<u64>,
     },
+    /// The image contains data in a range whose contents should be preserved.
+    #[error("The image contains data in the preserved range `{name}` ({range:#010x?}).")]
+    DataInPreservedRange {
+        /// The name of the preserved range.
+        name: String,
+        /// The preserved address range.
+        range: Range<u64>,
+    },
     /// No core can access this NVM region.
     #[error("No core can access the NVM region {0:?}.")]
     NoNvmCoreAccess(NvmRegion),
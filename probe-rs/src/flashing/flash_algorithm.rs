@@ -1,3 +1,4 @@
+use super::checksum::{self, ChecksumRoutine};
 use super::FlashError;
 use crate::{
     architecture::{arm, riscv},
@@ -40,6 +41,9 @@ pub struct FlashAlgorithm {
     pub pc_verify: Option<u64>,
     /// Address of the (non-standard) `ReadFlash()` entry point. Optional.
     pub pc_read: Option<u64>,
+    /// Address of the built-in CRC32 routine, which is appended to the algorithm if it fits
+    /// into RAM. Optional.
+    pub pc_crc32: Option<u64>,
     /// Initial value of the R9 register for calling flash algo entry points, which
     /// determines where the position-independent data resides.
     pub static_base: u64,
@@ -225,6 +229,50 @@ impl FlashAlgorithm {
         ram_region: &RamRegion,
         data_ram_region: &RamRegion,
         target: &Target,
+    ) -> Result<Self, FlashError> {
+        let algorithm = Self::assemble(raw, ram_region, data_ram_region, target, None)?;
+
+        // The checksum routine is only added if it doesn't take away RAM needed by the algorithm.
+        let routine = checksum::crc32_routine(target.architecture());
+        let algorithm =
+            match Self::assemble(raw, ram_region, data_ram_region, target, Some(routine)) {
+                Ok(with_checksum)
+                    if with_checksum.page_buffers.len() == algorithm.page_buffers.len()
+                        && !with_checksum.code_overlaps_data() =>
+                {
+                    with_checksum
+                }
+                _ => algorithm,
+            };
+
+        tracing::info!(
+            "The flash algorithm will be configured with {} bytes of stack",
+            algorithm.stack_size
+        );
+        tracing::info!("Stack top: {:#010x}", algorithm.stack_top);
+        tracing::debug!("Page buffers: {:#010x?}", algorithm.page_buffers);
+
+        Ok(algorithm)
+    }
+
+    /// Returns whether the code of the algorithm overlaps its page buffers or stack.
+    fn code_overlaps_data(&self) -> bool {
+        let code_end = self.load_address + (self.instructions.len() * 4) as u64;
+        let overlaps = |start: u64, end: u64| self.load_address < end && start < code_end;
+
+        overlaps(self.stack_top - self.stack_size, self.stack_top)
+            || self
+                .page_buffers
+                .iter()
+                .any(|&buffer| overlaps(buffer, buffer + self.flash_properties.page_size as u64))
+    }
+
+    fn assemble(
+        raw: &RawFlashAlgorithm,
+        ram_region: &RamRegion,
+        data_ram_region: &RamRegion,
+        target: &Target,
+        checksum_routine: Option<ChecksumRoutine>,
     ) -> Result<Self, FlashError> {
         use std::mem::size_of;
 
@@ -249,7 +297,7 @@ impl FlashAlgorithm {
         };
 
         let header = Self::algorithm_header(target.architecture());
-        let instructions: Vec<u32> = header
+        let mut instructions: Vec<u32> = header
             .iter()
             .copied()
             .chain(
@@ -279,6 +327,13 @@ impl FlashAlgorithm {
             return Err(FlashError::InvalidFlashAlgorithmLoadAddress { address: addr_load });
         }
 
+        // The checksum routine is placed right after the code of the algorithm.
+        let pc_crc32 = checksum_routine.map(|routine| {
+            let address = addr_load + (instructions.len() * size_of::<u32>()) as u64;
+            instructions.extend_from_slice(routine.code);
+            address + routine.entry
+        });
+
         // Memory layout:
         // - Header
         // - Code
@@ -298,7 +353,6 @@ impl FlashAlgorithm {
         let buffer_page_size = raw.flash_properties.page_size as u64;
 
         let stack_size = raw.stack_size.unwrap_or(Self::FLASH_ALGO_STACK_SIZE) as u64;
-        tracing::info!("The flash algorithm will be configured with {stack_size} bytes of stack");
 
         let data_load_addr = if let Some(data_load_addr) = raw.data_load_address {
             data_load_addr
@@ -358,7 +412,6 @@ impl FlashAlgorithm {
 
         // Now we can place the stack.
         let stack_top = stack_bottom + stack_size;
-        tracing::info!("Stack top: {:#010x}", stack_top);
 
         if stack_top > ram_region.range.end {
             return Err(FlashError::InvalidFlashAlgorithmStackSize { size: stack_size });
@@ -372,8 +425,6 @@ impl FlashAlgorithm {
             vec![data_load_addr]
         };
 
-        tracing::debug!("Page buffers: {:#010x?}", page_buffers);
-
         let name = raw.name.clone();
 
         Ok(FlashAlgorithm {
@@ -388,6 +439,7 @@ impl FlashAlgorithm {
             pc_erase_all: raw.pc_erase_all.map(|v| code_start + v),
             pc_verify: raw.pc_verify.map(|v| code_start + v),
             pc_read: raw.pc_read.map(|v| code_start + v),
+            pc_crc32,
             static_base: code_start + raw.data_section_offset,
             stack_top,
             stack_size,
@@ -645,4 +697,23 @@ mod test {
         ];
         assert_eq!(&got, expected);
     }
+
+    #[test]
+    fn checksum_routine_is_appended() {
+        let target = crate::config::get_target_by_name("nRF52840_xxAA").unwrap();
+        let raw = &target.flash_algorithms[0];
+
+        let algorithm =
+            FlashAlgorithm::assemble_from_raw_with_core(raw, &target.cores[0].name, &target)
+                .unwrap();
+
+        let routine = crate::flashing::checksum::crc32_routine(target.architecture());
+        let pc_crc32 = algorithm.pc_crc32.unwrap();
+        let routine_start =
+            algorithm.load_address + 4 * (algorithm.instructions.len() - routine.code.len()) as u64;
+
+        assert_eq!(pc_crc32, routine_start + routine.entry);
+        assert!(algorithm.instructions.ends_with(routine.code));
+        assert!(!algorithm.code_overlaps_data());
+    }
 }
@@ -1,6 +1,7 @@
 use probe_rs_target::RawFlashAlgorithm;
 use tracing::Level;
 
+use super::checksum;
 use super::{FlashAlgorithm, FlashBuilder, FlashError, FlashPage, FlashProgress};
 use crate::config::NvmRegion;
 use crate::error::Error;
@@ -13,12 +14,16 @@ use crate::{CoreStatus, Target};
 use std::marker::PhantomData;
 use std::{
     fmt::Debug,
+    ops::Range,
     time::{Duration, Instant},
 };
 
 /// The timeout for init/uninit routines.
 const INIT_TIMEOUT: Duration = Duration::from_secs(2);
 
+/// The maximum number of bytes checksummed by a single call to the checksum routine.
+const CHECKSUM_CHUNK_SIZE: u64 = 0x1_0000;
+
 pub(super) trait Operation {
     const OPERATION: u32;
     const NAME: &'static str;
@@ -253,11 +258,19 @@ impl Flasher {
         session.has_sequence_erase_all() || self.flash_algorithm().pc_erase_all.is_some()
     }
 
+    /// Returns whether the flash contents can be compared using checksums computed on the target.
+    pub(super) fn checksum_supported(&self) -> bool {
+        self.flash_algorithm.pc_crc32.is_some()
+    }
+
     /// Program the contents of given `FlashBuilder` to the flash.
     ///
     /// If `restore_unwritten_bytes` is `true`, all bytes of a sector,
     /// that are not to be written during flashing will be read from the flash first
     /// and written again once the sector is erased.
+    ///
+    /// If `preverify` is `true` and the flash algorithm supports checksums, sectors which
+    /// already contain the data are neither erased nor programmed.
     #[allow(clippy::too_many_arguments)] // The plan is to remove at least `verify` in the future.
     pub(super) fn program(
         &mut self,
@@ -268,12 +281,22 @@ impl Flasher {
         restore_unwritten_bytes: bool,
         enable_double_buffering: bool,
         skip_erasing: bool,
+        preverify: bool,
         verify: bool,
     ) -> Result<(), FlashError> {
         tracing::debug!("Starting program procedure.");
         // Convert the list of flash operations into flash sectors and pages.
         let mut flash_layout = self.flash_layout(region, flash_builder, restore_unwritten_bytes)?;
 
+        if preverify && self.checksum_supported() {
+            self.skip_unchanged_sectors(session, progress, &mut flash_layout)?;
+
+            if flash_layout.sectors().is_empty() {
+                tracing::info!("Contents match, skipping flashing.");
+                return Ok(());
+            }
+        }
+
         tracing::debug!("Double Buffering enabled: {:?}", enable_double_buffering);
         tracing::debug!(
             "Restoring unwritten bytes enabled: {:?}",
@@ -350,6 +373,57 @@ impl Flasher {
         result
     }
 
+    /// Removes the sectors whose contents already match the data in `layout` from it.
+    ///
+    /// The contents are compared using checksums computed on the target, the bytes which are
+    /// only filled in are ignored.
+    fn skip_unchanged_sectors(
+        &mut self,
+        session: &mut Session,
+        progress: &FlashProgress,
+        layout: &mut FlashLayout,
+    ) -> Result<(), FlashError> {
+        let Some(pc_crc32) = self.flash_algorithm.pc_crc32 else {
+            return Ok(());
+        };
+
+        let unchanged = self.run_verify(session, progress, |active| {
+            let mut unchanged = Vec::new();
+
+            for sector in layout.sectors() {
+                let ranges = checksum_ranges(
+                    layout,
+                    sector.address()..sector.address() + sector.size(),
+                    true,
+                );
+
+                let mut matches = !ranges.is_empty();
+                for (address, data) in ranges {
+                    let crc = active.checksum(pc_crc32, address, data.len() as u64)?;
+                    if crc != checksum::crc32(0, &data) {
+                        matches = false;
+                        break;
+                    }
+                }
+
+                if matches {
+                    unchanged.push(sector.address());
+                }
+            }
+
+            Ok(unchanged)
+        })?;
+
+        tracing::info!(
+            "{} of {} sectors are up to date, skipping them.",
+            unchanged.len(),
+            layout.sectors().len()
+        );
+        layout.retain_sectors(|sector| !unchanged.contains(&sector.address()));
+
+        Ok(())
+    }
+
     /// Verifies all the to-be-written bytes of `layout`.
     pub(super) fn verify(
         &mut self,
@@ -400,6 +474,18 @@ impl Flasher {
                         return Ok(false);
                     }
                 }
+            } else if let Some(pc_crc32) = active.flash_algorithm.pc_crc32 {
+                tracing::debug!("Verify using checksums");
+                for (address, data) in checksum_ranges(layout, 0..u64::MAX, ignore_filled) {
+                    let crc = active.checksum(pc_crc32, address, data.len() as u64)?;
+                    if crc != checksum::crc32(0, &data) {
+                        tracing::debug!(
+                            "Verification failed for the range at address {:#010x}",
+                            address
+                        );
+                        return Ok(false);
+                    }
+                }
             } else {
                 tracing::debug!("Verify using manual comparison");
                 for (idx, page) in layout.pages.iter().enumerate() {
@@ -571,6 +657,55 @@ impl Flasher {
     }
 }
 
+/// Returns the contiguous ranges of data in the pages of `layout` which start in `range`.
+///
+/// If `ignore_filled` is `true`, the fills are left out, as their contents are allowed to differ.
+fn checksum_ranges(
+    layout: &FlashLayout,
+    range: Range<u64>,
+    ignore_filled: bool,
+) -> Vec<(u64, Vec<u8>)> {
+    let mut ranges: Vec<(u64, Vec<u8>)> = Vec::new();
+
+    for (index, page) in layout.pages().iter().enumerate() {
+        if !range.contains(&page.address()) {
+            continue;
+        }
+
+        let mut segments = Vec::new();
+        let mut offset = 0;
+        if ignore_filled {
+            let mut fills = layout
+                .fills()
+                .iter()
+                .filter(|fill| fill.page_index() == index)
+                .collect::<Vec<_>>();
+            fills.sort_by_key(|fill| fill.address());
+
+            for fill in fills {
+                let fill_offset = (fill.address() - page.address()) as usize;
+                segments.push(offset..fill_offset);
+                offset = fill_offset + fill.size() as usize;
+            }
+        }
+        segments.push(offset..page.data().len());
+
+        for segment in segments.into_iter().filter(|segment| !segment.is_empty()) {
+            let address = page.address() + segment.start as u64;
+            let data = &page.data()[segment];
+
+            match ranges.last_mut() {
+                Some((start, bytes)) if *start + bytes.len() as u64 == address => {
+                    bytes.extend_from_slice(data)
+                }
+                _ => ranges.push((address, data.to_vec())),
+            }
+        }
+    }
+
+    ranges
+}
+
 struct Registers {
     pc: u32,
     r0: Option<u32>,
@@ -872,36 +1007,11 @@ impl<O: Operation> ActiveFlasher<'_, O> {
     pub(super) fn read_flash(&mut self, address: u64, data: &mut [u8]) -> Result<(), FlashError> {
         if let Some(read_flash) = self.flash_algorithm.pc_read {
             let page_size = self.flash_algorithm.flash_properties.page_size;
-            let buffer_address = self.flash_algorithm.page_buffers[0];
 
             let mut read_address = address;
             for slice in data.chunks_mut(page_size as usize) {
-                // Call ReadFlash to load from flash to RAM. The function has a similar signature
-                // to the program_page function.
-                let result = self
-                    .call_function_and_wait(
-                        &Registers {
-                            pc: into_reg(read_flash)?,
-                            r0: Some(into_reg(read_address)?),
-                            r1: Some(into_reg(slice.len() as u64)?),
-                            r2: Some(into_reg(buffer_address)?),
-                            r3: None,
-                        },
-                        false,
-                        Duration::from_secs(30),
-                    )
-                    .map_err(|error| FlashError::FlashReadFailed {
-                        source: Box::new(error),
-                    })?;
-
-                if result != 0 {
-                    return Err(FlashError::FlashReadFailed {
-                        source: Box::new(FlashError::RoutineCallFailed {
-                            name: "read_flash",
-                            error_code: result,
-                        }),
-                    });
-                };
+                let buffer_address =
+                    self.read_flash_to_buffer(read_flash, read_address, slice.len() as u64)?;
 
                 // Now read the data from RAM.
                 self.core
@@ -916,6 +1026,90 @@ impl<O: Operation> ActiveFlasher<'_, O> {
         }
     }
 
+    /// Calls ReadFlash to load up to one page from flash to the first page buffer, and returns
+    /// the address of the buffer.
+    fn read_flash_to_buffer(
+        &mut self,
+        read_flash: u64,
+        address: u64,
+        size: u64,
+    ) -> Result<u64, FlashError> {
+        let buffer_address = self.flash_algorithm.page_buffers[0];
+
+        // The function has a similar signature to the program_page function.
+        let result = self
+            .call_function_and_wait(
+                &Registers {
+                    pc: into_reg(read_flash)?,
+                    r0: Some(into_reg(address)?),
+                    r1: Some(into_reg(size)?),
+                    r2: Some(into_reg(buffer_address)?),
+                    r3: None,
+                },
+                false,
+                Duration::from_secs(30),
+            )
+            .map_err(|error| FlashError::FlashReadFailed {
+                source: Box::new(error),
+            })?;
+
+        if result != 0 {
+            return Err(FlashError::FlashReadFailed {
+                source: Box::new(FlashError::RoutineCallFailed {
+                    name: "read_flash",
+                    error_code: result,
+                }),
+            });
+        };
+
+        Ok(buffer_address)
+    }
+
+    /// Computes the CRC32 of the flash contents in `address..address + size` on the target,
+    /// using the checksum routine at `pc_crc32`.
+    ///
+    /// If the flash is read with ReadFlash, it is copied to the page buffer page by page first.
+    pub(super) fn checksum(
+        &mut self,
+        pc_crc32: u64,
+        address: u64,
+        size: u64,
+    ) -> Result<u32, FlashError> {
+        let read_flash = self.flash_algorithm.pc_read;
+        let chunk_size = match read_flash {
+            Some(_) => self.flash_algorithm.flash_properties.page_size as u64,
+            None => CHECKSUM_CHUNK_SIZE,
+        };
+
+        let mut crc = 0;
+        let mut offset = 0;
+        while offset < size {
+            let chunk_address = address + offset;
+            let chunk_size = chunk_size.min(size - offset);
+            let data_address = match read_flash {
+                Some(read_flash) => {
+                    self.read_flash_to_buffer(read_flash, chunk_address, chunk_size)?
+                }
+                None => chunk_address,
+            };
+
+            crc = self.call_function_and_wait(
+                &Registers {
+                    pc: into_reg(pc_crc32)?,
+                    r0: Some(into_reg(data_address)?),
+                    r1: Some(into_reg(chunk_size)?),
+                    r2: Some(crc),
+                    r3: None,
+                },
+                false,
+                Duration::from_secs(30),
+            )?;
+            offset += chunk_size;
+        }
+
+        Ok(crc)
+    }
+
     /// Returns the address of the buffer that was used.
     pub(super) fn load_page_buffer(
         &mut self,
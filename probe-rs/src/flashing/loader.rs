@@ -13,7 +13,7 @@ use std::time::Duration;
 use super::builder::FlashBuilder;
 use super::{
     extract_from_elf, BinOptions, DownloadOptions, FileDownloadError, FlashError, Flasher,
-    IdfOptions,
+    IdfOptions, PreservedRange,
 };
 use crate::config::DebugSequence;
 use crate::flashing::{FlashLayout, FlashProgress, Format};
@@ -51,6 +51,8 @@ impl ImageLoader for Format {
             Format::Hex => HexLoader.load(flash_loader, session, file),
             Format::Idf(options) => IdfLoader(options.clone()).load(flash_loader, session, file),
             Format::Uf2 => Uf2Loader.load(flash_loader, session, file),
+            Format::SRecord => SRecordLoader.load(flash_loader, session, file),
+            Format::TiTxt => TiTxtLoader.load(flash_loader, session, file),
         }
     }
 }
@@ -172,6 +174,188 @@ impl ImageLoader for HexLoader {
     }
 }
 
+/// Reads the data records of a Motorola S-record file and adds them as loadable data blocks to
+/// the loader. This does not create any flash loader instructions yet.
+struct SRecordLoader;
+
+impl ImageLoader for SRecordLoader {
+    fn load(
+        &self,
+        flash_loader: &mut FlashLoader,
+        _session: &mut Session,
+        file: &mut dyn ImageReader,
+    ) -> Result<(), FileDownloadError> {
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+
+        for (address, data) in parse_srecord(&data)? {
+            flash_loader.add_data(address, &data)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the sections of a TI-TXT file and adds them as loadable data blocks to the loader.
+/// This does not create any flash loader instructions yet.
+struct TiTxtLoader;
+
+impl ImageLoader for TiTxtLoader {
+    fn load(
+        &self,
+        flash_loader: &mut FlashLoader,
+        _session: &mut Session,
+        file: &mut dyn ImageReader,
+    ) -> Result<(), FileDownloadError> {
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+
+        for (address, data) in parse_ti_txt(&data)? {
+            flash_loader.add_data(address, &data)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Appends `data` at `address` to the list of blocks, extending the last block if it is contiguous.
+fn push_block(blocks: &mut Vec<(u64, Vec<u8>)>, address: u64, data: &[u8]) {
+    match blocks.last_mut() {
+        Some((start, block)) if *start + block.len() as u64 == address => {
+            block.extend_from_slice(data)
+        }
+        _ => blocks.push((address, data.to_vec())),
+    }
+}
+
+/// Decodes a string of hex digit pairs into bytes.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Parses a Motorola S-record file into blocks of contiguous data.
+///
+/// Only the data records (S1, S2 and S3) are used, header, count and start address records are
+/// checked and skipped.
+fn parse_srecord(file: &str) -> Result<Vec<(u64, Vec<u8>)>, FileDownloadError> {
+    let mut blocks = Vec::new();
+
+    for (index, line) in file.lines().enumerate() {
+        let error = |reason: &str| FileDownloadError::ImageDecode {
+            format: "S-record",
+            line: index + 1,
+            reason: reason.to_string(),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let Some(record) = line.strip_prefix('S') else {
+            return Err(error("records have to start with `S`"));
+        };
+        let Some(kind) = record.chars().next().and_then(|kind| kind.to_digit(10)) else {
+            return Err(error("invalid record type"));
+        };
+        let bytes = decode_hex(&record[1..]).ok_or_else(|| error("invalid hex data"))?;
+
+        let Some((&count, rest)) = bytes.split_first() else {
+            return Err(error("missing byte count"));
+        };
+        if rest.len() != count as usize {
+            return Err(error("byte count does not match the length of the record"));
+        }
+        let checksum = !bytes
+            .iter()
+            .take(bytes.len() - 1)
+            .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        let Some((&expected, rest)) = rest.split_last() else {
+            return Err(error("missing checksum"));
+        };
+        if checksum != expected {
+            return Err(error("checksum mismatch"));
+        }
+
+        let address_len = match kind {
+            1 => 2,
+            2 => 3,
+            3 => 4,
+            0 | 5 | 6 | 7 | 8 | 9 => continue,
+            _ => return Err(error("invalid record type")),
+        };
+        if rest.len() < address_len {
+            return Err(error("record is too short for its address"));
+        }
+
+        let (address, data) = rest.split_at(address_len);
+        let address = address
+            .iter()
+            .fold(0u64, |address, byte| address << 8 | *byte as u64);
+        push_block(&mut blocks, address, data);
+    }
+
+    Ok(blocks)
+}
+
+/// Parses a TI-TXT file into blocks of contiguous data.
+///
+/// Sections start with an `@ADDRESS` line, followed by lines of space separated hex bytes. The
+/// file ends with a `q` line.
+fn parse_ti_txt(file: &str) -> Result<Vec<(u64, Vec<u8>)>, FileDownloadError> {
+    let mut blocks = Vec::new();
+    let mut address = None;
+
+    for (index, line) in file.lines().enumerate() {
+        let error = |reason: &str| FileDownloadError::ImageDecode {
+            format: "TI-TXT",
+            line: index + 1,
+            reason: reason.to_string(),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.eq_ignore_ascii_case("q") {
+            break;
+        }
+
+        if let Some(section) = line.strip_prefix('@') {
+            address = Some(
+                u64::from_str_radix(section.trim(), 16)
+                    .map_err(|_| error("invalid section address"))?,
+            );
+            continue;
+        }
+
+        let Some(current) = address.as_mut() else {
+            return Err(error("data before the first section address"));
+        };
+        let data = line
+            .split_ascii_whitespace()
+            .map(|byte| match byte.len() {
+                2 => u8::from_str_radix(byte, 16).ok(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| error("invalid hex data"))?;
+
+        push_block(&mut blocks, *current, &data);
+        *current += data.len() as u64;
+    }
+
+    Ok(blocks)
+}
+
 /// Prepares the data sections that have to be loaded into flash from an UF2 file.
 /// This will validate the UF2 file and transform all its data into sections but no flash loader commands yet.
 struct Uf2Loader;
@@ -312,6 +496,7 @@ pub enum BootInfo {
 /// Once you are done adding all your data, use `commit()` to flash the data.
 /// The flash loader will make sure to select the appropriate flash region for the right data chunks.
 /// Region crossing data chunks are allowed as long as the regions are contiguous.
+#[derive(Clone)]
 pub struct FlashLoader {
     memory_map: Vec<MemoryRegion>,
     builder: FlashBuilder,
@@ -386,6 +571,34 @@ impl FlashLoader {
         self.builder.add_data(address, data)
     }
 
+    /// Replaces the staged data in the range of the chunk, like a serial number in an image.
+    ///
+    /// Parts of the chunk which do not overlap staged data are staged as well. The chunk must
+    /// be located in non-volatile memory.
+    pub fn patch_data(&mut self, address: u64, data: &[u8]) -> Result<(), FlashError> {
+        tracing::debug!(
+            "Patching data at address {:#010x} with size {} bytes",
+            address,
+            data.len()
+        );
+
+        let range = address..address + data.len() as u64;
+        let mut current = range.start;
+        while current < range.end {
+            match Self::get_region_for_address(&self.memory_map, current) {
+                Some(MemoryRegion::Nvm(region)) => current = region.range.end,
+                _ => {
+                    return Err(FlashError::NoSuitableNvm {
+                        range,
+                        description_source: self.source.clone(),
+                    })
+                }
+            }
+        }
+
+        self.builder.patch_data(address, data)
+    }
+
     pub(super) fn get_region_for_address(
         memory_map: &[MemoryRegion],
         address: u64,
@@ -467,6 +680,29 @@ impl FlashLoader {
         mut options: DownloadOptions,
     ) -> Result<(), FlashError> {
         tracing::debug!("Committing FlashLoader!");
+
+        if !options.patches.is_empty() {
+            let mut loader = self.clone();
+            for patch in std::mem::take(&mut options.patches) {
+                loader.patch_data(patch.address, &patch.data)?;
+            }
+
+            return loader.commit(session, options);
+        }
+
+        if !options.preserved_ranges.is_empty() {
+            let preserved_ranges = std::mem::take(&mut options.preserved_ranges);
+            self.check_preserved_ranges(&preserved_ranges)?;
+
+            // A dry run does not erase anything, so there is nothing to read back.
+            if !options.dry_run {
+                let mut loader = self.clone();
+                loader.stage_preserved_ranges(session, &preserved_ranges, options.do_chip_erase)?;
+
+                return loader.commit(session, options);
+            }
+        }
+
         let algos = self.prepare_plan(session)?;
 
         if options.dry_run {
@@ -512,7 +748,8 @@ impl FlashLoader {
                 did_chip_erase = true;
             }
 
-            if options.preverify && !did_chip_erase {
+            // With checksum support, the flasher skips the matching sectors by itself.
+            if options.preverify && !did_chip_erase && !flasher.checksum_supported() {
                 tracing::info!("Pre-verifying!");
 
                 let mut contents_match = true;
@@ -553,6 +790,7 @@ impl FlashLoader {
                     options.keep_unwritten_bytes,
                     do_use_double_buffering,
                     options.skip_erase || did_chip_erase,
+                    options.preverify && !did_chip_erase,
                     options.verify,
                 )?;
             }
@@ -642,6 +880,116 @@ impl FlashLoader {
         Ok(())
     }
 
+    /// Checks that none of the preserved ranges overlaps the staged data, which would have to be
+    /// written into them.
+    fn check_preserved_ranges(
+        &self,
+        preserved_ranges: &[PreservedRange],
+    ) -> Result<(), FlashError> {
+        for preserved in preserved_ranges {
+            if self.builder.has_data_in_range(&preserved.range) {
+                return Err(FlashError::DataInPreservedRange {
+                    name: preserved.name.clone(),
+                    range: preserved.range.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stages the current contents of the preserved ranges which are located in flash that will
+    /// be erased, so they are written back after erasing.
+    ///
+    /// The contents are read through the flash algorithm, like the bytes kept by
+    /// [`DownloadOptions::keep_unwritten_bytes`].
+    fn stage_preserved_ranges(
+        &mut self,
+        session: &mut Session,
+        preserved_ranges: &[PreservedRange],
+        chip_erase: bool,
+    ) -> Result<(), FlashError> {
+        // The erased ranges, grouped by the flasher which can read them.
+        let mut erased = Vec::new();
+        if chip_erase {
+            for region in self
+                .memory_map
+                .iter()
+                .filter_map(MemoryRegion::as_nvm_region)
+                .filter(|region| !region.is_alias)
+            {
+                if !preserved_ranges
+                    .iter()
+                    .any(|preserved| preserved.range.intersects_range(&region.range))
+                {
+                    continue;
+                }
+
+                let Some(core_name) = region.cores.first() else {
+                    return Err(FlashError::NoNvmCoreAccess(region.clone()));
+                };
+                let target = session.target();
+                let core = target.core_index_by_name(core_name).unwrap();
+                let algo = Self::get_flash_algorithm_for_region(region, target)?;
+                erased.push((
+                    vec![region.range.clone()],
+                    Flasher::new(target, core, algo)?,
+                ));
+            }
+        } else {
+            for el in self.prepare_plan(session)? {
+                let mut sectors = Vec::new();
+                for region in &el.regions {
+                    let flash_layout = el.flasher.flash_layout(region, &self.builder, false)?;
+                    for sector in flash_layout.sectors() {
+                        sectors.push(sector.address()..sector.address() + sector.size());
+                    }
+                }
+                erased.push((sectors, el.flasher));
+            }
+        }
+
+        let progress = FlashProgress::empty();
+        for (ranges, mut flasher) in erased {
+            let mut preserved_data = Vec::new();
+            for preserved in preserved_ranges {
+                for range in &ranges {
+                    let start = preserved.range.start.max(range.start);
+                    let end = preserved.range.end.min(range.end);
+                    if start >= end {
+                        continue;
+                    }
+
+                    tracing::info!(
+                        "Preserving {} ({:#010x}..{:#010x})",
+                        preserved.name,
+                        start,
+                        end
+                    );
+                    preserved_data.push((start, vec![0; (end - start) as usize]));
+                }
+            }
+
+            if preserved_data.is_empty() {
+                continue;
+            }
+
+            flasher.run_verify(session, &progress, |active| {
+                for (address, data) in preserved_data.iter_mut() {
+                    active.read_flash(*address, data)?;
+                }
+
+                Ok(())
+            })?;
+
+            for (address, data) in preserved_data {
+                self.builder.add_data(address, &data)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn prepare_plan(&self, session: &mut Session) -> Result<Vec<FlasherWithRegions>, FlashError> {
         tracing::debug!("Contents of builder:");
         for (&address, data) in &self.builder.data {
@@ -858,3 +1206,85 @@ pub(super) struct FlasherWithRegions {
     pub(super) regions: Vec<NvmRegion>,
     pub(super) flasher: Flasher,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_srecord, parse_ti_txt};
+
+    #[test]
+    fn parse_srecord_file() {
+        let file = "S00F000068656C6C6F202020202000003C\n\
+                    S11F00007C0802A6900100049421FFF07C6C1B787C8C23783C6000003863000026\n\
+                    S11F001C4BFFFFE5398000007D83637880010014382100107C0803A64E800020E9\n\
+                    S20801000001020304EC\n\
+                    S5030003F9\n\
+                    S9030000FC\n";
+
+        let blocks = parse_srecord(file).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].0, 0x0000);
+        assert_eq!(blocks[0].1.len(), 56);
+        assert_eq!(&blocks[0].1[..4], &[0x7C, 0x08, 0x02, 0xA6]);
+        assert_eq!(blocks[1], (0x010000, vec![0x01, 0x02, 0x03, 0x04]));
+
+        assert!(parse_srecord("S1070000010203040F\n").is_err());
+        assert!(parse_srecord(":020000040800F2\n").is_err());
+    }
+
+    #[test]
+    fn parse_ti_txt_file() {
+        let file = "@08000000\r\n\
+                    00 10 20 30 40 50 60 70\r\n\
+                    80 90\r\n\
+                    @08000100\r\n\
+                    AA BB\r\n\
+                    q\r\n";
+
+        assert_eq!(
+            parse_ti_txt(file).unwrap(),
+            vec![
+                (
+                    0x0800_0000,
+                    vec![0x00, 0x10, 0x20, 0x30, 0x40, 0x50, 0x60, 0x70, 0x80, 0x90]
+                ),
+                (0x0800_0100, vec![0xAA, 0xBB]),
+            ]
+        );
+
+        assert!(parse_ti_txt("00 11\nq\n").is_err());
+        assert!(parse_ti_txt("@0\n0g\n").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "builtin-targets")]
+    fn dry_run_checks_preserved_ranges() {
+        use crate::{
+            flashing::{DownloadOptions, FlashError, PreservedRange},
+            probe::fake_probe::FakeProbe,
+            Permissions,
+        };
+
+        let mut session = FakeProbe::with_mocked_core()
+            .into_probe()
+            .attach("nrf51822_xxAC", Permissions::default())
+            .unwrap();
+
+        let mut loader = session.target().flash_loader();
+        loader.add_data(0x1000, &[0xAA; 0x100]).unwrap();
+
+        let options = |range| DownloadOptions {
+            dry_run: true,
+            preserved_ranges: vec![PreservedRange {
+                name: "config".to_string(),
+                range,
+            }],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            loader.commit(&mut session, options(0x1080..0x1100)),
+            Err(FlashError::DataInPreservedRange { .. })
+        ));
+        assert!(loader.commit(&mut session, options(0x1100..0x1200)).is_ok());
+    }
+}
@@ -44,7 +44,10 @@
 //!
 
 mod builder;
+mod checksum;
+pub(crate) mod configuration;
 mod download;
+mod elf_algorithm;
 mod encoder;
 mod erase;
 mod error;
@@ -58,7 +61,9 @@ use builder::*;
 use flasher::*;
 
 pub use builder::{FlashDataBlockSpan, FlashFill, FlashLayout, FlashPage, FlashSector};
+pub use configuration::ConfigurationError;
 pub use download::*;
+pub use elf_algorithm::*;
 pub use erase::*;
 pub use error::*;
 pub use flash_algorithm::*;
@@ -1,4 +1,8 @@
 //! Helper functions for integration tests in your application using probe-rs.
 
-pub use crate::probe::fake_probe::{FakeProbe, Operation};
+pub use crate::probe::fake_probe::{
+    emulator::{EmulatedCore, Nvmc},
+    FakeProbe, Operation,
+};
 pub use crate::probe::list::ProbeLister;
+pub use crate::probe::recording::{RecordingProbe, ReplayLister, ReplayProbe};
@@ -81,6 +81,7 @@ pub mod gdb_server;
 pub mod integration;
 mod memory;
 pub mod probe;
+pub mod protection;
 pub mod rtt;
 pub mod semihosting;
 mod session;
@@ -4,13 +4,17 @@ pub(crate) mod common;
 pub(crate) mod usb_util;
 
 pub mod blackmagic;
+pub mod boundary_scan;
 pub mod cmsisdap;
 pub mod espusbjtag;
 pub mod fake_probe;
 pub mod ftdi;
 pub mod jlink;
 pub mod list;
+pub mod qemu;
+pub mod recording;
 pub mod stlink;
+pub mod svf;
 pub mod wlink;
 
 use crate::architecture::arm::sequences::{ArmDebugSequence, DefaultArmSequence};
@@ -24,8 +28,8 @@ use crate::architecture::xtensa::communication_interface::{
     XtensaCommunicationInterface, XtensaDebugInterfaceState, XtensaError,
 };
 use crate::config::TargetSelector;
-use crate::probe::common::IdCode;
 use crate::{Error, Permissions, Session};
+pub use common::IdCode;
 use common::ScanChainError;
 use nusb::DeviceInfo;
 use probe_rs_target::ScanChainElement;
@@ -584,6 +588,28 @@ impl Probe {
     pub fn try_into_jlink(&mut self) -> Result<&mut jlink::JLink, DebugProbeError> {
         self.inner.try_into_jlink()
     }
+
+    /// Discovers the devices on the JTAG chain, see [`JTAGAccess::discover_scan_chain`].
+    ///
+    /// This requires the probe to be attached using JTAG, and to offer low-level JTAG access.
+    pub fn discover_jtag_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        self.try_as_jtag_access()?.discover_scan_chain()
+    }
+
+    /// Gets low-level access to the JTAG chain.
+    ///
+    /// This requires the probe to be attached using JTAG, and is not supported on all probes.
+    pub fn try_as_jtag_access(&mut self) -> Result<&mut dyn JTAGAccess, DebugProbeError> {
+        if !self.attached {
+            return Err(DebugProbeError::NotAttached);
+        }
+
+        self.inner
+            .try_as_jtag_access()
+            .ok_or(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "JTAG",
+            })
+    }
 }
 
 /// An abstraction over a probe driver type.
@@ -742,6 +768,13 @@ pub trait DebugProbe: Send + fmt::Debug {
         false
     }
 
+    /// Get the low-level JTAG interface of the probe.
+    ///
+    /// This is not available on all probes.
+    fn try_as_jtag_access(&mut self) -> Option<&mut dyn JTAGAccess> {
+        None
+    }
+
     /// Get a SWO interface from the debug probe.
     ///
     /// This is not available on all debug probes.
@@ -910,7 +943,21 @@ pub struct DebugProbeSelector {
 }
 
 impl DebugProbeSelector {
-    pub(crate) fn matches(&self, info: &DeviceInfo) -> bool {
+    /// Returns whether the selector matches the probe described by `probe`, e.g. a
+    /// [`DebugProbeInfo`].
+    ///
+    /// A selector without a serial number matches all probes with its VID and PID.
+    pub fn matches(&self, probe: impl Into<DebugProbeSelector>) -> bool {
+        let probe = probe.into();
+        self.match_probe_selector(
+            probe.vendor_id,
+            probe.product_id,
+            probe.serial_number.as_deref(),
+        )
+    }
+
+    /// Returns whether the selector matches the USB device described by `info`.
+    pub(crate) fn matches_device(&self, info: &DeviceInfo) -> bool {
         self.match_probe_selector(info.vendor_id(), info.product_id(), info.serial_number())
     }
 
@@ -1021,6 +1068,19 @@ pub trait JTAGAccess: DebugProbe {
     /// The measured scan chain will be stored in the probe's internal state.
     fn scan_chain(&mut self) -> Result<(), DebugProbeError>;
 
+    /// Discovers the devices on the JTAG chain, without relying on a configured scan chain.
+    ///
+    /// The length of the chain is measured by flushing all instruction registers with BYPASS,
+    /// and the IR lengths are derived from the IR capture pattern. If a scan chain is
+    /// configured, its IR lengths are verified against the capture.
+    ///
+    /// This resets all TAPs, but keeps the selected TAP.
+    fn discover_scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "discover_scan_chain",
+        })
+    }
+
     /// Executes a TAP reset.
     fn tap_reset(&mut self) -> Result<(), DebugProbeError>;
 
@@ -1059,6 +1119,22 @@ pub trait JTAGAccess: DebugProbe {
     /// The data shifted out of the DR register will be returned.
     fn write_dr(&mut self, data: &[u8], len: u32) -> Result<Vec<u8>, DebugProbeError>;
 
+    /// Shifts raw bit sequences through the JTAG chain.
+    ///
+    /// Unlike the register accesses, the sequences are shifted as they are: the TAP state
+    /// machine follows the TMS levels, and the bypass bits of other TAPs on the chain are not
+    /// added. The captured TDO bits are returned packed, least significant bit first, in the
+    /// order they were shifted.
+    fn shift_raw_sequence(
+        &mut self,
+        sequences: &[JtagSequence],
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        let _ = sequences;
+        Err(DebugProbeError::NotImplemented {
+            function_name: "shift_raw_sequence",
+        })
+    }
+
     /// Executes a sequence of JTAG commands.
     fn write_register_batch(
         &mut self,
@@ -1147,8 +1223,24 @@ impl From<ShiftDrCommand> for JtagCommand {
     }
 }
 
+/// A sequence of bits shifted through the JTAG chain while TMS is held at a constant level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JtagSequence {
+    /// The TMS level held while shifting.
+    pub tms: bool,
+
+    /// The bits shifted out on TDI, least significant bit first.
+    pub tdi: Vec<u8>,
+
+    /// The number of bits in `tdi`.
+    pub len: usize,
+
+    /// Whether the bits on TDO are captured.
+    pub capture: bool,
+}
+
 /// Represents a Jtag Tap within the chain.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct JtagChainItem {
     /// The IDCODE of the device.
     pub idcode: Option<IdCode>,
@@ -1198,6 +1198,10 @@ impl DebugProbe for BlackMagicProbe {
     fn has_xtensa_interface(&self) -> bool {
         true
     }
+
+    fn try_as_jtag_access(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
 }
 
 impl DapProbe for BlackMagicProbe {}
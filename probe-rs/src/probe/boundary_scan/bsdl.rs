@@ -0,0 +1,605 @@
+//! Parser for Boundary Scan Description Language (BSDL) files, as defined by IEEE 1149.1.
+//!
+//! Only the attributes needed for boundary scan are extracted: the instruction register, the
+//! IDCODE, the boundary register cells, and the package pin map.
+
+use std::collections::HashMap;
+
+use crate::probe::IdCode;
+
+/// An error which occurred while parsing a BSDL file.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum BsdlError {
+    /// Invalid BSDL at line {line}: {message}
+    Syntax {
+        /// The line of the statement, starting at 1.
+        line: usize,
+        /// A description of the problem.
+        message: String,
+    },
+
+    /// The BSDL file does not define the {0} attribute.
+    MissingAttribute(&'static str),
+}
+
+/// The function of a boundary register cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellFunction {
+    /// Captures the level of an input pin.
+    Input,
+    /// Drives an output pin which cannot be disabled.
+    Output2,
+    /// Drives an output pin which can be disabled by a control cell.
+    Output3,
+    /// Enables or disables output cells.
+    Control,
+    /// A control cell which disables the outputs in Test-Logic-Reset.
+    ControlR,
+    /// A cell which is not connected to a pin.
+    Internal,
+    /// Captures the level of a clock input.
+    Clock,
+    /// Drives a pin and captures its level.
+    Bidir,
+    /// Captures the level of a pin without driving it.
+    ObserveOnly,
+}
+
+impl CellFunction {
+    /// Returns whether the cell captures the level of its pin.
+    pub fn is_input(self) -> bool {
+        matches!(
+            self,
+            CellFunction::Input
+                | CellFunction::Clock
+                | CellFunction::Bidir
+                | CellFunction::ObserveOnly
+        )
+    }
+
+    /// Returns whether the cell drives its pin in EXTEST.
+    pub fn is_output(self) -> bool {
+        matches!(
+            self,
+            CellFunction::Output2 | CellFunction::Output3 | CellFunction::Bidir
+        )
+    }
+}
+
+/// The control cell which enables an output cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlCell {
+    /// The number of the control cell.
+    pub cell: usize,
+    /// The value of the control cell which disables the output.
+    pub disable_value: bool,
+}
+
+/// A cell of the boundary register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundaryCell {
+    /// The position of the cell in the boundary register, where cell 0 is closest to TDO.
+    pub number: usize,
+    /// The name of the cell design, e.g. `BC_1`.
+    pub cell_type: String,
+    /// The port connected to the cell, if any.
+    pub port: Option<String>,
+    /// The function of the cell.
+    pub function: CellFunction,
+    /// The value which is safe to load into the cell, if any.
+    pub safe: Option<bool>,
+    /// The control cell of an output, if it can be disabled.
+    pub control: Option<ControlCell>,
+}
+
+/// An IDCODE pattern, where the bits which are not set in `mask` can have any value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IdCodePattern {
+    value: u32,
+    mask: u32,
+}
+
+/// The description of a device, parsed from a BSDL file.
+#[derive(Debug, Clone)]
+pub struct Bsdl {
+    entity: String,
+    instruction_length: usize,
+    instructions: Vec<(String, u32)>,
+    idcodes: Vec<IdCodePattern>,
+    boundary_length: usize,
+    cells: Vec<BoundaryCell>,
+    pin_map: HashMap<String, String>,
+}
+
+impl Bsdl {
+    /// Parses a BSDL file.
+    pub fn parse(source: &str) -> Result<Self, BsdlError> {
+        let statements = statements(source);
+
+        let mut entity = None;
+        let mut default_pin_map = None;
+        let mut attributes = HashMap::new();
+        let mut constants = HashMap::new();
+
+        for statement in &statements {
+            let words = statement.words();
+            let Some(keyword) = words.first() else {
+                continue;
+            };
+
+            match keyword.to_ascii_lowercase().as_str() {
+                "entity" if entity.is_none() => {
+                    entity = words.get(1).map(|name| name.to_string());
+                    if statement
+                        .head
+                        .to_ascii_uppercase()
+                        .contains("PHYSICAL_PIN_MAP")
+                    {
+                        default_pin_map = statement.strings.first().cloned();
+                    }
+                }
+                "attribute" if words.len() >= 2 => {
+                    attributes.insert(words[1].to_ascii_uppercase(), statement);
+                }
+                "constant" if words.len() >= 2 => {
+                    constants.insert(words[1].to_ascii_uppercase(), statement);
+                }
+                _ => {}
+            }
+        }
+
+        let entity = entity.ok_or(BsdlError::MissingAttribute("entity"))?;
+        let attribute = |name: &'static str| {
+            attributes
+                .get(name)
+                .copied()
+                .ok_or(BsdlError::MissingAttribute(name))
+        };
+
+        let statement = attribute("INSTRUCTION_LENGTH")?;
+        let instruction_length = statement.number()?;
+        if !(1..=32).contains(&instruction_length) {
+            return Err(statement.syntax_error(format!(
+                "unsupported instruction length {instruction_length}"
+            )));
+        }
+
+        let statement = attribute("INSTRUCTION_OPCODE")?;
+        let instructions = parse_instructions(statement.line, &statement.value())?;
+        if let Some((name, _)) = instructions
+            .iter()
+            .find(|(_, opcode)| *opcode >= 1u64 << instruction_length)
+        {
+            return Err(BsdlError::Syntax {
+                line: statement.line,
+                message: format!("the opcode of {name} is longer than {instruction_length} bits"),
+            });
+        }
+        let instructions = instructions
+            .into_iter()
+            .map(|(name, opcode)| (name, opcode as u32))
+            .collect();
+
+        let idcodes = match attributes.get("IDCODE_REGISTER") {
+            Some(statement) => parse_idcodes(statement.line, &statement.value())?,
+            None => Vec::new(),
+        };
+
+        let boundary_length = attribute("BOUNDARY_LENGTH")?.number()?;
+        let statement = attribute("BOUNDARY_REGISTER")?;
+        let cells = parse_cells(statement.line, &statement.value(), boundary_length)?;
+
+        // The pin map is selected by the PHYSICAL_PIN_MAP generic, or is the only one defined.
+        let pin_map = default_pin_map
+            .and_then(|name| constants.get(&name.to_ascii_uppercase()).copied())
+            .or_else(|| {
+                let mut maps = constants.values().filter(|constant| {
+                    constant
+                        .head
+                        .to_ascii_uppercase()
+                        .contains("PIN_MAP_STRING")
+                });
+                maps.next().filter(|_| maps.next().is_none()).copied()
+            })
+            .map(|statement| parse_pin_map(&statement.value()))
+            .unwrap_or_default();
+
+        Ok(Self {
+            entity,
+            instruction_length,
+            instructions,
+            idcodes,
+            boundary_length,
+            cells,
+            pin_map,
+        })
+    }
+
+    /// The name of the device.
+    pub fn entity(&self) -> &str {
+        &self.entity
+    }
+
+    /// The length of the instruction register in bits.
+    pub fn instruction_length(&self) -> usize {
+        self.instruction_length
+    }
+
+    /// Returns the opcode of an instruction, like `EXTEST`.
+    ///
+    /// If an instruction has multiple opcodes, the first one is returned.
+    pub fn instruction(&self, name: &str) -> Option<u32> {
+        self.instructions
+            .iter()
+            .find(|(instruction, _)| instruction.eq_ignore_ascii_case(name))
+            .map(|(_, opcode)| *opcode)
+    }
+
+    /// Returns whether an IDCODE matches the IDCODE register of the device.
+    ///
+    /// Returns `false` if the device does not define an IDCODE register.
+    pub fn matches_idcode(&self, idcode: IdCode) -> bool {
+        self.idcodes
+            .iter()
+            .any(|pattern| idcode.0 & pattern.mask == pattern.value)
+    }
+
+    /// The length of the boundary register in bits.
+    pub fn boundary_length(&self) -> usize {
+        self.boundary_length
+    }
+
+    /// The cells of the boundary register, ordered by their number.
+    pub fn cells(&self) -> &[BoundaryCell] {
+        &self.cells
+    }
+
+    /// Returns the package pin of a port, if the BSDL file maps it.
+    pub fn package_pin(&self, port: &str) -> Option<&str> {
+        self.pin_map
+            .get(&port.to_ascii_uppercase())
+            .map(String::as_str)
+    }
+
+    /// Returns the port connected to a package pin, if the BSDL file maps it.
+    pub fn port_of_package_pin(&self, pin: &str) -> Option<&str> {
+        self.pin_map
+            .iter()
+            .find(|(_, package_pin)| package_pin.eq_ignore_ascii_case(pin))
+            .map(|(port, _)| port.as_str())
+    }
+}
+
+/// A VHDL statement, with its string literals separated from the rest.
+#[derive(Debug, Default)]
+struct Statement {
+    line: usize,
+    /// The text before the first string literal.
+    head: String,
+    strings: Vec<String>,
+}
+
+impl Statement {
+    fn syntax_error(&self, message: impl Into<String>) -> BsdlError {
+        BsdlError::Syntax {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    /// The words of the text before the first string literal.
+    fn words(&self) -> Vec<&str> {
+        self.head
+            .split(|c: char| c.is_whitespace() || c == ':')
+            .filter(|word| !word.is_empty())
+            .collect()
+    }
+
+    /// The value of an attribute or constant, which is either a concatenation of strings, or the
+    /// last word of the statement.
+    fn value(&self) -> String {
+        if self.strings.is_empty() {
+            self.words().last().copied().unwrap_or_default().to_string()
+        } else {
+            self.strings.concat()
+        }
+    }
+
+    fn number(&self) -> Result<usize, BsdlError> {
+        let value = self.value();
+        value
+            .parse()
+            .map_err(|_| self.syntax_error(format!("invalid number {value}")))
+    }
+}
+
+/// Splits a BSDL file into statements, removing comments.
+fn statements(source: &str) -> Vec<Statement> {
+    let mut statements = Vec::new();
+    let mut statement = Statement::default();
+    let mut depth = 0usize;
+
+    for (index, line) in source.lines().enumerate() {
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '-' if chars.peek() == Some(&'-') => break,
+                '"' => {
+                    let string = chars.by_ref().take_while(|&c| c != '"').collect();
+                    statement.strings.push(string);
+                }
+                ';' if depth == 0 => {
+                    statements.push(std::mem::take(&mut statement));
+                    continue;
+                }
+                c => {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth = depth.saturating_sub(1),
+                        _ => {}
+                    }
+                    if statement.strings.is_empty() {
+                        statement.head.push(c);
+                    }
+                }
+            }
+
+            if statement.line == 0 && !c.is_whitespace() {
+                statement.line = index + 1;
+            }
+        }
+
+        if statement.strings.is_empty() {
+            statement.head.push(' ');
+        }
+    }
+
+    statements
+}
+
+/// Splits a list at the commas which are not enclosed in parentheses.
+fn split_list(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                items.push(text[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(text[start..].trim());
+    items.retain(|item| !item.is_empty());
+
+    items
+}
+
+/// Splits `NAME (CONTENTS)` into the name and the contents.
+fn split_parenthesized(item: &str) -> Option<(&str, &str)> {
+    let (name, rest) = item.split_once('(')?;
+    let contents = rest.trim_end().strip_suffix(')')?;
+
+    Some((name.trim(), contents))
+}
+
+/// Parses a binary number, where `X` bits are treated as zeros.
+fn parse_binary(text: &str) -> Option<u64> {
+    if text.is_empty() || text.len() > 64 {
+        return None;
+    }
+
+    text.chars().try_fold(0u64, |value, c| match c {
+        '0' | 'X' | 'x' => Some(value << 1),
+        '1' => Some(value << 1 | 1),
+        _ => None,
+    })
+}
+
+/// Parses the instruction opcodes, `"NAME (opcode, opcode...), ..."`.
+fn parse_instructions(line: usize, text: &str) -> Result<Vec<(String, u64)>, BsdlError> {
+    let mut instructions = Vec::new();
+
+    for item in split_list(text) {
+        let error = || BsdlError::Syntax {
+            line,
+            message: format!("invalid instruction {item}"),
+        };
+
+        let (name, opcodes) = split_parenthesized(item).ok_or_else(error)?;
+        let opcode = opcodes
+            .split(',')
+            .next()
+            .and_then(|opcode| parse_binary(opcode.trim()))
+            .ok_or_else(error)?;
+        instructions.push((name.to_string(), opcode));
+    }
+
+    Ok(instructions)
+}
+
+/// Parses the IDCODE register, which is a 32-bit binary pattern with `X` for any bit.
+///
+/// Some devices list several patterns, separated by commas.
+fn parse_idcodes(line: usize, text: &str) -> Result<Vec<IdCodePattern>, BsdlError> {
+    text.split(',')
+        .map(|pattern| {
+            let pattern = pattern
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>();
+            let value = parse_binary(&pattern).filter(|_| pattern.len() == 32);
+            let mask = parse_binary(&pattern.replace(['0', '1'], "1").replace(['X', 'x'], "0"));
+
+            match (value, mask) {
+                (Some(value), Some(mask)) => Ok(IdCodePattern {
+                    value: value as u32,
+                    mask: mask as u32,
+                }),
+                _ => Err(BsdlError::Syntax {
+                    line,
+                    message: format!("invalid IDCODE {pattern}"),
+                }),
+            }
+        })
+        .collect()
+}
+
+fn parse_function(name: &str) -> Option<CellFunction> {
+    let function = match name.to_ascii_uppercase().as_str() {
+        "INPUT" => CellFunction::Input,
+        "OUTPUT2" => CellFunction::Output2,
+        "OUTPUT3" => CellFunction::Output3,
+        "CONTROL" => CellFunction::Control,
+        "CONTROLR" => CellFunction::ControlR,
+        "INTERNAL" => CellFunction::Internal,
+        "CLOCK" => CellFunction::Clock,
+        "BIDIR" => CellFunction::Bidir,
+        "OBSERVE_ONLY" => CellFunction::ObserveOnly,
+        _ => return None,
+    };
+
+    Some(function)
+}
+
+fn parse_bit(text: &str) -> Option<Option<bool>> {
+    match text {
+        "0" => Some(Some(false)),
+        "1" => Some(Some(true)),
+        "X" | "x" => Some(None),
+        _ => None,
+    }
+}
+
+/// Parses the boundary register, `"num (cell, port, function, safe[, ccell, disval, rslt]), ..."`.
+fn parse_cells(line: usize, text: &str, length: usize) -> Result<Vec<BoundaryCell>, BsdlError> {
+    let mut cells = Vec::new();
+
+    for item in split_list(text) {
+        let error = |message: &str| BsdlError::Syntax {
+            line,
+            message: format!("invalid boundary cell {item}: {message}"),
+        };
+
+        let (number, fields) = split_parenthesized(item).ok_or_else(|| error("syntax"))?;
+        let number = number
+            .parse::<usize>()
+            .ok()
+            .filter(|number| *number < length)
+            .ok_or_else(|| error("invalid cell number"))?;
+
+        let fields = split_list(fields);
+        let (cell_type, port, function, safe, control) = match fields.as_slice() {
+            [cell_type, port, function, safe] => (cell_type, port, function, safe, None),
+            [cell_type, port, function, safe, ccell, disval, _result] => {
+                let cell = ccell
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|cell| *cell < length)
+                    .ok_or_else(|| error("invalid control cell"))?;
+                let disable_value = parse_bit(disval)
+                    .flatten()
+                    .ok_or_else(|| error("invalid disable value"))?;
+                let control = ControlCell {
+                    cell,
+                    disable_value,
+                };
+                (cell_type, port, function, safe, Some(control))
+            }
+            _ => return Err(error("expected 4 or 7 fields")),
+        };
+
+        cells.push(BoundaryCell {
+            number,
+            cell_type: cell_type.to_string(),
+            port: (*port != "*").then(|| port.replace(' ', "")),
+            function: parse_function(function).ok_or_else(|| error("unknown function"))?,
+            safe: parse_bit(safe).ok_or_else(|| error("invalid safe value"))?,
+            control,
+        });
+    }
+
+    cells.sort_by_key(|cell| cell.number);
+
+    Ok(cells)
+}
+
+/// Parses a pin map, `"PORT : pin, VECTOR : (pin, pin...), ..."`.
+///
+/// Ports of vector type are skipped, as their pins are listed without the index range.
+fn parse_pin_map(text: &str) -> HashMap<String, String> {
+    split_list(text)
+        .into_iter()
+        .filter_map(|item| {
+            let (port, pin) = item.split_once(':')?;
+            let pin = pin.trim();
+            (!pin.starts_with('(')).then(|| (port.trim().to_ascii_uppercase(), pin.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::boundary_scan::tests::TEST_BSDL;
+
+    #[test]
+    fn parse_bsdl() {
+        let bsdl = Bsdl::parse(TEST_BSDL).unwrap();
+
+        assert_eq!(bsdl.entity(), "TEST_DEVICE");
+        assert_eq!(bsdl.instruction_length(), 4);
+        assert_eq!(bsdl.instruction("extest"), Some(0b0000));
+        assert_eq!(bsdl.instruction("SAMPLE"), Some(0b0010));
+        assert_eq!(bsdl.instruction("INTEST"), None);
+        assert!(bsdl.matches_idcode(IdCode(0x4BA00477)));
+        assert!(bsdl.matches_idcode(IdCode(0x1BA00477)));
+        assert!(!bsdl.matches_idcode(IdCode(0x4BA00478)));
+
+        assert_eq!(bsdl.boundary_length(), 5);
+        let cells = bsdl.cells();
+        assert_eq!(cells.len(), 5);
+        assert_eq!(cells[0].function, CellFunction::Internal);
+        assert_eq!(
+            cells[3],
+            BoundaryCell {
+                number: 3,
+                cell_type: String::from("BC_7"),
+                port: Some(String::from("PA0")),
+                function: CellFunction::Bidir,
+                safe: None,
+                control: Some(ControlCell {
+                    cell: 4,
+                    disable_value: true,
+                }),
+            }
+        );
+
+        assert_eq!(bsdl.package_pin("pa1"), Some("6"));
+        assert_eq!(bsdl.package_pin("VDD"), None);
+        assert_eq!(bsdl.port_of_package_pin("7"), Some("LED"));
+    }
+
+    #[test]
+    fn parse_invalid_bsdl() {
+        let source = TEST_BSDL.replace(
+            "\"0   (BC_1,  *,    INTERNAL, X)\"",
+            "\"5 (BC_1, *, INTERNAL, X)\"",
+        );
+        assert!(matches!(
+            Bsdl::parse(&source),
+            Err(BsdlError::Syntax { line: 42, .. })
+        ));
+
+        let source = TEST_BSDL.replace("BOUNDARY_LENGTH", "LENGTH");
+        assert!(matches!(
+            Bsdl::parse(&source),
+            Err(BsdlError::MissingAttribute("BOUNDARY_LENGTH"))
+        ));
+    }
+}
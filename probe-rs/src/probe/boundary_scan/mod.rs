@@ -0,0 +1,391 @@
+//! Boundary scan of devices on the JTAG chain, described by BSDL files.
+//!
+//! In the SAMPLE instruction, the boundary register captures the levels of the pins while the
+//! device keeps running normally. In EXTEST, the boundary register drives the output pins
+//! instead of the device logic, which allows checking solder joints and net continuity without
+//! any firmware on the board.
+
+mod bsdl;
+
+use bitvec::prelude::*;
+
+pub use bsdl::{BoundaryCell, Bsdl, BsdlError, CellFunction, ControlCell};
+
+use super::{DebugProbeError, JTAGAccess};
+
+/// The state a pin is driven to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinState {
+    /// The pin is driven low.
+    Low,
+    /// The pin is driven high.
+    High,
+    /// The output driver of the pin is disabled.
+    HighZ,
+}
+
+/// An error which occurred during boundary scan.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum BoundaryScanError {
+    /// The BSDL file does not define the {0} instruction.
+    MissingInstruction(&'static str),
+
+    /// No TAP on the JTAG chain matches the IDCODE of {0}.
+    DeviceNotFound(String),
+
+    /// There is no TAP {0} on the JTAG chain.
+    TapNotFound(usize),
+
+    /// The TAP has an IR length of {actual} bits, but the BSDL file describes {expected} bits.
+    IrLengthMismatch {
+        /// The IR length described by the BSDL file.
+        expected: usize,
+        /// The IR length of the TAP.
+        actual: usize,
+    },
+
+    /// The device has no pin named {0}.
+    UnknownPin(String),
+
+    /// Pin {0} has no input cell, so its level cannot be read.
+    NotReadable(String),
+
+    /// Pin {0} has no output cell, so it cannot be driven.
+    NotDrivable(String),
+
+    /// Pin {0} has no control cell, so its output cannot be disabled.
+    NotTristatable(String),
+
+    /// An error with the probe occurred.
+    Probe(#[from] DebugProbeError),
+}
+
+/// The contents of the boundary register, and the mapping of pins to its cells.
+struct BoundaryRegister<'bsdl> {
+    bsdl: &'bsdl Bsdl,
+    /// The values which are shifted into the boundary register.
+    update: BitVec<u8>,
+}
+
+impl<'bsdl> BoundaryRegister<'bsdl> {
+    /// Creates a boundary register loaded with the safe values of the cells.
+    fn new(bsdl: &'bsdl Bsdl) -> Self {
+        let mut update = bitvec![u8, Lsb0; 0; bsdl.boundary_length()];
+        for cell in bsdl.cells() {
+            update.set(cell.number, cell.safe.unwrap_or(false));
+        }
+
+        Self { bsdl, update }
+    }
+
+    /// Returns the port of a pin, which is given by its port name or package pin.
+    fn port(&self, pin: &str) -> Result<&'bsdl str, BoundaryScanError> {
+        self.bsdl
+            .cells()
+            .iter()
+            .filter_map(|cell| cell.port.as_deref())
+            .find(|port| port.eq_ignore_ascii_case(pin))
+            .or_else(|| self.bsdl.port_of_package_pin(pin))
+            .ok_or_else(|| BoundaryScanError::UnknownPin(pin.to_string()))
+    }
+
+    fn find_cell(
+        &self,
+        port: &str,
+        filter: impl Fn(CellFunction) -> bool,
+    ) -> Option<&'bsdl BoundaryCell> {
+        self.bsdl.cells().iter().find(|cell| {
+            cell.port
+                .as_deref()
+                .is_some_and(|cell_port| cell_port.eq_ignore_ascii_case(port))
+                && filter(cell.function)
+        })
+    }
+
+    /// Sets the cells which drive a pin.
+    ///
+    /// Enabling an output also enables the other outputs which share its control cell.
+    fn drive(&mut self, pin: &str, state: PinState) -> Result<(), BoundaryScanError> {
+        let port = self.port(pin)?;
+        let output = self
+            .find_cell(port, CellFunction::is_output)
+            .ok_or_else(|| BoundaryScanError::NotDrivable(port.to_string()))?;
+
+        let level = match state {
+            PinState::Low => false,
+            PinState::High => true,
+            PinState::HighZ => {
+                let control = output
+                    .control
+                    .ok_or_else(|| BoundaryScanError::NotTristatable(port.to_string()))?;
+                self.update.set(control.cell, control.disable_value);
+                return Ok(());
+            }
+        };
+
+        self.update.set(output.number, level);
+        if let Some(control) = output.control {
+            self.update.set(control.cell, !control.disable_value);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the level of a pin from the captured boundary register.
+    fn level(&self, captured: &BitSlice<u8>, pin: &str) -> Result<bool, BoundaryScanError> {
+        let port = self.port(pin)?;
+        let input = self
+            .find_cell(port, CellFunction::is_input)
+            .ok_or_else(|| BoundaryScanError::NotReadable(port.to_string()))?;
+
+        Ok(captured[input.number])
+    }
+
+    /// The ports which have an input cell, in the order of the cells.
+    fn readable_ports(&self) -> Vec<&'bsdl str> {
+        let mut ports = Vec::<&str>::new();
+        for cell in self.bsdl.cells() {
+            if let Some(port) = cell.port.as_deref() {
+                if cell.function.is_input() && !ports.contains(&port) {
+                    ports.push(port);
+                }
+            }
+        }
+
+        ports
+    }
+}
+
+/// Boundary scan of a device on the JTAG chain.
+///
+/// Pins are addressed by their port name in the BSDL file, or by their package pin if the BSDL
+/// file contains a pin map. Pins are only driven after the first call to
+/// [`BoundaryScan::drive_pin`], which switches the device from SAMPLE to EXTEST.
+pub struct BoundaryScan<'probe> {
+    jtag: &'probe mut dyn JTAGAccess,
+    register: BoundaryRegister<'probe>,
+    sample: u32,
+    preload: u32,
+    extest: u32,
+    extest_active: bool,
+}
+
+impl<'probe> BoundaryScan<'probe> {
+    /// Starts boundary scan of a device on the JTAG chain.
+    ///
+    /// If no TAP is given, the first TAP which matches the IDCODE of the BSDL file is used, or
+    /// the only TAP on the chain. This resets all TAPs on the chain.
+    pub fn new(
+        jtag: &'probe mut dyn JTAGAccess,
+        bsdl: &'probe Bsdl,
+        tap: Option<usize>,
+    ) -> Result<Self, BoundaryScanError> {
+        let instruction = |name: &'static str| {
+            bsdl.instruction(name)
+                .ok_or(BoundaryScanError::MissingInstruction(name))
+        };
+        let sample = instruction("SAMPLE")?;
+        let preload = bsdl.instruction("PRELOAD").unwrap_or(sample);
+        let extest = instruction("EXTEST")?;
+
+        let chain = jtag.discover_scan_chain()?;
+        let tap = match tap {
+            Some(tap) => tap,
+            None if chain.len() == 1 => 0,
+            None => chain
+                .iter()
+                .position(|item| {
+                    item.idcode
+                        .is_some_and(|idcode| bsdl.matches_idcode(idcode))
+                })
+                .ok_or_else(|| BoundaryScanError::DeviceNotFound(bsdl.entity().to_string()))?,
+        };
+
+        let item = chain.get(tap).ok_or(BoundaryScanError::TapNotFound(tap))?;
+        if item.irlen != bsdl.instruction_length() {
+            return Err(BoundaryScanError::IrLengthMismatch {
+                expected: bsdl.instruction_length(),
+                actual: item.irlen,
+            });
+        }
+
+        tracing::debug!("Boundary scan of {} on TAP {tap}", bsdl.entity());
+        jtag.select_jtag_tap(tap)?;
+
+        Ok(Self {
+            jtag,
+            register: BoundaryRegister::new(bsdl),
+            sample,
+            preload,
+            extest,
+            extest_active: false,
+        })
+    }
+
+    /// Shifts the boundary register, and returns the captured values.
+    fn scan(&mut self, instruction: u32) -> Result<BitVec<u8>, BoundaryScanError> {
+        let len = self.register.update.len();
+        let response = self.jtag.write_register(
+            instruction,
+            self.register.update.as_raw_slice(),
+            len as u32,
+        )?;
+
+        let mut captured = BitVec::<u8>::from_vec(response);
+        captured.resize(len, false);
+
+        Ok(captured)
+    }
+
+    /// Shifts the boundary register with the active instruction, and returns the captured values.
+    fn capture(&mut self) -> Result<BitVec<u8>, BoundaryScanError> {
+        let instruction = if self.extest_active {
+            self.extest
+        } else {
+            self.sample
+        };
+
+        self.scan(instruction)
+    }
+
+    /// Drives a pin, switching the device to EXTEST if necessary.
+    ///
+    /// The outputs of all other pins keep their safe values until they are driven.
+    pub fn drive_pin(&mut self, pin: &str, state: PinState) -> Result<(), BoundaryScanError> {
+        self.register.drive(pin, state)?;
+
+        if !self.extest_active {
+            // Preload the boundary register, so the outputs don't glitch when EXTEST is loaded.
+            self.scan(self.preload)?;
+            self.extest_active = true;
+        }
+        self.capture()?;
+
+        Ok(())
+    }
+
+    /// Reads the level of a pin.
+    pub fn read_pin(&mut self, pin: &str) -> Result<bool, BoundaryScanError> {
+        let captured = self.capture()?;
+        self.register.level(&captured, pin)
+    }
+
+    /// Reads the levels of all pins which have an input cell, with a single scan.
+    pub fn read_pins(&mut self) -> Result<Vec<(String, bool)>, BoundaryScanError> {
+        let captured = self.capture()?;
+
+        self.register
+            .readable_ports()
+            .into_iter()
+            .map(|port| Ok((port.to_string(), self.register.level(&captured, port)?)))
+            .collect()
+    }
+
+    /// Ends boundary scan by resetting the TAPs, which returns the pins to normal operation.
+    pub fn release(self) -> Result<(), BoundaryScanError> {
+        self.jtag.tap_reset()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) const TEST_BSDL: &str = r#"
+-- A small device for testing.
+entity TEST_DEVICE is
+    generic (PHYSICAL_PIN_MAP : string := "QFN8");
+
+    port (
+        TCK: in bit;
+        TDI: in bit;
+        TDO: out bit;
+        TMS: in bit;
+        PA0: inout bit;
+        PA1: in bit;
+        LED: out bit;
+        VDD: linkage bit_vector(1 to 2)
+    );
+
+    use STD_1149_1_2001.all;
+
+    attribute PIN_MAP of TEST_DEVICE : entity is PHYSICAL_PIN_MAP;
+
+    constant QFN8: PIN_MAP_STRING :=
+        "TCK: 1, TDI: 2, TDO: 3, TMS: 4, " &
+        "PA0: 5, PA1: 6, LED: 7, VDD: (8, 9)";
+
+    attribute INSTRUCTION_LENGTH of TEST_DEVICE: entity is 4;
+
+    attribute INSTRUCTION_OPCODE of TEST_DEVICE: entity is
+        "BYPASS  (1111)," &
+        "EXTEST  (0000)," &
+        "SAMPLE  (0010, 0011)," &
+        "PRELOAD (0010)," &
+        "IDCODE  (1110)";
+
+    attribute IDCODE_REGISTER of TEST_DEVICE: entity is
+        "XXXX" &              -- version
+        "1011101000000000" &  -- part number
+        "01000111011" &       -- manufacturer
+        "1";
+
+    attribute BOUNDARY_LENGTH of TEST_DEVICE: entity is 5;
+
+    attribute BOUNDARY_REGISTER of TEST_DEVICE: entity is
+    --   num  cell   port  function  safe  ccell disval rslt
+        "4   (BC_1,  *,    CONTROL,  1), " &
+        "3   (BC_7,  PA0,  BIDIR,    X,    4,    1,     Z), " &
+        "2   (BC_1,  LED,  OUTPUT2,  0), " &
+        "1   (BC_4,  PA1,  INPUT,    X), " &
+        "0   (BC_1,  *,    INTERNAL, X)";
+
+end TEST_DEVICE;
+"#;
+
+    #[test]
+    fn drive_pins() {
+        let bsdl = Bsdl::parse(TEST_BSDL).unwrap();
+        let mut register = BoundaryRegister::new(&bsdl);
+
+        // The control cell is safe, which disables PA0.
+        assert_eq!(register.update, bits![u8, Lsb0; 0, 0, 0, 0, 1]);
+
+        register.drive("pa0", PinState::High).unwrap();
+        assert_eq!(register.update, bits![u8, Lsb0; 0, 0, 0, 1, 0]);
+
+        // Package pin 7 is the LED.
+        register.drive("7", PinState::High).unwrap();
+        register.drive("PA0", PinState::HighZ).unwrap();
+        assert_eq!(register.update, bits![u8, Lsb0; 0, 0, 1, 1, 1]);
+
+        assert!(matches!(
+            register.drive("LED", PinState::HighZ),
+            Err(BoundaryScanError::NotTristatable(_))
+        ));
+        assert!(matches!(
+            register.drive("PA1", PinState::Low),
+            Err(BoundaryScanError::NotDrivable(_))
+        ));
+        assert!(matches!(
+            register.drive("PB0", PinState::Low),
+            Err(BoundaryScanError::UnknownPin(_))
+        ));
+    }
+
+    #[test]
+    fn read_pins() {
+        let bsdl = Bsdl::parse(TEST_BSDL).unwrap();
+        let register = BoundaryRegister::new(&bsdl);
+        let captured = bits![u8, Lsb0; 0, 1, 0, 0, 0];
+
+        assert_eq!(register.readable_ports(), ["PA1", "PA0"]);
+        assert!(register.level(captured, "PA1").unwrap());
+        assert!(!register.level(captured, "5").unwrap());
+        assert!(matches!(
+            register.level(captured, "LED"),
+            Err(BoundaryScanError::NotReadable(_))
+        ));
+    }
+}
@@ -6,6 +6,7 @@ pub mod swo;
 pub mod transfer;
 
 use crate::probe::cmsisdap::commands::general::info::PacketSizeCommand;
+use crate::probe::common::ScanChainError;
 use crate::probe::usb_util::InterfaceExt;
 use crate::probe::{ProbeError, WireProtocol};
 use std::io::ErrorKind;
@@ -52,6 +53,9 @@ pub enum CmsisDapError {
 
     /// Error scanning IR lengths.
     InvalidIR,
+
+    /// Error scanning the JTAG chain.
+    ScanChain(#[source] ScanChainError),
 }
 
 impl ProbeError for CmsisDapError {}
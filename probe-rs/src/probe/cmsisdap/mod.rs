@@ -1282,6 +1282,9 @@ impl From<ScanChainError> for CmsisDapError {
         match error {
             ScanChainError::InvalidIdCode => CmsisDapError::InvalidIdCode,
             ScanChainError::InvalidIR => CmsisDapError::InvalidIR,
+            error @ (ScanChainError::BrokenChain { .. }
+            | ScanChainError::EmptyChain
+            | ScanChainError::TapCountMismatch { .. }) => CmsisDapError::ScanChain(error),
         }
     }
 }
@@ -250,7 +250,7 @@ pub fn open_device_from_selector(
         for device in devices {
             tracing::trace!("Trying device {:?}", device);
 
-            if selector.matches(&device) {
+            if selector.matches_device(&device) {
                 hid_device_info = get_cmsisdap_info(&device);
 
                 if hid_device_info.is_some() {
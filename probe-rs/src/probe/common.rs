@@ -8,7 +8,7 @@ use probe_rs_target::ScanChainElement;
 
 use crate::probe::{
     BatchExecutionError, ChainParams, CommandResult, DebugProbe, DebugProbeError,
-    DeferredResultSet, JTAGAccess, JtagChainItem, JtagCommand, JtagCommandQueue,
+    DeferredResultSet, JTAGAccess, JtagChainItem, JtagCommand, JtagCommandQueue, JtagSequence,
 };
 
 pub(crate) fn bits_to_byte(bits: impl IntoIterator<Item = bool>) -> u32 {
@@ -85,6 +85,15 @@ pub enum ScanChainError {
     InvalidIdCode,
     #[error("Invalid IR scan chain")]
     InvalidIR,
+    #[error("The JTAG {name} scan chain is broken or longer than {max_length} bits")]
+    BrokenChain {
+        name: &'static str,
+        max_length: usize,
+    },
+    #[error("No TAPs found on the JTAG scan chain")]
+    EmptyChain,
+    #[error("Found {idcodes} IDCODEs, but {taps} TAPs in BYPASS")]
+    TapCountMismatch { idcodes: usize, taps: usize },
 }
 
 /// Convert a list of start positions to a list of lengths.
@@ -646,6 +655,112 @@ fn prepare_write_register(
     shift_dr(protocol, data, len as usize, capture)
 }
 
+/// Upper limit for the combined length of all instruction registers on the chain.
+const MAX_IR_CHAIN_LENGTH: usize = 512;
+
+/// Upper limit for the number of TAPs on the chain.
+const MAX_TAPS: usize = 64;
+
+/// Finds the length of a register chain after shifting `fill` zeros, followed by ones, through it.
+///
+/// The first one appears after as many bits as the chain is long. Returns `None` if the ones
+/// never appear, because the chain is broken or longer than `fill`.
+fn flushed_length(captured: &BitSlice<u8>, fill: usize) -> Option<usize> {
+    captured.get(fill..)?.first_one()
+}
+
+/// Discovers the TAPs on the JTAG chain.
+///
+/// The combined length of the instruction registers is measured by flushing them with ones, which
+/// also puts every TAP in BYPASS. The number of TAPs is then the length of the data register
+/// chain. Finally, the IDCODEs are read after a TAP reset, and the IR lengths are extracted from
+/// the IR capture pattern, verified against `expected_ir_lengths` if provided.
+///
+/// The TAPs are reset afterwards.
+pub(crate) fn discover_scan_chain(
+    protocol: &mut impl RawJtagIo,
+    expected_ir_lengths: Option<&[usize]>,
+) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+    protocol.reset_jtag_state_machine()?;
+    protocol.state_mut().chain_params = ChainParams::default();
+
+    // The ones are shifted in last, so the instruction registers end up all ones, which is BYPASS.
+    // The zeros never reach them, which would select EXTEST on many devices.
+    let input = bitvec![u8, Lsb0; 0; MAX_IR_CHAIN_LENGTH]
+        .into_iter()
+        .chain(iter::repeat(true).take(MAX_IR_CHAIN_LENGTH))
+        .collect::<BitVec<u8>>();
+    shift_ir(protocol, input.as_raw_slice(), input.len(), true)?;
+    let response = protocol.read_captured_bits()?;
+
+    let ir_length =
+        flushed_length(&response, MAX_IR_CHAIN_LENGTH).ok_or(ScanChainError::BrokenChain {
+            name: "IR",
+            max_length: MAX_IR_CHAIN_LENGTH,
+        })?;
+    if ir_length == 0 {
+        return Err(ScanChainError::EmptyChain.into());
+    }
+    let ir_capture = response[..ir_length].to_bitvec();
+    tracing::debug!("IR chain is {ir_length} bits long, capture: {ir_capture}");
+
+    // With all TAPs in BYPASS, every TAP adds a single bit to the DR chain.
+    let input = bitvec![u8, Lsb0; 0; MAX_TAPS]
+        .into_iter()
+        .chain(iter::repeat(true).take(MAX_TAPS))
+        .collect::<BitVec<u8>>();
+    shift_dr(protocol, input.as_raw_slice(), input.len(), true)?;
+    let response = protocol.read_captured_bits()?;
+
+    let taps = flushed_length(&response, MAX_TAPS).ok_or(ScanChainError::BrokenChain {
+        name: "DR",
+        max_length: MAX_TAPS,
+    })?;
+    if taps == 0 {
+        return Err(ScanChainError::EmptyChain.into());
+    }
+    tracing::debug!("Found {taps} TAPs in BYPASS");
+
+    // After a reset, the DR chain consists of the IDCODE or BYPASS registers. The trailing
+    // ones mark the end of the chain.
+    protocol.reset_jtag_state_machine()?;
+    let input = vec![0xFF; 4 * (taps + 1)];
+    shift_dr(protocol, &input, input.len() * 8, true)?;
+    let response = protocol.read_captured_bits()?;
+
+    let idcodes = extract_idcodes(&response)?;
+    if idcodes.len() != taps {
+        return Err(ScanChainError::TapCountMismatch {
+            idcodes: idcodes.len(),
+            taps,
+        }
+        .into());
+    }
+
+    let ir_lengths = extract_ir_lengths(&ir_capture, taps, expected_ir_lengths)?;
+
+    protocol.reset_jtag_state_machine()?;
+
+    Ok(idcodes
+        .into_iter()
+        .zip(ir_lengths)
+        .map(|(idcode, irlen)| JtagChainItem { idcode, irlen })
+        .collect())
+}
+
+impl JtagDriverState {
+    /// The IR lengths of the configured scan chain, if any.
+    fn expected_ir_lengths(&self) -> Option<Vec<usize>> {
+        self.expected_scan_chain.as_ref().map(|chain| {
+            chain
+                .iter()
+                .filter_map(|s| s.ir_len)
+                .map(|s| s as usize)
+                .collect()
+        })
+    }
+}
+
 impl<Probe: DebugProbe + RawJtagIo + 'static> JTAGAccess for Probe {
     fn scan_chain(&mut self) -> Result<(), DebugProbeError> {
         const MAX_CHAIN: usize = 8;
@@ -695,21 +810,8 @@ impl<Probe: DebugProbe + RawJtagIo + 'static> JTAGAccess for Probe {
 
         tracing::debug!("IR scan: {}", response);
 
-        let ir_lens = extract_ir_lengths(
-            response,
-            idcodes.len(),
-            self.state()
-                .expected_scan_chain
-                .as_ref()
-                .map(|chain| {
-                    chain
-                        .iter()
-                        .filter_map(|s| s.ir_len)
-                        .map(|s| s as usize)
-                        .collect::<Vec<usize>>()
-                })
-                .as_deref(),
-        )?;
+        let expected_ir_lengths = self.state().expected_ir_lengths();
+        let ir_lens = extract_ir_lengths(response, idcodes.len(), expected_ir_lengths.as_deref())?;
 
         tracing::info!("Found {} TAPs on reset scan", idcodes.len());
         tracing::debug!("Detected IR lens: {:?}", ir_lens);
@@ -725,6 +827,17 @@ impl<Probe: DebugProbe + RawJtagIo + 'static> JTAGAccess for Probe {
         Ok(())
     }
 
+    fn discover_scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        // Keep the selected TAP, which is reset but still addressable afterwards.
+        let chain_params = self.state().chain_params;
+        let expected_ir_lengths = self.state().expected_ir_lengths();
+
+        let result = discover_scan_chain(self, expected_ir_lengths.as_deref());
+
+        self.state_mut().chain_params = chain_params;
+        result
+    }
+
     fn tap_reset(&mut self) -> Result<(), DebugProbeError> {
         self.reset_jtag_state_machine()
     }
@@ -774,6 +887,33 @@ impl<Probe: DebugProbe + RawJtagIo + 'static> JTAGAccess for Probe {
         Ok(result)
     }
 
+    fn shift_raw_sequence(
+        &mut self,
+        sequences: &[JtagSequence],
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        for sequence in sequences {
+            let Some(tdi) = sequence.tdi.as_bits::<Lsb0>().get(..sequence.len) else {
+                return Err(DebugProbeError::Other(format!(
+                    "Invalid data length. Sequence bits: {}, expected: {}",
+                    sequence.tdi.len() * 8,
+                    sequence.len
+                )));
+            };
+
+            self.shift_bits(
+                iter::repeat(sequence.tms),
+                tdi.iter().by_vals(),
+                iter::repeat(sequence.capture),
+            )?;
+        }
+
+        let mut response = self.read_captured_bits()?;
+
+        // Implementations don't need to align to keep the code simple
+        response.force_align();
+        Ok(response.into_vec())
+    }
+
     #[tracing::instrument(skip(self, writes))]
     fn write_register_batch(
         &mut self,
@@ -930,6 +1070,161 @@ mod tests {
         assert_eq!(idcodes, vec![Some(ARM_TAP), None, Some(STM_BS_TAP)]);
     }
 
+    /// A TAP on a [`SimulatedChain`].
+    struct SimulatedTap {
+        ir_len: usize,
+        ir_capture: u32,
+        idcode: Option<IdCode>,
+        /// The current instruction, `None` for IDCODE.
+        ir: Option<u32>,
+    }
+
+    impl SimulatedTap {
+        fn new(ir_len: usize, idcode: Option<IdCode>) -> Self {
+            Self {
+                ir_len,
+                ir_capture: 0b01,
+                idcode,
+                ir: None,
+            }
+        }
+    }
+
+    /// A JTAG chain, where the first TAP is closest to TDO.
+    struct SimulatedChain {
+        state: JtagDriverState,
+        taps: Vec<SimulatedTap>,
+        shift: BitVec<u8>,
+        captured: BitVec<u8>,
+        /// Simulates a broken connection, with TDO stuck low.
+        tdo_stuck_low: bool,
+    }
+
+    impl SimulatedChain {
+        fn new(taps: Vec<SimulatedTap>) -> Self {
+            Self {
+                state: JtagDriverState::default(),
+                taps,
+                shift: BitVec::new(),
+                captured: BitVec::new(),
+                tdo_stuck_low: false,
+            }
+        }
+    }
+
+    impl RawJtagIo for SimulatedChain {
+        fn state_mut(&mut self) -> &mut JtagDriverState {
+            &mut self.state
+        }
+
+        fn state(&self) -> &JtagDriverState {
+            &self.state
+        }
+
+        fn shift_bit(
+            &mut self,
+            tms: bool,
+            tdi: bool,
+            capture: bool,
+        ) -> Result<(), DebugProbeError> {
+            let mut tdo = false;
+            if matches!(
+                self.state.state,
+                JtagState::Ir(RegisterState::Shift) | JtagState::Dr(RegisterState::Shift)
+            ) {
+                tdo = self.shift.remove(0);
+                self.shift.push(tdi);
+            }
+            if capture {
+                self.captured.push(tdo && !self.tdo_stuck_low);
+            }
+
+            self.state.state.update(tms);
+
+            match self.state.state {
+                JtagState::Reset => self.taps.iter_mut().for_each(|tap| tap.ir = None),
+                JtagState::Ir(RegisterState::Capture) => {
+                    self.shift.clear();
+                    for tap in &self.taps {
+                        let bits = tap.ir_capture.view_bits::<Lsb0>();
+                        self.shift.extend_from_bitslice(&bits[..tap.ir_len]);
+                    }
+                }
+                JtagState::Dr(RegisterState::Capture) => {
+                    self.shift.clear();
+                    for tap in &self.taps {
+                        match (tap.ir, tap.idcode) {
+                            (None, Some(idcode)) => self
+                                .shift
+                                .extend_from_bitslice(idcode.0.view_bits::<Lsb0>()),
+                            _ => self.shift.push(false),
+                        }
+                    }
+                }
+                JtagState::Ir(RegisterState::Update) => {
+                    let mut offset = 0;
+                    for tap in &mut self.taps {
+                        tap.ir = Some(self.shift[offset..offset + tap.ir_len].load_le());
+                        offset += tap.ir_len;
+                    }
+                }
+                _ => {}
+            }
+
+            Ok(())
+        }
+
+        fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, DebugProbeError> {
+            Ok(std::mem::take(&mut self.captured))
+        }
+    }
+
+    #[test]
+    fn discover_chain() {
+        let mut chain = SimulatedChain::new(vec![
+            SimulatedTap::new(4, Some(ARM_TAP)),
+            SimulatedTap::new(5, None),
+            SimulatedTap::new(5, Some(STM_BS_TAP)),
+        ]);
+
+        let taps = discover_scan_chain(&mut chain, None).unwrap();
+
+        assert_eq!(
+            taps.iter().map(|tap| tap.idcode).collect::<Vec<_>>(),
+            vec![Some(ARM_TAP), None, Some(STM_BS_TAP)]
+        );
+        assert_eq!(
+            taps.iter().map(|tap| tap.irlen).collect::<Vec<_>>(),
+            vec![4, 5, 5]
+        );
+    }
+
+    #[test]
+    fn discover_long_chain() {
+        let taps = (0..12)
+            .map(|_| SimulatedTap::new(4, Some(ARM_TAP)))
+            .collect();
+        let mut chain = SimulatedChain::new(taps);
+
+        let taps = discover_scan_chain(&mut chain, None).unwrap();
+
+        assert_eq!(taps.len(), 12);
+        assert!(taps.iter().all(|tap| tap.irlen == 4));
+    }
+
+    #[test]
+    fn discover_broken_chain() {
+        let mut chain = SimulatedChain::new(vec![SimulatedTap::new(4, Some(ARM_TAP))]);
+        chain.tdo_stuck_low = true;
+
+        let error = discover_scan_chain(&mut chain, None).unwrap_err();
+
+        assert!(matches!(
+            error,
+            DebugProbeError::JtagScanChain(ScanChainError::BrokenChain { name: "IR", .. })
+        ));
+    }
+
     #[test]
     fn reset_from_ir_shift() {
         let mut state = JtagState::Ir(RegisterState::Shift);
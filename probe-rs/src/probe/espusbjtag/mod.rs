@@ -221,4 +221,8 @@ impl DebugProbe for EspUsbJtag {
     fn has_xtensa_interface(&self) -> bool {
         true
     }
+
+    fn try_as_jtag_access(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
 }
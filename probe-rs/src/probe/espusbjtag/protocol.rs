@@ -89,7 +89,7 @@ impl ProtocolHandler {
         let device = nusb::list_devices()
             .map_err(ProbeCreationError::Usb)?
             .filter(is_espjtag_device)
-            .find(|device| selector.matches(device))
+            .find(|device| selector.matches_device(device))
             .ok_or(ProbeCreationError::NotFound)?;
 
         let device_handle = device.open().map_err(ProbeCreationError::Usb)?;
@@ -0,0 +1,494 @@
+//! An instruction level emulator for ARMv6-M and ARMv7-M cores.
+//!
+//! The [`EmulatedCore`] can be placed behind a [`FakeProbe`](super::FakeProbe) using
+//! [`FakeProbe::with_emulated_core`](super::FakeProbe::with_emulated_core). Unlike the mocked
+//! core, it actually executes code, which makes it possible to run flash algorithms and other
+//! code loaded by probe-rs without any hardware attached.
+//!
+//! The core is controlled through the usual debug registers (`DHCSR`, `DCRSR`, `DCRDR`, `DEMCR`,
+//! `DFSR` and `AIRCR`), so the regular Cortex-M implementation of probe-rs can halt, step, run and
+//! reset it. Since there is no real time, the core only executes instructions when the debugger
+//! polls `DHCSR` while the core is running.
+//!
+//! Flash memory can be emulated with an [`Nvmc`].
+
+mod nvmc;
+mod thumb;
+
+pub use nvmc::Nvmc;
+pub use thumb::Fault;
+
+use std::collections::HashMap;
+
+use thumb::{Bus, Cpu, Step};
+
+use crate::{
+    architecture::arm::{
+        communication_interface::SwdSequence, dp::DpAddress, memory::ArmMemoryInterface, ArmError,
+        ArmProbeInterface, DapAccess, FullyQualifiedApAddress,
+    },
+    probe::DebugProbeError,
+    CoreType, MemoryInterface,
+};
+
+/// Number of instructions executed each time the debugger polls a running core.
+const INSTRUCTIONS_PER_POLL: usize = 100_000;
+
+const PAGE_SIZE: u32 = 0x1000;
+
+const CPUID: u32 = 0xE000_ED00;
+const AIRCR: u32 = 0xE000_ED0C;
+const DFSR: u32 = 0xE000_ED30;
+const DHCSR: u32 = 0xE000_EDF0;
+const DCRSR: u32 = 0xE000_EDF4;
+const DCRDR: u32 = 0xE000_EDF8;
+const DEMCR: u32 = 0xE000_EDFC;
+
+const DHCSR_KEY: u32 = 0xA05F;
+const DHCSR_C_DEBUGEN: u32 = 1 << 0;
+const DHCSR_C_HALT: u32 = 1 << 1;
+const DHCSR_C_STEP: u32 = 1 << 2;
+const DHCSR_S_REGRDY: u32 = 1 << 16;
+const DHCSR_S_HALT: u32 = 1 << 17;
+const DHCSR_S_LOCKUP: u32 = 1 << 19;
+const DHCSR_S_RESET_ST: u32 = 1 << 25;
+
+const DFSR_HALTED: u32 = 1 << 0;
+const DFSR_BKPT: u32 = 1 << 1;
+const DFSR_VCATCH: u32 = 1 << 3;
+
+const DEMCR_VC_CORERESET: u32 = 1 << 0;
+
+const AIRCR_VECTKEY: u32 = 0x05FA;
+const AIRCR_VECTRESET: u32 = 1 << 0;
+const AIRCR_SYSRESETREQ: u32 = 1 << 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Running,
+    Halted,
+    LockedUp,
+}
+
+/// Sparse memory, where untouched bytes read as zero, with an optional flash controller.
+#[derive(Debug, Default)]
+struct Memory {
+    pages: HashMap<u32, Box<[u8]>>,
+    nvmc: Option<Nvmc>,
+}
+
+impl Memory {
+    fn read_byte(&self, address: u32) -> u8 {
+        self.pages
+            .get(&(address / PAGE_SIZE))
+            .map(|page| page[(address % PAGE_SIZE) as usize])
+            .unwrap_or(0)
+    }
+
+    fn write_byte(&mut self, address: u32, value: u8) {
+        let page = self
+            .pages
+            .entry(address / PAGE_SIZE)
+            .or_insert_with(|| vec![0; PAGE_SIZE as usize].into_boxed_slice());
+        page[(address % PAGE_SIZE) as usize] = value;
+    }
+}
+
+impl Bus for Memory {
+    fn read(&mut self, address: u32, size: u32) -> Result<u32, Fault> {
+        if let Some(value) = self.nvmc.as_ref().and_then(|nvmc| nvmc.read(address, size)) {
+            return Ok(value);
+        }
+
+        Ok((0..size).fold(0, |value, i| {
+            value | (self.read_byte(address.wrapping_add(i)) as u32) << (8 * i)
+        }))
+    }
+
+    fn write(&mut self, address: u32, size: u32, value: u32) -> Result<(), Fault> {
+        if let Some(nvmc) = self.nvmc.as_mut() {
+            if nvmc.write(address, size, value) {
+                return Ok(());
+            }
+        }
+
+        for i in 0..size {
+            self.write_byte(address.wrapping_add(i), (value >> (8 * i)) as u8);
+        }
+
+        Ok(())
+    }
+}
+
+/// An emulated ARMv6-M or ARMv7-M core, together with its memory.
+#[derive(Debug)]
+pub struct EmulatedCore {
+    cpu: Cpu,
+    memory: Memory,
+    state: State,
+    cpuid: u32,
+
+    /// The control bits of DHCSR.
+    dhcsr: u32,
+    reset_st: bool,
+    dfsr: u32,
+    demcr: u32,
+    dcrdr: u32,
+}
+
+impl EmulatedCore {
+    /// Creates a halted core of the given type, with empty memory.
+    ///
+    /// Panics if the core type is not ARMv6-M, ARMv7-M or ARMv7E-M.
+    pub fn new(core_type: CoreType) -> Self {
+        let (armv7, cpuid) = match core_type {
+            CoreType::Armv6m => (false, 0x410C_C200),
+            CoreType::Armv7m | CoreType::Armv7em => (true, 0x410F_C241),
+            other => panic!("The emulator does not support {other:?} cores"),
+        };
+
+        Self {
+            cpu: Cpu::new(armv7),
+            memory: Memory::default(),
+            state: State::Halted,
+            cpuid,
+            dhcsr: DHCSR_C_DEBUGEN | DHCSR_C_HALT,
+            reset_st: false,
+            dfsr: 0,
+            demcr: 0,
+            dcrdr: 0,
+        }
+    }
+
+    /// Adds a flash controller to the core.
+    pub fn with_nvmc(mut self, nvmc: Nvmc) -> Self {
+        self.memory.nvmc = Some(nvmc);
+        self
+    }
+
+    /// Copies `data` into memory at `address`.
+    ///
+    /// Flash is written directly, without having to be erased or enabled for writing first.
+    pub fn load(&mut self, address: u32, data: &[u8]) {
+        for (i, byte) in data.iter().enumerate() {
+            let address = address.wrapping_add(i as u32);
+            let in_flash = self
+                .memory
+                .nvmc
+                .as_mut()
+                .is_some_and(|nvmc| nvmc.load_byte(address, *byte));
+            if !in_flash {
+                self.memory.write_byte(address, *byte);
+            }
+        }
+    }
+
+    /// The flash controller, if any.
+    pub fn nvmc(&self) -> Option<&Nvmc> {
+        self.memory.nvmc.as_ref()
+    }
+
+    /// Reads a core register, using the register numbering of `DCRSR.REGSEL`.
+    ///
+    /// This can be used to inspect the results of code run on the core.
+    pub fn register(&self, regsel: u32) -> u32 {
+        let cpu = &self.cpu;
+        match regsel {
+            0..=15 => cpu.regs[regsel as usize],
+            16 => cpu.xpsr(),
+            17 => cpu.regs[13],
+            18 => cpu.psp,
+            20 => {
+                ((cpu.control as u32) << 24)
+                    | ((cpu.faultmask as u32) << 16)
+                    | ((cpu.basepri as u32) << 8)
+                    | cpu.primask as u32
+            }
+            _ => 0,
+        }
+    }
+
+    /// Writes a core register, using the register numbering of `DCRSR.REGSEL`.
+    pub fn set_register(&mut self, regsel: u32, value: u32) {
+        let cpu = &mut self.cpu;
+        match regsel {
+            0..=12 | 14 => cpu.regs[regsel as usize] = value,
+            13 | 17 => cpu.regs[13] = value & !0b11,
+            15 => cpu.set_pc(value),
+            16 => cpu.set_xpsr(value),
+            18 => cpu.psp = value & !0b11,
+            20 => {
+                cpu.primask = value & 1 != 0;
+                cpu.basepri = (value >> 8) as u8;
+                cpu.faultmask = (value >> 16) & 1 != 0;
+                cpu.control = ((value >> 24) & 0b11) as u8;
+            }
+            _ => {}
+        }
+    }
+
+    /// Resets the core, loading the stack pointer and program counter from the vector table at address 0.
+    fn reset(&mut self) {
+        let stack_pointer = self.memory.read(0, 4).unwrap_or_default();
+        let reset_vector = self.memory.read(4, 4).unwrap_or_default();
+        self.cpu.reset(stack_pointer, reset_vector);
+        self.reset_st = true;
+
+        if self.dhcsr & DHCSR_C_DEBUGEN != 0 && self.demcr & DEMCR_VC_CORERESET != 0 {
+            self.dfsr |= DFSR_VCATCH;
+            self.state = State::Halted;
+        } else if self.dhcsr & DHCSR_C_DEBUGEN != 0 && self.dhcsr & DHCSR_C_HALT != 0 {
+            self.state = State::Halted;
+        } else {
+            self.state = State::Running;
+        }
+    }
+
+    /// Executes a single instruction, halting or locking up the core if needed.
+    ///
+    /// Returns `true` if the core is still running.
+    fn execute(&mut self) -> bool {
+        match self.cpu.step(&mut self.memory) {
+            Ok(Step::Executed) => true,
+            Ok(Step::Breakpoint(_)) if self.dhcsr & DHCSR_C_DEBUGEN != 0 => {
+                self.dfsr |= DFSR_BKPT;
+                self.state = State::Halted;
+                false
+            }
+            Ok(Step::Breakpoint(_)) => self.lock_up(Fault::UndefinedInstruction {
+                address: self.cpu.pc(),
+                instruction: 0xBE00,
+            }),
+            Err(fault) => self.lock_up(fault),
+        }
+    }
+
+    fn lock_up(&mut self, fault: Fault) -> bool {
+        // There is no exception handling, so every fault is treated as unrecoverable.
+        tracing::warn!("Emulated core locked up: {fault}");
+        self.state = State::LockedUp;
+        false
+    }
+
+    fn run_slice(&mut self) {
+        for _ in 0..INSTRUCTIONS_PER_POLL {
+            if self.state != State::Running || !self.execute() {
+                break;
+            }
+        }
+    }
+
+    fn read_dhcsr(&mut self) -> u32 {
+        if self.state == State::Running {
+            self.run_slice();
+        }
+
+        let mut value = self.dhcsr | DHCSR_S_REGRDY;
+        match self.state {
+            State::Halted => value |= DHCSR_S_HALT,
+            State::LockedUp => value |= DHCSR_S_LOCKUP,
+            State::Running => {}
+        }
+        if std::mem::take(&mut self.reset_st) {
+            value |= DHCSR_S_RESET_ST;
+        }
+
+        value
+    }
+
+    fn write_dhcsr(&mut self, value: u32) {
+        if value >> 16 != DHCSR_KEY {
+            return;
+        }
+
+        self.dhcsr = value & 0b1111;
+
+        if self.dhcsr & DHCSR_C_DEBUGEN == 0 {
+            if self.state == State::Halted {
+                self.state = State::Running;
+            }
+        } else if self.dhcsr & DHCSR_C_HALT != 0 {
+            if self.state != State::Halted {
+                self.dfsr |= DFSR_HALTED;
+                self.state = State::Halted;
+            }
+        } else if self.dhcsr & DHCSR_C_STEP != 0 {
+            if self.state == State::Halted && self.execute() {
+                self.dfsr |= DFSR_HALTED;
+            }
+        } else if self.state == State::Halted {
+            self.state = State::Running;
+        }
+    }
+
+    fn write_dcrsr(&mut self, value: u32) {
+        let regsel = value & 0x7F;
+        if value & (1 << 16) != 0 {
+            self.set_register(regsel, self.dcrdr);
+        } else {
+            self.dcrdr = self.register(regsel);
+        }
+    }
+
+    fn write_aircr(&mut self, value: u32) {
+        if value >> 16 == AIRCR_VECTKEY && value & (AIRCR_SYSRESETREQ | AIRCR_VECTRESET) != 0 {
+            self.reset();
+        }
+    }
+
+    fn debug_read(&mut self, address: u64, size: u32) -> Result<u32, ArmError> {
+        let address = u32::try_from(address).map_err(|_| ArmError::OutOfBounds)?;
+
+        let value = match address & !0b11 {
+            CPUID => self.cpuid,
+            DFSR => self.dfsr,
+            DHCSR => self.read_dhcsr(),
+            DCRSR => 0,
+            DCRDR => self.dcrdr,
+            DEMCR => self.demcr,
+            _ => return Ok(self.memory.read(address, size).unwrap_or_default()),
+        };
+
+        Ok(value >> (8 * (address % 4)))
+    }
+
+    fn debug_write(&mut self, address: u64, size: u32, value: u32) -> Result<(), ArmError> {
+        let address = u32::try_from(address).map_err(|_| ArmError::OutOfBounds)?;
+
+        match address {
+            AIRCR => self.write_aircr(value),
+            DFSR => self.dfsr &= !value,
+            DHCSR => self.write_dhcsr(value),
+            DCRSR => self.write_dcrsr(value),
+            DCRDR => self.dcrdr = value,
+            DEMCR => self.demcr = value,
+            _ => self.memory.write(address, size, value).unwrap_or_default(),
+        }
+
+        Ok(())
+    }
+}
+
+impl SwdSequence for &mut EmulatedCore {
+    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "swj_sequence",
+        })
+    }
+
+    fn swj_pins(
+        &mut self,
+        _pin_out: u32,
+        _pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "swj_pins",
+        })
+    }
+}
+
+impl MemoryInterface<ArmError> for &mut EmulatedCore {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        for (i, word) in data.iter_mut().enumerate() {
+            let address = address + 8 * i as u64;
+            let low = self.debug_read(address, 4)? as u64;
+            let high = self.debug_read(address + 4, 4)? as u64;
+            *word = (high << 32) | low;
+        }
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        for (i, word) in data.iter_mut().enumerate() {
+            *word = self.debug_read(address + 4 * i as u64, 4)?;
+        }
+        Ok(())
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        for (i, halfword) in data.iter_mut().enumerate() {
+            *halfword = self.debug_read(address + 2 * i as u64, 2)? as u16;
+        }
+        Ok(())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = self.debug_read(address + i as u64, 1)? as u8;
+        }
+        Ok(())
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        for (i, word) in data.iter().enumerate() {
+            let address = address + 8 * i as u64;
+            self.debug_write(address, 4, *word as u32)?;
+            self.debug_write(address + 4, 4, (*word >> 32) as u32)?;
+        }
+        Ok(())
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        for (i, word) in data.iter().enumerate() {
+            self.debug_write(address + 4 * i as u64, 4, *word)?;
+        }
+        Ok(())
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+        for (i, halfword) in data.iter().enumerate() {
+            self.debug_write(address + 2 * i as u64, 2, *halfword as u32)?;
+        }
+        Ok(())
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        for (i, byte) in data.iter().enumerate() {
+            self.debug_write(address + i as u64, 1, *byte as u32)?;
+        }
+        Ok(())
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+}
+
+impl ArmMemoryInterface for &mut EmulatedCore {
+    fn fully_qualified_address(&self) -> FullyQualifiedApAddress {
+        FullyQualifiedApAddress::v1_with_dp(DpAddress::Default, 0)
+    }
+
+    fn base_address(&mut self) -> Result<u64, ArmError> {
+        Ok(0)
+    }
+
+    fn get_swd_sequence(&mut self) -> Result<&mut dyn SwdSequence, DebugProbeError> {
+        Ok(self)
+    }
+
+    fn get_arm_probe_interface(&mut self) -> Result<&mut dyn ArmProbeInterface, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "get_arm_probe_interface",
+        })
+    }
+
+    fn get_dap_access(&mut self) -> Result<&mut dyn DapAccess, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "get_dap_access",
+        })
+    }
+
+    fn generic_status(&mut self) -> Result<crate::architecture::arm::memory::Status, ArmError> {
+        Err(ArmError::NotImplemented("generic_status"))
+    }
+}
@@ -0,0 +1,202 @@
+//! A simple non-volatile memory controller, modelled after the NVMC of the nRF51 and nRF52 series.
+
+use std::ops::Range;
+
+/// Emulated flash memory, together with the registers used to erase and program it.
+///
+/// The registers follow the layout of the nRF51 and nRF52 NVMC, which is simple enough that
+/// flash algorithms written against it run unmodified. The size of the flash is reported
+/// through the `CODEPAGESIZE` and `CODESIZE` registers of the FICR, so that algorithms which
+/// query them see the configured geometry.
+///
+/// Erased flash reads as `0xFF`. Programming can only clear bits, and is only possible
+/// after enabling writes through the `CONFIG` register. This applies both to the core and
+/// to the debugger.
+#[derive(Debug, Clone)]
+pub struct Nvmc {
+    flash: Range<u32>,
+    page_size: u32,
+    register_base: u32,
+    ficr_base: u32,
+    config: u32,
+    contents: Vec<u8>,
+}
+
+const READY: u32 = 0x400;
+const READYNEXT: u32 = 0x408;
+const CONFIG: u32 = 0x504;
+const ERASEPAGE: u32 = 0x508;
+const ERASEALL: u32 = 0x50C;
+const ERASEPCR0: u32 = 0x510;
+const ERASEUICR: u32 = 0x514;
+
+const CODEPAGESIZE: u32 = 0x010;
+const CODESIZE: u32 = 0x014;
+
+const CONFIG_WEN: u32 = 0b01;
+const CONFIG_EEN: u32 = 0b10;
+
+impl Nvmc {
+    /// Base address of the NVMC registers on the nRF51 and nRF52.
+    pub const DEFAULT_REGISTER_BASE: u32 = 0x4001_E000;
+
+    /// Base address of the FICR on the nRF51 and nRF52.
+    pub const DEFAULT_FICR_BASE: u32 = 0x1000_0000;
+
+    /// Creates a controller for the given flash range, which has to consist of whole pages.
+    pub fn new(flash: Range<u32>, page_size: u32) -> Self {
+        assert!(page_size > 0, "The page size must not be zero");
+        assert_eq!(
+            (flash.end - flash.start) % page_size,
+            0,
+            "The flash range must consist of whole pages"
+        );
+
+        Self {
+            contents: vec![0xFF; (flash.end - flash.start) as usize],
+            flash,
+            page_size,
+            register_base: Self::DEFAULT_REGISTER_BASE,
+            ficr_base: Self::DEFAULT_FICR_BASE,
+            config: 0,
+        }
+    }
+
+    /// Places the NVMC registers at a different base address.
+    pub fn with_register_base(mut self, register_base: u32) -> Self {
+        self.register_base = register_base;
+        self
+    }
+
+    /// Places the FICR at a different base address.
+    pub fn with_ficr_base(mut self, ficr_base: u32) -> Self {
+        self.ficr_base = ficr_base;
+        self
+    }
+
+    /// The address range of the flash.
+    pub fn range(&self) -> Range<u32> {
+        self.flash.clone()
+    }
+
+    /// The current contents of the flash.
+    pub fn contents(&self) -> &[u8] {
+        &self.contents
+    }
+
+    /// Overwrites a byte of flash, returning `false` if the address is not part of the flash.
+    pub(crate) fn load_byte(&mut self, address: u32, value: u8) -> bool {
+        if !self.flash.contains(&address) {
+            return false;
+        }
+
+        self.contents[(address - self.flash.start) as usize] = value;
+        true
+    }
+
+    /// Reads `size` bytes, returning `None` if the address does not belong to the controller.
+    pub(crate) fn read(&self, address: u32, size: u32) -> Option<u32> {
+        if self.flash.contains(&address) {
+            let start = (address - self.flash.start) as usize;
+            let bytes = self.contents.get(start..start + size as usize)?;
+            return Some(
+                bytes
+                    .iter()
+                    .rev()
+                    .fold(0, |value, byte| (value << 8) | *byte as u32),
+            );
+        }
+
+        let register = self.register(address & !0b11)?;
+        Some(register >> (8 * (address % 4)))
+    }
+
+    /// Writes the lower `size` bytes of `value`, returning `false` if the address does not
+    /// belong to the controller.
+    pub(crate) fn write(&mut self, address: u32, size: u32, value: u32) -> bool {
+        if self.flash.contains(&address) {
+            if self.config & CONFIG_WEN == 0 {
+                tracing::warn!(
+                    "Ignoring write to flash at {address:#010x}, writes are not enabled"
+                );
+                return true;
+            }
+
+            let start = (address - self.flash.start) as usize;
+            for (i, byte) in self.contents[start..]
+                .iter_mut()
+                .take(size as usize)
+                .enumerate()
+            {
+                *byte &= (value >> (8 * i)) as u8;
+            }
+            return true;
+        }
+
+        let aligned = address & !0b11;
+        let Some(current) = self.register(aligned) else {
+            return false;
+        };
+
+        // Narrow writes only replace the written bytes of the register.
+        let shift = 8 * (address % 4);
+        let mask = if size >= 4 {
+            u32::MAX
+        } else {
+            ((1 << (8 * size)) - 1) << shift
+        };
+        self.write_register(aligned, (current & !mask) | ((value << shift) & mask));
+
+        true
+    }
+
+    fn register(&self, aligned: u32) -> Option<u32> {
+        if let Some(offset) = aligned.checked_sub(self.register_base) {
+            match offset {
+                READY | READYNEXT => return Some(1),
+                CONFIG => return Some(self.config),
+                ERASEPAGE | ERASEALL | ERASEPCR0 | ERASEUICR => return Some(0),
+                _ => {}
+            }
+        }
+
+        if let Some(offset) = aligned.checked_sub(self.ficr_base) {
+            match offset {
+                CODEPAGESIZE => return Some(self.page_size),
+                CODESIZE => return Some((self.flash.end - self.flash.start) / self.page_size),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn write_register(&mut self, address: u32, value: u32) {
+        let Some(offset) = address.checked_sub(self.register_base) else {
+            return;
+        };
+
+        match offset {
+            CONFIG => self.config = value & 0b11,
+            ERASEPAGE | ERASEPCR0 => {
+                if self.config & CONFIG_EEN == 0 {
+                    tracing::warn!("Ignoring page erase at {value:#010x}, erase is not enabled");
+                } else if !self.flash.contains(&value) {
+                    tracing::warn!("Ignoring erase of page {value:#010x} outside of flash");
+                } else {
+                    let start = (value - self.flash.start) / self.page_size * self.page_size;
+                    let start = start as usize;
+                    self.contents[start..start + self.page_size as usize].fill(0xFF);
+                }
+            }
+            ERASEALL if value & 1 != 0 => {
+                if self.config & CONFIG_EEN == 0 {
+                    tracing::warn!("Ignoring chip erase, erase is not enabled");
+                } else {
+                    self.contents.fill(0xFF);
+                }
+            }
+            _ => {}
+        }
+    }
+}
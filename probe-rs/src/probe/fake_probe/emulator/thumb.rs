@@ -0,0 +1,1656 @@
+//! Interpreter for the Thumb instruction set of ARMv6-M and ARMv7-M cores.
+//!
+//! All integer instructions of ARMv6-M and ARMv7-M are supported. Floating point,
+//! coprocessor and the DSP extension instructions of ARMv7E-M are not, and are
+//! reported as [`Fault::UndefinedInstruction`].
+
+/// Memory accessed by the emulated core.
+pub(crate) trait Bus {
+    /// Reads `size` bytes (1, 2 or 4) from `address`.
+    fn read(&mut self, address: u32, size: u32) -> Result<u32, Fault>;
+
+    /// Writes the lower `size` bytes (1, 2 or 4) of `value` to `address`.
+    fn write(&mut self, address: u32, size: u32, value: u32) -> Result<(), Fault>;
+}
+
+/// The reason why the emulated core stopped executing instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, docsplay::Display)]
+pub enum Fault {
+    /// The instruction {instruction:#010x} at {address:#010x} is undefined or not supported by the emulator.
+    UndefinedInstruction {
+        /// Address of the instruction.
+        address: u32,
+        /// The instruction. 16 bit instructions are stored in the lower half.
+        instruction: u32,
+    },
+
+    /// Accessing memory at {address:#010x} failed.
+    BusFault {
+        /// The accessed address.
+        address: u32,
+    },
+
+    /// Unaligned memory access at {address:#010x}.
+    UnalignedAccess {
+        /// The accessed address.
+        address: u32,
+    },
+
+    /// Tried to execute an instruction at {address:#010x} outside of Thumb state.
+    InvalidState {
+        /// Address of the instruction.
+        address: u32,
+    },
+
+    /// Supervisor call {imm} at {address:#010x}.
+    SupervisorCall {
+        /// Address of the instruction.
+        address: u32,
+        /// The immediate value of the SVC instruction.
+        imm: u8,
+    },
+}
+
+/// The outcome of executing a single instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// The instruction was executed.
+    Executed,
+    /// A `BKPT` instruction was encountered. The program counter still points to it.
+    Breakpoint(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Shift {
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+    Rrx,
+}
+
+fn decode_imm_shift(shift_type: u32, imm5: u32) -> (Shift, u32) {
+    match shift_type {
+        0b00 => (Shift::Lsl, imm5),
+        0b01 => (Shift::Lsr, if imm5 == 0 { 32 } else { imm5 }),
+        0b10 => (Shift::Asr, if imm5 == 0 { 32 } else { imm5 }),
+        _ if imm5 == 0 => (Shift::Rrx, 1),
+        _ => (Shift::Ror, imm5),
+    }
+}
+
+fn shift_c(value: u32, shift: Shift, amount: u32, carry_in: bool) -> (u32, bool) {
+    if amount == 0 {
+        return (value, carry_in);
+    }
+
+    match shift {
+        Shift::Lsl => match amount {
+            1..=31 => (value << amount, (value >> (32 - amount)) & 1 != 0),
+            32 => (0, value & 1 != 0),
+            _ => (0, false),
+        },
+        Shift::Lsr => match amount {
+            1..=31 => (value >> amount, (value >> (amount - 1)) & 1 != 0),
+            32 => (0, value >> 31 != 0),
+            _ => (0, false),
+        },
+        Shift::Asr => {
+            if amount >= 32 {
+                let sign = ((value as i32) >> 31) as u32;
+                (sign, sign != 0)
+            } else {
+                (
+                    ((value as i32) >> amount) as u32,
+                    (value >> (amount - 1)) & 1 != 0,
+                )
+            }
+        }
+        Shift::Ror => {
+            let result = value.rotate_right(amount % 32);
+            (result, result >> 31 != 0)
+        }
+        Shift::Rrx => ((value >> 1) | ((carry_in as u32) << 31), value & 1 != 0),
+    }
+}
+
+fn add_with_carry(x: u32, y: u32, carry_in: bool) -> (u32, bool, bool) {
+    let unsigned_sum = x as u64 + y as u64 + carry_in as u64;
+    let signed_sum = x as i32 as i64 + y as i32 as i64 + carry_in as i64;
+    let result = unsigned_sum as u32;
+
+    (
+        result,
+        result as u64 != unsigned_sum,
+        result as i32 as i64 != signed_sum,
+    )
+}
+
+fn thumb_expand_imm_c(imm12: u32, carry_in: bool) -> (u32, bool) {
+    if imm12 >> 10 == 0 {
+        let imm8 = imm12 & 0xff;
+        let value = match (imm12 >> 8) & 0b11 {
+            0b00 => imm8,
+            0b01 => (imm8 << 16) | imm8,
+            0b10 => (imm8 << 24) | (imm8 << 8),
+            _ => imm8 * 0x0101_0101,
+        };
+        (value, carry_in)
+    } else {
+        let value = (0x80 | (imm12 & 0x7f)).rotate_right(imm12 >> 7);
+        (value, value >> 31 != 0)
+    }
+}
+
+fn sign_extend(value: u32, bits: u32) -> u32 {
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as u32
+}
+
+fn bit(value: u32, n: u32) -> bool {
+    (value >> n) & 1 != 0
+}
+
+/// Registers and execution state of an emulated Cortex-M core.
+#[derive(Debug, Clone)]
+pub(crate) struct Cpu {
+    armv7: bool,
+
+    /// R0 to R15. R13 is the main stack pointer, R15 the address of the current instruction.
+    pub(crate) regs: [u32; 16],
+    pub(crate) psp: u32,
+
+    n: bool,
+    z: bool,
+    c: bool,
+    v: bool,
+    q: bool,
+    thumb: bool,
+    it_state: u8,
+
+    pub(crate) primask: bool,
+    pub(crate) faultmask: bool,
+    pub(crate) basepri: u8,
+    pub(crate) control: u8,
+
+    /// Address of the next instruction, updated by branches.
+    next_pc: u32,
+}
+
+impl Cpu {
+    pub(crate) fn new(armv7: bool) -> Self {
+        Self {
+            armv7,
+            regs: [0; 16],
+            psp: 0,
+            n: false,
+            z: false,
+            c: false,
+            v: false,
+            q: false,
+            thumb: true,
+            it_state: 0,
+            primask: false,
+            faultmask: false,
+            basepri: 0,
+            control: 0,
+            next_pc: 0,
+        }
+    }
+
+    /// Puts the core into its reset state, using the given initial stack pointer and reset vector.
+    pub(crate) fn reset(&mut self, stack_pointer: u32, reset_vector: u32) {
+        *self = Self::new(self.armv7);
+        self.regs[13] = stack_pointer & !0b11;
+        self.regs[14] = 0xffff_ffff;
+        self.regs[15] = reset_vector & !1;
+        self.thumb = reset_vector & 1 != 0;
+    }
+
+    pub(crate) fn pc(&self) -> u32 {
+        self.regs[15]
+    }
+
+    pub(crate) fn set_pc(&mut self, value: u32) {
+        self.regs[15] = value & !1;
+    }
+
+    pub(crate) fn xpsr(&self) -> u32 {
+        ((self.n as u32) << 31)
+            | ((self.z as u32) << 30)
+            | ((self.c as u32) << 29)
+            | ((self.v as u32) << 28)
+            | ((self.q as u32) << 27)
+            | (((self.it_state as u32) & 0b11) << 25)
+            | ((self.thumb as u32) << 24)
+            | (((self.it_state as u32) >> 2) << 10)
+    }
+
+    pub(crate) fn set_xpsr(&mut self, value: u32) {
+        self.set_apsr(value);
+        self.q = bit(value, 27);
+        self.thumb = bit(value, 24);
+        self.it_state = if self.armv7 {
+            (((value >> 25) & 0b11) | (((value >> 10) & 0x3f) << 2)) as u8
+        } else {
+            0
+        };
+    }
+
+    fn set_apsr(&mut self, value: u32) {
+        self.n = bit(value, 31);
+        self.z = bit(value, 30);
+        self.c = bit(value, 29);
+        self.v = bit(value, 28);
+    }
+
+    fn in_it_block(&self) -> bool {
+        self.it_state & 0xf != 0
+    }
+
+    fn advance_it(&mut self) {
+        if self.it_state & 0b111 == 0 {
+            self.it_state = 0;
+        } else {
+            self.it_state = (self.it_state & 0xe0) | ((self.it_state << 1) & 0x1f);
+        }
+    }
+
+    fn condition_passed(&self, condition: u32) -> bool {
+        let result = match condition >> 1 {
+            0b000 => self.z,
+            0b001 => self.c,
+            0b010 => self.n,
+            0b011 => self.v,
+            0b100 => self.c && !self.z,
+            0b101 => self.n == self.v,
+            0b110 => self.n == self.v && !self.z,
+            _ => true,
+        };
+
+        if condition & 1 == 1 && condition != 0b1111 {
+            !result
+        } else {
+            result
+        }
+    }
+
+    fn set_nz(&mut self, result: u32) {
+        self.n = result >> 31 != 0;
+        self.z = result == 0;
+    }
+
+    fn set_nzc(&mut self, result: u32, carry: bool) {
+        self.set_nz(result);
+        self.c = carry;
+    }
+
+    fn set_nzcv(&mut self, result: u32, carry: bool, overflow: bool) {
+        self.set_nzc(result, carry);
+        self.v = overflow;
+    }
+
+    /// Reads a register as seen by an instruction, where the PC reads as the
+    /// address of the current instruction plus 4.
+    fn reg(&self, n: u32) -> u32 {
+        if n == 15 {
+            self.regs[15].wrapping_add(4)
+        } else {
+            self.regs[n as usize]
+        }
+    }
+
+    /// The word-aligned PC value used for literal addressing.
+    fn aligned_pc(&self) -> u32 {
+        self.reg(15) & !0b11
+    }
+
+    fn set_reg(&mut self, n: u32, value: u32) {
+        match n {
+            15 => self.branch_write_pc(value),
+            13 => self.regs[13] = value & !0b11,
+            _ => self.regs[n as usize] = value,
+        }
+    }
+
+    fn branch_write_pc(&mut self, address: u32) {
+        self.next_pc = address & !1;
+    }
+
+    fn bx_write_pc(&mut self, address: u32) {
+        self.thumb = address & 1 != 0;
+        self.next_pc = address & !1;
+    }
+
+    /// Writes a value loaded from memory to a register, interworking on writes to the PC.
+    fn load_write_reg(&mut self, n: u32, value: u32) {
+        if n == 15 {
+            self.bx_write_pc(value);
+        } else {
+            self.set_reg(n, value);
+        }
+    }
+
+    fn undefined(&self, instruction: u32) -> Fault {
+        Fault::UndefinedInstruction {
+            address: self.regs[15],
+            instruction,
+        }
+    }
+
+    fn load(
+        &self,
+        bus: &mut dyn Bus,
+        address: u32,
+        size: u32,
+        require_alignment: bool,
+    ) -> Result<u32, Fault> {
+        if (require_alignment || !self.armv7) && address % size != 0 {
+            return Err(Fault::UnalignedAccess { address });
+        }
+        bus.read(address, size)
+    }
+
+    fn store(
+        &self,
+        bus: &mut dyn Bus,
+        address: u32,
+        size: u32,
+        value: u32,
+        require_alignment: bool,
+    ) -> Result<(), Fault> {
+        if (require_alignment || !self.armv7) && address % size != 0 {
+            return Err(Fault::UnalignedAccess { address });
+        }
+        bus.write(address, size, value)
+    }
+
+    /// Executes a single instruction.
+    pub(crate) fn step(&mut self, bus: &mut dyn Bus) -> Result<Step, Fault> {
+        let pc = self.regs[15];
+        if !self.thumb {
+            return Err(Fault::InvalidState { address: pc });
+        }
+        if pc % 2 != 0 {
+            return Err(Fault::UnalignedAccess { address: pc });
+        }
+
+        let hw1 = bus.read(pc, 2)?;
+        let is_32bit = matches!(hw1 >> 11, 0b11101..=0b11111);
+
+        if !is_32bit && hw1 & 0xff00 == 0xbe00 {
+            return Ok(Step::Breakpoint(hw1 as u8));
+        }
+
+        let (instruction, length) = if is_32bit {
+            ((hw1 << 16) | bus.read(pc.wrapping_add(2), 2)?, 4)
+        } else {
+            (hw1, 2)
+        };
+
+        self.next_pc = pc.wrapping_add(length);
+
+        // IT instructions set up the IT state themselves.
+        if !is_32bit && hw1 & 0xff00 == 0xbf00 && hw1 & 0xf != 0 {
+            if !self.armv7 || self.in_it_block() {
+                return Err(self.undefined(instruction));
+            }
+            self.it_state = hw1 as u8;
+            self.regs[15] = self.next_pc;
+            return Ok(Step::Executed);
+        }
+
+        let in_it_block = self.in_it_block();
+        let condition_passed = !in_it_block || self.condition_passed((self.it_state >> 4) as u32);
+
+        if condition_passed {
+            if is_32bit {
+                self.execute_32(bus, instruction)?;
+            } else {
+                self.execute_16(bus, instruction, in_it_block)?;
+            }
+        }
+
+        if in_it_block {
+            self.advance_it();
+        }
+
+        self.regs[15] = self.next_pc;
+
+        Ok(Step::Executed)
+    }
+
+    fn execute_16(&mut self, bus: &mut dyn Bus, hw: u32, in_it_block: bool) -> Result<(), Fault> {
+        let setflags = !in_it_block;
+        let low = |shift: u32| (hw >> shift) & 0b111;
+
+        match hw >> 11 {
+            // LSL, LSR, ASR (immediate), MOVS (register)
+            0b00000..=0b00010 => {
+                let (shift, amount) = decode_imm_shift(hw >> 11, (hw >> 6) & 0x1f);
+                let (result, carry) = shift_c(self.reg(low(3)), shift, amount, self.c);
+                self.set_reg(low(0), result);
+                if setflags {
+                    self.set_nzc(result, carry);
+                }
+            }
+            // ADD, SUB (register and 3-bit immediate)
+            0b00011 => {
+                let operand = if bit(hw, 10) {
+                    low(6)
+                } else {
+                    self.reg(low(6))
+                };
+                let (result, carry, overflow) = if bit(hw, 9) {
+                    add_with_carry(self.reg(low(3)), !operand, true)
+                } else {
+                    add_with_carry(self.reg(low(3)), operand, false)
+                };
+                self.set_reg(low(0), result);
+                if setflags {
+                    self.set_nzcv(result, carry, overflow);
+                }
+            }
+            // MOV, CMP, ADD, SUB (8-bit immediate)
+            0b00100..=0b00111 => {
+                let rdn = low(8);
+                let imm8 = hw & 0xff;
+                match (hw >> 11) & 0b11 {
+                    0b00 => {
+                        self.set_reg(rdn, imm8);
+                        if setflags {
+                            self.set_nz(imm8);
+                        }
+                    }
+                    0b01 => {
+                        let (result, carry, overflow) = add_with_carry(self.reg(rdn), !imm8, true);
+                        self.set_nzcv(result, carry, overflow);
+                    }
+                    op => {
+                        let (result, carry, overflow) = if op == 0b10 {
+                            add_with_carry(self.reg(rdn), imm8, false)
+                        } else {
+                            add_with_carry(self.reg(rdn), !imm8, true)
+                        };
+                        self.set_reg(rdn, result);
+                        if setflags {
+                            self.set_nzcv(result, carry, overflow);
+                        }
+                    }
+                }
+            }
+            0b01000 if !bit(hw, 10) => self.data_processing_16(hw, setflags),
+            0b01000 => self.special_data_16(hw)?,
+            // LDR (literal)
+            0b01001 => {
+                let address = self.aligned_pc().wrapping_add((hw & 0xff) << 2);
+                let value = self.load(bus, address, 4, false)?;
+                self.set_reg(low(8), value);
+            }
+            // Load/store (register offset)
+            0b01010 | 0b01011 => {
+                let address = self.reg(low(3)).wrapping_add(self.reg(low(6)));
+                let rt = low(0);
+                match (hw >> 9) & 0b111 {
+                    0b000 => self.store(bus, address, 4, self.reg(rt), false)?,
+                    0b001 => self.store(bus, address, 2, self.reg(rt), false)?,
+                    0b010 => self.store(bus, address, 1, self.reg(rt), false)?,
+                    0b011 => {
+                        let value = self.load(bus, address, 1, false)?;
+                        self.set_reg(rt, sign_extend(value, 8));
+                    }
+                    0b100 => {
+                        let value = self.load(bus, address, 4, false)?;
+                        self.set_reg(rt, value);
+                    }
+                    0b101 => {
+                        let value = self.load(bus, address, 2, false)?;
+                        self.set_reg(rt, value);
+                    }
+                    0b110 => {
+                        let value = self.load(bus, address, 1, false)?;
+                        self.set_reg(rt, value);
+                    }
+                    _ => {
+                        let value = self.load(bus, address, 2, false)?;
+                        self.set_reg(rt, sign_extend(value, 16));
+                    }
+                }
+            }
+            // Load/store (immediate offset)
+            0b01100..=0b10001 => {
+                let (size, is_load) = match hw >> 11 {
+                    0b01100 => (4, false),
+                    0b01101 => (4, true),
+                    0b01110 => (1, false),
+                    0b01111 => (1, true),
+                    0b10000 => (2, false),
+                    _ => (2, true),
+                };
+                let address = self.reg(low(3)).wrapping_add(((hw >> 6) & 0x1f) * size);
+                if is_load {
+                    let value = self.load(bus, address, size, false)?;
+                    self.set_reg(low(0), value);
+                } else {
+                    self.store(bus, address, size, self.reg(low(0)), false)?;
+                }
+            }
+            // STR, LDR (SP relative)
+            0b10010 | 0b10011 => {
+                let address = self.reg(13).wrapping_add((hw & 0xff) << 2);
+                if bit(hw, 11) {
+                    let value = self.load(bus, address, 4, false)?;
+                    self.set_reg(low(8), value);
+                } else {
+                    self.store(bus, address, 4, self.reg(low(8)), false)?;
+                }
+            }
+            // ADR
+            0b10100 => {
+                let value = self.aligned_pc().wrapping_add((hw & 0xff) << 2);
+                self.set_reg(low(8), value);
+            }
+            // ADD (SP plus immediate)
+            0b10101 => {
+                let value = self.reg(13).wrapping_add((hw & 0xff) << 2);
+                self.set_reg(low(8), value);
+            }
+            0b10110 | 0b10111 => self.miscellaneous_16(bus, hw)?,
+            // STM
+            0b11000 => {
+                let rn = low(8);
+                let mut address = self.reg(rn);
+                for register in (0..8).filter(|r| bit(hw, *r)) {
+                    self.store(bus, address, 4, self.reg(register), true)?;
+                    address = address.wrapping_add(4);
+                }
+                self.set_reg(rn, address);
+            }
+            // LDM
+            0b11001 => {
+                let rn = low(8);
+                let mut address = self.reg(rn);
+                for register in (0..8).filter(|r| bit(hw, *r)) {
+                    let value = self.load(bus, address, 4, true)?;
+                    self.set_reg(register, value);
+                    address = address.wrapping_add(4);
+                }
+                if !bit(hw, rn) {
+                    self.set_reg(rn, address);
+                }
+            }
+            // Conditional branch, UDF, SVC
+            0b11010 | 0b11011 => match (hw >> 8) & 0xf {
+                0b1110 => return Err(self.undefined(hw)),
+                0b1111 => {
+                    return Err(Fault::SupervisorCall {
+                        address: self.regs[15],
+                        imm: hw as u8,
+                    })
+                }
+                condition => {
+                    if self.condition_passed(condition) {
+                        let offset = sign_extend((hw & 0xff) << 1, 9);
+                        self.branch_write_pc(self.reg(15).wrapping_add(offset));
+                    }
+                }
+            },
+            // B (unconditional)
+            0b11100 => {
+                let offset = sign_extend((hw & 0x7ff) << 1, 12);
+                self.branch_write_pc(self.reg(15).wrapping_add(offset));
+            }
+            _ => return Err(self.undefined(hw)),
+        }
+
+        Ok(())
+    }
+
+    fn data_processing_16(&mut self, hw: u32, setflags: bool) {
+        let rdn = hw & 0b111;
+        let rm = (hw >> 3) & 0b111;
+        let a = self.reg(rdn);
+        let b = self.reg(rm);
+
+        let shift_by_register = |cpu: &mut Self, shift: Shift| {
+            let (result, carry) = shift_c(a, shift, b & 0xff, cpu.c);
+            cpu.set_reg(rdn, result);
+            if setflags {
+                cpu.set_nzc(result, carry);
+            }
+        };
+
+        match (hw >> 6) & 0xf {
+            // AND, EOR, ORR, BIC, MVN, TST
+            op @ (0b0000 | 0b0001 | 0b1100 | 0b1110 | 0b1111 | 0b1000) => {
+                let result = match op {
+                    0b0000 | 0b1000 => a & b,
+                    0b0001 => a ^ b,
+                    0b1100 => a | b,
+                    0b1110 => a & !b,
+                    _ => !b,
+                };
+                if op == 0b1000 {
+                    self.set_nz(result);
+                } else {
+                    self.set_reg(rdn, result);
+                    if setflags {
+                        self.set_nz(result);
+                    }
+                }
+            }
+            0b0010 => shift_by_register(self, Shift::Lsl),
+            0b0011 => shift_by_register(self, Shift::Lsr),
+            0b0100 => shift_by_register(self, Shift::Asr),
+            0b0111 => shift_by_register(self, Shift::Ror),
+            // ADC, SBC, RSB
+            op @ (0b0101 | 0b0110 | 0b1001) => {
+                let (result, carry, overflow) = match op {
+                    0b0101 => add_with_carry(a, b, self.c),
+                    0b0110 => add_with_carry(a, !b, self.c),
+                    _ => add_with_carry(!b, 0, true),
+                };
+                self.set_reg(rdn, result);
+                if setflags {
+                    self.set_nzcv(result, carry, overflow);
+                }
+            }
+            // CMP
+            0b1010 => {
+                let (result, carry, overflow) = add_with_carry(a, !b, true);
+                self.set_nzcv(result, carry, overflow);
+            }
+            // CMN
+            0b1011 => {
+                let (result, carry, overflow) = add_with_carry(a, b, false);
+                self.set_nzcv(result, carry, overflow);
+            }
+            // MUL
+            _ => {
+                let result = a.wrapping_mul(b);
+                self.set_reg(rdn, result);
+                if setflags {
+                    self.set_nz(result);
+                }
+            }
+        }
+    }
+
+    fn special_data_16(&mut self, hw: u32) -> Result<(), Fault> {
+        let rd = ((hw >> 4) & 0b1000) | (hw & 0b111);
+        let rm = (hw >> 3) & 0xf;
+
+        match (hw >> 8) & 0b11 {
+            // ADD (register)
+            0b00 => {
+                let result = self.reg(rd).wrapping_add(self.reg(rm));
+                self.set_reg(rd, result);
+            }
+            // CMP (register)
+            0b01 => {
+                let (result, carry, overflow) = add_with_carry(self.reg(rd), !self.reg(rm), true);
+                self.set_nzcv(result, carry, overflow);
+            }
+            // MOV (register)
+            0b10 => self.set_reg(rd, self.reg(rm)),
+            // BX, BLX
+            _ => {
+                let target = self.reg(rm);
+                if bit(hw, 7) {
+                    self.regs[14] = self.regs[15].wrapping_add(2) | 1;
+                }
+                self.bx_write_pc(target);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn miscellaneous_16(&mut self, bus: &mut dyn Bus, hw: u32) -> Result<(), Fault> {
+        let rd = hw & 0b111;
+        let rm = (hw >> 3) & 0b111;
+
+        if hw & 0xff00 == 0xb000 {
+            // ADD, SUB (SP plus immediate)
+            let offset = (hw & 0x7f) << 2;
+            let sp = self.reg(13);
+            let result = if bit(hw, 7) {
+                sp.wrapping_sub(offset)
+            } else {
+                sp.wrapping_add(offset)
+            };
+            self.set_reg(13, result);
+        } else if hw & 0xf500 == 0xb100 && self.armv7 {
+            // CBZ, CBNZ
+            let offset = (((hw >> 9) & 1) << 6) | (((hw >> 3) & 0x1f) << 1);
+            if (self.reg(rd) == 0) != bit(hw, 11) {
+                self.branch_write_pc(self.reg(15).wrapping_add(offset));
+            }
+        } else if hw & 0xff00 == 0xb200 {
+            // SXTH, SXTB, UXTH, UXTB
+            let value = self.reg(rm);
+            let result = match (hw >> 6) & 0b11 {
+                0b00 => sign_extend(value, 16),
+                0b01 => sign_extend(value, 8),
+                0b10 => value & 0xffff,
+                _ => value & 0xff,
+            };
+            self.set_reg(rd, result);
+        } else if hw & 0xfe00 == 0xb400 {
+            // PUSH
+            let mut registers: Vec<u32> = (0..8).filter(|r| bit(hw, *r)).collect();
+            if bit(hw, 8) {
+                registers.push(14);
+            }
+            let start = self.reg(13).wrapping_sub(4 * registers.len() as u32);
+            for (i, register) in registers.iter().enumerate() {
+                let address = start.wrapping_add(4 * i as u32);
+                self.store(bus, address, 4, self.reg(*register), true)?;
+            }
+            self.set_reg(13, start);
+        } else if hw & 0xffe8 == 0xb660 {
+            // CPS
+            let disable = bit(hw, 4);
+            if bit(hw, 1) {
+                self.primask = disable;
+            }
+            if bit(hw, 0) && self.armv7 {
+                self.faultmask = disable;
+            }
+        } else if hw & 0xff00 == 0xba00 && (hw >> 6) & 0b11 != 0b10 {
+            // REV, REV16, REVSH
+            let value = self.reg(rm);
+            let result = match (hw >> 6) & 0b11 {
+                0b00 => value.swap_bytes(),
+                0b01 => ((value & 0x00ff_00ff) << 8) | ((value >> 8) & 0x00ff_00ff),
+                _ => sign_extend((value as u16).swap_bytes() as u32, 16),
+            };
+            self.set_reg(rd, result);
+        } else if hw & 0xfe00 == 0xbc00 {
+            // POP
+            let mut address = self.reg(13);
+            let mut registers: Vec<u32> = (0..8).filter(|r| bit(hw, *r)).collect();
+            if bit(hw, 8) {
+                registers.push(15);
+            }
+            let mut values = Vec::with_capacity(registers.len());
+            for _ in &registers {
+                values.push(self.load(bus, address, 4, true)?);
+                address = address.wrapping_add(4);
+            }
+            self.set_reg(13, address);
+            for (register, value) in registers.into_iter().zip(values) {
+                self.load_write_reg(register, value);
+            }
+        } else if hw & 0xff0f == 0xbf00 && (hw >> 4) & 0xf <= 0b0100 {
+            // NOP, YIELD, WFE, WFI, SEV: there is nothing to wait for.
+        } else {
+            return Err(self.undefined(hw));
+        }
+
+        Ok(())
+    }
+
+    fn execute_32(&mut self, bus: &mut dyn Bus, instruction: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let op1 = (hw1 >> 11) & 0b11;
+        let op2 = (hw1 >> 4) & 0x7f;
+
+        if !self.armv7 {
+            // ARMv6-M only supports BL, MSR, MRS and the barriers as 32-bit instructions.
+            if op1 != 0b10 || !bit(hw2, 15) {
+                return Err(self.undefined(instruction));
+            }
+            return self.branches_and_control(instruction);
+        }
+
+        match op1 {
+            0b01 if op2 & 0b110_0100 == 0 => self.load_store_multiple(bus, instruction),
+            0b01 if op2 & 0b110_0100 == 0b000_0100 => {
+                self.load_store_dual_exclusive(bus, instruction)
+            }
+            0b01 if op2 & 0b110_0000 == 0b010_0000 => {
+                self.data_processing_shifted_register(instruction)
+            }
+            0b10 if bit(hw2, 15) => self.branches_and_control(instruction),
+            0b10 if !bit(hw1, 9) => self.data_processing_modified_immediate(instruction),
+            0b10 => self.data_processing_plain_immediate(instruction),
+            0b11 if op2 & 0b111_0001 == 0 => self.store_single(bus, instruction),
+            0b11 if op2 & 0b110_0111 == 0b000_0001 => self.load_single(bus, instruction, 1),
+            0b11 if op2 & 0b110_0111 == 0b000_0011 => self.load_single(bus, instruction, 2),
+            0b11 if op2 & 0b110_0111 == 0b000_0101 => self.load_single(bus, instruction, 4),
+            0b11 if op2 & 0b111_0000 == 0b010_0000 => self.data_processing_register(instruction),
+            0b11 if op2 & 0b111_1000 == 0b011_0000 => self.multiply(instruction),
+            0b11 if op2 & 0b111_1000 == 0b011_1000 => self.long_multiply_divide(instruction),
+            _ => Err(self.undefined(instruction)),
+        }
+    }
+
+    fn load_store_multiple(&mut self, bus: &mut dyn Bus, instruction: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let rn = hw1 & 0xf;
+        let writeback = bit(hw1, 5);
+        let is_load = bit(hw1, 4);
+        let registers: Vec<u32> = (0..16).filter(|r| bit(instruction, *r)).collect();
+        let size = 4 * registers.len() as u32;
+
+        let base = self.reg(rn);
+        let (start, end) = match (hw1 >> 7) & 0b11 {
+            // Increment after
+            0b01 => (base, base.wrapping_add(size)),
+            // Decrement before
+            0b10 => (base.wrapping_sub(size), base.wrapping_sub(size)),
+            _ => return Err(self.undefined(instruction)),
+        };
+
+        if is_load {
+            let mut values = Vec::with_capacity(registers.len());
+            for i in 0..registers.len() as u32 {
+                values.push(self.load(bus, start.wrapping_add(4 * i), 4, true)?);
+            }
+            if writeback && !registers.contains(&rn) {
+                self.set_reg(rn, end);
+            }
+            for (register, value) in registers.into_iter().zip(values) {
+                self.load_write_reg(register, value);
+            }
+        } else {
+            for (i, register) in registers.iter().enumerate() {
+                let address = start.wrapping_add(4 * i as u32);
+                self.store(bus, address, 4, self.reg(*register), true)?;
+            }
+            if writeback {
+                self.set_reg(rn, end);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_store_dual_exclusive(
+        &mut self,
+        bus: &mut dyn Bus,
+        instruction: u32,
+    ) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let op1 = (hw1 >> 7) & 0b11;
+        let op2 = (hw1 >> 4) & 0b11;
+        let op3 = (hw2 >> 4) & 0xf;
+        let rn = hw1 & 0xf;
+        let rt = hw2 >> 12;
+
+        match (op1, op2) {
+            // STREX
+            (0b00, 0b00) => {
+                let address = self.reg(rn).wrapping_add((hw2 & 0xff) << 2);
+                self.store(bus, address, 4, self.reg(rt), true)?;
+                self.set_reg((hw2 >> 8) & 0xf, 0);
+            }
+            // LDREX
+            (0b00, 0b01) => {
+                let address = self.reg(rn).wrapping_add((hw2 & 0xff) << 2);
+                let value = self.load(bus, address, 4, true)?;
+                self.set_reg(rt, value);
+            }
+            // STREXB, STREXH
+            (0b01, 0b00) if op3 == 0b0100 || op3 == 0b0101 => {
+                let size = if op3 == 0b0100 { 1 } else { 2 };
+                self.store(bus, self.reg(rn), size, self.reg(rt), true)?;
+                self.set_reg(hw2 & 0xf, 0);
+            }
+            // TBB, TBH
+            (0b01, 0b01) if op3 == 0b0000 || op3 == 0b0001 => {
+                let rm = self.reg(hw2 & 0xf);
+                let offset = if op3 == 0 {
+                    self.load(bus, self.reg(rn).wrapping_add(rm), 1, false)?
+                } else {
+                    self.load(bus, self.reg(rn).wrapping_add(rm << 1), 2, false)?
+                };
+                self.branch_write_pc(self.reg(15).wrapping_add(offset << 1));
+            }
+            // LDREXB, LDREXH
+            (0b01, 0b01) if op3 == 0b0100 || op3 == 0b0101 => {
+                let size = if op3 == 0b0100 { 1 } else { 2 };
+                let value = self.load(bus, self.reg(rn), size, true)?;
+                self.set_reg(rt, value);
+            }
+            // STRD, LDRD
+            _ if op1 & 0b10 != 0 || op2 & 0b10 != 0 => {
+                let index = bit(hw1, 8);
+                let add = bit(hw1, 7);
+                let writeback = bit(hw1, 5);
+                let offset = (hw2 & 0xff) << 2;
+                let rt2 = (hw2 >> 8) & 0xf;
+
+                let base = if rn == 15 {
+                    self.aligned_pc()
+                } else {
+                    self.reg(rn)
+                };
+                let offset_address = if add {
+                    base.wrapping_add(offset)
+                } else {
+                    base.wrapping_sub(offset)
+                };
+                let address = if index { offset_address } else { base };
+
+                if op2 & 1 != 0 {
+                    let first = self.load(bus, address, 4, true)?;
+                    let second = self.load(bus, address.wrapping_add(4), 4, true)?;
+                    self.set_reg(rt, first);
+                    self.set_reg(rt2, second);
+                } else {
+                    self.store(bus, address, 4, self.reg(rt), true)?;
+                    self.store(bus, address.wrapping_add(4), 4, self.reg(rt2), true)?;
+                }
+
+                if writeback {
+                    self.set_reg(rn, offset_address);
+                }
+            }
+            _ => return Err(self.undefined(instruction)),
+        }
+
+        Ok(())
+    }
+
+    /// Executes one of the data processing operations shared by the
+    /// register and immediate forms of the 32-bit instructions, which share
+    /// the location of the opcode and register fields.
+    fn data_processing(
+        &mut self,
+        instruction: u32,
+        operand: u32,
+        shifter_carry: bool,
+    ) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let op = (hw1 >> 5) & 0xf;
+        let setflags = bit(hw1, 4);
+        let rn = hw1 & 0xf;
+        let rd = (instruction >> 8) & 0xf;
+
+        // With Rd == PC and flags set, AND, EOR, ADD and SUB become TST, TEQ, CMN and CMP.
+        let is_test = rd == 15 && setflags;
+        let a = self.reg(rn);
+
+        let (result, carry, overflow, logical) = match op {
+            0b0000 => (a & operand, shifter_carry, self.v, true),
+            0b0001 if !is_test => (a & !operand, shifter_carry, self.v, true),
+            // ORR, or MOV if Rn == PC
+            0b0010 if !is_test => {
+                let a = if rn == 15 { 0 } else { a };
+                (a | operand, shifter_carry, self.v, true)
+            }
+            // ORN, or MVN if Rn == PC
+            0b0011 if !is_test => {
+                let a = if rn == 15 { 0 } else { a };
+                (a | !operand, shifter_carry, self.v, true)
+            }
+            0b0100 => (a ^ operand, shifter_carry, self.v, true),
+            0b1000 => {
+                let (result, carry, overflow) = add_with_carry(a, operand, false);
+                (result, carry, overflow, false)
+            }
+            0b1010 if !is_test => {
+                let (result, carry, overflow) = add_with_carry(a, operand, self.c);
+                (result, carry, overflow, false)
+            }
+            0b1011 if !is_test => {
+                let (result, carry, overflow) = add_with_carry(a, !operand, self.c);
+                (result, carry, overflow, false)
+            }
+            0b1101 => {
+                let (result, carry, overflow) = add_with_carry(a, !operand, true);
+                (result, carry, overflow, false)
+            }
+            0b1110 if !is_test => {
+                let (result, carry, overflow) = add_with_carry(!a, operand, true);
+                (result, carry, overflow, false)
+            }
+            _ => return Err(self.undefined(instruction)),
+        };
+
+        if !is_test {
+            self.set_reg(rd, result);
+        }
+
+        if setflags {
+            if logical {
+                self.set_nzc(result, carry);
+            } else {
+                self.set_nzcv(result, carry, overflow);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn data_processing_shifted_register(&mut self, instruction: u32) -> Result<(), Fault> {
+        let hw2 = instruction & 0xffff;
+        let imm5 = (((hw2 >> 12) & 0b111) << 2) | ((hw2 >> 6) & 0b11);
+        let (shift, amount) = decode_imm_shift((hw2 >> 4) & 0b11, imm5);
+        let (operand, carry) = shift_c(self.reg(hw2 & 0xf), shift, amount, self.c);
+
+        self.data_processing(instruction, operand, carry)
+    }
+
+    fn data_processing_modified_immediate(&mut self, instruction: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let imm12 = (((hw1 >> 10) & 1) << 11) | (((hw2 >> 12) & 0b111) << 8) | (hw2 & 0xff);
+        let (operand, carry) = thumb_expand_imm_c(imm12, self.c);
+
+        self.data_processing(instruction, operand, carry)
+    }
+
+    fn data_processing_plain_immediate(&mut self, instruction: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let rn = hw1 & 0xf;
+        let rd = (hw2 >> 8) & 0xf;
+        let imm12 = (((hw1 >> 10) & 1) << 11) | (((hw2 >> 12) & 0b111) << 8) | (hw2 & 0xff);
+        let imm16 = ((hw1 & 0xf) << 12) | imm12;
+        let lsb = (((hw2 >> 12) & 0b111) << 2) | ((hw2 >> 6) & 0b11);
+        let field = hw2 & 0x1f;
+
+        let result = match (hw1 >> 4) & 0x1f {
+            // ADDW, or ADR if Rn == PC
+            0b00000 if rn == 15 => self.aligned_pc().wrapping_add(imm12),
+            0b00000 => self.reg(rn).wrapping_add(imm12),
+            // MOVW
+            0b00100 => imm16,
+            // SUBW, or ADR if Rn == PC
+            0b01010 if rn == 15 => self.aligned_pc().wrapping_sub(imm12),
+            0b01010 => self.reg(rn).wrapping_sub(imm12),
+            // MOVT
+            0b01100 => (self.reg(rd) & 0xffff) | (imm16 << 16),
+            // SSAT, USAT
+            op @ (0b10000 | 0b10010 | 0b11000 | 0b11010) => {
+                let shift_type = if bit(hw1, 5) { 0b10 } else { 0b00 };
+                let (shift, amount) = decode_imm_shift(shift_type, lsb);
+                let (operand, _) = shift_c(self.reg(rn), shift, amount, self.c);
+                let operand = operand as i32 as i64;
+                let (min, max) = if op & 0b01000 == 0 {
+                    let bits = field + 1;
+                    (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1)
+                } else {
+                    (0, (1i64 << field) - 1)
+                };
+                if operand < min || operand > max {
+                    self.q = true;
+                }
+                operand.clamp(min, max) as u32
+            }
+            // SBFX, UBFX
+            op @ (0b10100 | 0b11100) => {
+                let width = field + 1;
+                if lsb + width > 32 {
+                    return Err(self.undefined(instruction));
+                }
+                let value = self.reg(rn) >> lsb;
+                if op == 0b10100 {
+                    sign_extend(value, width)
+                } else if width == 32 {
+                    value
+                } else {
+                    value & ((1 << width) - 1)
+                }
+            }
+            // BFI, or BFC if Rn == PC
+            0b10110 => {
+                let msb = field;
+                if msb < lsb {
+                    return Err(self.undefined(instruction));
+                }
+                let width = msb - lsb + 1;
+                let mask = if width == 32 {
+                    u32::MAX
+                } else {
+                    ((1 << width) - 1) << lsb
+                };
+                let source = if rn == 15 { 0 } else { self.reg(rn) << lsb };
+                (self.reg(rd) & !mask) | (source & mask)
+            }
+            _ => return Err(self.undefined(instruction)),
+        };
+
+        self.set_reg(rd, result);
+
+        Ok(())
+    }
+
+    fn branches_and_control(&mut self, instruction: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let s = (hw1 >> 10) & 1;
+        let j1 = (hw2 >> 13) & 1;
+        let j2 = (hw2 >> 11) & 1;
+
+        match (hw2 >> 12) & 0b101 {
+            // Conditional branch, and miscellaneous control instructions
+            0b000 => {
+                if (hw1 >> 7) & 0b111 != 0b111 {
+                    if !self.armv7 {
+                        return Err(self.undefined(instruction));
+                    }
+                    let offset = sign_extend(
+                        (s << 20)
+                            | (j2 << 19)
+                            | (j1 << 18)
+                            | ((hw1 & 0x3f) << 12)
+                            | ((hw2 & 0x7ff) << 1),
+                        21,
+                    );
+                    if self.condition_passed((hw1 >> 6) & 0xf) {
+                        self.branch_write_pc(self.reg(15).wrapping_add(offset));
+                    }
+                    return Ok(());
+                }
+
+                match (hw1 >> 4) & 0x7f {
+                    0b011_1000 | 0b011_1001 => self.msr(hw1 & 0xf, hw2),
+                    // Hints
+                    0b011_1010 if self.armv7 && hw2 & 0x7ff <= 0b100 => {}
+                    // DSB, DMB, ISB, and CLREX on ARMv7-M
+                    0b011_1011 if matches!((hw2 >> 4) & 0xf, 0b0100..=0b0110) => {}
+                    0b011_1011 if self.armv7 && (hw2 >> 4) & 0xf == 0b0010 => {}
+                    0b011_1110 | 0b011_1111 => {
+                        let value = self.mrs(hw2 & 0xff);
+                        self.set_reg((hw2 >> 8) & 0xf, value);
+                    }
+                    _ => return Err(self.undefined(instruction)),
+                }
+            }
+            // B (unconditional)
+            0b001 if self.armv7 => {
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let offset = sign_extend(
+                    (s << 24)
+                        | (i1 << 23)
+                        | (i2 << 22)
+                        | ((hw1 & 0x3ff) << 12)
+                        | ((hw2 & 0x7ff) << 1),
+                    25,
+                );
+                self.branch_write_pc(self.reg(15).wrapping_add(offset));
+            }
+            // BL
+            0b101 => {
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let offset = sign_extend(
+                    (s << 24)
+                        | (i1 << 23)
+                        | (i2 << 22)
+                        | ((hw1 & 0x3ff) << 12)
+                        | ((hw2 & 0x7ff) << 1),
+                    25,
+                );
+                self.regs[14] = self.next_pc | 1;
+                self.branch_write_pc(self.reg(15).wrapping_add(offset));
+            }
+            _ => return Err(self.undefined(instruction)),
+        }
+
+        Ok(())
+    }
+
+    fn mrs(&self, sysm: u32) -> u32 {
+        match sysm {
+            // APSR, IAPSR, EAPSR, XPSR. IPSR is always zero in thread mode, and the EPSR reads as zero.
+            0..=3 => self.xpsr() & 0xf800_0000,
+            8 => self.regs[13],
+            9 => self.psp,
+            16 => self.primask as u32,
+            17 | 18 => self.basepri as u32,
+            19 => self.faultmask as u32,
+            20 => self.control as u32,
+            _ => 0,
+        }
+    }
+
+    fn msr(&mut self, rn: u32, hw2: u32) {
+        let value = self.reg(rn);
+        match hw2 & 0xff {
+            0..=3 if bit(hw2, 11) => {
+                self.set_apsr(value);
+                if self.armv7 {
+                    self.q = bit(value, 27);
+                }
+            }
+            8 => self.set_reg(13, value),
+            9 => self.psp = value & !0b11,
+            16 => self.primask = bit(value, 0),
+            17 if self.armv7 => self.basepri = value as u8,
+            18 if self.armv7 => {
+                let value = value as u8;
+                if value != 0 && (value < self.basepri || self.basepri == 0) {
+                    self.basepri = value;
+                }
+            }
+            19 if self.armv7 => self.faultmask = bit(value, 0),
+            20 => self.control = (value & 0b11) as u8,
+            _ => {}
+        }
+    }
+
+    fn store_single(&mut self, bus: &mut dyn Bus, instruction: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let size = match (hw1 >> 5) & 0b11 {
+            0b00 => 1,
+            0b01 => 2,
+            0b10 => 4,
+            _ => return Err(self.undefined(instruction)),
+        };
+        let rn = hw1 & 0xf;
+        let rt = hw2 >> 12;
+
+        if rn == 15 {
+            return Err(self.undefined(instruction));
+        }
+
+        let (address, writeback) = self.single_address(instruction)?;
+        self.store(bus, address, size, self.reg(rt), false)?;
+        if let Some(address) = writeback {
+            self.set_reg(rn, address);
+        }
+
+        Ok(())
+    }
+
+    fn load_single(&mut self, bus: &mut dyn Bus, instruction: u32, size: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let signed = bit(hw1, 8);
+        let rn = hw1 & 0xf;
+        let rt = hw2 >> 12;
+
+        let (address, writeback) = if rn == 15 {
+            let offset = hw2 & 0xfff;
+            let address = if bit(hw1, 7) {
+                self.aligned_pc().wrapping_add(offset)
+            } else {
+                self.aligned_pc().wrapping_sub(offset)
+            };
+            (address, None)
+        } else {
+            self.single_address(instruction)?
+        };
+
+        // Byte and halfword loads to the PC are preload hints.
+        if rt == 15 && size != 4 {
+            return Ok(());
+        }
+
+        let value = self.load(bus, address, size, false)?;
+        let value = if signed {
+            sign_extend(value, size * 8)
+        } else {
+            value
+        };
+
+        if let Some(address) = writeback {
+            self.set_reg(rn, address);
+        }
+        self.load_write_reg(rt, value);
+
+        Ok(())
+    }
+
+    /// Computes the address of a single load or store with a base register,
+    /// and the value written back to the base register, if any.
+    fn single_address(&self, instruction: u32) -> Result<(u32, Option<u32>), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let base = self.reg(hw1 & 0xf);
+
+        if bit(hw1, 7) {
+            // 12-bit immediate offset
+            Ok((base.wrapping_add(hw2 & 0xfff), None))
+        } else if bit(hw2, 11) {
+            // 8-bit immediate with index, add and writeback bits
+            let index = bit(hw2, 10);
+            let add = bit(hw2, 9);
+            let writeback = bit(hw2, 8);
+            let offset = hw2 & 0xff;
+            let offset_address = if add {
+                base.wrapping_add(offset)
+            } else {
+                base.wrapping_sub(offset)
+            };
+            let address = if index { offset_address } else { base };
+            Ok((address, writeback.then_some(offset_address)))
+        } else if (hw2 >> 6) & 0x3f == 0 {
+            // Register offset
+            let offset = self.reg(hw2 & 0xf) << ((hw2 >> 4) & 0b11);
+            Ok((base.wrapping_add(offset), None))
+        } else {
+            Err(self.undefined(instruction))
+        }
+    }
+
+    fn data_processing_register(&mut self, instruction: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let op1 = (hw1 >> 4) & 0xf;
+        let op2 = (hw2 >> 4) & 0xf;
+        let rn = hw1 & 0xf;
+        let rd = (hw2 >> 8) & 0xf;
+        let rm = hw2 & 0xf;
+
+        if hw2 & 0xf000 != 0xf000 {
+            return Err(self.undefined(instruction));
+        }
+
+        match (op1, op2) {
+            // LSL, LSR, ASR, ROR (register)
+            (0b0000..=0b0111, 0b0000) => {
+                let shift = match op1 >> 1 {
+                    0b00 => Shift::Lsl,
+                    0b01 => Shift::Lsr,
+                    0b10 => Shift::Asr,
+                    _ => Shift::Ror,
+                };
+                let (result, carry) = shift_c(self.reg(rn), shift, self.reg(rm) & 0xff, self.c);
+                self.set_reg(rd, result);
+                if op1 & 1 != 0 {
+                    self.set_nzc(result, carry);
+                }
+            }
+            // SXTAH, UXTAH, SXTAB, UXTAB and the variants without addition if Rn == PC
+            (0b0000 | 0b0001 | 0b0100 | 0b0101, 0b1000..=0b1111) => {
+                let rotated = self.reg(rm).rotate_right(((hw2 >> 4) & 0b11) * 8);
+                let extended = match op1 {
+                    0b0000 => sign_extend(rotated, 16),
+                    0b0001 => rotated & 0xffff,
+                    0b0100 => sign_extend(rotated, 8),
+                    _ => rotated & 0xff,
+                };
+                let addend = if rn == 15 { 0 } else { self.reg(rn) };
+                self.set_reg(rd, addend.wrapping_add(extended));
+            }
+            // REV, REV16, RBIT, REVSH
+            (0b1001, 0b1000..=0b1011) => {
+                let value = self.reg(rm);
+                let result = match op2 & 0b11 {
+                    0b00 => value.swap_bytes(),
+                    0b01 => ((value & 0x00ff_00ff) << 8) | ((value >> 8) & 0x00ff_00ff),
+                    0b10 => value.reverse_bits(),
+                    _ => sign_extend((value as u16).swap_bytes() as u32, 16),
+                };
+                self.set_reg(rd, result);
+            }
+            // CLZ
+            (0b1011, 0b1000) => self.set_reg(rd, self.reg(rm).leading_zeros()),
+            _ => return Err(self.undefined(instruction)),
+        }
+
+        Ok(())
+    }
+
+    fn multiply(&mut self, instruction: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let rn = self.reg(hw1 & 0xf);
+        let rm = self.reg(hw2 & 0xf);
+        let ra = (hw2 >> 12) & 0xf;
+        let rd = (hw2 >> 8) & 0xf;
+
+        let product = rn.wrapping_mul(rm);
+        let result = match ((hw1 >> 4) & 0b111, (hw2 >> 4) & 0b11) {
+            (0b000, 0b00) if ra == 15 => product,
+            (0b000, 0b00) => self.reg(ra).wrapping_add(product),
+            (0b000, 0b01) => self.reg(ra).wrapping_sub(product),
+            _ => return Err(self.undefined(instruction)),
+        };
+
+        self.set_reg(rd, result);
+
+        Ok(())
+    }
+
+    fn long_multiply_divide(&mut self, instruction: u32) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xffff;
+        let rn = self.reg(hw1 & 0xf);
+        let rm = self.reg(hw2 & 0xf);
+        let rd_lo = (hw2 >> 12) & 0xf;
+        let rd_hi = (hw2 >> 8) & 0xf;
+        let accumulator = ((self.reg(rd_hi) as u64) << 32) | self.reg(rd_lo) as u64;
+
+        let result = match ((hw1 >> 4) & 0b111, (hw2 >> 4) & 0xf) {
+            // SMULL
+            (0b000, 0b0000) => (rn as i32 as i64).wrapping_mul(rm as i32 as i64) as u64,
+            // UMULL
+            (0b010, 0b0000) => (rn as u64) * (rm as u64),
+            // SMLAL
+            (0b100, 0b0000) => {
+                ((rn as i32 as i64).wrapping_mul(rm as i32 as i64) as u64).wrapping_add(accumulator)
+            }
+            // UMLAL
+            (0b110, 0b0000) => ((rn as u64) * (rm as u64)).wrapping_add(accumulator),
+            // SDIV. Division by zero returns zero, as division by zero trapping is disabled at reset.
+            (0b001, 0b1111) => {
+                let result = if rm == 0 {
+                    0
+                } else {
+                    (rn as i32).wrapping_div(rm as i32) as u32
+                };
+                self.set_reg(rd_hi, result);
+                return Ok(());
+            }
+            // UDIV
+            (0b011, 0b1111) => {
+                let result = rn.checked_div(rm).unwrap_or(0);
+                self.set_reg(rd_hi, result);
+                return Ok(());
+            }
+            _ => return Err(self.undefined(instruction)),
+        };
+
+        self.set_reg(rd_lo, result as u32);
+        self.set_reg(rd_hi, (result >> 32) as u32);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct TestMemory(HashMap<u32, u8>);
+
+    impl Bus for TestMemory {
+        fn read(&mut self, address: u32, size: u32) -> Result<u32, Fault> {
+            Ok((0..size).fold(0, |value, i| {
+                value | (*self.0.get(&(address + i)).unwrap_or(&0) as u32) << (8 * i)
+            }))
+        }
+
+        fn write(&mut self, address: u32, size: u32, value: u32) -> Result<(), Fault> {
+            for i in 0..size {
+                self.0.insert(address + i, (value >> (8 * i)) as u8);
+            }
+            Ok(())
+        }
+    }
+
+    /// Loads the given halfwords at address 0, and runs them until a breakpoint is hit.
+    fn run(armv7: bool, code: &[u16], setup: impl FnOnce(&mut Cpu)) -> (Cpu, TestMemory) {
+        let mut memory = TestMemory::default();
+        for (i, halfword) in code.iter().enumerate() {
+            memory.write(2 * i as u32, 2, *halfword as u32).unwrap();
+        }
+
+        let mut cpu = Cpu::new(armv7);
+        cpu.reset(0x2000_1000, 1);
+        setup(&mut cpu);
+
+        for _ in 0..1000 {
+            if cpu.step(&mut memory).unwrap() != Step::Executed {
+                return (cpu, memory);
+            }
+        }
+
+        panic!("No breakpoint reached");
+    }
+
+    #[test]
+    fn shift_with_carry() {
+        assert_eq!(shift_c(0x8000_0001, Shift::Lsl, 1, false), (2, true));
+        assert_eq!(
+            shift_c(0x8000_0001, Shift::Lsr, 1, false),
+            (0x4000_0000, true)
+        );
+        assert_eq!(
+            shift_c(0x8000_0000, Shift::Asr, 32, false),
+            (u32::MAX, true)
+        );
+        assert_eq!(
+            shift_c(0x0000_0001, Shift::Ror, 1, false),
+            (0x8000_0000, true)
+        );
+        assert_eq!(
+            shift_c(0x0000_0001, Shift::Rrx, 1, true),
+            (0x8000_0000, true)
+        );
+    }
+
+    #[test]
+    fn expand_immediate() {
+        assert_eq!(thumb_expand_imm_c(0x0ab, false), (0xab, false));
+        assert_eq!(thumb_expand_imm_c(0x1ab, false), (0x00ab_00ab, false));
+        assert_eq!(thumb_expand_imm_c(0x2ab, false), (0xab00_ab00, false));
+        assert_eq!(thumb_expand_imm_c(0x3ab, false), (0xabab_abab, false));
+        assert_eq!(thumb_expand_imm_c(0x4ff, false), (0x7f80_0000, false));
+    }
+
+    #[test]
+    fn armv6m_loop() {
+        // Sums the numbers from 1 to 10 using a function call:
+        //
+        //     movs r0, #0
+        //     movs r1, #10
+        // loop:
+        //     bl add
+        //     subs r1, #1
+        //     bne loop
+        //     bkpt #1
+        // add:
+        //     adds r0, r0, r1
+        //     bx lr
+        let (cpu, _) = run(
+            false,
+            &[
+                0x2000, 0x210a, 0xf000, 0xf804, 0x3901, 0xd1fb, 0xbe01, 0x0000, 0x1840, 0x4770,
+            ],
+            |_| {},
+        );
+
+        assert_eq!(cpu.regs[0], 55);
+        assert_eq!(cpu.regs[1], 0);
+        assert_eq!(cpu.pc(), 0xc);
+    }
+
+    #[test]
+    fn armv6m_push_pop_and_memory() {
+        //     push {r4, lr}
+        //     ldr r4, =0x20000000
+        //     str r0, [r4, #4]
+        //     ldrb r1, [r4, #4]
+        //     ldrh r2, [r4, #6]
+        //     pop {r4, pc}
+        //     .word 0x20000000
+        //     bkpt #0
+        let (cpu, mut memory) = run(
+            false,
+            &[
+                0xb510, 0x4c02, 0x6060, 0x7921, 0x88e2, 0xbd10, 0x0000, 0x2000, 0xbe00,
+            ],
+            |cpu| {
+                cpu.regs[0] = 0x1234_5678;
+                cpu.regs[4] = 0xdead_beef;
+                // Return to a breakpoint.
+                cpu.regs[14] = 0x11;
+            },
+        );
+
+        assert_eq!(memory.read(0x2000_0004, 4).unwrap(), 0x1234_5678);
+        assert_eq!(cpu.regs[1], 0x78);
+        assert_eq!(cpu.regs[2], 0x1234);
+        assert_eq!(cpu.regs[4], 0xdead_beef);
+        assert_eq!(cpu.regs[13], 0x2000_1000);
+        assert_eq!(cpu.pc(), 0x10);
+    }
+
+    #[test]
+    fn armv6m_rejects_thumb2() {
+        // movw r0, #0x1234
+        let mut memory = TestMemory::default();
+        memory.write(0, 4, 0x2034_f241).unwrap();
+
+        let mut cpu = Cpu::new(false);
+        cpu.reset(0x2000_1000, 1);
+
+        assert_eq!(
+            cpu.step(&mut memory),
+            Err(Fault::UndefinedInstruction {
+                address: 0,
+                instruction: 0xf241_2034
+            })
+        );
+    }
+
+    #[test]
+    fn armv7m_thumb2() {
+        //     movw r0, #0x5678
+        //     movt r0, #0x1234
+        //     ubfx r1, r0, #8, #8
+        //     mov.w r2, #0xff00ff00
+        //     udiv r3, r0, r1
+        //     cmp r1, #0x56
+        //     ite eq
+        //     moveq r4, #1
+        //     movne r4, #2
+        //     bkpt #0
+        let (cpu, _) = run(
+            true,
+            &[
+                0xf245, 0x6078, 0xf2c1, 0x2034, 0xf3c0, 0x2107, 0xf04f, 0x22ff, 0xfbb0, 0xf3f1,
+                0x2956, 0xbf0c, 0x2401, 0x2402, 0xbe00,
+            ],
+            |_| {},
+        );
+
+        assert_eq!(cpu.regs[0], 0x1234_5678);
+        assert_eq!(cpu.regs[1], 0x56);
+        assert_eq!(cpu.regs[2], 0xff00_ff00);
+        assert_eq!(cpu.regs[3], 0x1234_5678 / 0x56);
+        assert_eq!(cpu.regs[4], 1);
+    }
+
+    #[test]
+    fn armv7m_table_branch() {
+        //     tbb [pc, r0]
+        //     .byte 2, 3
+        //     bkpt #1
+        //     bkpt #2
+        //     bkpt #3
+        let (cpu, _) = run(
+            true,
+            &[0xe8df, 0xf000, 0x0302, 0xbe01, 0xbe02, 0xbe03],
+            |cpu| {
+                cpu.regs[0] = 1;
+            },
+        );
+
+        assert_eq!(cpu.pc(), 0xa);
+    }
+}
@@ -1,4 +1,6 @@
 #![allow(missing_docs)] // Don't require docs for test code
+pub mod emulator;
+
 use crate::{
     architecture::arm::{
         ap_v1::memory_ap::mock::MockMemoryAp,
@@ -51,6 +53,8 @@ enum MockedAp {
     MemoryAp(MockMemoryAp),
     /// Mock an ARM core behind a memory AP
     Core(MockCore),
+    /// Emulate an ARM core behind a memory AP
+    Emulated(emulator::EmulatedCore),
 }
 
 struct LoadableSegment {
@@ -262,7 +266,7 @@ impl MemoryInterface<ArmError> for &mut MockCore {
     }
 
     fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
-        todo!()
+        Ok(false)
     }
 }
 
@@ -364,6 +368,14 @@ impl FakeProbe {
         }
     }
 
+    /// Fake probe with an emulated core, which executes the code loaded into it.
+    pub fn with_emulated_core(core: emulator::EmulatedCore) -> Self {
+        FakeProbe {
+            memory_ap: MockedAp::Emulated(core),
+            ..Self::default()
+        }
+    }
+
     /// This sets the read handler for DAP register reads.
     /// Can be used to hook into the read.
     pub fn set_dap_register_read_handler(
@@ -676,6 +688,7 @@ impl ArmProbeInterface for FakeArmInterface<Initialized> {
                 Ok(Box::new(memory) as _)
             }
             MockedAp::Core(ref mut core) => Ok(Box::new(core) as _),
+            MockedAp::Emulated(ref mut core) => Ok(Box::new(core) as _),
         }
     }
 
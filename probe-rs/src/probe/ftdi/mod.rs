@@ -281,7 +281,7 @@ impl ProbeFactory for FtdiProbeFactory {
 
         let mut probes = nusb::list_devices()
             .map_err(FtdiError::from)?
-            .filter(|usb_info| selector.matches(usb_info))
+            .filter(|usb_info| selector.matches_device(usb_info))
             .collect::<Vec<_>>();
 
         if probes.is_empty() {
@@ -430,6 +430,10 @@ impl DebugProbe for FtdiProbe {
     fn has_xtensa_interface(&self) -> bool {
         true
     }
+
+    fn try_as_jtag_access(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
 }
 
 impl DapProbe for FtdiProbe {}
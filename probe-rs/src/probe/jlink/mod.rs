@@ -82,7 +82,7 @@ impl ProbeFactory for JLinkFactory {
         let mut jlinks = nusb::list_devices()
             .map_err(DebugProbeError::Usb)?
             .filter(is_jlink)
-            .filter(|info| selector.matches(info))
+            .filter(|info| selector.matches_device(info))
             .collect::<Vec<_>>();
 
         if jlinks.is_empty() {
@@ -1165,6 +1165,14 @@ impl DebugProbe for JLink {
         self.supported_protocols.contains(&WireProtocol::Jtag)
     }
 
+    fn try_as_jtag_access(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.supported_protocols.contains(&WireProtocol::Jtag) {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
     fn try_into_jlink(&mut self) -> Result<&mut JLink, DebugProbeError> {
         Ok(self)
     }
@@ -0,0 +1,740 @@
+//! Cortex-M debug registers on top of the GDB remote protocol.
+
+use std::{collections::BTreeSet, ops::Range, sync::Arc};
+
+use zerocopy::IntoBytes;
+
+use crate::{
+    architecture::arm::{
+        communication_interface::{SwdSequence, UninitializedArmProbe},
+        dp::{DpAddress, DpRegisterAddress},
+        memory::ArmMemoryInterface,
+        sequences::ArmDebugSequence,
+        ArmError, ArmProbeInterface, DapAccess, FullyQualifiedApAddress, SwoAccess,
+    },
+    probe::{DebugProbeError, Probe},
+    Error as ProbeRsError, MemoryInterface,
+};
+
+use super::{
+    rsp::{StopReason, SIGTRAP},
+    QemuError, QemuProbe, RegisterInfo,
+};
+
+const FP_CTRL: u64 = 0xE000_2000;
+const FP_REMAP: u64 = 0xE000_2004;
+const FP_COMP0: u64 = 0xE000_2008;
+const CPUID: u64 = 0xE000_ED00;
+const AIRCR: u64 = 0xE000_ED0C;
+const DFSR: u64 = 0xE000_ED30;
+const DHCSR: u64 = 0xE000_EDF0;
+const DCRSR: u64 = 0xE000_EDF4;
+const DCRDR: u64 = 0xE000_EDF8;
+const DEMCR: u64 = 0xE000_EDFC;
+
+/// Number of breakpoint comparators offered by the emulated Flash Patch and Breakpoint unit.
+const NUM_COMPARATORS: usize = 6;
+
+/// Registers which are emulated instead of being forwarded to QEMU.
+const EMULATED: [Range<u64>; 4] = [
+    FP_CTRL..FP_COMP0 + 4 * NUM_COMPARATORS as u64,
+    AIRCR..AIRCR + 4,
+    DFSR..DFSR + 4,
+    DHCSR..DEMCR + 4,
+];
+
+const DHCSR_KEY: u32 = 0xA05F;
+const DHCSR_C_DEBUGEN: u32 = 1 << 0;
+const DHCSR_C_HALT: u32 = 1 << 1;
+const DHCSR_C_STEP: u32 = 1 << 2;
+const DHCSR_C_MASKINTS: u32 = 1 << 3;
+const DHCSR_S_REGRDY: u32 = 1 << 16;
+const DHCSR_S_HALT: u32 = 1 << 17;
+const DHCSR_S_RESET_ST: u32 = 1 << 25;
+
+const DFSR_HALTED: u32 = 1 << 0;
+const DFSR_BKPT: u32 = 1 << 1;
+const DFSR_DWTTRAP: u32 = 1 << 2;
+const DFSR_VCATCH: u32 = 1 << 3;
+
+const DEMCR_VC_CORERESET: u32 = 1 << 0;
+
+const AIRCR_VECTKEY: u32 = 0x05FA;
+const AIRCR_VECTRESET: u32 = 1 << 0;
+const AIRCR_SYSRESETREQ: u32 = 1 << 2;
+
+const DCRSR_REGWNR: u32 = 1 << 16;
+
+/// Names of the registers selected by `DCRSR.REGSEL`, as used in QEMU's target description.
+const CORE_REGISTERS: [&str; 19] = [
+    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "sp", "lr",
+    "pc", "xpsr", "msp", "psp",
+];
+
+/// The special registers which are combined in `REGSEL` 20, from the least significant byte.
+const SPECIAL_REGISTERS: [&str; 4] = ["primask", "basepri", "faultmask", "control"];
+
+/// The state of the emulated debug registers.
+#[derive(Debug, Default)]
+struct DebugRegisters {
+    /// The control bits of `DHCSR`.
+    dhcsr: u32,
+    dfsr: u32,
+    dcrdr: u32,
+    demcr: u32,
+    /// `DHCSR.S_RESET_ST`, which is cleared by reading it.
+    reset_st: bool,
+    fp_enable: bool,
+    /// Whether the comparators use the encoding of FPB version 2, as used on ARMv8-M.
+    fp_rev2: bool,
+    comparators: [u32; NUM_COMPARATORS],
+    /// Breakpoints which are currently inserted in QEMU.
+    inserted: BTreeSet<u64>,
+}
+
+#[derive(Debug)]
+pub(crate) struct UninitializedQemuArmProbe {
+    probe: Box<QemuProbe>,
+}
+
+impl UninitializedQemuArmProbe {
+    pub fn new(probe: Box<QemuProbe>) -> Self {
+        Self { probe }
+    }
+}
+
+impl UninitializedArmProbe for UninitializedQemuArmProbe {
+    fn initialize(
+        self: Box<Self>,
+        _sequence: Arc<dyn ArmDebugSequence>,
+        dp: DpAddress,
+    ) -> Result<Box<dyn ArmProbeInterface>, (Box<dyn UninitializedArmProbe>, ProbeRsError)> {
+        // There is no debug port to power up, so the sequence is not needed.
+        match QemuArmDebug::new(self.probe, dp) {
+            Ok(interface) => Ok(Box::new(interface)),
+            Err((probe, error)) => Err((
+                Box::new(UninitializedQemuArmProbe { probe }),
+                DebugProbeError::from(error).into(),
+            )),
+        }
+    }
+
+    fn close(self: Box<Self>) -> Probe {
+        Probe::from_attached_probe(self.probe)
+    }
+}
+
+impl SwdSequence for UninitializedQemuArmProbe {
+    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "swj_sequence",
+        })
+    }
+
+    fn swj_pins(
+        &mut self,
+        _pin_out: u32,
+        _pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "swj_pins",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct QemuArmDebug {
+    probe: Box<QemuProbe>,
+    dp: DpAddress,
+    registers: DebugRegisters,
+}
+
+impl QemuArmDebug {
+    fn new(mut probe: Box<QemuProbe>, dp: DpAddress) -> Result<Self, (Box<QemuProbe>, QemuError)> {
+        let mut cpuid = [0; 4];
+        if let Err(error) = probe.remote.read_memory(CPUID, &mut cpuid) {
+            return Err((probe, error));
+        }
+
+        // ARMv8-M cores have part numbers 0xD2x and use the second FPB revision.
+        let partno = (u32::from_le_bytes(cpuid) >> 4) & 0xFFF;
+
+        let mut registers = DebugRegisters {
+            fp_rev2: partno & 0xFF0 == 0xD20,
+            ..Default::default()
+        };
+
+        match probe.remote.is_running() {
+            Ok(false) => registers.dfsr = DFSR_HALTED,
+            Ok(true) => {}
+            Err(error) => return Err((probe, error)),
+        }
+
+        Ok(Self {
+            probe,
+            dp,
+            registers,
+        })
+    }
+
+    /// Updates `DFSR` if the target stopped since the last access.
+    fn update_status(&mut self) -> Result<bool, QemuError> {
+        let running = self.probe.remote.is_running()?;
+
+        if let Some(reason) = self.probe.remote.take_stop_reason() {
+            self.registers.dfsr |= match reason {
+                StopReason::Breakpoint | StopReason::Signal(SIGTRAP) => DFSR_BKPT,
+                StopReason::Watchpoint(_) => DFSR_DWTTRAP,
+                StopReason::Signal(_) | StopReason::Exited => DFSR_HALTED,
+            };
+        }
+
+        Ok(running)
+    }
+
+    fn read_register(&mut self, address: u64) -> Result<u32, QemuError> {
+        let running = self.update_status()?;
+        let registers = &mut self.registers;
+
+        let value = match address {
+            FP_CTRL => {
+                let num_code = NUM_COMPARATORS as u32;
+                let revision = if registers.fp_rev2 { 1 << 28 } else { 0 };
+                revision | (num_code << 4) | registers.fp_enable as u32
+            }
+            AIRCR => AIRCR_VECTKEY << 16,
+            DFSR => registers.dfsr,
+            DHCSR => {
+                let mut value = registers.dhcsr | DHCSR_S_REGRDY;
+                if !running {
+                    value |= DHCSR_S_HALT;
+                }
+                if std::mem::take(&mut registers.reset_st) {
+                    value |= DHCSR_S_RESET_ST;
+                }
+                value
+            }
+            FP_REMAP | DCRSR => 0,
+            DCRDR => registers.dcrdr,
+            DEMCR => registers.demcr,
+            comparator => {
+                let index = (comparator - FP_COMP0) as usize / 4;
+                registers.comparators[index]
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn write_register(&mut self, address: u64, value: u32) -> Result<(), QemuError> {
+        self.update_status()?;
+
+        match address {
+            FP_CTRL => {
+                // Writes without the key bit are ignored.
+                if value & 0b10 != 0 {
+                    self.registers.fp_enable = value & 1 != 0;
+                }
+            }
+            AIRCR => {
+                if value >> 16 == AIRCR_VECTKEY
+                    && value & (AIRCR_SYSRESETREQ | AIRCR_VECTRESET) != 0
+                {
+                    self.reset()?;
+                }
+            }
+            FP_REMAP => {}
+            DFSR => self.registers.dfsr &= !value,
+            DHCSR => self.write_dhcsr(value)?,
+            DCRSR => self.write_dcrsr(value)?,
+            DCRDR => self.registers.dcrdr = value,
+            DEMCR => self.registers.demcr = value,
+            comparator => {
+                let index = (comparator - FP_COMP0) as usize / 4;
+                self.registers.comparators[index] = value;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_dhcsr(&mut self, value: u32) -> Result<(), QemuError> {
+        if value >> 16 != DHCSR_KEY {
+            return Ok(());
+        }
+
+        self.registers.dhcsr =
+            value & (DHCSR_C_DEBUGEN | DHCSR_C_HALT | DHCSR_C_STEP | DHCSR_C_MASKINTS);
+        let control = self.registers.dhcsr;
+        let remote = &mut self.probe.remote;
+
+        if control & DHCSR_C_DEBUGEN == 0 {
+            // Without halting debug, breakpoints have no effect.
+            self.sync_breakpoints()?;
+            self.probe.remote.resume()?;
+        } else if control & DHCSR_C_HALT != 0 {
+            if let Some(reason) = remote.interrupt()? {
+                if !reason.is_interrupt() {
+                    // The target stopped on its own, so let the usual handling report it.
+                    return Ok(());
+                }
+                remote.take_stop_reason();
+                self.registers.dfsr |= DFSR_HALTED;
+            }
+        } else if control & DHCSR_C_STEP != 0 {
+            if !remote.is_running()? {
+                remote.step()?;
+                remote.take_stop_reason();
+                self.registers.dfsr |= DFSR_HALTED;
+            }
+        } else if !remote.is_running()? {
+            self.sync_breakpoints()?;
+            self.probe.remote.resume()?;
+        }
+
+        Ok(())
+    }
+
+    fn write_dcrsr(&mut self, value: u32) -> Result<(), QemuError> {
+        let regsel = value & 0x7F;
+
+        if value & DCRSR_REGWNR != 0 {
+            self.write_core_register(regsel, self.registers.dcrdr)
+        } else {
+            self.registers.dcrdr = self.read_core_register(regsel)?;
+            Ok(())
+        }
+    }
+
+    /// Looks up a register by name, for registers which may be missing on some cores.
+    fn register(&self, name: &str) -> Option<RegisterInfo> {
+        self.probe.description.register(name)
+    }
+
+    fn read_core_register(&mut self, regsel: u32) -> Result<u32, QemuError> {
+        let read = |interface: &mut Self, name: &str| match interface.register(name) {
+            Some(register) => interface.probe.read_register(register),
+            None => Ok(0),
+        };
+
+        let value = match regsel {
+            0..=18 => read(self, CORE_REGISTERS[regsel as usize])?,
+            20 => {
+                let mut value = 0;
+                for (i, name) in SPECIAL_REGISTERS.iter().enumerate() {
+                    value |= (read(self, name)? & 0xFF) << (8 * i);
+                }
+                value
+            }
+            33 => read(self, "fpscr")?,
+            64..=95 => {
+                let index = regsel - 64;
+                match self.register(&format!("s{index}")) {
+                    Some(register) => self.probe.read_register(register)?,
+                    None => read(self, &format!("d{}", index / 2))? >> (32 * (index % 2)),
+                }
+            }
+            _ => {
+                tracing::warn!("Reading unknown register {regsel}");
+                0
+            }
+        };
+
+        Ok(value as u32)
+    }
+
+    fn write_core_register(&mut self, regsel: u32, value: u32) -> Result<(), QemuError> {
+        let write = |interface: &mut Self, name: &str, value: u64| match interface.register(name) {
+            Some(register) => interface.probe.write_register(register, value),
+            None => {
+                tracing::warn!("Ignoring write to register {name}, which QEMU does not provide");
+                Ok(())
+            }
+        };
+
+        match regsel {
+            0..=18 => write(self, CORE_REGISTERS[regsel as usize], value as u64)?,
+            20 => {
+                for (i, name) in SPECIAL_REGISTERS.iter().enumerate() {
+                    write(self, name, ((value >> (8 * i)) & 0xFF) as u64)?;
+                }
+            }
+            33 => write(self, "fpscr", value as u64)?,
+            64..=95 => {
+                let index = regsel - 64;
+                match self.register(&format!("s{index}")) {
+                    Some(register) => self.probe.write_register(register, value as u64)?,
+                    None => {
+                        let name = format!("d{}", index / 2);
+                        let Some(register) = self.register(&name) else {
+                            return write(self, &name, 0);
+                        };
+                        let shift = 32 * (index % 2);
+                        let current = self.probe.read_register(register)?;
+                        let updated =
+                            (current & !(0xFFFF_FFFF << shift)) | ((value as u64) << shift);
+                        self.probe.write_register(register, updated)?;
+                    }
+                }
+            }
+            _ => tracing::warn!("Ignoring write to unknown register {regsel}"),
+        }
+
+        Ok(())
+    }
+
+    /// Resets the emulated system, halting it if a reset vector catch is set.
+    fn reset(&mut self) -> Result<(), QemuError> {
+        self.probe.reset_system()?;
+        self.registers.reset_st = true;
+
+        let debug_enabled = self.registers.dhcsr & DHCSR_C_DEBUGEN != 0;
+        if debug_enabled && self.registers.demcr & DEMCR_VC_CORERESET != 0 {
+            self.registers.dfsr |= DFSR_VCATCH;
+        } else if !debug_enabled || self.registers.dhcsr & DHCSR_C_HALT == 0 {
+            self.sync_breakpoints()?;
+            self.probe.remote.resume()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the address a comparator breaks on, if it is enabled.
+    fn comparator_address(&self, value: u32) -> Option<u64> {
+        if !self.registers.fp_enable || value & 1 == 0 {
+            return None;
+        }
+
+        if self.registers.fp_rev2 {
+            return Some((value & !1) as u64);
+        }
+
+        // The first revision encodes the halfword in the REPLACE field.
+        let address = value & 0x1FFF_FFFC;
+        match value >> 30 {
+            0b01 => Some(address as u64),
+            0b10 => Some(address as u64 | 2),
+            _ => None,
+        }
+    }
+
+    /// Inserts and removes the breakpoints in QEMU to match the comparators.
+    fn sync_breakpoints(&mut self) -> Result<(), QemuError> {
+        let wanted: BTreeSet<u64> = if self.registers.dhcsr & DHCSR_C_DEBUGEN != 0 {
+            self.registers
+                .comparators
+                .iter()
+                .filter_map(|value| self.comparator_address(*value))
+                .collect()
+        } else {
+            BTreeSet::new()
+        };
+
+        // The kind of an ARM breakpoint is its size, 2 for Thumb instructions.
+        for address in self.registers.inserted.difference(&wanted) {
+            self.probe.remote.remove_breakpoint(*address, 2)?;
+        }
+        for address in wanted.difference(&self.registers.inserted) {
+            self.probe.remote.insert_breakpoint(*address, 2)?;
+        }
+
+        self.registers.inserted = wanted;
+        Ok(())
+    }
+
+    /// Reads memory, emulating the debug registers.
+    fn read(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        let end = address + data.len() as u64;
+        let mut offset = 0;
+
+        while offset < data.len() {
+            let current = address + offset as u64;
+            let register = current & !0b11;
+
+            let count = if is_emulated(register) {
+                let value = self.read_register(register).map_err(to_arm_error)?;
+                let start = (current - register) as usize;
+                let count = (4 - start).min(data.len() - offset);
+                data[offset..offset + count]
+                    .copy_from_slice(&value.to_le_bytes()[start..][..count]);
+                count
+            } else {
+                let count = (next_emulated(current, end) - current) as usize;
+                self.probe
+                    .remote
+                    .read_memory(current, &mut data[offset..offset + count])
+                    .map_err(to_arm_error)?;
+                count
+            };
+
+            offset += count;
+        }
+
+        Ok(())
+    }
+
+    /// Writes memory, emulating the debug registers.
+    fn write(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        let end = address + data.len() as u64;
+        let mut offset = 0;
+
+        while offset < data.len() {
+            let current = address + offset as u64;
+            let register = current & !0b11;
+
+            let count = if is_emulated(register) {
+                // Narrow writes to the debug registers replace the remaining bytes with zeros.
+                let start = (current - register) as usize;
+                let count = (4 - start).min(data.len() - offset);
+                let mut bytes = [0; 4];
+                bytes[start..][..count].copy_from_slice(&data[offset..offset + count]);
+                self.write_register(register, u32::from_le_bytes(bytes))
+                    .map_err(to_arm_error)?;
+                count
+            } else {
+                let count = (next_emulated(current, end) - current) as usize;
+                self.probe
+                    .remote
+                    .write_memory(current, &data[offset..offset + count])
+                    .map_err(to_arm_error)?;
+                count
+            };
+
+            offset += count;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_emulated(address: u64) -> bool {
+    EMULATED.iter().any(|range| range.contains(&address))
+}
+
+/// Returns the start of the next emulated register after `address`, or `end`.
+fn next_emulated(address: u64, end: u64) -> u64 {
+    EMULATED
+        .iter()
+        .map(|range| range.start)
+        .filter(|start| *start > address)
+        .fold(end, u64::min)
+}
+
+fn to_arm_error(error: QemuError) -> ArmError {
+    ArmError::Probe(error.into())
+}
+
+impl ArmProbeInterface for QemuArmDebug {
+    fn reinitialize(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+
+    fn access_ports(
+        &mut self,
+        dp: DpAddress,
+    ) -> Result<BTreeSet<FullyQualifiedApAddress>, ArmError> {
+        Ok(BTreeSet::from([FullyQualifiedApAddress::v1_with_dp(dp, 0)]))
+    }
+
+    fn close(self: Box<Self>) -> Probe {
+        Probe::from_attached_probe(self.probe)
+    }
+
+    fn current_debug_port(&self) -> DpAddress {
+        self.dp
+    }
+
+    fn memory_interface(
+        &mut self,
+        access_port: &FullyQualifiedApAddress,
+    ) -> Result<Box<dyn ArmMemoryInterface + '_>, ArmError> {
+        Ok(Box::new(QemuMemoryInterface {
+            address: access_port.clone(),
+            probe: self,
+        }))
+    }
+}
+
+impl SwoAccess for QemuArmDebug {
+    fn enable_swo(
+        &mut self,
+        _config: &crate::architecture::arm::SwoConfig,
+    ) -> Result<(), ArmError> {
+        Err(ArmError::NotImplemented("swo not implemented"))
+    }
+
+    fn disable_swo(&mut self) -> Result<(), ArmError> {
+        Err(ArmError::NotImplemented("swo not implemented"))
+    }
+
+    fn read_swo_timeout(&mut self, _timeout: std::time::Duration) -> Result<Vec<u8>, ArmError> {
+        Err(ArmError::NotImplemented("swo not implemented"))
+    }
+}
+
+impl SwdSequence for QemuArmDebug {
+    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "swj_sequence",
+        })
+    }
+
+    fn swj_pins(
+        &mut self,
+        _pin_out: u32,
+        _pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "swj_pins",
+        })
+    }
+}
+
+impl DapAccess for QemuArmDebug {
+    fn read_raw_dp_register(
+        &mut self,
+        _dp: DpAddress,
+        _address: DpRegisterAddress,
+    ) -> Result<u32, ArmError> {
+        Err(ArmError::NotImplemented("QEMU has no debug port"))
+    }
+
+    fn write_raw_dp_register(
+        &mut self,
+        _dp: DpAddress,
+        _address: DpRegisterAddress,
+        _value: u32,
+    ) -> Result<(), ArmError> {
+        Err(ArmError::NotImplemented("QEMU has no debug port"))
+    }
+
+    fn read_raw_ap_register(
+        &mut self,
+        _ap: &FullyQualifiedApAddress,
+        _address: u8,
+    ) -> Result<u32, ArmError> {
+        Err(ArmError::NotImplemented("QEMU has no access ports"))
+    }
+
+    fn write_raw_ap_register(
+        &mut self,
+        _ap: &FullyQualifiedApAddress,
+        _address: u8,
+        _value: u32,
+    ) -> Result<(), ArmError> {
+        Err(ArmError::NotImplemented("QEMU has no access ports"))
+    }
+}
+
+#[derive(Debug)]
+struct QemuMemoryInterface<'probe> {
+    probe: &'probe mut QemuArmDebug,
+    address: FullyQualifiedApAddress,
+}
+
+impl MemoryInterface<ArmError> for QemuMemoryInterface<'_> {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        self.probe.read(address, data.as_mut_bytes())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        self.probe.read(address, data.as_mut_bytes())
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        self.probe.read(address, data.as_mut_bytes())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        self.probe.read(address, data)
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        self.probe.write(address, data.as_bytes())
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        self.probe.write(address, data.as_bytes())
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+        self.probe.write(address, data.as_bytes())
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        self.probe.write(address, data)
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+}
+
+impl ArmMemoryInterface for QemuMemoryInterface<'_> {
+    fn fully_qualified_address(&self) -> FullyQualifiedApAddress {
+        self.address.clone()
+    }
+
+    fn base_address(&mut self) -> Result<u64, ArmError> {
+        Ok(0)
+    }
+
+    fn get_swd_sequence(&mut self) -> Result<&mut dyn SwdSequence, DebugProbeError> {
+        Ok(self.probe)
+    }
+
+    fn get_arm_probe_interface(&mut self) -> Result<&mut dyn ArmProbeInterface, DebugProbeError> {
+        Ok(self.probe)
+    }
+
+    fn get_dap_access(&mut self) -> Result<&mut dyn DapAccess, DebugProbeError> {
+        Ok(self.probe)
+    }
+
+    fn generic_status(&mut self) -> Result<crate::architecture::arm::memory::Status, ArmError> {
+        Err(ArmError::NotImplemented("generic_status"))
+    }
+}
+
+impl SwdSequence for QemuMemoryInterface<'_> {
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        self.probe.swj_sequence(bit_len, bits)
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        self.probe.swj_pins(pin_out, pin_select, pin_wait)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn emulated_ranges() {
+        assert!(is_emulated(DHCSR));
+        assert!(is_emulated(DEMCR));
+        assert!(is_emulated(FP_COMP0 + 4 * 5));
+        assert!(!is_emulated(FP_COMP0 + 4 * 6));
+        assert!(!is_emulated(CPUID));
+
+        assert_eq!(next_emulated(0x2000_0000, 0x2000_0100), 0x2000_0100);
+        assert_eq!(next_emulated(CPUID, 0xE000_EE00), AIRCR);
+        assert_eq!(next_emulated(DFSR + 4, 0xE000_EE00), DHCSR);
+    }
+}
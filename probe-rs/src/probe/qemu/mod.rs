@@ -0,0 +1,586 @@
+//! A probe which debugs a QEMU system emulator through its GDB stub.
+//!
+//! QEMU is started with `-gdb tcp::1234` (or `-s`), optionally together with `-S` to keep the
+//! emulated CPU halted until the debugger connects. The probe is selected with
+//! `--probe 1234:0001:<host>:<port>`, where the host and port default to `localhost:1234`.
+//!
+//! The GDB remote protocol only offers registers, memory, breakpoints and run control, so the
+//! probe emulates the debug registers which probe-rs uses to control a core on top of it:
+//!
+//! - On Cortex-M targets, accesses to `DHCSR`, `DCRSR`, `DCRDR`, `DEMCR`, `DFSR`, `AIRCR` and
+//!   the Flash Patch and Breakpoint unit are intercepted, everything else is forwarded to the
+//!   memory of the emulated system.
+//! - On RISC-V targets, the probe acts as a Debug Transport Module, and implements a debug
+//!   module with abstract register access and system bus access.
+//!
+//! Breakpoints are implemented with QEMU's hardware breakpoints. Software breakpoints and code
+//! which relies on `BKPT` or `ebreak` to return to the debugger, such as flash algorithms, are
+//! not supported, since QEMU delivers these to the guest instead of the debugger.
+
+mod arm;
+mod riscv;
+mod rsp;
+
+use std::collections::HashMap;
+
+use probe_rs_target::ScanChainElement;
+
+use crate::{
+    architecture::{
+        arm::communication_interface::UninitializedArmProbe,
+        riscv::communication_interface::RiscvInterfaceBuilder,
+    },
+    probe::{
+        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, ProbeCreationError,
+        ProbeError, ProbeFactory, WireProtocol,
+    },
+};
+
+use arm::UninitializedQemuArmProbe;
+use riscv::QemuDtmBuilder;
+use rsp::GdbRemote;
+
+/// The vendor ID used to select the QEMU probe, which is the PCI vendor ID of QEMU.
+pub const QEMU_VID: u16 = 0x1234;
+
+/// The product ID used to select the QEMU probe.
+pub const QEMU_PID: u16 = 0x0001;
+
+/// The address of the GDB stub if the selector does not contain one.
+const DEFAULT_ADDRESS: &str = "localhost:1234";
+
+/// An error which occurs while communicating with the QEMU GDB stub.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum QemuError {
+    /// Communication with the GDB stub failed.
+    Io(#[from] std::io::Error),
+
+    /// The GDB stub closed the connection.
+    Disconnected,
+
+    /// The GDB stub did not respond in time.
+    Timeout,
+
+    /// The GDB stub sent an unexpected response: {0}
+    UnexpectedResponse(String),
+
+    /// The GDB stub does not support the command '{0}'.
+    NotSupported(String),
+
+    /// The command '{command}' failed with error code {code}.
+    CommandFailed {
+        /// The command which failed.
+        command: String,
+        /// The error code reported by the GDB stub.
+        code: u8,
+    },
+
+    /// The memory at address {0:#010x} is not accessible.
+    MemoryAccess(u64),
+
+    /// The command cannot be executed while the target is running.
+    TargetRunning,
+
+    /// The emulated architecture '{0}' is not supported.
+    UnsupportedArchitecture(String),
+
+    /// The target description is invalid: {0}
+    InvalidTargetDescription(String),
+}
+
+impl ProbeError for QemuError {}
+
+/// The architecture of the emulated CPU, as reported in the target description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Architecture {
+    Arm,
+    Riscv32,
+}
+
+/// Size and number of a register in the GDB remote protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RegisterInfo {
+    number: usize,
+    size: usize,
+}
+
+/// The registers of the emulated CPU, parsed from the target description XML.
+#[derive(Debug, Default)]
+struct TargetDescription {
+    architecture: Option<String>,
+    registers: HashMap<String, RegisterInfo>,
+    /// The register number of CSR 0 on RISC-V targets.
+    ///
+    /// QEMU numbers the CSRs in the `org.gnu.gdb.riscv.csr` feature by adding the CSR number to
+    /// the first register number of the feature.
+    csr_base: Option<usize>,
+    /// The register number assigned to the next register without an explicit number.
+    next_number: usize,
+}
+
+impl TargetDescription {
+    /// Reads the target description from the stub, following `xi:include` elements.
+    fn read(remote: &mut GdbRemote) -> Result<Self, QemuError> {
+        let mut description = Self::default();
+        description.parse("target.xml", 0, &mut |annex| {
+            remote.read_target_description(annex)
+        })?;
+        Ok(description)
+    }
+
+    fn parse(
+        &mut self,
+        annex: &str,
+        depth: usize,
+        read: &mut impl FnMut(&str) -> Result<String, QemuError>,
+    ) -> Result<(), QemuError> {
+        if depth > 4 {
+            return Err(QemuError::InvalidTargetDescription(format!(
+                "Includes of '{annex}' are nested too deeply"
+            )));
+        }
+
+        let document = read(annex)?;
+
+        for element in Elements::new(&document) {
+            match element.name {
+                "architecture" => {
+                    self.architecture = Some(element.text.trim().to_string());
+                }
+                "xi:include" => {
+                    let href = element.attribute("href").ok_or_else(|| {
+                        QemuError::InvalidTargetDescription("Include without href".into())
+                    })?;
+                    self.parse(href, depth + 1, read)?;
+                }
+                "feature" => {
+                    if element.attribute("name") == Some("org.gnu.gdb.riscv.csr") {
+                        self.csr_base = Some(self.next_number);
+                    }
+                }
+                "reg" => self.add_register(&element)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_register(&mut self, element: &Element) -> Result<(), QemuError> {
+        let invalid = || {
+            QemuError::InvalidTargetDescription(format!(
+                "Invalid register {:?}",
+                element.attributes
+            ))
+        };
+
+        let name = element.attribute("name").ok_or_else(invalid)?;
+        let bits: usize = element
+            .attribute("bitsize")
+            .and_then(|bits| bits.parse().ok())
+            .ok_or_else(invalid)?;
+        let number = match element.attribute("regnum") {
+            Some(number) => number.parse().map_err(|_| invalid())?,
+            None => self.next_number,
+        };
+
+        self.next_number = number + 1;
+        self.registers.insert(
+            name.to_string(),
+            RegisterInfo {
+                number,
+                size: bits.div_ceil(8),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn architecture(&self) -> Result<Architecture, QemuError> {
+        let architecture = self.architecture.as_deref().unwrap_or_default();
+
+        if architecture.starts_with("arm") {
+            Ok(Architecture::Arm)
+        } else if architecture == "riscv:rv32" {
+            Ok(Architecture::Riscv32)
+        } else {
+            Err(QemuError::UnsupportedArchitecture(architecture.to_string()))
+        }
+    }
+
+    fn register(&self, name: &str) -> Option<RegisterInfo> {
+        self.registers.get(name).copied()
+    }
+
+    /// Looks up a register by its number in the GDB remote protocol.
+    fn register_by_number(&self, number: usize) -> Option<RegisterInfo> {
+        self.registers
+            .values()
+            .find(|register| register.number == number)
+            .copied()
+    }
+
+    /// Looks up a RISC-V CSR by its number.
+    fn csr(&self, number: u16) -> Option<RegisterInfo> {
+        self.register_by_number(self.csr_base? + number as usize)
+    }
+}
+
+/// An element of the target description, with its attributes and text content.
+#[derive(Debug, PartialEq)]
+struct Element<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+    text: &'a str,
+}
+
+impl<'a> Element<'a> {
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Iterates over the start tags of a target description.
+///
+/// Target descriptions use a small subset of XML without entities or nested text, so
+/// a simple tag scanner is sufficient.
+struct Elements<'a> {
+    remaining: &'a str,
+}
+
+impl<'a> Elements<'a> {
+    fn new(document: &'a str) -> Self {
+        Self {
+            remaining: document,
+        }
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Element<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.remaining.find('<')?;
+            self.remaining = &self.remaining[start + 1..];
+
+            // Comments may contain '>', so they have to be skipped separately.
+            if let Some(comment) = self.remaining.strip_prefix("!--") {
+                let end = comment.find("-->")?;
+                self.remaining = &comment[end + 3..];
+                continue;
+            }
+
+            let end = self.remaining.find('>')?;
+            let tag = &self.remaining[..end];
+            self.remaining = &self.remaining[end + 1..];
+
+            // Skip end tags, processing instructions and the document type.
+            if tag.starts_with(['/', '?', '!']) {
+                continue;
+            }
+
+            let tag = tag.trim_end_matches('/');
+            let (name, mut rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+
+            let mut attributes = Vec::new();
+            while let Some((key, value)) = rest.split_once('=') {
+                let value = value.trim_start();
+                let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                    break;
+                };
+                let Some((value, tail)) = value[1..].split_once(quote) else {
+                    break;
+                };
+                attributes.push((key.trim(), value));
+                rest = tail;
+            }
+
+            let text_end = self.remaining.find('<').unwrap_or(self.remaining.len());
+
+            return Some(Element {
+                name,
+                attributes,
+                text: &self.remaining[..text_end],
+            });
+        }
+    }
+}
+
+/// A factory for creating [`QemuProbe`] instances.
+#[derive(Debug)]
+pub struct QemuProbeFactory;
+
+impl std::fmt::Display for QemuProbeFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("QEMU")
+    }
+}
+
+impl ProbeFactory for QemuProbeFactory {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+        if selector.vendor_id != QEMU_VID || selector.product_id != QEMU_PID {
+            return Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NotFound,
+            ));
+        }
+
+        let address = match selector.serial_number.as_deref() {
+            Some(address) if !address.is_empty() => address,
+            _ => DEFAULT_ADDRESS,
+        };
+
+        let probe = QemuProbe::connect(address)?;
+
+        Ok(Box::new(probe))
+    }
+
+    fn list_probes(&self) -> Vec<DebugProbeInfo> {
+        // Probing for a GDB stub would mean connecting to it, which resumes an emulator that
+        // waits for the debugger, so QEMU instances are never listed.
+        vec![]
+    }
+}
+
+/// A connection to the GDB stub of a QEMU system emulator.
+#[derive(Debug)]
+pub struct QemuProbe {
+    remote: GdbRemote,
+    description: TargetDescription,
+    architecture: Architecture,
+    protocol: Option<WireProtocol>,
+    speed_khz: u32,
+}
+
+impl QemuProbe {
+    fn connect(address: &str) -> Result<Self, DebugProbeError> {
+        let mut remote = GdbRemote::connect(address)?;
+
+        if !remote.supports_target_description() {
+            return Err(QemuError::NotSupported("qXfer:features:read".into()).into());
+        }
+
+        let description = TargetDescription::read(&mut remote)?;
+        let architecture = description.architecture()?;
+
+        tracing::info!(
+            "Connected to the QEMU GDB stub at {address}, emulating {}",
+            description.architecture.as_deref().unwrap_or_default()
+        );
+
+        Ok(Self {
+            remote,
+            description,
+            architecture,
+            protocol: None,
+            speed_khz: 0,
+        })
+    }
+
+    /// Resets the emulated system, keeping the CPU halted.
+    fn reset_system(&mut self) -> Result<(), QemuError> {
+        self.remote.interrupt()?;
+        self.remote.take_stop_reason();
+        self.remote.monitor("system_reset")?;
+        Ok(())
+    }
+
+    /// Reads a register, zero extended to 64 bits.
+    fn read_register(&mut self, register: RegisterInfo) -> Result<u64, QemuError> {
+        let bytes = self.remote.read_register(register.number)?;
+
+        Ok(bytes
+            .iter()
+            .take(8)
+            .rev()
+            .fold(0, |value, byte| (value << 8) | *byte as u64))
+    }
+
+    /// Writes a register, truncating the value to the size of the register.
+    fn write_register(&mut self, register: RegisterInfo, value: u64) -> Result<(), QemuError> {
+        let mut bytes = vec![0; register.size];
+        for (i, byte) in bytes.iter_mut().take(8).enumerate() {
+            *byte = (value >> (8 * i)) as u8;
+        }
+
+        self.remote.write_register(register.number, &bytes)
+    }
+}
+
+impl DebugProbe for QemuProbe {
+    fn get_name(&self) -> &str {
+        "QEMU"
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        // There is no wire, so any speed is fine.
+        self.speed_khz = speed_khz;
+        Ok(speed_khz)
+    }
+
+    fn set_scan_chain(
+        &mut self,
+        _scan_chain: Vec<ScanChainElement>,
+    ) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+        Ok(&[])
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        self.remote.detach().map_err(DebugProbeError::from)?;
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.reset_system()?;
+        self.remote.resume()?;
+        Ok(())
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "target_reset_assert",
+        })
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "target_reset_deassert",
+        })
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        // The protocol has no meaning for an emulator, but is remembered for consistency.
+        self.protocol = Some(protocol);
+        Ok(())
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.protocol
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        self.architecture == Architecture::Arm
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        if self.architecture != Architecture::Arm {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "ARM",
+                },
+            ));
+        }
+
+        Ok(Box::new(UninitializedQemuArmProbe::new(self)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        self.architecture == Architecture::Riscv32
+    }
+
+    fn try_get_riscv_interface_builder<'probe>(
+        &'probe mut self,
+    ) -> Result<Box<dyn RiscvInterfaceBuilder<'probe> + 'probe>, DebugProbeError> {
+        if self.architecture != Architecture::Riscv32 {
+            return Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "RISC-V",
+            });
+        }
+
+        Ok(Box::new(QemuDtmBuilder::new(self)))
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scan_elements() {
+        let document = r#"<?xml version="1.0"?>
+            <!DOCTYPE target SYSTEM "gdb-target.dtd">
+            <!-- A comment with <tags> -->
+            <target>
+              <architecture>arm</architecture>
+              <xi:include href='arm-m-profile.xml'/>
+            </target>"#;
+
+        let elements: Vec<_> = Elements::new(document).collect();
+
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements[0].name, "target");
+        assert_eq!(elements[1].name, "architecture");
+        assert_eq!(elements[1].text, "arm");
+        assert_eq!(elements[2].name, "xi:include");
+        assert_eq!(elements[2].attribute("href"), Some("arm-m-profile.xml"));
+    }
+
+    #[test]
+    fn register_numbers() {
+        let documents = HashMap::from([
+            (
+                "target.xml",
+                r#"<target>
+                  <architecture>riscv:rv32</architecture>
+                  <xi:include href="riscv-32bit-cpu.xml"/>
+                  <xi:include href="riscv-32bit-csr.xml"/>
+                </target>"#,
+            ),
+            (
+                "riscv-32bit-cpu.xml",
+                r#"<feature name="org.gnu.gdb.riscv.cpu">
+                  <reg name="zero" bitsize="32" type="int"/>
+                  <reg name="ra" bitsize="32" type="code_ptr"/>
+                  <reg name="pc" bitsize="32" regnum="32"/>
+                </feature>"#,
+            ),
+            (
+                "riscv-32bit-csr.xml",
+                r#"<feature name="org.gnu.gdb.riscv.csr">
+                  <reg name="mstatus" bitsize="32" regnum="801"/>
+                  <reg name="misa" bitsize="32" regnum="802"/>
+                </feature>"#,
+            ),
+        ]);
+
+        let mut description = TargetDescription::default();
+        description
+            .parse("target.xml", 0, &mut |annex| {
+                Ok(documents[annex].to_string())
+            })
+            .unwrap();
+
+        assert_eq!(description.architecture().unwrap(), Architecture::Riscv32);
+        assert_eq!(
+            description.register("ra"),
+            Some(RegisterInfo { number: 1, size: 4 })
+        );
+        assert_eq!(description.register("pc").unwrap().number, 32);
+        assert_eq!(description.csr_base, Some(33));
+        assert_eq!(description.csr(0x301).unwrap().number, 802);
+        assert_eq!(description.csr(0x7b0), None);
+    }
+}
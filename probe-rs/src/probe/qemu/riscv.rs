@@ -0,0 +1,707 @@
+//! A RISC-V debug module on top of the GDB remote protocol.
+//!
+//! The probe acts as the Debug Transport Module, and answers accesses to the registers of a
+//! version 0.13 debug module with a single hart. Registers are accessed with abstract
+//! commands, and memory through the system bus. There is no program buffer.
+
+use std::{collections::BTreeSet, time::Duration};
+
+use crate::{
+    architecture::riscv::{
+        communication_interface::{
+            RiscvCommunicationInterface, RiscvDebugInterfaceState, RiscvError,
+            RiscvInterfaceBuilder,
+        },
+        dtm::dtm_access::DtmAccess,
+    },
+    probe::{CommandResult, DebugProbeError, DeferredResultIndex, DeferredResultSet},
+};
+
+use super::{
+    rsp::{StopReason, SIGTRAP},
+    QemuError, QemuProbe, RegisterInfo,
+};
+
+const DATA0: u64 = 0x04;
+const DATA1: u64 = 0x05;
+const DMCONTROL: u64 = 0x10;
+const DMSTATUS: u64 = 0x11;
+const ABSTRACTCS: u64 = 0x16;
+const COMMAND: u64 = 0x17;
+const HALTSUM0: u64 = 0x40;
+const SBCS: u64 = 0x38;
+const SBADDRESS0: u64 = 0x39;
+const SBDATA0: u64 = 0x3c;
+
+const DMCONTROL_HALTREQ: u32 = 1 << 31;
+const DMCONTROL_RESUMEREQ: u32 = 1 << 30;
+const DMCONTROL_ACKHAVERESET: u32 = 1 << 28;
+const DMCONTROL_SETRESETHALTREQ: u32 = 1 << 3;
+const DMCONTROL_CLRRESETHALTREQ: u32 = 1 << 2;
+const DMCONTROL_NDMRESET: u32 = 1 << 1;
+const DMCONTROL_DMACTIVE: u32 = 1 << 0;
+
+const DMSTATUS_ALLHAVERESET: u32 = 1 << 19;
+const DMSTATUS_ANYHAVERESET: u32 = 1 << 18;
+const DMSTATUS_ALLRESUMEACK: u32 = 1 << 17;
+const DMSTATUS_ANYRESUMEACK: u32 = 1 << 16;
+const DMSTATUS_ALLRUNNING: u32 = 1 << 11;
+const DMSTATUS_ANYRUNNING: u32 = 1 << 10;
+const DMSTATUS_ALLHALTED: u32 = 1 << 9;
+const DMSTATUS_ANYHALTED: u32 = 1 << 8;
+const DMSTATUS_AUTHENTICATED: u32 = 1 << 7;
+const DMSTATUS_HASRESETHALTREQ: u32 = 1 << 5;
+/// Version 0.13 of the debug specification.
+const DMSTATUS_VERSION: u32 = 2;
+
+const DATACOUNT: u32 = 2;
+
+const COMMAND_POSTEXEC: u32 = 1 << 18;
+const COMMAND_TRANSFER: u32 = 1 << 17;
+const COMMAND_WRITE: u32 = 1 << 16;
+
+const CMDERR_NOT_SUPPORTED: u32 = 2;
+const CMDERR_EXCEPTION: u32 = 3;
+const CMDERR_HALT_RESUME: u32 = 4;
+
+const SBCS_SBVERSION: u32 = 1 << 29;
+const SBCS_SBBUSYERROR: u32 = 1 << 22;
+const SBCS_SBREADONADDR: u32 = 1 << 20;
+const SBCS_SBAUTOINCREMENT: u32 = 1 << 16;
+const SBCS_SBREADONDATA: u32 = 1 << 15;
+const SBCS_CONTROL: u32 =
+    SBCS_SBREADONADDR | (0b111 << 17) | SBCS_SBAUTOINCREMENT | SBCS_SBREADONDATA;
+const SBCS_SBASIZE: u32 = 32 << 5;
+/// 8, 16 and 32 bit accesses are supported.
+const SBCS_SBACCESS: u32 = 0b111;
+
+const SBERROR_BAD_ADDRESS: u32 = 2;
+const SBERROR_SIZE: u32 = 4;
+
+const TSELECT: u16 = 0x7a0;
+const TDATA1: u16 = 0x7a1;
+const TDATA2: u16 = 0x7a2;
+const TINFO: u16 = 0x7a4;
+const DCSR: u16 = 0x7b0;
+const DPC: u16 = 0x7b1;
+
+/// Number of triggers offered by the emulated trigger module.
+const NUM_TRIGGERS: usize = 4;
+
+/// Triggers are address match triggers, type 2.
+const MCONTROL_TYPE: u32 = 2 << 28;
+
+const DCSR_XDEBUGVER: u32 = 4 << 28;
+/// The bits of `dcsr` which can be written: `ebreakm`, `ebreaks`, `ebreaku`, `stepie`,
+/// `stopcount`, `stoptime` and `step`.
+const DCSR_CONTROL: u32 = (1 << 15) | (0b11 << 12) | (0b111 << 9) | DCSR_STEP;
+const DCSR_STEP: u32 = 1 << 2;
+/// The hart is always debugged in machine mode.
+const DCSR_PRV_MACHINE: u32 = 0b11;
+
+const CAUSE_TRIGGER: u32 = 2;
+const CAUSE_HALTREQ: u32 = 3;
+const CAUSE_STEP: u32 = 4;
+const CAUSE_RESETHALTREQ: u32 = 5;
+
+/// The state of the emulated debug module.
+#[derive(Debug, Default)]
+struct DmState {
+    results: DeferredResultSet,
+
+    dmactive: bool,
+    ndmreset: bool,
+    resethaltreq: bool,
+    havereset: bool,
+    resumeack: bool,
+
+    /// The reason of the last halt, as reported in `dcsr.cause`.
+    cause: u32,
+    /// The control bits of `dcsr`.
+    dcsr: u32,
+    data: [u32; 2],
+    cmderr: u32,
+
+    /// The control bits of `sbcs`.
+    sbcs: u32,
+    sberror: u32,
+    sbbusyerror: bool,
+    sbaddress: u32,
+    sbdata: u32,
+    /// Consecutive system bus writes which have not been sent to QEMU yet.
+    pending_writes: Option<(u32, Vec<u8>)>,
+
+    tselect: u32,
+    /// `tdata1` and `tdata2` of each trigger.
+    triggers: [(u32, u32); NUM_TRIGGERS],
+    /// Breakpoints which are currently inserted in QEMU.
+    inserted: BTreeSet<u64>,
+}
+
+/// Why an abstract command failed.
+enum CommandError {
+    /// The command failed, with the given `cmderr`.
+    Command(u32),
+    Qemu(QemuError),
+}
+
+impl From<QemuError> for CommandError {
+    fn from(error: QemuError) -> Self {
+        match error {
+            // The stub rejected the access, which the debugger sees as an exception.
+            QemuError::CommandFailed { .. } | QemuError::NotSupported(_) => {
+                CommandError::Command(CMDERR_EXCEPTION)
+            }
+            other => CommandError::Qemu(other),
+        }
+    }
+}
+
+pub(crate) struct QemuDtmBuilder<'probe>(&'probe mut QemuProbe);
+
+impl<'probe> QemuDtmBuilder<'probe> {
+    pub fn new(probe: &'probe mut QemuProbe) -> Self {
+        Self(probe)
+    }
+}
+
+impl<'probe> RiscvInterfaceBuilder<'probe> for QemuDtmBuilder<'probe> {
+    fn create_state(&self) -> RiscvDebugInterfaceState {
+        RiscvDebugInterfaceState::new(Box::<DmState>::default())
+    }
+
+    fn attach<'state>(
+        self: Box<Self>,
+        state: &'state mut RiscvDebugInterfaceState,
+    ) -> Result<RiscvCommunicationInterface<'state>, DebugProbeError>
+    where
+        'probe: 'state,
+    {
+        let dm_state = state.dtm_state.downcast_mut::<DmState>().unwrap();
+
+        Ok(RiscvCommunicationInterface::new(
+            Box::new(QemuDtm {
+                probe: self.0,
+                state: dm_state,
+            }),
+            &mut state.interface_state,
+        ))
+    }
+}
+
+/// Emulates the debug module, executing every access immediately.
+#[derive(Debug)]
+struct QemuDtm<'probe> {
+    probe: &'probe mut QemuProbe,
+    state: &'probe mut DmState,
+}
+
+impl QemuDtm<'_> {
+    fn read(&mut self, address: u64) -> Result<u32, QemuError> {
+        self.flush_writes()?;
+
+        let value = match address {
+            DATA0 | DATA1 => self.state.data[(address - DATA0) as usize],
+            DMCONTROL => {
+                (self.state.ndmreset as u32 * DMCONTROL_NDMRESET)
+                    | (self.state.dmactive as u32 * DMCONTROL_DMACTIVE)
+            }
+            DMSTATUS => self.read_dmstatus()?,
+            ABSTRACTCS => (self.state.cmderr << 8) | DATACOUNT,
+            HALTSUM0 => !self.update_status()? as u32,
+            SBCS => {
+                SBCS_SBVERSION
+                    | (self.state.sbbusyerror as u32 * SBCS_SBBUSYERROR)
+                    | self.state.sbcs
+                    | (self.state.sberror << 12)
+                    | SBCS_SBASIZE
+                    | SBCS_SBACCESS
+            }
+            SBADDRESS0 => self.state.sbaddress,
+            SBDATA0 => {
+                let value = self.state.sbdata;
+                if self.state.sbcs & SBCS_SBREADONDATA != 0 {
+                    self.read_bus()?;
+                }
+                value
+            }
+            // Everything else is either not implemented or reads as zero, including the
+            // program buffer, `hartinfo`, `abstractauto` and `confstrptr`.
+            _ => 0,
+        };
+
+        Ok(value)
+    }
+
+    fn write(&mut self, address: u64, value: u32) -> Result<(), QemuError> {
+        if address != SBDATA0 {
+            self.flush_writes()?;
+        }
+
+        match address {
+            DATA0 | DATA1 => self.state.data[(address - DATA0) as usize] = value,
+            DMCONTROL => self.write_dmcontrol(value)?,
+            ABSTRACTCS => self.state.cmderr &= !((value >> 8) & 0b111),
+            COMMAND => self.execute_command(value)?,
+            SBCS => {
+                if value & SBCS_SBBUSYERROR != 0 {
+                    self.state.sbbusyerror = false;
+                }
+                self.state.sberror &= !((value >> 12) & 0b111);
+                self.state.sbcs = value & SBCS_CONTROL;
+            }
+            SBADDRESS0 => {
+                self.state.sbaddress = value;
+                if self.state.sbcs & SBCS_SBREADONADDR != 0 {
+                    self.read_bus()?;
+                }
+            }
+            SBDATA0 => self.write_bus(value)?,
+            _ => tracing::debug!("Ignoring write of {value:#010x} to DM register {address:#x}"),
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the hart is running, and records why it halted.
+    fn update_status(&mut self) -> Result<bool, QemuError> {
+        let running = self.probe.remote.is_running()?;
+
+        if let Some(reason) = self.probe.remote.take_stop_reason() {
+            self.state.cause = match reason {
+                StopReason::Breakpoint => CAUSE_TRIGGER,
+                StopReason::Signal(SIGTRAP) => {
+                    let pc = self.read_pc()?;
+                    if self.active_triggers().contains(&pc) {
+                        CAUSE_TRIGGER
+                    } else {
+                        CAUSE_HALTREQ
+                    }
+                }
+                _ => CAUSE_HALTREQ,
+            };
+        }
+
+        Ok(running)
+    }
+
+    fn read_pc(&mut self) -> Result<u64, QemuError> {
+        let pc = self.gdb_register("pc")?;
+        self.probe.read_register(pc)
+    }
+
+    fn gdb_register(&self, name: &str) -> Result<RegisterInfo, QemuError> {
+        self.probe.description.register(name).ok_or_else(|| {
+            QemuError::InvalidTargetDescription(format!("Register {name} is missing"))
+        })
+    }
+
+    fn read_dmstatus(&mut self) -> Result<u32, QemuError> {
+        let running = self.update_status()?;
+        let state = &self.state;
+
+        let mut value = DMSTATUS_AUTHENTICATED | DMSTATUS_HASRESETHALTREQ | DMSTATUS_VERSION;
+        value |= if running {
+            DMSTATUS_ALLRUNNING | DMSTATUS_ANYRUNNING
+        } else {
+            DMSTATUS_ALLHALTED | DMSTATUS_ANYHALTED
+        };
+        if state.resumeack {
+            value |= DMSTATUS_ALLRESUMEACK | DMSTATUS_ANYRESUMEACK;
+        }
+        if state.havereset {
+            value |= DMSTATUS_ALLHAVERESET | DMSTATUS_ANYHAVERESET;
+        }
+
+        Ok(value)
+    }
+
+    fn write_dmcontrol(&mut self, value: u32) -> Result<(), QemuError> {
+        if value & DMCONTROL_DMACTIVE == 0 {
+            // Deactivating the debug module resets it, except for the breakpoints which are
+            // still inserted.
+            *self.state = DmState {
+                results: std::mem::take(&mut self.state.results),
+                inserted: std::mem::take(&mut self.state.inserted),
+                ..Default::default()
+            };
+            return Ok(());
+        }
+        self.state.dmactive = true;
+
+        if value & DMCONTROL_CLRRESETHALTREQ != 0 {
+            self.state.resethaltreq = false;
+        }
+        if value & DMCONTROL_SETRESETHALTREQ != 0 {
+            self.state.resethaltreq = true;
+        }
+        if value & DMCONTROL_ACKHAVERESET != 0 {
+            self.state.havereset = false;
+        }
+
+        let haltreq = value & DMCONTROL_HALTREQ != 0;
+        let ndmreset = value & DMCONTROL_NDMRESET != 0;
+
+        if ndmreset && !self.state.ndmreset {
+            // The system stays halted while the reset is asserted.
+            self.probe.reset_system()?;
+            self.state.havereset = true;
+            self.state.resumeack = false;
+        } else if !ndmreset && self.state.ndmreset {
+            if haltreq {
+                self.state.cause = CAUSE_HALTREQ;
+            } else if self.state.resethaltreq {
+                self.state.cause = CAUSE_RESETHALTREQ;
+            } else {
+                self.sync_triggers()?;
+                self.probe.remote.resume()?;
+            }
+        }
+        self.state.ndmreset = ndmreset;
+
+        if haltreq {
+            self.halt()?;
+        } else if value & DMCONTROL_RESUMEREQ != 0 {
+            self.resume()?;
+        }
+
+        Ok(())
+    }
+
+    fn halt(&mut self) -> Result<(), QemuError> {
+        match self.probe.remote.interrupt()? {
+            Some(reason) if reason.is_interrupt() => {
+                self.probe.remote.take_stop_reason();
+                self.state.cause = CAUSE_HALTREQ;
+            }
+            // Either the hart was halted already, or it stopped for a different reason, which
+            // is reported by the next status update.
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), QemuError> {
+        self.state.resumeack = false;
+
+        if !self.update_status()? {
+            self.sync_triggers()?;
+
+            if self.state.dcsr & DCSR_STEP != 0 {
+                self.probe.remote.step()?;
+                self.probe.remote.take_stop_reason();
+                self.state.cause = CAUSE_STEP;
+            } else {
+                self.probe.remote.resume()?;
+            }
+        }
+
+        self.state.resumeack = true;
+        Ok(())
+    }
+
+    fn execute_command(&mut self, command: u32) -> Result<(), QemuError> {
+        // Commands are ignored until the previous error is cleared.
+        if self.state.cmderr != 0 {
+            return Ok(());
+        }
+
+        match self.access_register(command) {
+            Ok(()) => Ok(()),
+            Err(CommandError::Command(cmderr)) => {
+                self.state.cmderr = cmderr;
+                Ok(())
+            }
+            Err(CommandError::Qemu(error)) => Err(error),
+        }
+    }
+
+    /// Executes an "Access Register" abstract command.
+    fn access_register(&mut self, command: u32) -> Result<(), CommandError> {
+        let cmdtype = command >> 24;
+        let aarsize = (command >> 20) & 0b111;
+
+        if cmdtype != 0 || !(2..=3).contains(&aarsize) || command & COMMAND_POSTEXEC != 0 {
+            return Err(CommandError::Command(CMDERR_NOT_SUPPORTED));
+        }
+
+        if self.update_status()? {
+            return Err(CommandError::Command(CMDERR_HALT_RESUME));
+        }
+
+        if command & COMMAND_TRANSFER == 0 {
+            return Ok(());
+        }
+
+        let regno = (command & 0xFFFF) as u16;
+        let write = command & COMMAND_WRITE != 0;
+
+        if write {
+            let mut value = self.state.data[0] as u64;
+            if aarsize == 3 {
+                value |= (self.state.data[1] as u64) << 32;
+            }
+            self.write_abstract_register(regno, value)
+        } else {
+            let value = self.read_abstract_register(regno)?;
+            self.state.data[0] = value as u32;
+            if aarsize == 3 {
+                self.state.data[1] = (value >> 32) as u32;
+            }
+            Ok(())
+        }
+    }
+
+    fn read_abstract_register(&mut self, regno: u16) -> Result<u64, CommandError> {
+        let state = &self.state;
+
+        let value = match regno {
+            TSELECT => state.tselect as u64,
+            TDATA1 => (state.triggers[state.tselect as usize].0 | MCONTROL_TYPE) as u64,
+            TDATA2 => state.triggers[state.tselect as usize].1 as u64,
+            TINFO => 1 << (MCONTROL_TYPE >> 28),
+            DCSR => (DCSR_XDEBUGVER | (state.cause << 6) | state.dcsr | DCSR_PRV_MACHINE) as u64,
+            regno => {
+                let register = self.gdb_register_for(regno)?;
+                self.probe.read_register(register)?
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn write_abstract_register(&mut self, regno: u16, value: u64) -> Result<(), CommandError> {
+        let state = &mut *self.state;
+
+        match regno {
+            TSELECT => {
+                if value as usize >= NUM_TRIGGERS {
+                    return Err(CommandError::Command(CMDERR_EXCEPTION));
+                }
+                state.tselect = value as u32;
+            }
+            TDATA1 => state.triggers[state.tselect as usize].0 = value as u32 & !(0xF << 28),
+            TDATA2 => state.triggers[state.tselect as usize].1 = value as u32,
+            TINFO => {}
+            DCSR => state.dcsr = value as u32 & DCSR_CONTROL,
+            regno => {
+                let register = self.gdb_register_for(regno)?;
+                self.probe.write_register(register, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Maps the number of an abstract register to a register of the GDB stub.
+    fn gdb_register_for(&self, regno: u16) -> Result<RegisterInfo, CommandError> {
+        let description = &self.probe.description;
+
+        let register = match regno {
+            DPC => description.register("pc"),
+            0x0000..=0x0FFF => description.csr(regno),
+            0x1000..=0x101F => description.register_by_number((regno - 0x1000) as usize),
+            0x1020..=0x103F => description.register("ft0").and_then(|ft0| {
+                description.register_by_number(ft0.number + (regno - 0x1020) as usize)
+            }),
+            _ => None,
+        };
+
+        register.ok_or(CommandError::Command(CMDERR_NOT_SUPPORTED))
+    }
+
+    /// Returns the addresses of the enabled execute triggers.
+    fn active_triggers(&self) -> BTreeSet<u64> {
+        self.state
+            .triggers
+            .iter()
+            .filter(|(tdata1, _)| {
+                let action = (tdata1 >> 12) & 0xF;
+                let match_ = (tdata1 >> 7) & 0xF;
+                let execute = tdata1 & (1 << 2) != 0;
+                // One of the `m`, `s` and `u` bits has to be set.
+                let any_mode = tdata1 & 0b101_1000 != 0;
+
+                action == 1 && match_ == 0 && execute && any_mode
+            })
+            .map(|(_, tdata2)| *tdata2 as u64)
+            .collect()
+    }
+
+    /// Inserts and removes the breakpoints in QEMU to match the triggers.
+    fn sync_triggers(&mut self) -> Result<(), QemuError> {
+        let wanted = self.active_triggers();
+
+        for address in self.state.inserted.difference(&wanted) {
+            self.probe.remote.remove_breakpoint(*address, 4)?;
+        }
+        for address in wanted.difference(&self.state.inserted) {
+            self.probe.remote.insert_breakpoint(*address, 4)?;
+        }
+
+        self.state.inserted = wanted;
+        Ok(())
+    }
+
+    /// The size of a system bus access in bytes, or `None` if the size is not supported.
+    fn access_size(&self) -> Option<u32> {
+        let sbaccess = (self.state.sbcs >> 17) & 0b111;
+        (sbaccess <= 2).then(|| 1 << sbaccess)
+    }
+
+    fn bus_error(&self) -> bool {
+        self.state.sberror != 0 || self.state.sbbusyerror
+    }
+
+    fn increment_address(&mut self, size: u32) {
+        if self.state.sbcs & SBCS_SBAUTOINCREMENT != 0 {
+            self.state.sbaddress = self.state.sbaddress.wrapping_add(size);
+        }
+    }
+
+    /// Reads memory at `sbaddress0` into `sbdata0`.
+    fn read_bus(&mut self) -> Result<(), QemuError> {
+        if self.bus_error() {
+            return Ok(());
+        }
+        let Some(size) = self.access_size() else {
+            self.state.sberror = SBERROR_SIZE;
+            return Ok(());
+        };
+
+        let mut bytes = [0; 4];
+        let address = self.state.sbaddress;
+        match self
+            .probe
+            .remote
+            .read_memory(address as u64, &mut bytes[..size as usize])
+        {
+            Ok(()) => {
+                self.state.sbdata = u32::from_le_bytes(bytes);
+                self.increment_address(size);
+            }
+            Err(QemuError::MemoryAccess(_)) => self.state.sberror = SBERROR_BAD_ADDRESS,
+            Err(other) => return Err(other),
+        }
+
+        Ok(())
+    }
+
+    /// Writes `sbdata0` to `sbaddress0`.
+    ///
+    /// Consecutive writes are collected and sent to QEMU together, before the next access
+    /// to another register.
+    fn write_bus(&mut self, value: u32) -> Result<(), QemuError> {
+        if self.bus_error() {
+            return Ok(());
+        }
+        let Some(size) = self.access_size() else {
+            self.state.sberror = SBERROR_SIZE;
+            return Ok(());
+        };
+
+        self.state.sbdata = value;
+        let address = self.state.sbaddress;
+        let bytes = &value.to_le_bytes()[..size as usize];
+
+        match &mut self.state.pending_writes {
+            Some((start, pending)) if *start as usize + pending.len() == address as usize => {
+                pending.extend_from_slice(bytes);
+            }
+            _ => {
+                self.flush_writes()?;
+                self.state.pending_writes = Some((address, bytes.to_vec()));
+            }
+        }
+
+        self.increment_address(size);
+        Ok(())
+    }
+
+    fn flush_writes(&mut self) -> Result<(), QemuError> {
+        let Some((address, data)) = self.state.pending_writes.take() else {
+            return Ok(());
+        };
+
+        match self.probe.remote.write_memory(address as u64, &data) {
+            Ok(()) => Ok(()),
+            Err(QemuError::MemoryAccess(_)) => {
+                self.state.sberror = SBERROR_BAD_ADDRESS;
+                Ok(())
+            }
+            Err(other) => Err(other),
+        }
+    }
+}
+
+fn to_riscv_error(error: QemuError) -> RiscvError {
+    RiscvError::DebugProbe(error.into())
+}
+
+impl DtmAccess for QemuDtm<'_> {
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "target_reset_assert",
+        })
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "target_reset_deassert",
+        })
+    }
+
+    fn clear_error_state(&mut self) -> Result<(), RiscvError> {
+        Ok(())
+    }
+
+    fn read_deferred_result(
+        &mut self,
+        index: DeferredResultIndex,
+    ) -> Result<CommandResult, RiscvError> {
+        self.state
+            .results
+            .take(index)
+            .map_err(|_| RiscvError::BatchedResultNotAvailable)
+    }
+
+    fn execute(&mut self) -> Result<(), RiscvError> {
+        self.flush_writes().map_err(to_riscv_error)
+    }
+
+    fn schedule_write(
+        &mut self,
+        address: u64,
+        value: u32,
+    ) -> Result<Option<DeferredResultIndex>, RiscvError> {
+        self.write(address, value).map_err(to_riscv_error)?;
+        Ok(None)
+    }
+
+    fn schedule_read(&mut self, address: u64) -> Result<DeferredResultIndex, RiscvError> {
+        let value = self.read(address).map_err(to_riscv_error)?;
+
+        let index = DeferredResultIndex::new();
+        self.state.results.push(&index, CommandResult::U32(value));
+        Ok(index)
+    }
+
+    fn read_with_timeout(&mut self, address: u64, _timeout: Duration) -> Result<u32, RiscvError> {
+        self.read(address).map_err(to_riscv_error)
+    }
+
+    fn write_with_timeout(
+        &mut self,
+        address: u64,
+        value: u32,
+        _timeout: Duration,
+    ) -> Result<Option<u32>, RiscvError> {
+        self.write(address, value).map_err(to_riscv_error)?;
+        Ok(None)
+    }
+
+    fn read_idcode(&mut self) -> Result<Option<u32>, DebugProbeError> {
+        Ok(None)
+    }
+}
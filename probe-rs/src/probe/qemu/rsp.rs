@@ -0,0 +1,689 @@
+//! Client side of the GDB remote serial protocol.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use super::QemuError;
+
+/// How long to wait for a response from the stub.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Packet size assumed if the stub does not report one.
+const DEFAULT_PACKET_SIZE: usize = 4096;
+
+/// Why the target stopped, as reported in a stop reply packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StopReason {
+    /// The target stopped because of a signal, usually `SIGTRAP` or `SIGINT`.
+    Signal(u8),
+    /// The target hit a breakpoint.
+    Breakpoint,
+    /// The target hit a watchpoint at the given address.
+    Watchpoint(u64),
+    /// The target exited or was terminated.
+    Exited,
+}
+
+const SIGINT: u8 = 2;
+pub(crate) const SIGTRAP: u8 = 5;
+
+impl StopReason {
+    fn parse(packet: &[u8]) -> Result<Self, QemuError> {
+        let invalid = || QemuError::UnexpectedResponse(String::from_utf8_lossy(packet).into());
+
+        let (kind, rest) = packet.split_first().ok_or_else(invalid)?;
+        match kind {
+            b'W' | b'X' => Ok(StopReason::Exited),
+            b'S' | b'T' => {
+                let signal = rest.get(..2).and_then(parse_hex_u8).ok_or_else(invalid)?;
+
+                let mut reason = StopReason::Signal(signal);
+                if *kind == b'T' {
+                    for pair in rest[2..].split(|b| *b == b';') {
+                        let mut parts = pair.splitn(2, |b| *b == b':');
+                        let (Some(key), Some(value)) = (parts.next(), parts.next()) else {
+                            continue;
+                        };
+                        match key {
+                            b"swbreak" | b"hwbreak" => reason = StopReason::Breakpoint,
+                            b"watch" | b"rwatch" | b"awatch" => {
+                                reason = StopReason::Watchpoint(
+                                    parse_hex_u64(value).ok_or_else(invalid)?,
+                                )
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                Ok(reason)
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Whether the target stopped because the debugger interrupted it.
+    pub(crate) fn is_interrupt(&self) -> bool {
+        *self == StopReason::Signal(SIGINT)
+    }
+}
+
+fn parse_hex_u8(hex: &[u8]) -> Option<u8> {
+    u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
+
+fn parse_hex_u64(hex: &[u8]) -> Option<u64> {
+    u64::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
+
+pub(crate) fn decode_hex(hex: &[u8]) -> Result<Vec<u8>, QemuError> {
+    if hex.len() % 2 != 0 {
+        return Err(QemuError::UnexpectedResponse(
+            String::from_utf8_lossy(hex).into(),
+        ));
+    }
+
+    hex.chunks(2)
+        .map(|pair| {
+            parse_hex_u8(pair)
+                .ok_or_else(|| QemuError::UnexpectedResponse(String::from_utf8_lossy(hex).into()))
+        })
+        .collect()
+}
+
+pub(crate) fn encode_hex(data: &[u8]) -> String {
+    use std::fmt::Write;
+
+    data.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Frames a packet, escaping the characters which have a special meaning in the protocol.
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(payload.len() + 4);
+    packet.push(b'$');
+
+    let mut checksum = 0u8;
+    let mut push = |byte: u8| {
+        checksum = checksum.wrapping_add(byte);
+        packet.push(byte);
+    };
+    for byte in payload {
+        match byte {
+            b'$' | b'#' | b'}' | b'*' => {
+                push(b'}');
+                push(byte ^ 0x20);
+            }
+            _ => push(*byte),
+        }
+    }
+
+    packet.extend_from_slice(format!("#{checksum:02x}").as_bytes());
+    packet
+}
+
+/// Incrementally decodes the packets received from the stub.
+#[derive(Debug, Default)]
+struct PacketDecoder {
+    state: DecoderState,
+    payload: Vec<u8>,
+    checksum: u8,
+    escape: bool,
+    repeat: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+enum DecoderState {
+    #[default]
+    Idle,
+    Payload,
+    Checksum(Option<u8>),
+}
+
+/// A complete unit received from the stub.
+#[derive(Debug, PartialEq, Eq)]
+enum Received {
+    Ack,
+    Nack,
+    Packet(Vec<u8>),
+    /// A packet with a bad checksum.
+    Corrupted,
+}
+
+impl PacketDecoder {
+    fn push(&mut self, byte: u8) -> Option<Received> {
+        match self.state {
+            DecoderState::Idle => match byte {
+                b'+' => Some(Received::Ack),
+                b'-' => Some(Received::Nack),
+                b'$' => {
+                    self.state = DecoderState::Payload;
+                    self.payload.clear();
+                    self.checksum = 0;
+                    self.escape = false;
+                    self.repeat = false;
+                    None
+                }
+                _ => None,
+            },
+            DecoderState::Payload if byte == b'#' => {
+                self.state = DecoderState::Checksum(None);
+                None
+            }
+            DecoderState::Payload => {
+                self.checksum = self.checksum.wrapping_add(byte);
+
+                if self.escape {
+                    self.payload.push(byte ^ 0x20);
+                    self.escape = false;
+                } else if self.repeat {
+                    // Run length encoding, `c*n` repeats `c` another `n - 29` times.
+                    if let Some(last) = self.payload.last().copied() {
+                        let count = byte.saturating_sub(29) as usize;
+                        self.payload.extend(std::iter::repeat(last).take(count));
+                    }
+                    self.repeat = false;
+                } else if byte == b'}' {
+                    self.escape = true;
+                } else if byte == b'*' {
+                    self.repeat = true;
+                } else {
+                    self.payload.push(byte);
+                }
+                None
+            }
+            DecoderState::Checksum(None) => {
+                self.state = DecoderState::Checksum(Some(byte));
+                None
+            }
+            DecoderState::Checksum(Some(high)) => {
+                self.state = DecoderState::Idle;
+                let valid = parse_hex_u8(&[high, byte]) == Some(self.checksum);
+                if valid {
+                    Some(Received::Packet(std::mem::take(&mut self.payload)))
+                } else {
+                    Some(Received::Corrupted)
+                }
+            }
+        }
+    }
+}
+
+/// A connection to a GDB stub.
+///
+/// The stub is assumed to operate in all-stop mode, which means that nothing but an interrupt
+/// can be sent while the target is running.
+#[derive(Debug)]
+pub(crate) struct GdbRemote {
+    stream: TcpStream,
+    decoder: PacketDecoder,
+    no_ack: bool,
+    packet_size: usize,
+    running: bool,
+    /// Stop reply received while polling a running target.
+    stop: Option<StopReason>,
+    features: Vec<String>,
+}
+
+impl GdbRemote {
+    /// Connects to the stub at `address` and negotiates the protocol features.
+    pub(crate) fn connect(address: &str) -> Result<Self, QemuError> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        let mut remote = Self {
+            stream,
+            decoder: PacketDecoder::default(),
+            no_ack: false,
+            packet_size: DEFAULT_PACKET_SIZE,
+            running: false,
+            stop: None,
+            features: Vec::new(),
+        };
+
+        // Acknowledge anything the stub might have sent before we connected.
+        remote.stream.write_all(b"+")?;
+
+        let supported = remote.command("qSupported:swbreak+;hwbreak+;xmlRegisters=arm,riscv")?;
+        remote.features = String::from_utf8_lossy(&supported)
+            .split(';')
+            .map(str::to_string)
+            .collect();
+
+        if let Some(size) = remote
+            .features
+            .iter()
+            .find_map(|feature| feature.strip_prefix("PacketSize="))
+        {
+            remote.packet_size = usize::from_str_radix(size, 16).unwrap_or(DEFAULT_PACKET_SIZE);
+        }
+
+        if remote.supports("QStartNoAckMode+") && remote.command("QStartNoAckMode")? == b"OK" {
+            remote.no_ack = true;
+        }
+
+        // Find out whether the target is running. QEMU started with `-S` reports a stop.
+        let status = remote.command("?")?;
+        remote.running = status.is_empty();
+
+        Ok(remote)
+    }
+
+    fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+
+    /// Whether the stub supports reading the target description.
+    pub(crate) fn supports_target_description(&self) -> bool {
+        self.supports("qXfer:features:read+")
+    }
+
+    fn send(&mut self, payload: &[u8]) -> Result<(), QemuError> {
+        let packet = frame(payload);
+
+        for _ in 0..3 {
+            self.stream.write_all(&packet)?;
+
+            if self.no_ack {
+                return Ok(());
+            }
+
+            match self.receive(RESPONSE_TIMEOUT)? {
+                Received::Ack => return Ok(()),
+                Received::Nack => continue,
+                other => {
+                    return Err(QemuError::UnexpectedResponse(format!(
+                        "{other:?} instead of an acknowledgement"
+                    )))
+                }
+            }
+        }
+
+        Err(QemuError::UnexpectedResponse(
+            "The packet was rejected repeatedly".into(),
+        ))
+    }
+
+    /// Receives the next unit from the stub, waiting at most `timeout`.
+    fn receive(&mut self, timeout: Duration) -> Result<Received, QemuError> {
+        let deadline = Instant::now() + timeout;
+        let mut byte = [0u8];
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(QemuError::Timeout);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+
+            match self.stream.read(&mut byte) {
+                Ok(0) => return Err(QemuError::Disconnected),
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Err(QemuError::Timeout)
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            match self.decoder.push(byte[0]) {
+                Some(Received::Corrupted) if !self.no_ack => {
+                    self.stream.write_all(b"-")?;
+                }
+                Some(Received::Packet(packet)) => {
+                    if !self.no_ack {
+                        self.stream.write_all(b"+")?;
+                    }
+                    return Ok(Received::Packet(packet));
+                }
+                Some(received) => return Ok(received),
+                None => {}
+            }
+        }
+    }
+
+    fn receive_packet(&mut self, timeout: Duration) -> Result<Vec<u8>, QemuError> {
+        loop {
+            if let Received::Packet(packet) = self.receive(timeout)? {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// Sends a command and returns its response.
+    pub(crate) fn command(&mut self, command: &str) -> Result<Vec<u8>, QemuError> {
+        if self.running {
+            return Err(QemuError::TargetRunning);
+        }
+
+        self.send(command.as_bytes())?;
+        self.receive_packet(RESPONSE_TIMEOUT)
+    }
+
+    /// Sends a command which is answered with `OK` or an error.
+    fn command_ok(&mut self, command: &str) -> Result<(), QemuError> {
+        let response = self.command(command)?;
+        check_ok(command, &response)
+    }
+
+    /// Whether the target is currently running.
+    pub(crate) fn is_running(&mut self) -> Result<bool, QemuError> {
+        if self.running {
+            self.poll()?;
+        }
+
+        Ok(self.running)
+    }
+
+    /// Checks for a stop reply without blocking.
+    fn poll(&mut self) -> Result<(), QemuError> {
+        self.stream.set_nonblocking(true)?;
+        let mut peek = [0u8];
+        let pending = match self.stream.peek(&mut peek) {
+            Ok(0) => Err(QemuError::Disconnected),
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e.into()),
+        };
+        self.stream.set_nonblocking(false)?;
+
+        if pending? {
+            self.wait_for_stop(RESPONSE_TIMEOUT)?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the stop reply of a running target.
+    fn wait_for_stop(&mut self, timeout: Duration) -> Result<StopReason, QemuError> {
+        loop {
+            let packet = self.receive_packet(timeout)?;
+
+            // Console output of the target.
+            if let Some(output) = packet.strip_prefix(b"O") {
+                if !output.is_empty() {
+                    let output = decode_hex(output)?;
+                    tracing::info!("Target output: {}", String::from_utf8_lossy(&output));
+                    continue;
+                }
+            }
+
+            let reason = StopReason::parse(&packet)?;
+            self.running = false;
+            self.stop = Some(reason);
+            return Ok(reason);
+        }
+    }
+
+    /// Returns and clears the reason of the last stop.
+    pub(crate) fn take_stop_reason(&mut self) -> Option<StopReason> {
+        self.stop.take()
+    }
+
+    /// Resumes the target.
+    pub(crate) fn resume(&mut self) -> Result<(), QemuError> {
+        if self.running {
+            return Ok(());
+        }
+
+        self.send(b"c")?;
+        self.running = true;
+        self.stop = None;
+        Ok(())
+    }
+
+    /// Executes a single instruction.
+    pub(crate) fn step(&mut self) -> Result<StopReason, QemuError> {
+        if self.running {
+            return Err(QemuError::TargetRunning);
+        }
+
+        self.send(b"s")?;
+        self.running = true;
+        self.wait_for_stop(RESPONSE_TIMEOUT)
+    }
+
+    /// Stops the target, if it is running, and returns the reason reported by the stub.
+    pub(crate) fn interrupt(&mut self) -> Result<Option<StopReason>, QemuError> {
+        if !self.is_running()? {
+            return Ok(None);
+        }
+
+        // The interrupt is sent as a raw byte, outside of a packet.
+        self.stream.write_all(&[0x03])?;
+        self.wait_for_stop(RESPONSE_TIMEOUT).map(Some)
+    }
+
+    /// Runs `operation` with the target stopped, resuming it afterwards if it was running.
+    ///
+    /// The stop caused by the temporary halt is not reported. If the target stopped for a
+    /// different reason in the meantime, for example because it hit a breakpoint, it stays
+    /// stopped so the stop can be reported.
+    pub(crate) fn while_stopped<T>(
+        &mut self,
+        operation: impl FnOnce(&mut Self) -> Result<T, QemuError>,
+    ) -> Result<T, QemuError> {
+        let resume = match self.interrupt()? {
+            Some(reason) if reason.is_interrupt() => {
+                self.stop = None;
+                true
+            }
+            _ => false,
+        };
+
+        let result = operation(self);
+
+        if resume {
+            self.resume()?;
+        }
+
+        result
+    }
+
+    fn max_payload(&self) -> usize {
+        // Leave space for the framing, the command and the hex encoding.
+        ((self.packet_size.saturating_sub(32)) / 2).max(16)
+    }
+
+    /// Reads target memory.
+    pub(crate) fn read_memory(&mut self, address: u64, data: &mut [u8]) -> Result<(), QemuError> {
+        let chunk_size = self.max_payload();
+        self.while_stopped(|remote| {
+            for (i, chunk) in data.chunks_mut(chunk_size).enumerate() {
+                let address = address + (i * chunk_size) as u64;
+                let command = format!("m{address:x},{:x}", chunk.len());
+                let response = remote.command(&command)?;
+                check_error(&command, &response)?;
+
+                let bytes = decode_hex(&response)?;
+                if bytes.len() != chunk.len() {
+                    return Err(QemuError::MemoryAccess(address));
+                }
+                chunk.copy_from_slice(&bytes);
+            }
+            Ok(())
+        })
+    }
+
+    /// Writes target memory.
+    pub(crate) fn write_memory(&mut self, address: u64, data: &[u8]) -> Result<(), QemuError> {
+        let chunk_size = self.max_payload();
+        self.while_stopped(|remote| {
+            for (i, chunk) in data.chunks(chunk_size).enumerate() {
+                let address = address + (i * chunk_size) as u64;
+                let command = format!("M{address:x},{:x}:{}", chunk.len(), encode_hex(chunk));
+                remote
+                    .command_ok(&command)
+                    .map_err(|_| QemuError::MemoryAccess(address))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Reads a register, in target byte order.
+    pub(crate) fn read_register(&mut self, number: usize) -> Result<Vec<u8>, QemuError> {
+        self.while_stopped(|remote| {
+            let command = format!("p{number:x}");
+            let response = remote.command(&command)?;
+            check_error(&command, &response)?;
+            decode_hex(&response)
+        })
+    }
+
+    /// Writes a register, in target byte order.
+    pub(crate) fn write_register(&mut self, number: usize, value: &[u8]) -> Result<(), QemuError> {
+        self.while_stopped(|remote| {
+            remote.command_ok(&format!("P{number:x}={}", encode_hex(value)))
+        })
+    }
+
+    /// Inserts a hardware breakpoint.
+    pub(crate) fn insert_breakpoint(&mut self, address: u64, kind: u8) -> Result<(), QemuError> {
+        self.while_stopped(|remote| remote.command_ok(&format!("Z1,{address:x},{kind:x}")))
+    }
+
+    /// Removes a hardware breakpoint.
+    pub(crate) fn remove_breakpoint(&mut self, address: u64, kind: u8) -> Result<(), QemuError> {
+        self.while_stopped(|remote| remote.command_ok(&format!("z1,{address:x},{kind:x}")))
+    }
+
+    /// Runs a monitor command, such as `system_reset` on QEMU.
+    pub(crate) fn monitor(&mut self, command: &str) -> Result<String, QemuError> {
+        self.while_stopped(|remote| {
+            remote.send(format!("qRcmd,{}", encode_hex(command.as_bytes())).as_bytes())?;
+
+            let mut output = String::new();
+            loop {
+                let response = remote.receive_packet(RESPONSE_TIMEOUT)?;
+                match response.strip_prefix(b"O") {
+                    Some(hex) if !hex.is_empty() => {
+                        output.push_str(&String::from_utf8_lossy(&decode_hex(hex)?))
+                    }
+                    _ => {
+                        check_ok(command, &response)?;
+                        return Ok(output);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Reads a target description document, such as `target.xml`.
+    pub(crate) fn read_target_description(&mut self, annex: &str) -> Result<String, QemuError> {
+        let chunk_size = self.max_payload();
+        let mut document = Vec::new();
+
+        loop {
+            let command = format!(
+                "qXfer:features:read:{annex}:{:x},{chunk_size:x}",
+                document.len()
+            );
+            let response = self.command(&command)?;
+            check_error(&command, &response)?;
+
+            match response.split_first() {
+                Some((b'm', data)) => document.extend_from_slice(data),
+                Some((b'l', data)) => {
+                    document.extend_from_slice(data);
+                    break;
+                }
+                _ => {
+                    return Err(QemuError::UnexpectedResponse(
+                        String::from_utf8_lossy(&response).into(),
+                    ))
+                }
+            }
+        }
+
+        Ok(String::from_utf8_lossy(&document).into())
+    }
+
+    /// Detaches from the target, which continues running.
+    pub(crate) fn detach(&mut self) -> Result<(), QemuError> {
+        self.interrupt()?;
+        self.command_ok("D")
+    }
+}
+
+fn check_error(command: &str, response: &[u8]) -> Result<(), QemuError> {
+    if response.is_empty() {
+        return Err(QemuError::NotSupported(command.into()));
+    }
+
+    if response.len() == 3 && response[0] == b'E' {
+        return Err(QemuError::CommandFailed {
+            command: command.into(),
+            code: parse_hex_u8(&response[1..]).unwrap_or_default(),
+        });
+    }
+
+    Ok(())
+}
+
+fn check_ok(command: &str, response: &[u8]) -> Result<(), QemuError> {
+    check_error(command, response)?;
+
+    if response != b"OK" {
+        return Err(QemuError::UnexpectedResponse(
+            String::from_utf8_lossy(response).into(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(data: &[u8]) -> Vec<Received> {
+        let mut decoder = PacketDecoder::default();
+        data.iter().filter_map(|byte| decoder.push(*byte)).collect()
+    }
+
+    #[test]
+    fn framing() {
+        assert_eq!(frame(b"m0,4"), b"$m0,4#fd");
+        assert_eq!(frame(b"X0,1:#"), b"$X0,1:}\x03#9f");
+    }
+
+    #[test]
+    fn decoding() {
+        assert_eq!(
+            decode(b"+$OK#9a-$E01#a6"),
+            vec![
+                Received::Ack,
+                Received::Packet(b"OK".to_vec()),
+                Received::Nack,
+                Received::Packet(b"E01".to_vec()),
+            ]
+        );
+        assert_eq!(decode(b"$OK#00"), vec![Received::Corrupted]);
+        assert_eq!(decode(b"$}]#da"), vec![Received::Packet(b"}".to_vec())]);
+    }
+
+    #[test]
+    fn run_length_decoding() {
+        // `0* ` repeats the zero three more times.
+        assert_eq!(decode(b"$0* #7a"), vec![Received::Packet(b"0000".to_vec())]);
+    }
+
+    #[test]
+    fn stop_replies() {
+        assert_eq!(StopReason::parse(b"S05").unwrap(), StopReason::Signal(5));
+        assert!(StopReason::parse(b"T02thread:01;").unwrap().is_interrupt());
+        assert_eq!(
+            StopReason::parse(b"T05thread:01;hwbreak:;").unwrap(),
+            StopReason::Breakpoint
+        );
+        assert_eq!(
+            StopReason::parse(b"T05watch:20000010;thread:01;").unwrap(),
+            StopReason::Watchpoint(0x2000_0010)
+        );
+        assert_eq!(StopReason::parse(b"W00").unwrap(), StopReason::Exited);
+    }
+}
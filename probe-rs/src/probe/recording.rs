@@ -0,0 +1,2444 @@
+//! Recording and replaying of debug probe sessions.
+//!
+//! A [`RecordingProbe`] wraps any [`DebugProbe`] and logs every operation performed through it,
+//! including DAP register accesses, memory transfers and JTAG shifts, together with their results.
+//! The log is written as a YAML list of [`RecordedOperation`]s.
+//!
+//! A [`ReplayProbe`] serves such a recording back without any hardware attached, and the
+//! [`ReplayLister`] makes it available through a [`Lister`](super::list::Lister). This allows
+//! turning a session captured on real hardware into a deterministic regression test.
+//!
+//! Operations are replayed strictly in order. If the code under test issues a different
+//! request than the one that was recorded, the replay fails with [`ReplayError::Diverged`].
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use probe_rs_target::ScanChainElement;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    architecture::{
+        arm::{
+            ap_v1, ap_v2,
+            communication_interface::{DapProbe, SwdSequence, UninitializedArmProbe},
+            dp::{DpAddress, DpRegisterAddress},
+            memory::{ArmMemoryInterface, Status},
+            sequences::ArmDebugSequence,
+            ArmError, ArmProbeInterface, DapAccess, DapError, FullyQualifiedApAddress, SwoAccess,
+            SwoConfig,
+        },
+        riscv::{communication_interface::RiscvInterfaceBuilder, dtm::jtag_dtm::JtagDtmBuilder},
+        xtensa::communication_interface::{
+            XtensaCommunicationInterface, XtensaDebugInterfaceState,
+        },
+    },
+    probe::{
+        list::ProbeLister, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector,
+        JTAGAccess, JtagSequence, Probe, ProbeCreationError, ProbeError, ProbeFactory,
+        WireProtocol,
+    },
+    CoreStatus, Error, MemoryInterface,
+};
+
+/// A request issued to a debug probe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    /// [`DebugProbe::set_speed`]
+    SetSpeed {
+        /// The requested speed.
+        speed_khz: u32,
+    },
+    /// [`DebugProbe::select_protocol`]
+    SelectProtocol {
+        /// The requested protocol.
+        protocol: WireProtocol,
+    },
+    /// [`DebugProbe::set_scan_chain`]
+    SetScanChain {
+        /// The configured scan chain.
+        scan_chain: Vec<ScanChainElement>,
+    },
+    /// [`DebugProbe::attach`]
+    Attach,
+    /// [`DebugProbe::select_jtag_tap`]
+    SelectJtagTap {
+        /// Index of the TAP.
+        index: usize,
+    },
+    /// [`DebugProbe::detach`]
+    Detach,
+    /// [`DebugProbe::target_reset`]
+    TargetReset,
+    /// [`DebugProbe::target_reset_assert`]
+    TargetResetAssert,
+    /// [`DebugProbe::target_reset_deassert`]
+    TargetResetDeassert,
+    /// [`DebugProbe::get_target_voltage`]
+    TargetVoltage,
+
+    /// [`DebugProbe::try_get_arm_interface`]
+    ArmInterface,
+    /// [`UninitializedArmProbe::initialize`]
+    ArmInitialize {
+        /// The debug port to initialize.
+        dp: DpAddress,
+    },
+    /// [`ArmProbeInterface::reinitialize`]
+    ArmReinitialize,
+    /// [`ArmProbeInterface::access_ports`]
+    AccessPorts {
+        /// The debug port to list access ports of.
+        dp: DpAddress,
+    },
+    /// [`DapAccess::read_raw_dp_register`]
+    ReadDpRegister {
+        /// The debug port.
+        dp: DpAddress,
+        /// The register address.
+        address: DpRegisterAddress,
+    },
+    /// [`DapAccess::write_raw_dp_register`]
+    WriteDpRegister {
+        /// The debug port.
+        dp: DpAddress,
+        /// The register address.
+        address: DpRegisterAddress,
+        /// The value written.
+        value: u32,
+    },
+    /// [`DapAccess::read_raw_ap_register`]
+    ReadApRegister {
+        /// The access port.
+        ap: FullyQualifiedApAddress,
+        /// The register address.
+        address: u8,
+    },
+    /// [`DapAccess::read_raw_ap_register_repeated`]
+    ReadApRegisterRepeated {
+        /// The access port.
+        ap: FullyQualifiedApAddress,
+        /// The register address.
+        address: u8,
+        /// The number of reads.
+        count: usize,
+    },
+    /// [`DapAccess::write_raw_ap_register`]
+    WriteApRegister {
+        /// The access port.
+        ap: FullyQualifiedApAddress,
+        /// The register address.
+        address: u8,
+        /// The value written.
+        value: u32,
+    },
+    /// [`DapAccess::write_raw_ap_register_repeated`]
+    WriteApRegisterRepeated {
+        /// The access port.
+        ap: FullyQualifiedApAddress,
+        /// The register address.
+        address: u8,
+        /// The values written.
+        values: Vec<u32>,
+    },
+    /// [`DapAccess::flush`]
+    DapFlush,
+    /// [`SwdSequence::swj_sequence`]
+    SwjSequence {
+        /// Number of bits to send.
+        bit_len: u8,
+        /// The bits to send.
+        bits: u64,
+    },
+    /// [`SwdSequence::swj_pins`]
+    SwjPins {
+        /// Pin values.
+        pin_out: u32,
+        /// Pins to drive.
+        pin_select: u32,
+        /// Time to wait, in microseconds.
+        pin_wait: u32,
+    },
+    /// [`SwoAccess::enable_swo`]
+    EnableSwo,
+    /// [`SwoAccess::disable_swo`]
+    DisableSwo,
+    /// [`SwoAccess::read_swo_timeout`]
+    ReadSwo,
+
+    /// [`ArmProbeInterface::memory_interface`]
+    MemoryInterface {
+        /// The memory access port.
+        ap: FullyQualifiedApAddress,
+    },
+    /// [`ArmMemoryInterface::base_address`]
+    MemoryBaseAddress {
+        /// The memory access port.
+        ap: FullyQualifiedApAddress,
+    },
+    /// [`ArmMemoryInterface::generic_status`]
+    MemoryStatus {
+        /// The memory access port.
+        ap: FullyQualifiedApAddress,
+    },
+    /// A block read through [`MemoryInterface`].
+    ReadMemory {
+        /// The memory access port.
+        ap: FullyQualifiedApAddress,
+        /// The start address.
+        address: u64,
+        /// The access width in bits.
+        width: u8,
+        /// Number of words read.
+        count: usize,
+    },
+    /// A block write through [`MemoryInterface`].
+    WriteMemory {
+        /// The memory access port.
+        ap: FullyQualifiedApAddress,
+        /// The start address.
+        address: u64,
+        /// The access width in bits.
+        width: u8,
+        /// The written words, as little-endian bytes.
+        data: HexBytes,
+    },
+    /// [`MemoryInterface::flush`]
+    MemoryFlush {
+        /// The memory access port.
+        ap: FullyQualifiedApAddress,
+    },
+
+    /// [`JTAGAccess::scan_chain`]
+    JtagScanChain,
+    /// [`JTAGAccess::tap_reset`]
+    JtagTapReset,
+    /// [`JTAGAccess::set_idle_cycles`]
+    JtagSetIdleCycles {
+        /// Number of idle cycles.
+        idle_cycles: u8,
+    },
+    /// [`JTAGAccess::write_register`]
+    JtagWriteRegister {
+        /// The IR value.
+        address: u32,
+        /// The data shifted into DR.
+        data: HexBytes,
+        /// Length of the DR in bits.
+        len: u32,
+    },
+    /// [`JTAGAccess::write_dr`]
+    JtagWriteDr {
+        /// The data shifted into DR.
+        data: HexBytes,
+        /// Length of the DR in bits.
+        len: u32,
+    },
+    /// [`JTAGAccess::shift_raw_sequence`]
+    JtagShiftRawSequence {
+        /// The shifted sequences.
+        sequences: Vec<RecordedJtagSequence>,
+    },
+}
+
+/// A [`JtagSequence`] shifted by a [`Request::JtagShiftRawSequence`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedJtagSequence {
+    /// The TMS level held while shifting.
+    pub tms: bool,
+    /// The bits shifted out on TDI, least significant bit first.
+    pub tdi: HexBytes,
+    /// The number of bits in `tdi`.
+    pub len: usize,
+    /// Whether the bits on TDO are captured.
+    pub capture: bool,
+}
+
+impl From<&JtagSequence> for RecordedJtagSequence {
+    fn from(sequence: &JtagSequence) -> Self {
+        Self {
+            tms: sequence.tms,
+            tdi: HexBytes(sequence.tdi.clone()),
+            len: sequence.len,
+            capture: sequence.capture,
+        }
+    }
+}
+
+fn recorded_sequences(sequences: &[JtagSequence]) -> Vec<RecordedJtagSequence> {
+    sequences.iter().map(RecordedJtagSequence::from).collect()
+}
+
+/// The successful result of a [`Request`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+    /// The request returned no data.
+    None,
+    /// A single 32 bit value.
+    U32(u32),
+    /// A single 64 bit value.
+    U64(u64),
+    /// A list of 32 bit values.
+    Values(Vec<u32>),
+    /// Raw data.
+    Data(HexBytes),
+    /// The scan chain known to the probe after the request.
+    ScanChain(Option<Vec<ScanChainElement>>),
+    /// The measured target voltage.
+    Voltage(Option<f32>),
+    /// The list of access ports.
+    AccessPorts(Vec<FullyQualifiedApAddress>),
+    /// Capabilities of a memory interface.
+    MemoryInterface {
+        /// See [`MemoryInterface::supports_native_64bit_access`].
+        native_64bit_access: bool,
+        /// See [`MemoryInterface::supports_8bit_transfers`].
+        supports_8bit_transfers: bool,
+    },
+    /// The CSW of a memory access port.
+    Status {
+        /// Whether the access port is an APv2 access port.
+        v2: bool,
+        /// The raw CSW value.
+        csw: u32,
+    },
+}
+
+/// An error returned by a recorded [`Request`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordedError {
+    /// A DAP transfer error.
+    Dap(DapError),
+    /// A timeout.
+    Timeout,
+    /// Any other error. Only the message is kept, so it is replayed as a generic error.
+    Other(String),
+}
+
+impl From<&ArmError> for RecordedError {
+    fn from(error: &ArmError) -> Self {
+        match error {
+            ArmError::Dap(error) => RecordedError::Dap(*error),
+            ArmError::Timeout | ArmError::Probe(DebugProbeError::Timeout) => RecordedError::Timeout,
+            other => RecordedError::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<&DebugProbeError> for RecordedError {
+    fn from(error: &DebugProbeError) -> Self {
+        match error {
+            DebugProbeError::Timeout => RecordedError::Timeout,
+            other => RecordedError::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<&Error> for RecordedError {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::Arm(error) => error.into(),
+            Error::Probe(error) => error.into(),
+            other => RecordedError::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<RecordedError> for ArmError {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::Dap(error) => ArmError::Dap(error),
+            RecordedError::Timeout => ArmError::Timeout,
+            RecordedError::Other(message) => ArmError::Other(message),
+        }
+    }
+}
+
+impl From<RecordedError> for DebugProbeError {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::Dap(error) => DebugProbeError::Other(error.to_string()),
+            RecordedError::Timeout => DebugProbeError::Timeout,
+            RecordedError::Other(message) => DebugProbeError::Other(message),
+        }
+    }
+}
+
+impl From<RecordedError> for Error {
+    fn from(error: RecordedError) -> Self {
+        Error::Probe(error.into())
+    }
+}
+
+/// A single operation performed on a probe, together with its result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SerializedOperation", into = "SerializedOperation")]
+pub struct RecordedOperation {
+    /// The request issued to the probe.
+    pub request: Request,
+    /// The result returned by the probe.
+    pub result: Result<Response, RecordedError>,
+}
+
+/// The serialized form of a [`RecordedOperation`].
+///
+/// YAML cannot represent an enum nested directly in another enum, so the result
+/// is split into two optional fields.
+#[derive(Serialize, Deserialize)]
+struct SerializedOperation {
+    request: Request,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<Response>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RecordedError>,
+}
+
+impl From<RecordedOperation> for SerializedOperation {
+    fn from(operation: RecordedOperation) -> Self {
+        let (response, error) = match operation.result {
+            Ok(response) => (Some(response), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        Self {
+            request: operation.request,
+            response,
+            error,
+        }
+    }
+}
+
+impl From<SerializedOperation> for RecordedOperation {
+    fn from(operation: SerializedOperation) -> Self {
+        let result = match (operation.response, operation.error) {
+            (_, Some(error)) => Err(error),
+            (response, None) => Ok(response.unwrap_or(Response::None)),
+        };
+
+        Self {
+            request: operation.request,
+            result,
+        }
+    }
+}
+
+/// Binary data, serialized as a hex string.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct HexBytes(pub Vec<u8>);
+
+impl fmt::Debug for HexBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for HexBytes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{self:?}"))
+    }
+}
+
+impl<'de> Deserialize<'de> for HexBytes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+
+        if string.len() % 2 != 0 {
+            return Err(serde::de::Error::custom("hex string has an odd length"));
+        }
+
+        (0..string.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&string[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map(HexBytes)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Converts words of the given width into little-endian bytes.
+trait Word: Copy + Default {
+    const BITS: u8;
+
+    fn to_bytes(words: &[Self]) -> Vec<u8>;
+    fn from_bytes(bytes: &[u8], words: &mut [Self]);
+}
+
+macro_rules! impl_word {
+    ($ty:ty) => {
+        impl Word for $ty {
+            const BITS: u8 = <$ty>::BITS as u8;
+
+            fn to_bytes(words: &[Self]) -> Vec<u8> {
+                words.iter().flat_map(|word| word.to_le_bytes()).collect()
+            }
+
+            fn from_bytes(bytes: &[u8], words: &mut [Self]) {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+                for (word, bytes) in words.iter_mut().zip(bytes.chunks_exact(SIZE)) {
+                    *word = <$ty>::from_le_bytes(bytes.try_into().unwrap());
+                }
+            }
+        }
+    };
+}
+
+impl_word!(u8);
+impl_word!(u16);
+impl_word!(u32);
+impl_word!(u64);
+
+/// Writes recorded operations to a sink.
+struct Recorder {
+    sink: Box<dyn Write + Send>,
+    failed: bool,
+}
+
+impl Recorder {
+    fn new(sink: impl Write + Send + 'static) -> Self {
+        Self {
+            sink: Box::new(sink),
+            failed: false,
+        }
+    }
+
+    /// Records the outcome of a request and passes the original result through.
+    fn record<T, E>(
+        &mut self,
+        request: Request,
+        result: Result<T, E>,
+        response: impl FnOnce(&T) -> Response,
+    ) -> Result<T, E>
+    where
+        for<'a> &'a E: Into<RecordedError>,
+    {
+        let operation = RecordedOperation {
+            request,
+            result: match &result {
+                Ok(value) => Ok(response(value)),
+                Err(error) => Err(error.into()),
+            },
+        };
+
+        // Each operation is written as a single-element list, so that the concatenated
+        // output forms one YAML list, even if the session is aborted.
+        if let Err(error) = serde_yaml::to_writer(&mut self.sink, &[operation]) {
+            if !self.failed {
+                tracing::warn!("Failed to record probe operation: {error}");
+                self.failed = true;
+            }
+        }
+
+        result
+    }
+
+    fn flush(&mut self) {
+        if let Err(error) = self.sink.flush() {
+            tracing::warn!("Failed to flush probe recording: {error}");
+        }
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").finish_non_exhaustive()
+    }
+}
+
+/// A probe which records all operations performed on a wrapped probe.
+///
+/// See the [module level documentation](self) for details.
+///
+/// Memory transfers through an [`ArmMemoryInterface`] are recorded as a whole, not as the
+/// individual register accesses the wrapped probe uses to perform them. To capture JTAG
+/// shifts, the wrapped probe has to provide [`DebugProbe::try_as_jtag_access`].
+#[derive(Debug)]
+pub struct RecordingProbe {
+    inner: Box<dyn DebugProbe>,
+    recorder: Recorder,
+    idle_cycles: u8,
+}
+
+impl RecordingProbe {
+    /// Wraps `probe` so that all operations performed on it are written to `sink`.
+    pub fn wrap(probe: Probe, sink: impl Write + Send + 'static) -> Probe {
+        let attached = probe.attached;
+        let recording = Box::new(RecordingProbe {
+            inner: probe.inner,
+            recorder: Recorder::new(sink),
+            idle_cycles: 0,
+        });
+
+        Probe {
+            inner: recording,
+            attached,
+        }
+    }
+
+    /// Wraps `probe` so that all operations performed on it are written to the file at `path`.
+    ///
+    /// An existing file is overwritten.
+    pub fn wrap_to_file(probe: Probe, path: &Path) -> Result<Probe, std::io::Error> {
+        let file = File::create(path)?;
+
+        Ok(Self::wrap(probe, BufWriter::new(file)))
+    }
+
+    fn jtag(&mut self) -> Result<&mut dyn JTAGAccess, DebugProbeError> {
+        self.inner
+            .try_as_jtag_access()
+            .ok_or(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "JTAG",
+            })
+    }
+}
+
+impl DebugProbe for RecordingProbe {
+    fn get_name(&self) -> &str {
+        self.inner.get_name()
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.inner.speed_khz()
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        let result = self.inner.set_speed(speed_khz);
+        self.recorder
+            .record(Request::SetSpeed { speed_khz }, result, |speed| {
+                Response::U32(*speed)
+            })
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        let result = self.inner.set_scan_chain(scan_chain.clone());
+        self.recorder
+            .record(Request::SetScanChain { scan_chain }, result, |_| {
+                Response::None
+            })
+    }
+
+    fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+        self.inner.scan_chain()
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.inner.attach();
+        let scan_chain = self.inner.scan_chain().ok().map(<[_]>::to_vec);
+        self.recorder
+            .record(Request::Attach, result, |_| Response::ScanChain(scan_chain))
+    }
+
+    fn select_jtag_tap(&mut self, index: usize) -> Result<(), DebugProbeError> {
+        let result = self.inner.select_jtag_tap(index);
+        self.recorder
+            .record(Request::SelectJtagTap { index }, result, |_| Response::None)
+    }
+
+    fn detach(&mut self) -> Result<(), Error> {
+        let result = self.inner.detach();
+        let result = self
+            .recorder
+            .record(Request::Detach, result, |_| Response::None);
+        self.recorder.flush();
+        result
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.inner.target_reset();
+        self.recorder
+            .record(Request::TargetReset, result, |_| Response::None)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.inner.target_reset_assert();
+        self.recorder
+            .record(Request::TargetResetAssert, result, |_| Response::None)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.inner.target_reset_deassert();
+        self.recorder
+            .record(Request::TargetResetDeassert, result, |_| Response::None)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        let result = self.inner.select_protocol(protocol);
+        self.recorder
+            .record(Request::SelectProtocol { protocol }, result, |_| {
+                Response::None
+            })
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.inner.active_protocol()
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        self.inner.has_arm_interface()
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        let RecordingProbe {
+            inner,
+            mut recorder,
+            idle_cycles,
+        } = *self;
+
+        match inner.try_get_arm_interface() {
+            Ok(interface) => {
+                let _ =
+                    recorder.record::<_, DebugProbeError>(Request::ArmInterface, Ok(()), |_| {
+                        Response::None
+                    });
+
+                Ok(Box::new(RecordingUninitializedArmProbe {
+                    inner: interface,
+                    recorder,
+                    idle_cycles,
+                }))
+            }
+            Err((inner, error)) => {
+                let error = recorder
+                    .record::<(), _>(Request::ArmInterface, Err(error), |_| Response::None)
+                    .unwrap_err();
+
+                Err((
+                    Box::new(RecordingProbe {
+                        inner,
+                        recorder,
+                        idle_cycles,
+                    }),
+                    error,
+                ))
+            }
+        }
+    }
+
+    fn try_get_riscv_interface_builder<'probe>(
+        &'probe mut self,
+    ) -> Result<Box<dyn RiscvInterfaceBuilder<'probe> + 'probe>, DebugProbeError> {
+        if self.inner.try_as_jtag_access().is_none() {
+            return Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "RISC-V",
+            });
+        }
+
+        Ok(Box::new(JtagDtmBuilder::new(self)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        self.inner.has_riscv_interface()
+    }
+
+    fn try_get_xtensa_interface<'probe>(
+        &'probe mut self,
+        state: &'probe mut XtensaDebugInterfaceState,
+    ) -> Result<XtensaCommunicationInterface<'probe>, DebugProbeError> {
+        if self.inner.try_as_jtag_access().is_none() {
+            return Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "Xtensa",
+            });
+        }
+
+        Ok(XtensaCommunicationInterface::new(self, state))
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        self.inner.has_xtensa_interface()
+    }
+
+    fn try_as_jtag_access(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.inner.try_as_jtag_access().is_some() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        // Raw DAP access would bypass the recording.
+        None
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        let result = self.inner.get_target_voltage();
+        self.recorder
+            .record(Request::TargetVoltage, result, |voltage| {
+                Response::Voltage(*voltage)
+            })
+    }
+}
+
+impl JTAGAccess for RecordingProbe {
+    fn scan_chain(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.jtag().and_then(|jtag| jtag.scan_chain());
+        let scan_chain = self.inner.scan_chain().ok().map(<[_]>::to_vec);
+        self.recorder.record(Request::JtagScanChain, result, |_| {
+            Response::ScanChain(scan_chain)
+        })
+    }
+
+    fn tap_reset(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.jtag().and_then(|jtag| jtag.tap_reset());
+        self.recorder
+            .record(Request::JtagTapReset, result, |_| Response::None)
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        let result = self.jtag().map(|jtag| jtag.set_idle_cycles(idle_cycles));
+        self.idle_cycles = idle_cycles;
+        // The trait offers no way to report an error here.
+        let _ = self
+            .recorder
+            .record(Request::JtagSetIdleCycles { idle_cycles }, result, |_| {
+                Response::None
+            });
+    }
+
+    fn idle_cycles(&self) -> u8 {
+        self.idle_cycles
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        let result = self
+            .jtag()
+            .and_then(|jtag| jtag.write_register(address, data, len));
+        self.recorder.record(
+            Request::JtagWriteRegister {
+                address,
+                data: HexBytes(data.to_vec()),
+                len,
+            },
+            result,
+            |response| Response::Data(HexBytes(response.clone())),
+        )
+    }
+
+    fn write_dr(&mut self, data: &[u8], len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        let result = self.jtag().and_then(|jtag| jtag.write_dr(data, len));
+        self.recorder.record(
+            Request::JtagWriteDr {
+                data: HexBytes(data.to_vec()),
+                len,
+            },
+            result,
+            |response| Response::Data(HexBytes(response.clone())),
+        )
+    }
+
+    fn shift_raw_sequence(
+        &mut self,
+        sequences: &[JtagSequence],
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        let result = self
+            .jtag()
+            .and_then(|jtag| jtag.shift_raw_sequence(sequences));
+        self.recorder.record(
+            Request::JtagShiftRawSequence {
+                sequences: recorded_sequences(sequences),
+            },
+            result,
+            |response| Response::Data(HexBytes(response.clone())),
+        )
+    }
+}
+
+#[derive(Debug)]
+struct RecordingUninitializedArmProbe {
+    inner: Box<dyn UninitializedArmProbe>,
+    recorder: Recorder,
+    idle_cycles: u8,
+}
+
+impl SwdSequence for RecordingUninitializedArmProbe {
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        let result = self.inner.swj_sequence(bit_len, bits);
+        self.recorder
+            .record(Request::SwjSequence { bit_len, bits }, result, |_| {
+                Response::None
+            })
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        let result = self.inner.swj_pins(pin_out, pin_select, pin_wait);
+        self.recorder.record(
+            Request::SwjPins {
+                pin_out,
+                pin_select,
+                pin_wait,
+            },
+            result,
+            |pins| Response::U32(*pins),
+        )
+    }
+}
+
+impl UninitializedArmProbe for RecordingUninitializedArmProbe {
+    fn initialize(
+        self: Box<Self>,
+        sequence: Arc<dyn ArmDebugSequence>,
+        dp: DpAddress,
+    ) -> Result<Box<dyn ArmProbeInterface>, (Box<dyn UninitializedArmProbe>, Error)> {
+        let RecordingUninitializedArmProbe {
+            inner,
+            mut recorder,
+            idle_cycles,
+        } = *self;
+
+        match inner.initialize(sequence, dp) {
+            Ok(interface) => {
+                let _ = recorder
+                    .record::<_, Error>(Request::ArmInitialize { dp }, Ok(()), |_| Response::None);
+
+                Ok(Box::new(RecordingArmInterface {
+                    inner: interface,
+                    recorder,
+                    idle_cycles,
+                }))
+            }
+            Err((inner, error)) => {
+                let error = recorder
+                    .record::<(), _>(Request::ArmInitialize { dp }, Err(error), |_| {
+                        Response::None
+                    })
+                    .unwrap_err();
+
+                Err((
+                    Box::new(RecordingUninitializedArmProbe {
+                        inner,
+                        recorder,
+                        idle_cycles,
+                    }),
+                    error,
+                ))
+            }
+        }
+    }
+
+    fn close(self: Box<Self>) -> Probe {
+        let probe = self.inner.close();
+
+        Probe {
+            inner: Box::new(RecordingProbe {
+                inner: probe.inner,
+                recorder: self.recorder,
+                idle_cycles: self.idle_cycles,
+            }),
+            attached: probe.attached,
+        }
+    }
+}
+
+struct RecordingArmInterface {
+    inner: Box<dyn ArmProbeInterface>,
+    recorder: Recorder,
+    idle_cycles: u8,
+}
+
+impl RecordingArmInterface {
+    fn recording(&mut self) -> ArmRecorder<'_> {
+        ArmRecorder {
+            inner: &mut *self.inner,
+            recorder: &mut self.recorder,
+        }
+    }
+}
+
+/// Records the operations performed on a borrowed ARM interface.
+///
+/// This is shared by the [`RecordingArmInterface`] and the [`RecordingMemoryInterface`], which
+/// has to record the accesses made through [`ArmMemoryInterface::get_arm_probe_interface`] as well.
+struct ArmRecorder<'interface> {
+    inner: &'interface mut dyn ArmProbeInterface,
+    recorder: &'interface mut Recorder,
+}
+
+impl<'interface> ArmRecorder<'interface> {
+    fn reinitialize(self) -> Result<(), ArmError> {
+        let result = self.inner.reinitialize();
+        self.recorder
+            .record(Request::ArmReinitialize, result, |_| Response::None)
+    }
+
+    fn access_ports(self, dp: DpAddress) -> Result<BTreeSet<FullyQualifiedApAddress>, ArmError> {
+        let result = self.inner.access_ports(dp);
+        self.recorder
+            .record(Request::AccessPorts { dp }, result, |access_ports| {
+                Response::AccessPorts(access_ports.iter().cloned().collect())
+            })
+    }
+
+    fn memory_interface(
+        self,
+        access_port: &FullyQualifiedApAddress,
+    ) -> Result<Box<dyn ArmMemoryInterface + 'interface>, ArmError> {
+        let ArmRecorder { inner, recorder } = self;
+
+        let result = inner.memory_interface(access_port).and_then(|mut memory| {
+            let native_64bit_access = memory.supports_native_64bit_access();
+            let supports_8bit_transfers = memory.supports_8bit_transfers()?;
+            Ok((memory, native_64bit_access, supports_8bit_transfers))
+        });
+
+        let (memory, native_64bit_access, supports_8bit_transfers) = recorder.record(
+            Request::MemoryInterface {
+                ap: access_port.clone(),
+            },
+            result,
+            |(_, native_64bit_access, supports_8bit_transfers)| Response::MemoryInterface {
+                native_64bit_access: *native_64bit_access,
+                supports_8bit_transfers: *supports_8bit_transfers,
+            },
+        )?;
+
+        Ok(Box::new(RecordingMemoryInterface {
+            memory,
+            recorder,
+            ap: access_port.clone(),
+            native_64bit_access,
+            supports_8bit_transfers,
+        }))
+    }
+}
+
+impl SwdSequence for ArmRecorder<'_> {
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        let result = self.inner.swj_sequence(bit_len, bits);
+        self.recorder
+            .record(Request::SwjSequence { bit_len, bits }, result, |_| {
+                Response::None
+            })
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        let result = self.inner.swj_pins(pin_out, pin_select, pin_wait);
+        self.recorder.record(
+            Request::SwjPins {
+                pin_out,
+                pin_select,
+                pin_wait,
+            },
+            result,
+            |pins| Response::U32(*pins),
+        )
+    }
+}
+
+impl SwoAccess for ArmRecorder<'_> {
+    fn enable_swo(&mut self, config: &SwoConfig) -> Result<(), ArmError> {
+        let result = self.inner.enable_swo(config);
+        self.recorder
+            .record(Request::EnableSwo, result, |_| Response::None)
+    }
+
+    fn disable_swo(&mut self) -> Result<(), ArmError> {
+        let result = self.inner.disable_swo();
+        self.recorder
+            .record(Request::DisableSwo, result, |_| Response::None)
+    }
+
+    fn read_swo_timeout(&mut self, timeout: Duration) -> Result<Vec<u8>, ArmError> {
+        let result = self.inner.read_swo_timeout(timeout);
+        self.recorder.record(Request::ReadSwo, result, |data| {
+            Response::Data(HexBytes(data.clone()))
+        })
+    }
+}
+
+impl DapAccess for ArmRecorder<'_> {
+    fn read_raw_dp_register(
+        &mut self,
+        dp: DpAddress,
+        address: DpRegisterAddress,
+    ) -> Result<u32, ArmError> {
+        let result = self.inner.read_raw_dp_register(dp, address);
+        self.recorder
+            .record(Request::ReadDpRegister { dp, address }, result, |value| {
+                Response::U32(*value)
+            })
+    }
+
+    fn write_raw_dp_register(
+        &mut self,
+        dp: DpAddress,
+        address: DpRegisterAddress,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        let result = self.inner.write_raw_dp_register(dp, address, value);
+        self.recorder.record(
+            Request::WriteDpRegister { dp, address, value },
+            result,
+            |_| Response::None,
+        )
+    }
+
+    fn read_raw_ap_register(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u8,
+    ) -> Result<u32, ArmError> {
+        let result = self.inner.read_raw_ap_register(ap, address);
+        self.recorder.record(
+            Request::ReadApRegister {
+                ap: ap.clone(),
+                address,
+            },
+            result,
+            |value| Response::U32(*value),
+        )
+    }
+
+    fn read_raw_ap_register_repeated(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u8,
+        values: &mut [u32],
+    ) -> Result<(), ArmError> {
+        let result = self
+            .inner
+            .read_raw_ap_register_repeated(ap, address, values);
+        self.recorder.record(
+            Request::ReadApRegisterRepeated {
+                ap: ap.clone(),
+                address,
+                count: values.len(),
+            },
+            result,
+            |_| Response::Values(values.to_vec()),
+        )
+    }
+
+    fn write_raw_ap_register(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        let result = self.inner.write_raw_ap_register(ap, address, value);
+        self.recorder.record(
+            Request::WriteApRegister {
+                ap: ap.clone(),
+                address,
+                value,
+            },
+            result,
+            |_| Response::None,
+        )
+    }
+
+    fn write_raw_ap_register_repeated(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u8,
+        values: &[u32],
+    ) -> Result<(), ArmError> {
+        let result = self
+            .inner
+            .write_raw_ap_register_repeated(ap, address, values);
+        self.recorder.record(
+            Request::WriteApRegisterRepeated {
+                ap: ap.clone(),
+                address,
+                values: values.to_vec(),
+            },
+            result,
+            |_| Response::None,
+        )
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        let result = DapAccess::flush(&mut *self.inner);
+        self.recorder
+            .record(Request::DapFlush, result, |_| Response::None)
+    }
+}
+
+/// Implements the ARM interface traits for a type with a `recording` method returning an
+/// [`ArmRecorder`], or a result of one.
+macro_rules! impl_recording_arm_interface {
+    ($ty:ty, |$this:ident| $recording:expr) => {
+        impl SwdSequence for $ty {
+            fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+                let $this = self;
+                $recording.swj_sequence(bit_len, bits)
+            }
+
+            fn swj_pins(
+                &mut self,
+                pin_out: u32,
+                pin_select: u32,
+                pin_wait: u32,
+            ) -> Result<u32, DebugProbeError> {
+                let $this = self;
+                $recording.swj_pins(pin_out, pin_select, pin_wait)
+            }
+        }
+
+        impl SwoAccess for $ty {
+            fn enable_swo(&mut self, config: &SwoConfig) -> Result<(), ArmError> {
+                let $this = self;
+                $recording.enable_swo(config)
+            }
+
+            fn disable_swo(&mut self) -> Result<(), ArmError> {
+                let $this = self;
+                $recording.disable_swo()
+            }
+
+            fn read_swo_timeout(&mut self, timeout: Duration) -> Result<Vec<u8>, ArmError> {
+                let $this = self;
+                $recording.read_swo_timeout(timeout)
+            }
+        }
+
+        impl DapAccess for $ty {
+            fn read_raw_dp_register(
+                &mut self,
+                dp: DpAddress,
+                address: DpRegisterAddress,
+            ) -> Result<u32, ArmError> {
+                let $this = self;
+                $recording.read_raw_dp_register(dp, address)
+            }
+
+            fn write_raw_dp_register(
+                &mut self,
+                dp: DpAddress,
+                address: DpRegisterAddress,
+                value: u32,
+            ) -> Result<(), ArmError> {
+                let $this = self;
+                $recording.write_raw_dp_register(dp, address, value)
+            }
+
+            fn read_raw_ap_register(
+                &mut self,
+                ap: &FullyQualifiedApAddress,
+                address: u8,
+            ) -> Result<u32, ArmError> {
+                let $this = self;
+                $recording.read_raw_ap_register(ap, address)
+            }
+
+            fn read_raw_ap_register_repeated(
+                &mut self,
+                ap: &FullyQualifiedApAddress,
+                address: u8,
+                values: &mut [u32],
+            ) -> Result<(), ArmError> {
+                let $this = self;
+                $recording.read_raw_ap_register_repeated(ap, address, values)
+            }
+
+            fn write_raw_ap_register(
+                &mut self,
+                ap: &FullyQualifiedApAddress,
+                address: u8,
+                value: u32,
+            ) -> Result<(), ArmError> {
+                let $this = self;
+                $recording.write_raw_ap_register(ap, address, value)
+            }
+
+            fn write_raw_ap_register_repeated(
+                &mut self,
+                ap: &FullyQualifiedApAddress,
+                address: u8,
+                values: &[u32],
+            ) -> Result<(), ArmError> {
+                let $this = self;
+                $recording.write_raw_ap_register_repeated(ap, address, values)
+            }
+
+            fn flush(&mut self) -> Result<(), ArmError> {
+                let $this = self;
+                DapAccess::flush(&mut $recording)
+            }
+        }
+    };
+}
+
+impl_recording_arm_interface!(RecordingArmInterface, |this| this.recording());
+
+impl ArmProbeInterface for RecordingArmInterface {
+    fn reinitialize(&mut self) -> Result<(), ArmError> {
+        self.recording().reinitialize()
+    }
+
+    fn access_ports(
+        &mut self,
+        dp: DpAddress,
+    ) -> Result<BTreeSet<FullyQualifiedApAddress>, ArmError> {
+        self.recording().access_ports(dp)
+    }
+
+    fn close(self: Box<Self>) -> Probe {
+        let probe = self.inner.close();
+
+        Probe {
+            inner: Box::new(RecordingProbe {
+                inner: probe.inner,
+                recorder: self.recorder,
+                idle_cycles: self.idle_cycles,
+            }),
+            attached: probe.attached,
+        }
+    }
+
+    fn current_debug_port(&self) -> DpAddress {
+        self.inner.current_debug_port()
+    }
+
+    fn memory_interface(
+        &mut self,
+        access_port: &FullyQualifiedApAddress,
+    ) -> Result<Box<dyn ArmMemoryInterface + '_>, ArmError> {
+        self.recording().memory_interface(access_port)
+    }
+}
+
+/// Records memory transfers through a memory access port.
+///
+/// The memory interface of the wrapped probe is created once and used for all transfers, so
+/// that recording does not change the traffic to the target. Accesses through
+/// [`ArmMemoryInterface::get_arm_probe_interface`] go to the interface the wrapped memory
+/// interface was created from, and are recorded like those on the [`RecordingArmInterface`].
+struct RecordingMemoryInterface<'interface> {
+    memory: Box<dyn ArmMemoryInterface + 'interface>,
+    recorder: &'interface mut Recorder,
+    ap: FullyQualifiedApAddress,
+    native_64bit_access: bool,
+    supports_8bit_transfers: bool,
+}
+
+impl RecordingMemoryInterface<'_> {
+    fn recording(&mut self) -> Result<ArmRecorder<'_>, DebugProbeError> {
+        Ok(ArmRecorder {
+            inner: self.memory.get_arm_probe_interface()?,
+            recorder: &mut *self.recorder,
+        })
+    }
+
+    fn read<W: Word>(
+        &mut self,
+        address: u64,
+        data: &mut [W],
+        read: impl FnOnce(&mut dyn ArmMemoryInterface, u64, &mut [W]) -> Result<(), ArmError>,
+    ) -> Result<(), ArmError> {
+        let result = read(&mut *self.memory, address, data);
+
+        self.recorder.record(
+            Request::ReadMemory {
+                ap: self.ap.clone(),
+                address,
+                width: W::BITS,
+                count: data.len(),
+            },
+            result,
+            |_| Response::Data(HexBytes(W::to_bytes(data))),
+        )
+    }
+
+    fn write<W: Word>(
+        &mut self,
+        address: u64,
+        data: &[W],
+        write: impl FnOnce(&mut dyn ArmMemoryInterface, u64, &[W]) -> Result<(), ArmError>,
+    ) -> Result<(), ArmError> {
+        let result = write(&mut *self.memory, address, data);
+
+        self.recorder.record(
+            Request::WriteMemory {
+                ap: self.ap.clone(),
+                address,
+                width: W::BITS,
+                data: HexBytes(W::to_bytes(data)),
+            },
+            result,
+            |_| Response::None,
+        )
+    }
+}
+
+impl_recording_arm_interface!(RecordingMemoryInterface<'_>, |this| this.recording()?);
+
+impl ArmProbeInterface for RecordingMemoryInterface<'_> {
+    fn reinitialize(&mut self) -> Result<(), ArmError> {
+        self.recording()?.reinitialize()
+    }
+
+    fn access_ports(
+        &mut self,
+        dp: DpAddress,
+    ) -> Result<BTreeSet<FullyQualifiedApAddress>, ArmError> {
+        self.recording()?.access_ports(dp)
+    }
+
+    fn close(self: Box<Self>) -> Probe {
+        unreachable!("A memory interface is only handed out as a borrowed ARM interface")
+    }
+
+    fn current_debug_port(&self) -> DpAddress {
+        self.ap.dp()
+    }
+
+    fn memory_interface(
+        &mut self,
+        access_port: &FullyQualifiedApAddress,
+    ) -> Result<Box<dyn ArmMemoryInterface + '_>, ArmError> {
+        self.recording()?.memory_interface(access_port)
+    }
+}
+
+impl MemoryInterface<ArmError> for RecordingMemoryInterface<'_> {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        self.native_64bit_access
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        self.read(address, data, |memory, address, data| {
+            memory.read_64(address, data)
+        })
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        self.read(address, data, |memory, address, data| {
+            memory.read_32(address, data)
+        })
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        self.read(address, data, |memory, address, data| {
+            memory.read_16(address, data)
+        })
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        self.read(address, data, |memory, address, data| {
+            memory.read_8(address, data)
+        })
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        self.write(address, data, |memory, address, data| {
+            memory.write_64(address, data)
+        })
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        self.write(address, data, |memory, address, data| {
+            memory.write_32(address, data)
+        })
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+        self.write(address, data, |memory, address, data| {
+            memory.write_16(address, data)
+        })
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        self.write(address, data, |memory, address, data| {
+            memory.write_8(address, data)
+        })
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(self.supports_8bit_transfers)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        let result = self.memory.flush();
+
+        self.recorder.record(
+            Request::MemoryFlush {
+                ap: self.ap.clone(),
+            },
+            result,
+            |_| Response::None,
+        )
+    }
+}
+
+impl ArmMemoryInterface for RecordingMemoryInterface<'_> {
+    fn fully_qualified_address(&self) -> FullyQualifiedApAddress {
+        self.ap.clone()
+    }
+
+    fn base_address(&mut self) -> Result<u64, ArmError> {
+        let result = self.memory.base_address();
+
+        self.recorder.record(
+            Request::MemoryBaseAddress {
+                ap: self.ap.clone(),
+            },
+            result,
+            |address| Response::U64(*address),
+        )
+    }
+
+    fn get_swd_sequence(&mut self) -> Result<&mut dyn SwdSequence, DebugProbeError> {
+        Ok(self)
+    }
+
+    fn get_arm_probe_interface(&mut self) -> Result<&mut dyn ArmProbeInterface, DebugProbeError> {
+        Ok(self)
+    }
+
+    fn get_dap_access(&mut self) -> Result<&mut dyn DapAccess, DebugProbeError> {
+        Ok(self)
+    }
+
+    fn generic_status(&mut self) -> Result<Status, ArmError> {
+        let result = self.memory.generic_status();
+
+        self.recorder.record(
+            Request::MemoryStatus {
+                ap: self.ap.clone(),
+            },
+            result,
+            |status| match status {
+                Status::V1(csw) => Response::Status {
+                    v2: false,
+                    csw: (*csw).into(),
+                },
+                Status::V2(csw) => Response::Status {
+                    v2: true,
+                    csw: (*csw).into(),
+                },
+            },
+        )
+    }
+
+    fn update_core_status(&mut self, state: CoreStatus) {
+        self.memory.update_core_status(state);
+    }
+}
+
+/// An error which occurs while replaying a recorded session.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum ReplayError {
+    /// The recording could not be read.
+    Io(#[from] std::io::Error),
+
+    /// The recording could not be parsed.
+    Parse(#[from] serde_yaml::Error),
+
+    /// The recording has ended, but {0:?} was requested.
+    EndOfRecording(Box<Request>),
+
+    /// The replay diverged from the recording: expected {expected:?}, but got {actual:?}.
+    Diverged {
+        /// The next request in the recording.
+        expected: Box<Request>,
+        /// The request which was actually issued.
+        actual: Box<Request>,
+    },
+
+    /// The recorded response {0:?} does not fit the request.
+    UnexpectedResponse(Response),
+}
+
+impl ProbeError for ReplayError {}
+
+impl From<ReplayError> for ArmError {
+    fn from(error: ReplayError) -> Self {
+        ArmError::Probe(error.into())
+    }
+}
+
+impl From<ReplayError> for Error {
+    fn from(error: ReplayError) -> Self {
+        Error::Probe(error.into())
+    }
+}
+
+/// Loads a recording written by a [`RecordingProbe`].
+pub fn load_recording(path: &Path) -> Result<Vec<RecordedOperation>, ReplayError> {
+    let recording = std::fs::read_to_string(path)?;
+
+    parse_recording(&recording)
+}
+
+/// Parses a recording written by a [`RecordingProbe`].
+pub fn parse_recording(recording: &str) -> Result<Vec<RecordedOperation>, ReplayError> {
+    if recording.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(serde_yaml::from_str(recording)?)
+}
+
+/// The recorded operations which remain to be replayed.
+#[derive(Debug)]
+struct Replay {
+    operations: VecDeque<RecordedOperation>,
+}
+
+impl Replay {
+    /// Returns the recorded result for `request`, which has to be the next recorded request.
+    fn next<E>(&mut self, request: Request) -> Result<Response, E>
+    where
+        E: From<ReplayError> + From<RecordedError>,
+    {
+        let Some(operation) = self.operations.pop_front() else {
+            tracing::error!("Recording ended, but got {request:?}");
+            return Err(ReplayError::EndOfRecording(Box::new(request)).into());
+        };
+
+        if operation.request != request {
+            tracing::error!(
+                "Replay diverged: expected {:?}, got {:?}",
+                operation.request,
+                request
+            );
+            return Err(ReplayError::Diverged {
+                expected: Box::new(operation.request),
+                actual: Box::new(request),
+            }
+            .into());
+        }
+
+        Ok(operation.result?)
+    }
+
+    fn next_unit<E>(&mut self, request: Request) -> Result<(), E>
+    where
+        E: From<ReplayError> + From<RecordedError>,
+    {
+        match self.next::<E>(request)? {
+            Response::None => Ok(()),
+            other => Err(ReplayError::UnexpectedResponse(other).into()),
+        }
+    }
+
+    fn next_u32<E>(&mut self, request: Request) -> Result<u32, E>
+    where
+        E: From<ReplayError> + From<RecordedError>,
+    {
+        match self.next::<E>(request)? {
+            Response::U32(value) => Ok(value),
+            other => Err(ReplayError::UnexpectedResponse(other).into()),
+        }
+    }
+
+    fn next_data<E>(&mut self, request: Request) -> Result<Vec<u8>, E>
+    where
+        E: From<ReplayError> + From<RecordedError>,
+    {
+        match self.next::<E>(request)? {
+            Response::Data(data) => Ok(data.0),
+            other => Err(ReplayError::UnexpectedResponse(other).into()),
+        }
+    }
+
+    fn next_scan_chain<E>(&mut self, request: Request) -> Result<Option<Vec<ScanChainElement>>, E>
+    where
+        E: From<ReplayError> + From<RecordedError>,
+    {
+        match self.next::<E>(request)? {
+            Response::ScanChain(scan_chain) => Ok(scan_chain),
+            other => Err(ReplayError::UnexpectedResponse(other).into()),
+        }
+    }
+}
+
+/// A probe which replays a session recorded by a [`RecordingProbe`].
+///
+/// See the [module level documentation](self) for details.
+#[derive(Debug)]
+pub struct ReplayProbe {
+    replay: Replay,
+    speed_khz: u32,
+    protocol: Option<WireProtocol>,
+    scan_chain: Option<Vec<ScanChainElement>>,
+    idle_cycles: u8,
+}
+
+impl ReplayProbe {
+    /// Creates a probe which replays the given operations.
+    pub fn new(operations: Vec<RecordedOperation>) -> Self {
+        Self {
+            replay: Replay {
+                operations: operations.into(),
+            },
+            speed_khz: 0,
+            protocol: None,
+            scan_chain: None,
+            idle_cycles: 0,
+        }
+    }
+
+    /// Creates a probe which replays the recording in the file at `path`.
+    pub fn from_file(path: &Path) -> Result<Self, ReplayError> {
+        Ok(Self::new(load_recording(path)?))
+    }
+
+    /// Returns the number of recorded operations which have not been replayed yet.
+    pub fn remaining_operations(&self) -> usize {
+        self.replay.operations.len()
+    }
+
+    /// Makes a generic probe out of the [`ReplayProbe`].
+    pub fn into_probe(self) -> Probe {
+        Probe::from_specific_probe(Box::new(self))
+    }
+}
+
+impl DebugProbe for ReplayProbe {
+    fn get_name(&self) -> &str {
+        "Replay probe"
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        let speed = self
+            .replay
+            .next_u32::<DebugProbeError>(Request::SetSpeed { speed_khz })?;
+        self.speed_khz = speed;
+        Ok(speed)
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        self.replay
+            .next_unit::<DebugProbeError>(Request::SetScanChain {
+                scan_chain: scan_chain.clone(),
+            })?;
+        self.scan_chain = Some(scan_chain);
+        Ok(())
+    }
+
+    fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+        match &self.scan_chain {
+            Some(chain) => Ok(chain),
+            None => Err(DebugProbeError::Other(
+                "No scan chain was recorded".to_string(),
+            )),
+        }
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        self.scan_chain = self
+            .replay
+            .next_scan_chain::<DebugProbeError>(Request::Attach)?;
+        Ok(())
+    }
+
+    fn select_jtag_tap(&mut self, index: usize) -> Result<(), DebugProbeError> {
+        self.replay.next_unit(Request::SelectJtagTap { index })
+    }
+
+    fn detach(&mut self) -> Result<(), Error> {
+        self.replay.next_unit(Request::Detach)
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.replay.next_unit(Request::TargetReset)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.replay.next_unit(Request::TargetResetAssert)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.replay.next_unit(Request::TargetResetDeassert)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.replay
+            .next_unit::<DebugProbeError>(Request::SelectProtocol { protocol })?;
+        self.protocol = Some(protocol);
+        Ok(())
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.protocol
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        true
+    }
+
+    fn try_get_arm_interface<'probe>(
+        mut self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        match self.replay.next_unit(Request::ArmInterface) {
+            Ok(()) => Ok(Box::new(ReplayArmInterface { probe: self })),
+            Err(error) => Err((self, error)),
+        }
+    }
+
+    fn try_get_riscv_interface_builder<'probe>(
+        &'probe mut self,
+    ) -> Result<Box<dyn RiscvInterfaceBuilder<'probe> + 'probe>, DebugProbeError> {
+        Ok(Box::new(JtagDtmBuilder::new(self)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        true
+    }
+
+    fn try_get_xtensa_interface<'probe>(
+        &'probe mut self,
+        state: &'probe mut XtensaDebugInterfaceState,
+    ) -> Result<XtensaCommunicationInterface<'probe>, DebugProbeError> {
+        Ok(XtensaCommunicationInterface::new(self, state))
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        true
+    }
+
+    fn try_as_jtag_access(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        match self
+            .replay
+            .next::<DebugProbeError>(Request::TargetVoltage)?
+        {
+            Response::Voltage(voltage) => Ok(voltage),
+            other => Err(ReplayError::UnexpectedResponse(other).into()),
+        }
+    }
+}
+
+impl JTAGAccess for ReplayProbe {
+    fn scan_chain(&mut self) -> Result<(), DebugProbeError> {
+        self.scan_chain = self
+            .replay
+            .next_scan_chain::<DebugProbeError>(Request::JtagScanChain)?;
+        Ok(())
+    }
+
+    fn tap_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.replay.next_unit(Request::JtagTapReset)
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        self.idle_cycles = idle_cycles;
+        if let Err(error) = self
+            .replay
+            .next_unit::<DebugProbeError>(Request::JtagSetIdleCycles { idle_cycles })
+        {
+            tracing::warn!("Failed to replay setting the idle cycles: {error}");
+        }
+    }
+
+    fn idle_cycles(&self) -> u8 {
+        self.idle_cycles
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.replay.next_data(Request::JtagWriteRegister {
+            address,
+            data: HexBytes(data.to_vec()),
+            len,
+        })
+    }
+
+    fn write_dr(&mut self, data: &[u8], len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        self.replay.next_data(Request::JtagWriteDr {
+            data: HexBytes(data.to_vec()),
+            len,
+        })
+    }
+
+    fn shift_raw_sequence(
+        &mut self,
+        sequences: &[JtagSequence],
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.replay.next_data(Request::JtagShiftRawSequence {
+            sequences: recorded_sequences(sequences),
+        })
+    }
+}
+
+/// The ARM interface of a [`ReplayProbe`].
+///
+/// The same type is used before and after initialization, as the initialization
+/// itself is not replayed in detail.
+#[derive(Debug)]
+struct ReplayArmInterface {
+    probe: Box<ReplayProbe>,
+}
+
+impl ReplayArmInterface {
+    fn replay(&mut self) -> &mut Replay {
+        &mut self.probe.replay
+    }
+}
+
+impl SwdSequence for ReplayArmInterface {
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        self.replay()
+            .next_unit(Request::SwjSequence { bit_len, bits })
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        self.replay().next_u32(Request::SwjPins {
+            pin_out,
+            pin_select,
+            pin_wait,
+        })
+    }
+}
+
+impl UninitializedArmProbe for ReplayArmInterface {
+    fn initialize(
+        mut self: Box<Self>,
+        _sequence: Arc<dyn ArmDebugSequence>,
+        dp: DpAddress,
+    ) -> Result<Box<dyn ArmProbeInterface>, (Box<dyn UninitializedArmProbe>, Error)> {
+        match self.replay().next_unit(Request::ArmInitialize { dp }) {
+            Ok(()) => Ok(self),
+            Err(error) => Err((self, error)),
+        }
+    }
+
+    fn close(self: Box<Self>) -> Probe {
+        Probe::from_attached_probe(self.probe)
+    }
+}
+
+impl SwoAccess for ReplayArmInterface {
+    fn enable_swo(&mut self, _config: &SwoConfig) -> Result<(), ArmError> {
+        self.replay().next_unit(Request::EnableSwo)
+    }
+
+    fn disable_swo(&mut self) -> Result<(), ArmError> {
+        self.replay().next_unit(Request::DisableSwo)
+    }
+
+    fn read_swo_timeout(&mut self, _timeout: Duration) -> Result<Vec<u8>, ArmError> {
+        self.replay().next_data(Request::ReadSwo)
+    }
+}
+
+impl DapAccess for ReplayArmInterface {
+    fn read_raw_dp_register(
+        &mut self,
+        dp: DpAddress,
+        address: DpRegisterAddress,
+    ) -> Result<u32, ArmError> {
+        self.replay()
+            .next_u32(Request::ReadDpRegister { dp, address })
+    }
+
+    fn write_raw_dp_register(
+        &mut self,
+        dp: DpAddress,
+        address: DpRegisterAddress,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.replay()
+            .next_unit(Request::WriteDpRegister { dp, address, value })
+    }
+
+    fn read_raw_ap_register(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u8,
+    ) -> Result<u32, ArmError> {
+        self.replay().next_u32(Request::ReadApRegister {
+            ap: ap.clone(),
+            address,
+        })
+    }
+
+    fn read_raw_ap_register_repeated(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u8,
+        values: &mut [u32],
+    ) -> Result<(), ArmError> {
+        let response = self
+            .replay()
+            .next::<ArmError>(Request::ReadApRegisterRepeated {
+                ap: ap.clone(),
+                address,
+                count: values.len(),
+            })?;
+
+        match response {
+            Response::Values(recorded) if recorded.len() == values.len() => {
+                values.copy_from_slice(&recorded);
+                Ok(())
+            }
+            other => Err(ReplayError::UnexpectedResponse(other).into()),
+        }
+    }
+
+    fn write_raw_ap_register(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.replay().next_unit(Request::WriteApRegister {
+            ap: ap.clone(),
+            address,
+            value,
+        })
+    }
+
+    fn write_raw_ap_register_repeated(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u8,
+        values: &[u32],
+    ) -> Result<(), ArmError> {
+        self.replay().next_unit(Request::WriteApRegisterRepeated {
+            ap: ap.clone(),
+            address,
+            values: values.to_vec(),
+        })
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        self.replay().next_unit(Request::DapFlush)
+    }
+}
+
+impl ArmProbeInterface for ReplayArmInterface {
+    fn reinitialize(&mut self) -> Result<(), ArmError> {
+        self.replay().next_unit(Request::ArmReinitialize)
+    }
+
+    fn access_ports(
+        &mut self,
+        dp: DpAddress,
+    ) -> Result<BTreeSet<FullyQualifiedApAddress>, ArmError> {
+        match self
+            .replay()
+            .next::<ArmError>(Request::AccessPorts { dp })?
+        {
+            Response::AccessPorts(access_ports) => Ok(access_ports.into_iter().collect()),
+            other => Err(ReplayError::UnexpectedResponse(other).into()),
+        }
+    }
+
+    fn close(self: Box<Self>) -> Probe {
+        Probe::from_attached_probe(self.probe)
+    }
+
+    fn current_debug_port(&self) -> DpAddress {
+        // The recording does not track debug port switches, so we report the default one.
+        DpAddress::Default
+    }
+
+    fn memory_interface(
+        &mut self,
+        access_port: &FullyQualifiedApAddress,
+    ) -> Result<Box<dyn ArmMemoryInterface + '_>, ArmError> {
+        let response = self.replay().next::<ArmError>(Request::MemoryInterface {
+            ap: access_port.clone(),
+        })?;
+
+        let Response::MemoryInterface {
+            native_64bit_access,
+            supports_8bit_transfers,
+        } = response
+        else {
+            return Err(ReplayError::UnexpectedResponse(response).into());
+        };
+
+        Ok(Box::new(ReplayMemoryInterface {
+            interface: self,
+            ap: access_port.clone(),
+            native_64bit_access,
+            supports_8bit_transfers,
+        }))
+    }
+}
+
+struct ReplayMemoryInterface<'interface> {
+    interface: &'interface mut ReplayArmInterface,
+    ap: FullyQualifiedApAddress,
+    native_64bit_access: bool,
+    supports_8bit_transfers: bool,
+}
+
+impl ReplayMemoryInterface<'_> {
+    fn read<W: Word>(&mut self, address: u64, data: &mut [W]) -> Result<(), ArmError> {
+        let recorded = self
+            .interface
+            .replay()
+            .next_data::<ArmError>(Request::ReadMemory {
+                ap: self.ap.clone(),
+                address,
+                width: W::BITS,
+                count: data.len(),
+            })?;
+
+        if recorded.len() != data.len() * usize::from(W::BITS / 8) {
+            return Err(ReplayError::UnexpectedResponse(Response::Data(HexBytes(recorded))).into());
+        }
+
+        W::from_bytes(&recorded, data);
+        Ok(())
+    }
+
+    fn write<W: Word>(&mut self, address: u64, data: &[W]) -> Result<(), ArmError> {
+        self.interface.replay().next_unit(Request::WriteMemory {
+            ap: self.ap.clone(),
+            address,
+            width: W::BITS,
+            data: HexBytes(W::to_bytes(data)),
+        })
+    }
+}
+
+impl MemoryInterface<ArmError> for ReplayMemoryInterface<'_> {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        self.native_64bit_access
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        self.read(address, data)
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        self.read(address, data)
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        self.read(address, data)
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        self.read(address, data)
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        self.write(address, data)
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        self.write(address, data)
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+        self.write(address, data)
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        self.write(address, data)
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(self.supports_8bit_transfers)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        self.interface.replay().next_unit(Request::MemoryFlush {
+            ap: self.ap.clone(),
+        })
+    }
+}
+
+impl ArmMemoryInterface for ReplayMemoryInterface<'_> {
+    fn fully_qualified_address(&self) -> FullyQualifiedApAddress {
+        self.ap.clone()
+    }
+
+    fn base_address(&mut self) -> Result<u64, ArmError> {
+        let response = self
+            .interface
+            .replay()
+            .next::<ArmError>(Request::MemoryBaseAddress {
+                ap: self.ap.clone(),
+            })?;
+
+        match response {
+            Response::U64(address) => Ok(address),
+            other => Err(ReplayError::UnexpectedResponse(other).into()),
+        }
+    }
+
+    fn get_swd_sequence(&mut self) -> Result<&mut dyn SwdSequence, DebugProbeError> {
+        Ok(self.interface)
+    }
+
+    fn get_arm_probe_interface(&mut self) -> Result<&mut dyn ArmProbeInterface, DebugProbeError> {
+        Ok(self.interface)
+    }
+
+    fn get_dap_access(&mut self) -> Result<&mut dyn DapAccess, DebugProbeError> {
+        Ok(self.interface)
+    }
+
+    fn generic_status(&mut self) -> Result<Status, ArmError> {
+        let response = self
+            .interface
+            .replay()
+            .next::<ArmError>(Request::MemoryStatus {
+                ap: self.ap.clone(),
+            })?;
+
+        match response {
+            Response::Status { v2: false, csw } => {
+                Ok(Status::V1(ap_v1::memory_ap::registers::CSW::try_from(csw)?))
+            }
+            Response::Status { v2: true, csw } => {
+                Ok(Status::V2(ap_v2::registers::CSW::try_from(csw)?))
+            }
+            other => Err(ReplayError::UnexpectedResponse(other).into()),
+        }
+    }
+}
+
+/// Probe factory used for the [`DebugProbeInfo`] of replayed probes.
+///
+/// Replayed probes can only be opened through a [`ReplayLister`].
+#[derive(Debug)]
+pub struct ReplayProbeFactory;
+
+impl fmt::Display for ReplayProbeFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Replay")
+    }
+}
+
+impl ProbeFactory for ReplayProbeFactory {
+    fn open(&self, _selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+        Err(DebugProbeError::ProbeCouldNotBeCreated(
+            ProbeCreationError::NotFound,
+        ))
+    }
+
+    fn list_probes(&self) -> Vec<DebugProbeInfo> {
+        vec![]
+    }
+}
+
+/// A [`ProbeLister`] which offers a single probe replaying a recorded session.
+///
+/// Every time the probe is opened, the recording is replayed from the start.
+#[derive(Debug)]
+pub struct ReplayLister {
+    operations: Vec<RecordedOperation>,
+}
+
+impl ReplayLister {
+    /// The USB vendor ID reported for the replayed probe.
+    pub const VENDOR_ID: u16 = 0;
+    /// The USB product ID reported for the replayed probe.
+    pub const PRODUCT_ID: u16 = 0;
+
+    /// Creates a lister which replays the given operations.
+    pub fn new(operations: Vec<RecordedOperation>) -> Self {
+        Self { operations }
+    }
+
+    /// Creates a lister which replays the recording in the file at `path`.
+    pub fn from_file(path: &Path) -> Result<Self, ReplayError> {
+        Ok(Self::new(load_recording(path)?))
+    }
+
+    fn probe_info(&self) -> DebugProbeInfo {
+        DebugProbeInfo::new(
+            "Replay probe",
+            Self::VENDOR_ID,
+            Self::PRODUCT_ID,
+            None,
+            &ReplayProbeFactory,
+            None,
+        )
+    }
+}
+
+impl ProbeLister for ReplayLister {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Probe, DebugProbeError> {
+        if selector.vendor_id != Self::VENDOR_ID || selector.product_id != Self::PRODUCT_ID {
+            return Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NotFound,
+            ));
+        }
+
+        Ok(ReplayProbe::new(self.operations.clone()).into_probe())
+    }
+
+    fn list_all(&self) -> Vec<DebugProbeInfo> {
+        vec![self.probe_info()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::probe::fake_probe::FakeProbe;
+    use std::sync::Mutex;
+
+    /// A sink which can be read back after the recording probe is gone.
+    #[derive(Clone, Default)]
+    struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedSink {
+        fn operations(&self) -> Vec<RecordedOperation> {
+            let data = self.0.lock().unwrap();
+            parse_recording(std::str::from_utf8(&data).unwrap()).unwrap()
+        }
+    }
+
+    #[test]
+    fn hex_bytes_roundtrip() {
+        let bytes = HexBytes(vec![0x00, 0x12, 0xab, 0xff]);
+        let serialized = serde_yaml::to_string(&bytes).unwrap();
+
+        assert_eq!(serialized.trim(), "0012abff");
+        assert_eq!(
+            serde_yaml::from_str::<HexBytes>(&serialized).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn record_and_replay_probe_operations() {
+        let sink = SharedSink::default();
+        let mut probe = RecordingProbe::wrap(FakeProbe::new().into_probe(), sink.clone());
+
+        probe.set_speed(4000).unwrap();
+        probe.select_protocol(WireProtocol::Jtag).unwrap();
+        assert!(probe.target_reset().is_err());
+
+        let operations = sink.operations();
+        assert_eq!(operations.len(), 3);
+        assert_eq!(
+            operations[0],
+            RecordedOperation {
+                request: Request::SetSpeed { speed_khz: 4000 },
+                result: Ok(Response::U32(4000)),
+            }
+        );
+
+        let mut replay = ReplayProbe::new(operations).into_probe();
+        assert_eq!(replay.set_speed(4000).unwrap(), 4000);
+        assert_eq!(replay.speed_khz(), 4000);
+        replay.select_protocol(WireProtocol::Jtag).unwrap();
+        assert_eq!(replay.protocol(), Some(WireProtocol::Jtag));
+        assert!(replay.target_reset().is_err());
+    }
+
+    #[test]
+    fn record_and_replay_raw_jtag_sequences() {
+        let sequences = [
+            JtagSequence {
+                tms: true,
+                tdi: vec![0x00],
+                len: 5,
+                capture: false,
+            },
+            JtagSequence {
+                tms: false,
+                tdi: vec![0xff, 0x01],
+                len: 9,
+                capture: true,
+            },
+        ];
+        let recorded = vec![RecordedOperation {
+            request: Request::JtagShiftRawSequence {
+                sequences: recorded_sequences(&sequences),
+            },
+            result: Ok(Response::Data(HexBytes(vec![0x5a, 0x01]))),
+        }];
+
+        // Raw sequences are only shifted on an attached probe.
+        let mut replay = ReplayProbe::new(recorded.clone()).into_probe();
+        replay.attached = true;
+
+        let sink = SharedSink::default();
+        let mut probe = RecordingProbe::wrap(replay, sink.clone());
+        assert_eq!(
+            probe
+                .try_as_jtag_access()
+                .unwrap()
+                .shift_raw_sequence(&sequences)
+                .unwrap(),
+            [0x5a, 0x01]
+        );
+
+        assert_eq!(sink.operations(), recorded);
+    }
+
+    #[test]
+    fn replay_detects_divergence() {
+        let mut probe = ReplayProbe::new(vec![RecordedOperation {
+            request: Request::SetSpeed { speed_khz: 1000 },
+            result: Ok(Response::U32(1000)),
+        }]);
+
+        let error = probe.set_speed(2000).unwrap_err();
+        assert!(matches!(
+            error,
+            DebugProbeError::ProbeSpecific(ref e) if matches!(e.downcast_ref::<ReplayError>(), Some(ReplayError::Diverged { .. }))
+        ));
+
+        let error = probe.set_speed(1000).unwrap_err();
+        assert!(matches!(
+            error,
+            DebugProbeError::ProbeSpecific(ref e) if matches!(e.downcast_ref::<ReplayError>(), Some(ReplayError::EndOfRecording(_)))
+        ));
+    }
+}
@@ -0,0 +1,676 @@
+//! Playback of SVF and XSVF files over low-level JTAG access.
+//!
+//! Serial Vector Format (SVF) files, and their compact binary Xilinx variant (XSVF), describe
+//! JTAG scans and state transitions for the whole scan chain. Vendors use them to program CPLDs
+//! and FPGAs, and to run test vectors, independently of the JTAG adapter in use.
+//!
+//! Files are first compiled into a list of operations, so syntax errors are reported before the
+//! JTAG chain is touched, and then played using [`JTAGAccess::shift_raw_sequence`].
+
+mod parser;
+mod xsvf;
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use bitvec::prelude::*;
+
+use super::common::{JtagState, RegisterState};
+use super::{DebugProbeError, JTAGAccess, JtagSequence};
+
+/// The number of bits which are queued before they are sent to the probe.
+const MAX_PENDING_BITS: usize = 1 << 16;
+
+/// An error which occurred while parsing or playing an SVF or XSVF file.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum SvfError {
+    /// Invalid statement at {location}: {message}
+    Syntax {
+        /// The location of the statement.
+        location: Location,
+        /// A description of the problem.
+        message: String,
+    },
+
+    /// The {statement} statement at {location} is not supported.
+    Unsupported {
+        /// The location of the statement.
+        location: Location,
+        /// The name of the statement.
+        statement: String,
+    },
+
+    /// TDO mismatch at {location}: expected {expected}, captured {captured} with mask {mask}.
+    TdoMismatch {
+        /// The location of the scan.
+        location: Location,
+        /// The expected TDO bits, as a hexadecimal number.
+        expected: String,
+        /// The captured TDO bits, as a hexadecimal number.
+        captured: String,
+        /// The bits which are compared, as a hexadecimal number.
+        mask: String,
+    },
+
+    /// An error occurred while accessing the JTAG chain at {location}.
+    Probe {
+        /// The location of the statement.
+        location: Location,
+        /// The underlying error.
+        #[source]
+        source: DebugProbeError,
+    },
+}
+
+/// The location of a statement in an SVF or XSVF file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    /// A line of an SVF file, starting at 1.
+    Line(usize),
+    /// The byte offset of an XSVF command.
+    Offset(usize),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Line(line) => write!(f, "line {line}"),
+            Location::Offset(offset) => write!(f, "offset {offset:#x}"),
+        }
+    }
+}
+
+/// Statistics about a played file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlaybackSummary {
+    /// The number of IR and DR scans.
+    pub scans: usize,
+    /// The number of scans whose TDO bits were checked.
+    pub tdo_checks: usize,
+}
+
+/// Plays an SVF file on the JTAG chain.
+///
+/// Playback starts with a TAP reset. `FREQUENCY` statements change the speed of the probe, and
+/// `RUNTEST` statements clock TCK and wait as requested. `SCK` cycles are clocked on TCK. `PIO`
+/// statements are not supported, and `TRST` is emulated by resetting the TAPs with TMS.
+pub fn play_svf(jtag: &mut dyn JTAGAccess, source: &str) -> Result<PlaybackSummary, SvfError> {
+    let operations = parser::parse(source)?;
+    Player::new(jtag).play(&operations)
+}
+
+/// Plays an XSVF file on the JTAG chain.
+///
+/// Failed TDO checks of DR scans are retried as often as set by `XREPEAT`, by going through
+/// Pause-DR and shifting the data again.
+pub fn play_xsvf(jtag: &mut dyn JTAGAccess, data: &[u8]) -> Result<PlaybackSummary, SvfError> {
+    let operations = xsvf::parse(data)?;
+    Player::new(jtag).play(&operations)
+}
+
+/// The register targeted by a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    Ir,
+    Dr,
+}
+
+impl Register {
+    fn state(self, state: RegisterState) -> JtagState {
+        match self {
+            Register::Ir => JtagState::Ir(state),
+            Register::Dr => JtagState::Dr(state),
+        }
+    }
+}
+
+/// The expected TDO bits of a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expected {
+    tdo: BitVec<u8>,
+    mask: BitVec<u8>,
+}
+
+/// A scan of the instruction or data registers of the whole chain.
+#[derive(Debug, Clone, PartialEq)]
+struct Scan {
+    register: Register,
+    tdi: BitVec<u8>,
+    expected: Option<Expected>,
+    /// Whether the scan enters Shift-xR, or continues a previous scan.
+    enter: bool,
+    /// Whether the scan leaves Shift-xR, or is continued by the next scan.
+    exit: bool,
+    /// The state after the scan, if it leaves Shift-xR.
+    end_state: JtagState,
+    /// How often a failed TDO check is retried.
+    retries: u32,
+}
+
+/// A compiled statement of an SVF or XSVF file.
+#[derive(Debug, Clone, PartialEq)]
+enum Operation {
+    /// Resets the TAPs by clocking TMS high.
+    Reset,
+    /// Moves the TAPs to the given state.
+    MoveTo(JtagState),
+    Scan(Scan),
+    /// Clocks TCK in a stable state, and waits for at least `min_time`.
+    RunTest {
+        state: JtagState,
+        cycles: u64,
+        min_time: Duration,
+        end_state: JtagState,
+    },
+    /// Changes the TCK frequency, or restores the initial one if `None`.
+    Frequency(Option<f64>),
+}
+
+/// Returns whether the TAPs can stay in a state while TCK is clocked.
+fn is_stable(state: JtagState) -> bool {
+    matches!(
+        state,
+        JtagState::Reset
+            | JtagState::Idle
+            | JtagState::Dr(RegisterState::Pause)
+            | JtagState::Ir(RegisterState::Pause)
+    )
+}
+
+/// Formats bits as a hexadecimal number.
+fn to_hex(bits: &BitSlice<u8>) -> String {
+    let digits = bits
+        .chunks(4)
+        .rev()
+        .map(|nibble| {
+            let value = nibble.load_le::<u8>();
+            char::from_digit(value as u32, 16).unwrap_or('?')
+        })
+        .collect::<String>();
+
+    if digits.is_empty() {
+        String::from("0")
+    } else {
+        digits
+    }
+}
+
+struct Player<'probe> {
+    jtag: &'probe mut dyn JTAGAccess,
+    state: JtagState,
+    pending: Vec<JtagSequence>,
+    pending_bits: usize,
+    initial_speed_khz: u32,
+    summary: PlaybackSummary,
+}
+
+impl<'probe> Player<'probe> {
+    fn new(jtag: &'probe mut dyn JTAGAccess) -> Self {
+        let initial_speed_khz = jtag.speed_khz();
+
+        Self {
+            jtag,
+            state: JtagState::Reset,
+            pending: Vec::new(),
+            pending_bits: 0,
+            initial_speed_khz,
+            summary: PlaybackSummary::default(),
+        }
+    }
+
+    fn play(mut self, operations: &[(Location, Operation)]) -> Result<PlaybackSummary, SvfError> {
+        self.reset();
+
+        for (location, operation) in operations {
+            tracing::trace!("{location}: {operation:?}");
+            self.execute(operation)
+                .map_err(|source| SvfError::Probe {
+                    location: *location,
+                    source,
+                })?
+                .map_err(|mismatch| mismatch.into_error(*location))?;
+        }
+
+        if let Some((location, _)) = operations.last() {
+            self.flush().map_err(|source| SvfError::Probe {
+                location: *location,
+                source,
+            })?;
+        }
+
+        Ok(self.summary)
+    }
+
+    /// Executes an operation, returning the failed TDO check if there is one.
+    fn execute(&mut self, operation: &Operation) -> Result<Result<(), Mismatch>, DebugProbeError> {
+        match operation {
+            Operation::Reset => self.reset(),
+            Operation::MoveTo(state) => self.move_to(*state),
+            Operation::Scan(scan) => return self.scan(scan),
+            Operation::RunTest {
+                state,
+                cycles,
+                min_time,
+                end_state,
+            } => self.run_test(*state, *cycles, *min_time, *end_state)?,
+            Operation::Frequency(frequency) => self.set_frequency(*frequency)?,
+        }
+
+        if self.pending_bits > MAX_PENDING_BITS {
+            self.flush()?;
+        }
+
+        Ok(Ok(()))
+    }
+
+    /// Queues bits with a constant TMS level, merging them with the previous sequence if possible.
+    fn push(&mut self, tms: bool, tdi: &BitSlice<u8>, capture: bool) {
+        if tdi.is_empty() {
+            return;
+        }
+        self.pending_bits += tdi.len();
+
+        if let Some(last) = self.pending.last_mut() {
+            if last.tms == tms && last.capture == capture {
+                let mut bits = BitVec::<u8>::from_vec(std::mem::take(&mut last.tdi));
+                bits.truncate(last.len);
+                bits.extend_from_bitslice(tdi);
+                last.len = bits.len();
+                last.tdi = bits.into_vec();
+                return;
+            }
+        }
+
+        self.pending.push(JtagSequence {
+            tms,
+            tdi: tdi.to_bitvec().into_vec(),
+            len: tdi.len(),
+            capture,
+        });
+    }
+
+    /// Sends the queued bits to the probe, and returns the captured bits.
+    fn flush(&mut self) -> Result<BitVec<u8>, DebugProbeError> {
+        if self.pending.is_empty() {
+            return Ok(BitVec::new());
+        }
+
+        let sequences = std::mem::take(&mut self.pending);
+        self.pending_bits = 0;
+
+        let captured_bits = sequences
+            .iter()
+            .filter(|sequence| sequence.capture)
+            .map(|sequence| sequence.len)
+            .sum::<usize>();
+
+        let response = self.jtag.shift_raw_sequence(&sequences)?;
+        let mut captured = BitVec::<u8>::from_vec(response);
+        if captured.len() < captured_bits {
+            return Err(DebugProbeError::Other(format!(
+                "Expected {captured_bits} captured bits, received {}",
+                captured.len()
+            )));
+        }
+        captured.truncate(captured_bits);
+
+        Ok(captured)
+    }
+
+    fn clock_tms(&mut self, tms: bool) {
+        self.push(tms, bits![u8, Lsb0; 0], false);
+        self.state.update(tms);
+    }
+
+    fn reset(&mut self) {
+        for _ in 0..5 {
+            self.clock_tms(true);
+        }
+    }
+
+    fn move_to(&mut self, target: JtagState) {
+        while let Some(tms) = self.state.step_toward(target) {
+            self.clock_tms(tms);
+        }
+    }
+
+    fn scan(&mut self, scan: &Scan) -> Result<Result<(), Mismatch>, DebugProbeError> {
+        self.summary.scans += 1;
+        if scan.tdi.is_empty() {
+            if scan.exit {
+                self.move_to(scan.end_state);
+            }
+            return Ok(Ok(()));
+        }
+
+        if scan.enter {
+            self.move_to(scan.register.state(RegisterState::Shift));
+        }
+
+        let capture = scan.expected.is_some();
+        let mut attempt = 0;
+        loop {
+            self.shift(scan, capture);
+
+            let Some(expected) = &scan.expected else {
+                break;
+            };
+            self.summary.tdo_checks += 1;
+
+            let captured = self.flush()?;
+            let matches = captured
+                .iter()
+                .by_vals()
+                .zip(expected.tdo.iter().by_vals())
+                .zip(expected.mask.iter().by_vals())
+                .all(|((captured, expected), compare)| !compare || captured == expected);
+            if matches {
+                break;
+            }
+
+            let mismatch = Mismatch {
+                expected: expected.clone(),
+                captured,
+            };
+            if attempt >= scan.retries || !scan.exit {
+                return Ok(Err(mismatch));
+            }
+
+            attempt += 1;
+            tracing::debug!("TDO mismatch, retrying (attempt {attempt})");
+            self.move_to(scan.register.state(RegisterState::Pause));
+            self.move_to(scan.register.state(RegisterState::Shift));
+        }
+
+        if scan.exit {
+            self.move_to(scan.end_state);
+        }
+
+        Ok(Ok(()))
+    }
+
+    /// Shifts the bits of a scan, leaving Shift-xR with the last bit if the scan exits.
+    fn shift(&mut self, scan: &Scan, capture: bool) {
+        let (last, body) = match scan.tdi.split_last() {
+            Some((last, body)) if scan.exit => (Some(*last), body),
+            _ => (None, scan.tdi.as_bitslice()),
+        };
+
+        self.push(false, body, capture);
+        if let Some(last) = last {
+            let mut bit = BitVec::<u8>::new();
+            bit.push(last);
+            self.push(true, &bit, capture);
+            self.state.update(true);
+        }
+    }
+
+    fn run_test(
+        &mut self,
+        state: JtagState,
+        cycles: u64,
+        min_time: Duration,
+        end_state: JtagState,
+    ) -> Result<(), DebugProbeError> {
+        self.move_to(state);
+
+        // Stay in the stable state: TMS is high in Test-Logic-Reset, and low otherwise.
+        let tms = state == JtagState::Reset;
+        let mut remaining = cycles;
+        while remaining > 0 {
+            let chunk = remaining.min(MAX_PENDING_BITS as u64);
+            self.push(tms, &bitvec![u8, Lsb0; 0; chunk as usize], false);
+            remaining -= chunk;
+            if self.pending_bits > MAX_PENDING_BITS {
+                self.flush()?;
+            }
+        }
+
+        if !min_time.is_zero() {
+            let start = Instant::now();
+            self.flush()?;
+            if let Some(remaining) = min_time.checked_sub(start.elapsed()) {
+                std::thread::sleep(remaining);
+            }
+        }
+
+        self.move_to(end_state);
+        Ok(())
+    }
+
+    fn set_frequency(&mut self, frequency: Option<f64>) -> Result<(), DebugProbeError> {
+        self.flush()?;
+
+        let requested_khz = match frequency {
+            Some(hz) => ((hz / 1000.0) as u32).max(1),
+            None => self.initial_speed_khz,
+        };
+        let actual_khz = self.jtag.set_speed(requested_khz)?;
+        if actual_khz > requested_khz {
+            tracing::warn!(
+                "Requested a TCK frequency of {requested_khz} kHz, but the probe runs at {actual_khz} kHz"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// A failed TDO check.
+struct Mismatch {
+    expected: Expected,
+    captured: BitVec<u8>,
+}
+
+impl Mismatch {
+    fn into_error(self, location: Location) -> SvfError {
+        SvfError::TdoMismatch {
+            location,
+            expected: to_hex(&self.expected.tdo),
+            captured: to_hex(&self.captured),
+            mask: to_hex(&self.expected.mask),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use probe_rs_target::ScanChainElement;
+
+    use super::*;
+    use crate::probe::common::{JtagDriverState, RawJtagIo};
+    use crate::probe::{DebugProbe, WireProtocol};
+
+    const IDCODE: u32 = 0x4BA00477;
+    const IDCODE_INSTRUCTION: u8 = 0b1110;
+
+    /// A single TAP with a 4-bit IR, which has the IDCODE and BYPASS data registers.
+    #[derive(Debug)]
+    struct SimulatedTap {
+        state: JtagDriverState,
+        instruction: u8,
+        ir: BitVec<u8>,
+        dr: BitVec<u8>,
+        captured: BitVec<u8>,
+    }
+
+    impl SimulatedTap {
+        fn new() -> Self {
+            Self {
+                state: JtagDriverState::default(),
+                instruction: IDCODE_INSTRUCTION,
+                ir: BitVec::new(),
+                dr: BitVec::new(),
+                captured: BitVec::new(),
+            }
+        }
+    }
+
+    impl RawJtagIo for SimulatedTap {
+        fn state_mut(&mut self) -> &mut JtagDriverState {
+            &mut self.state
+        }
+
+        fn state(&self) -> &JtagDriverState {
+            &self.state
+        }
+
+        fn shift_bit(
+            &mut self,
+            tms: bool,
+            tdi: bool,
+            capture: bool,
+        ) -> Result<(), DebugProbeError> {
+            let register = match self.state.state {
+                JtagState::Ir(RegisterState::Shift) => Some(&mut self.ir),
+                JtagState::Dr(RegisterState::Shift) => Some(&mut self.dr),
+                _ => None,
+            };
+            let tdo = register.is_some_and(|register| {
+                let tdo = register.remove(0);
+                register.push(tdi);
+                tdo
+            });
+            if capture {
+                self.captured.push(tdo);
+            }
+
+            self.state.state.update(tms);
+            match self.state.state {
+                JtagState::Reset => self.instruction = IDCODE_INSTRUCTION,
+                JtagState::Ir(RegisterState::Capture) => {
+                    self.ir = 0b0001u8.view_bits::<Lsb0>()[..4].to_bitvec()
+                }
+                JtagState::Ir(RegisterState::Update) => self.instruction = self.ir.load_le(),
+                JtagState::Dr(RegisterState::Capture) if self.instruction == IDCODE_INSTRUCTION => {
+                    self.dr = IDCODE.view_bits::<Lsb0>().iter().by_vals().collect()
+                }
+                JtagState::Dr(RegisterState::Capture) => self.dr = bitvec![u8, Lsb0; 0],
+                _ => {}
+            }
+
+            Ok(())
+        }
+
+        fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, DebugProbeError> {
+            Ok(std::mem::take(&mut self.captured))
+        }
+    }
+
+    impl DebugProbe for SimulatedTap {
+        fn get_name(&self) -> &str {
+            "Simulated TAP"
+        }
+
+        fn speed_khz(&self) -> u32 {
+            1000
+        }
+
+        fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+            Ok(speed_khz)
+        }
+
+        fn set_scan_chain(
+            &mut self,
+            _scan_chain: Vec<ScanChainElement>,
+        ) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+            Ok(&[])
+        }
+
+        fn attach(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn detach(&mut self) -> Result<(), crate::Error> {
+            Ok(())
+        }
+
+        fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn select_protocol(&mut self, _protocol: WireProtocol) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn active_protocol(&self) -> Option<WireProtocol> {
+            Some(WireProtocol::Jtag)
+        }
+
+        fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+            self
+        }
+    }
+
+    #[test]
+    fn play_svf_scans() {
+        let mut tap = SimulatedTap::new();
+
+        let summary = play_svf(
+            &mut tap,
+            "FREQUENCY 1E6 HZ;\n\
+             SIR 4 TDI (e) TDO (1) MASK (3);\n\
+             SDR 32 TDI (0) TDO (4ba00477);\n\
+             RUNTEST 10 TCK;\n\
+             SIR 4 TDI (f);\n\
+             SDR 8 TDI (a5) TDO (4a) MASK (fe);\n\
+             STATE RESET;\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            summary,
+            PlaybackSummary {
+                scans: 4,
+                tdo_checks: 3,
+            }
+        );
+        assert_eq!(tap.state.state, JtagState::Reset);
+    }
+
+    #[test]
+    fn play_svf_mismatch() {
+        let mut tap = SimulatedTap::new();
+
+        let error = play_svf(&mut tap, "STATE IDLE;\nSDR 32 TDI (0) TDO (12345678);").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "TDO mismatch at line 2: expected 12345678, captured 4ba00477 with mask ffffffff."
+        );
+    }
+
+    #[test]
+    fn play_xsvf_retries() {
+        let mut tap = SimulatedTap::new();
+        let header = [0x07, 2, 0x08, 0, 0, 0, 32, 0x01, 0xFF, 0xFF, 0xFF, 0xFF];
+
+        let mut data = header.to_vec();
+        data.extend_from_slice(&[0x09, 0, 0, 0, 0, 0x4B, 0xA0, 0x04, 0x77, 0x00]);
+        let summary = play_xsvf(&mut tap, &data).unwrap();
+        assert_eq!(summary.tdo_checks, 1);
+
+        let mut data = header.to_vec();
+        data.extend_from_slice(&[0x09, 0, 0, 0, 0, 0x12, 0x34, 0x56, 0x78, 0x00]);
+        let error = play_xsvf(&mut tap, &data).unwrap_err();
+        assert!(matches!(
+            error,
+            SvfError::TdoMismatch {
+                location: Location::Offset(12),
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,585 @@
+//! Compiler for SVF files, which resolves the remembered scan patterns and end states of
+//! the statements into [`Operation`]s.
+
+use std::time::Duration;
+
+use bitvec::prelude::*;
+
+use super::{is_stable, Expected, Location, Operation, Register, Scan, SvfError};
+use crate::probe::common::{JtagState, RegisterState};
+
+/// A token of an SVF statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A keyword or number.
+    Word(String),
+    /// The contents of parentheses without whitespace, usually hexadecimal digits.
+    Hex(String),
+}
+
+/// An SVF statement, which is terminated by a semicolon.
+#[derive(Debug)]
+struct Statement {
+    location: Location,
+    tokens: Vec<Token>,
+}
+
+fn syntax_error(location: Location, message: impl Into<String>) -> SvfError {
+    SvfError::Syntax {
+        location,
+        message: message.into(),
+    }
+}
+
+/// Splits an SVF file into statements, removing comments.
+fn statements(source: &str) -> Result<Vec<Statement>, SvfError> {
+    let mut statements = Vec::new();
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut hex: Option<String> = None;
+    let mut start_line = None;
+    let mut line = 1;
+
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        let is_comment = c == '!' || (c == '/' && chars.peek() == Some(&'/'));
+        if is_comment {
+            while chars.next_if(|&c| c != '\n').is_some() {}
+            continue;
+        }
+
+        if c == '\n' {
+            line += 1;
+        }
+
+        if let Some(digits) = &mut hex {
+            match c {
+                ')' => tokens.push(Token::Hex(hex.take().unwrap_or_default())),
+                c if c.is_whitespace() => {}
+                c => digits.push(c),
+            }
+            continue;
+        }
+
+        if c.is_whitespace() || c == '(' || c == ';' {
+            if !word.is_empty() {
+                tokens.push(Token::Word(std::mem::take(&mut word)));
+            }
+        } else {
+            word.push(c);
+        }
+        if !c.is_whitespace() {
+            start_line.get_or_insert(line);
+        }
+
+        match c {
+            '(' => hex = Some(String::new()),
+            ';' => statements.push(Statement {
+                location: Location::Line(start_line.take().unwrap_or(line)),
+                tokens: std::mem::take(&mut tokens),
+            }),
+            _ => {}
+        }
+    }
+
+    if hex.is_some() || !word.is_empty() || !tokens.is_empty() {
+        return Err(syntax_error(
+            Location::Line(start_line.unwrap_or(line)),
+            "unterminated statement",
+        ));
+    }
+
+    Ok(statements)
+}
+
+/// Parses a hexadecimal number into `len` bits, least significant bit first.
+fn parse_hex(location: Location, digits: &str, len: usize) -> Result<BitVec<u8>, SvfError> {
+    let mut bits = BitVec::<u8>::with_capacity(digits.len() * 4);
+    for digit in digits.chars().rev() {
+        let value = digit
+            .to_digit(16)
+            .ok_or_else(|| syntax_error(location, format!("invalid hexadecimal digit '{digit}'")))?
+            as u8;
+        bits.extend_from_bitslice(&value.view_bits::<Lsb0>()[..4]);
+    }
+
+    if bits.get(len..).is_some_and(|excess| excess.any()) {
+        return Err(syntax_error(
+            location,
+            format!("the value ({digits}) is longer than {len} bits"),
+        ));
+    }
+    bits.resize(len, false);
+
+    Ok(bits)
+}
+
+fn parse_state(location: Location, name: &str) -> Result<JtagState, SvfError> {
+    let state = match name.to_ascii_uppercase().as_str() {
+        "RESET" => JtagState::Reset,
+        "IDLE" => JtagState::Idle,
+        "DRSELECT" => JtagState::Dr(RegisterState::Select),
+        "DRCAPTURE" => JtagState::Dr(RegisterState::Capture),
+        "DRSHIFT" => JtagState::Dr(RegisterState::Shift),
+        "DREXIT1" => JtagState::Dr(RegisterState::Exit1),
+        "DRPAUSE" => JtagState::Dr(RegisterState::Pause),
+        "DREXIT2" => JtagState::Dr(RegisterState::Exit2),
+        "DRUPDATE" => JtagState::Dr(RegisterState::Update),
+        "IRSELECT" => JtagState::Ir(RegisterState::Select),
+        "IRCAPTURE" => JtagState::Ir(RegisterState::Capture),
+        "IRSHIFT" => JtagState::Ir(RegisterState::Shift),
+        "IREXIT1" => JtagState::Ir(RegisterState::Exit1),
+        "IRPAUSE" => JtagState::Ir(RegisterState::Pause),
+        "IREXIT2" => JtagState::Ir(RegisterState::Exit2),
+        "IRUPDATE" => JtagState::Ir(RegisterState::Update),
+        _ => return Err(syntax_error(location, format!("unknown state {name}"))),
+    };
+
+    Ok(state)
+}
+
+fn parse_stable_state(location: Location, name: &str) -> Result<JtagState, SvfError> {
+    let state = parse_state(location, name)?;
+    if !is_stable(state) {
+        return Err(syntax_error(
+            location,
+            format!("{name} is not a stable state"),
+        ));
+    }
+
+    Ok(state)
+}
+
+/// The pattern of a header, trailer or scan statement, parts of which are remembered.
+#[derive(Debug, Default)]
+struct Pattern {
+    tdi: BitVec<u8>,
+    tdo: Option<BitVec<u8>>,
+    mask: BitVec<u8>,
+}
+
+impl Pattern {
+    /// Updates the pattern from the arguments of a statement, `length TDI (..) TDO (..) ...`.
+    fn update(&mut self, location: Location, arguments: &[Token]) -> Result<(), SvfError> {
+        let Some(Token::Word(len)) = arguments.first() else {
+            return Err(syntax_error(location, "missing length"));
+        };
+        let len = len
+            .parse::<usize>()
+            .map_err(|_| syntax_error(location, format!("invalid length {len}")))?;
+
+        // The TDI, MASK and SMASK values are only remembered for scans of the same length.
+        if len != self.tdi.len() {
+            *self = Pattern {
+                tdi: BitVec::new(),
+                tdo: None,
+                mask: bitvec![u8, Lsb0; 1; len],
+            };
+        }
+        self.tdo = None;
+
+        let mut tdi = None;
+        for pair in arguments[1..].chunks(2) {
+            let [Token::Word(name), Token::Hex(digits)] = pair else {
+                return Err(syntax_error(
+                    location,
+                    "expected a name and a value in parentheses",
+                ));
+            };
+
+            let value = parse_hex(location, digits, len)?;
+            match name.to_ascii_uppercase().as_str() {
+                "TDI" => tdi = Some(value),
+                "TDO" => self.tdo = Some(value),
+                "MASK" => self.mask = value,
+                // All TDI bits are driven, so it is irrelevant which of them are don't care.
+                "SMASK" => {}
+                _ => return Err(syntax_error(location, format!("unknown value {name}"))),
+            }
+        }
+
+        match tdi {
+            Some(tdi) => self.tdi = tdi,
+            None if self.tdi.len() != len => {
+                return Err(syntax_error(location, "missing TDI value"))
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.tdi.len()
+    }
+}
+
+/// Builds a scan from a header, the scan pattern and a trailer, which are shifted in this order.
+fn build_scan(register: Register, parts: [&Pattern; 3], end_state: JtagState) -> Scan {
+    let mut tdi = BitVec::new();
+    for part in parts {
+        tdi.extend_from_bitslice(&part.tdi);
+    }
+
+    let expected = parts.iter().any(|part| part.tdo.is_some()).then(|| {
+        let mut expected = Expected {
+            tdo: BitVec::new(),
+            mask: BitVec::new(),
+        };
+        for part in parts {
+            match &part.tdo {
+                Some(tdo) => {
+                    expected.tdo.extend_from_bitslice(tdo);
+                    expected.mask.extend_from_bitslice(&part.mask);
+                }
+                None => {
+                    expected.tdo.resize(expected.tdo.len() + part.len(), false);
+                    expected
+                        .mask
+                        .resize(expected.mask.len() + part.len(), false);
+                }
+            }
+        }
+        expected
+    });
+
+    Scan {
+        register,
+        tdi,
+        expected,
+        enter: true,
+        exit: true,
+        end_state,
+        retries: 0,
+    }
+}
+
+struct Parser {
+    hir: Pattern,
+    hdr: Pattern,
+    tir: Pattern,
+    tdr: Pattern,
+    sir: Pattern,
+    sdr: Pattern,
+    end_ir: JtagState,
+    end_dr: JtagState,
+    run_state: JtagState,
+    run_end_state: JtagState,
+    operations: Vec<(Location, Operation)>,
+}
+
+impl Parser {
+    fn new() -> Self {
+        Self {
+            hir: Pattern::default(),
+            hdr: Pattern::default(),
+            tir: Pattern::default(),
+            tdr: Pattern::default(),
+            sir: Pattern::default(),
+            sdr: Pattern::default(),
+            end_ir: JtagState::Idle,
+            end_dr: JtagState::Idle,
+            run_state: JtagState::Idle,
+            run_end_state: JtagState::Idle,
+            operations: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, location: Location, operation: Operation) {
+        self.operations.push((location, operation));
+    }
+
+    fn statement(&mut self, statement: Statement) -> Result<(), SvfError> {
+        let location = statement.location;
+        let Some((Token::Word(command), arguments)) = statement.tokens.split_first() else {
+            return Err(syntax_error(location, "missing command"));
+        };
+
+        let words = || {
+            arguments.iter().map(|token| match token {
+                Token::Word(word) => Ok(word.as_str()),
+                Token::Hex(_) => Err(syntax_error(location, "unexpected value in parentheses")),
+            })
+        };
+
+        match command.to_ascii_uppercase().as_str() {
+            "ENDIR" | "ENDDR" => {
+                let [Token::Word(state)] = arguments else {
+                    return Err(syntax_error(location, "expected a single state"));
+                };
+                let state = parse_stable_state(location, state)?;
+                if command.eq_ignore_ascii_case("ENDIR") {
+                    self.end_ir = state;
+                } else {
+                    self.end_dr = state;
+                }
+            }
+            "FREQUENCY" => {
+                let frequency = match words().collect::<Result<Vec<_>, _>>()?.as_slice() {
+                    [] => None,
+                    [frequency, unit] if unit.eq_ignore_ascii_case("HZ") => {
+                        Some(parse_number(location, frequency)?)
+                    }
+                    _ => return Err(syntax_error(location, "expected a frequency in HZ")),
+                };
+                self.push(location, Operation::Frequency(frequency));
+            }
+            "HIR" => self.hir.update(location, arguments)?,
+            "HDR" => self.hdr.update(location, arguments)?,
+            "TIR" => self.tir.update(location, arguments)?,
+            "TDR" => self.tdr.update(location, arguments)?,
+            "SIR" => {
+                self.sir.update(location, arguments)?;
+                let scan = build_scan(Register::Ir, [&self.hir, &self.sir, &self.tir], self.end_ir);
+                self.push(location, Operation::Scan(scan));
+            }
+            "SDR" => {
+                self.sdr.update(location, arguments)?;
+                let scan = build_scan(Register::Dr, [&self.hdr, &self.sdr, &self.tdr], self.end_dr);
+                self.push(location, Operation::Scan(scan));
+            }
+            "RUNTEST" => {
+                let words = words().collect::<Result<Vec<_>, _>>()?;
+                self.run_test(location, &words)?;
+            }
+            "STATE" => {
+                let states = words()
+                    .map(|name| parse_state(location, name?))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match states.last() {
+                    None => return Err(syntax_error(location, "missing state")),
+                    Some(last) if !is_stable(*last) => {
+                        return Err(syntax_error(location, "the last state is not stable"))
+                    }
+                    Some(_) => {}
+                }
+
+                for state in states {
+                    let operation = match state {
+                        JtagState::Reset => Operation::Reset,
+                        state => Operation::MoveTo(state),
+                    };
+                    self.push(location, operation);
+                }
+            }
+            "TRST" => {
+                let [Token::Word(mode)] = arguments else {
+                    return Err(syntax_error(location, "expected a TRST mode"));
+                };
+                match mode.to_ascii_uppercase().as_str() {
+                    // Probes don't drive TRST, but resetting the TAPs with TMS has the same effect.
+                    "ON" => self.push(location, Operation::Reset),
+                    "OFF" | "Z" | "ABSENT" => {}
+                    _ => return Err(syntax_error(location, format!("invalid TRST mode {mode}"))),
+                }
+            }
+            "PIO" | "PIOMAP" => {
+                return Err(SvfError::Unsupported {
+                    location,
+                    statement: command.to_ascii_uppercase(),
+                })
+            }
+            _ => return Err(syntax_error(location, format!("unknown command {command}"))),
+        }
+
+        Ok(())
+    }
+
+    /// Parses `RUNTEST [run_state] [count TCK|SCK] [min_time SEC] [MAXIMUM max_time SEC]
+    /// [ENDSTATE end_state]`, where at least a count or a minimum time is required.
+    fn run_test(&mut self, location: Location, words: &[&str]) -> Result<(), SvfError> {
+        let mut words = words.iter().copied().peekable();
+
+        if let Some(state) = words.next_if(|word| parse_state(location, word).is_ok()) {
+            self.run_state = parse_stable_state(location, state)?;
+            self.run_end_state = self.run_state;
+        }
+
+        let mut cycles = None;
+        let mut min_time = None;
+        while let Some(value) = words.next_if(|word| parse_number(location, word).is_ok()) {
+            let value = parse_number(location, value)?;
+            match words
+                .next()
+                .map(|unit| unit.to_ascii_uppercase())
+                .as_deref()
+            {
+                Some("TCK" | "SCK") if cycles.is_none() && min_time.is_none() => {
+                    cycles = Some(value as u64)
+                }
+                Some("SEC") if min_time.is_none() => min_time = Some(value),
+                _ => return Err(syntax_error(location, "expected a count or a time")),
+            }
+        }
+        if cycles.is_none() && min_time.is_none() {
+            return Err(syntax_error(location, "missing run count or minimum time"));
+        }
+
+        if words
+            .next_if(|word| word.eq_ignore_ascii_case("MAXIMUM"))
+            .is_some()
+        {
+            // The maximum time cannot be enforced, as the probe determines the timing.
+            match (words.next(), words.next()) {
+                (Some(time), Some(unit)) if unit.eq_ignore_ascii_case("SEC") => {
+                    parse_number(location, time)?;
+                }
+                _ => return Err(syntax_error(location, "expected a maximum time")),
+            }
+        }
+
+        if words
+            .next_if(|word| word.eq_ignore_ascii_case("ENDSTATE"))
+            .is_some()
+        {
+            let Some(state) = words.next() else {
+                return Err(syntax_error(location, "missing end state"));
+            };
+            self.run_end_state = parse_stable_state(location, state)?;
+        }
+
+        if let Some(word) = words.next() {
+            return Err(syntax_error(location, format!("unexpected {word}")));
+        }
+
+        self.push(
+            location,
+            Operation::RunTest {
+                state: self.run_state,
+                cycles: cycles.unwrap_or(0),
+                min_time: Duration::from_secs_f64(min_time.unwrap_or(0.0)),
+                end_state: self.run_end_state,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+/// Parses a non-negative number, which may use scientific notation like `1.0E-3`.
+fn parse_number(location: Location, word: &str) -> Result<f64, SvfError> {
+    match word.parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+        _ => Err(syntax_error(location, format!("invalid number {word}"))),
+    }
+}
+
+/// Compiles an SVF file into operations.
+pub(super) fn parse(source: &str) -> Result<Vec<(Location, Operation)>, SvfError> {
+    let mut parser = Parser::new();
+    for statement in statements(source)? {
+        parser.statement(statement)?;
+    }
+
+    Ok(parser.operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(operation: &(Location, Operation)) -> &Scan {
+        match &operation.1 {
+            Operation::Scan(scan) => scan,
+            operation => panic!("Expected a scan, got {operation:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_scans() {
+        let operations = parse(
+            "! Header comment\n\
+             HIR 2 TDI (3);\n\
+             ENDDR DRPAUSE;\n\
+             SIR 4 TDI (e) // inline comment\n\
+                 TDO (1) MASK (3);\n\
+             SDR 40 TDI (00 0000 0000)\n\
+                 TDO (12 3456 7890);\n\
+             SDR 40 TDO (00 0000 0001);\n",
+        )
+        .unwrap();
+
+        assert_eq!(operations.len(), 3);
+
+        let sir = scan(&operations[0]);
+        assert_eq!(operations[0].0, Location::Line(4));
+        assert_eq!(sir.register, Register::Ir);
+        assert_eq!(sir.tdi, bits![u8, Lsb0; 1, 1, 0, 1, 1, 1]);
+        let expected = sir.expected.as_ref().unwrap();
+        assert_eq!(expected.tdo, bits![u8, Lsb0; 0, 0, 1, 0, 0, 0]);
+        assert_eq!(expected.mask, bits![u8, Lsb0; 0, 0, 1, 1, 0, 0]);
+        assert_eq!(sir.end_state, JtagState::Idle);
+
+        let sdr = scan(&operations[1]);
+        assert_eq!(operations[1].0, Location::Line(6));
+        assert_eq!(sdr.tdi.len(), 40);
+        assert_eq!(
+            sdr.expected.as_ref().unwrap().tdo.load_le::<u64>(),
+            0x12_3456_7890
+        );
+        assert_eq!(sdr.end_state, JtagState::Dr(RegisterState::Pause));
+
+        // TDI is remembered for scans of the same length.
+        let sdr = scan(&operations[2]);
+        assert_eq!(sdr.tdi, bitvec![u8, Lsb0; 0; 40]);
+        assert!(sdr.expected.as_ref().unwrap().tdo[0]);
+    }
+
+    #[test]
+    fn parse_run_test() {
+        let operations = parse(
+            "RUNTEST DRPAUSE 100 TCK 1.0E-3 SEC MAXIMUM 1 SEC ENDSTATE IDLE;\n\
+             RUNTEST 2E-2 SEC;\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            operations[0].1,
+            Operation::RunTest {
+                state: JtagState::Dr(RegisterState::Pause),
+                cycles: 100,
+                min_time: Duration::from_millis(1),
+                end_state: JtagState::Idle,
+            }
+        );
+        assert_eq!(
+            operations[1].1,
+            Operation::RunTest {
+                state: JtagState::Dr(RegisterState::Pause),
+                cycles: 0,
+                min_time: Duration::from_millis(20),
+                end_state: JtagState::Idle,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            parse("SIR 4 TDI (1f);"),
+            Err(SvfError::Syntax {
+                location: Location::Line(1),
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("STATE IDLE;\nSDR 8;"),
+            Err(SvfError::Syntax {
+                location: Location::Line(2),
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("ENDIR IRSHIFT;"),
+            Err(SvfError::Syntax { .. })
+        ));
+        assert!(matches!(
+            parse("PIOMAP (IN A);"),
+            Err(SvfError::Unsupported { .. })
+        ));
+        assert!(matches!(
+            parse("SIR 4 TDI (1)"),
+            Err(SvfError::Syntax { .. })
+        ));
+    }
+}
@@ -0,0 +1,378 @@
+//! Decoder for XSVF files, the binary variant of SVF described in Xilinx application note XAPP503.
+
+use std::time::Duration;
+
+use bitvec::prelude::*;
+
+use super::{Expected, Location, Operation, Register, Scan, SvfError};
+use crate::probe::common::{JtagState, RegisterState};
+
+const XCOMPLETE: u8 = 0x00;
+const XTDOMASK: u8 = 0x01;
+const XSIR: u8 = 0x02;
+const XSDR: u8 = 0x03;
+const XRUNTEST: u8 = 0x04;
+const XREPEAT: u8 = 0x07;
+const XSDRSIZE: u8 = 0x08;
+const XSDRTDO: u8 = 0x09;
+const XSETSDRMASKS: u8 = 0x0A;
+const XSDRINC: u8 = 0x0B;
+const XSDRB: u8 = 0x0C;
+const XSDRC: u8 = 0x0D;
+const XSDRE: u8 = 0x0E;
+const XSDRTDOB: u8 = 0x0F;
+const XSDRTDOC: u8 = 0x10;
+const XSDRTDOE: u8 = 0x11;
+const XSTATE: u8 = 0x12;
+const XENDIR: u8 = 0x13;
+const XENDDR: u8 = 0x14;
+const XSIR2: u8 = 0x15;
+const XCOMMENT: u8 = 0x16;
+const XWAIT: u8 = 0x17;
+
+struct Reader<'data> {
+    data: &'data [u8],
+    offset: usize,
+    /// The offset of the command being decoded.
+    command: usize,
+}
+
+impl Reader<'_> {
+    fn location(&self) -> Location {
+        Location::Offset(self.command)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&[u8], SvfError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or(SvfError::Syntax {
+                location: self.location(),
+                message: String::from("unexpected end of file"),
+            })?;
+        self.offset += len;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SvfError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SvfError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SvfError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a big-endian value of `len` bits, and returns its bits least significant bit first.
+    fn bits(&mut self, len: usize) -> Result<BitVec<u8>, SvfError> {
+        let bytes = self.bytes(len.div_ceil(8))?.iter().rev().copied().collect();
+        let mut bits = BitVec::<u8>::from_vec(bytes);
+        bits.truncate(len);
+
+        Ok(bits)
+    }
+
+    fn state(&mut self) -> Result<JtagState, SvfError> {
+        let state = match self.u8()? {
+            0x00 => JtagState::Reset,
+            0x01 => JtagState::Idle,
+            0x02 => JtagState::Dr(RegisterState::Select),
+            0x03 => JtagState::Dr(RegisterState::Capture),
+            0x04 => JtagState::Dr(RegisterState::Shift),
+            0x05 => JtagState::Dr(RegisterState::Exit1),
+            0x06 => JtagState::Dr(RegisterState::Pause),
+            0x07 => JtagState::Dr(RegisterState::Exit2),
+            0x08 => JtagState::Dr(RegisterState::Update),
+            0x09 => JtagState::Ir(RegisterState::Select),
+            0x0A => JtagState::Ir(RegisterState::Capture),
+            0x0B => JtagState::Ir(RegisterState::Shift),
+            0x0C => JtagState::Ir(RegisterState::Exit1),
+            0x0D => JtagState::Ir(RegisterState::Pause),
+            0x0E => JtagState::Ir(RegisterState::Exit2),
+            0x0F => JtagState::Ir(RegisterState::Update),
+            state => {
+                return Err(SvfError::Syntax {
+                    location: self.location(),
+                    message: format!("invalid state {state:#04x}"),
+                })
+            }
+        };
+
+        Ok(state)
+    }
+}
+
+struct Decoder {
+    operations: Vec<(Location, Operation)>,
+    sdr_size: usize,
+    tdo_mask: BitVec<u8>,
+    tdo_expected: BitVec<u8>,
+    run_test: Duration,
+    repeat: u32,
+    end_ir: JtagState,
+    end_dr: JtagState,
+}
+
+impl Decoder {
+    fn push(&mut self, location: Location, operation: Operation) {
+        self.operations.push((location, operation));
+    }
+
+    /// Adds a scan which leaves Shift-xR, followed by the XRUNTEST wait if one is set.
+    fn scan_and_wait(&mut self, location: Location, mut scan: Scan) {
+        if self.run_test.is_zero() {
+            self.push(location, Operation::Scan(scan));
+            return;
+        }
+
+        // With a wait, the scan ends in Run-Test/Idle instead of the XENDIR or XENDDR state.
+        scan.end_state = JtagState::Idle;
+        self.push(location, Operation::Scan(scan));
+        self.push(
+            location,
+            Operation::RunTest {
+                state: JtagState::Idle,
+                cycles: 0,
+                min_time: self.run_test,
+                end_state: JtagState::Idle,
+            },
+        );
+    }
+
+    /// Builds a DR scan, checking TDO against the expected value and mask if any bit is compared.
+    fn dr_scan(&self, tdi: BitVec<u8>, check: bool, enter: bool, exit: bool) -> Scan {
+        let expected = (check && self.tdo_mask.any()).then(|| Expected {
+            tdo: self.tdo_expected.clone(),
+            mask: self.tdo_mask.clone(),
+        });
+
+        Scan {
+            register: Register::Dr,
+            tdi,
+            expected,
+            enter,
+            exit,
+            end_state: self.end_dr,
+            retries: self.repeat,
+        }
+    }
+
+    fn command(&mut self, reader: &mut Reader<'_>, command: u8) -> Result<(), SvfError> {
+        let location = reader.location();
+
+        match command {
+            XTDOMASK => self.tdo_mask = reader.bits(self.sdr_size)?,
+            XSIR | XSIR2 => {
+                let len = if command == XSIR {
+                    reader.u8()? as usize
+                } else {
+                    reader.u16()? as usize
+                };
+                let scan = Scan {
+                    register: Register::Ir,
+                    tdi: reader.bits(len)?,
+                    expected: None,
+                    enter: true,
+                    exit: true,
+                    end_state: self.end_ir,
+                    retries: 0,
+                };
+                self.scan_and_wait(location, scan);
+            }
+            XSDR | XSDRTDO => {
+                let tdi = reader.bits(self.sdr_size)?;
+                if command == XSDRTDO {
+                    self.tdo_expected = reader.bits(self.sdr_size)?;
+                }
+                let scan = self.dr_scan(tdi, true, true, true);
+                self.scan_and_wait(location, scan);
+            }
+            XRUNTEST => self.run_test = Duration::from_micros(reader.u32()? as u64),
+            XREPEAT => self.repeat = reader.u8()? as u32,
+            XSDRSIZE => {
+                self.sdr_size = reader.u32()? as usize;
+                self.tdo_mask.resize(self.sdr_size, false);
+                self.tdo_expected.resize(self.sdr_size, false);
+            }
+            XSDRB | XSDRC | XSDRE | XSDRTDOB | XSDRTDOC | XSDRTDOE => {
+                let check = matches!(command, XSDRTDOB | XSDRTDOC | XSDRTDOE);
+                let tdi = reader.bits(self.sdr_size)?;
+                if check {
+                    self.tdo_expected = reader.bits(self.sdr_size)?;
+                }
+
+                let enter = matches!(command, XSDRB | XSDRTDOB);
+                let exit = matches!(command, XSDRE | XSDRTDOE);
+                let mut scan = self.dr_scan(tdi, check, enter, exit);
+                scan.retries = 0;
+
+                if exit {
+                    self.scan_and_wait(location, scan);
+                } else {
+                    self.push(location, Operation::Scan(scan));
+                }
+            }
+            XSTATE => {
+                let operation = match reader.state()? {
+                    JtagState::Reset => Operation::Reset,
+                    state => Operation::MoveTo(state),
+                };
+                self.push(location, operation);
+            }
+            XENDIR | XENDDR => {
+                let (register, end_state) = if command == XENDIR {
+                    (Register::Ir, &mut self.end_ir)
+                } else {
+                    (Register::Dr, &mut self.end_dr)
+                };
+                *end_state = match reader.u8()? {
+                    0 => JtagState::Idle,
+                    1 => register.state(RegisterState::Pause),
+                    state => {
+                        return Err(SvfError::Syntax {
+                            location,
+                            message: format!("invalid end state {state}"),
+                        })
+                    }
+                };
+            }
+            XCOMMENT => {
+                let comment = reader.data[reader.offset..]
+                    .split(|&byte| byte == 0)
+                    .next()
+                    .unwrap_or_default();
+                reader.bytes(comment.len() + 1)?;
+                tracing::debug!("{}", String::from_utf8_lossy(comment));
+            }
+            XWAIT => {
+                let state = reader.state()?;
+                let end_state = reader.state()?;
+                let min_time = Duration::from_micros(reader.u32()? as u64);
+                self.push(
+                    location,
+                    Operation::RunTest {
+                        state,
+                        cycles: 0,
+                        min_time,
+                        end_state,
+                    },
+                );
+            }
+            XSETSDRMASKS | XSDRINC => {
+                return Err(SvfError::Unsupported {
+                    location,
+                    statement: String::from(if command == XSDRINC {
+                        "XSDRINC"
+                    } else {
+                        "XSETSDRMASKS"
+                    }),
+                })
+            }
+            command => {
+                return Err(SvfError::Syntax {
+                    location,
+                    message: format!("unknown command {command:#04x}"),
+                })
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes an XSVF file into operations.
+pub(super) fn parse(data: &[u8]) -> Result<Vec<(Location, Operation)>, SvfError> {
+    let mut reader = Reader {
+        data,
+        offset: 0,
+        command: 0,
+    };
+    let mut decoder = Decoder {
+        operations: Vec::new(),
+        sdr_size: 0,
+        tdo_mask: BitVec::new(),
+        tdo_expected: BitVec::new(),
+        run_test: Duration::ZERO,
+        repeat: 0,
+        end_ir: JtagState::Idle,
+        end_dr: JtagState::Idle,
+    };
+
+    loop {
+        reader.command = reader.offset;
+        let command = reader.u8()?;
+        if command == XCOMPLETE {
+            break;
+        }
+        decoder.command(&mut reader, command)?;
+    }
+
+    Ok(decoder.operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_xsvf() {
+        let data = [
+            XREPEAT, 3, //
+            XRUNTEST, 0, 0, 0x03, 0xE8, //
+            XSIR, 6, 0x09, //
+            XSDRSIZE, 0, 0, 0, 12, //
+            XTDOMASK, 0x0F, 0xFF, //
+            XSDRTDO, 0x01, 0x23, 0x04, 0x56, //
+            XSTATE, 0x00, //
+            XCOMMENT, b'h', b'i', 0, //
+            XCOMPLETE,
+        ];
+
+        let operations = parse(&data).unwrap();
+        assert_eq!(operations.len(), 5);
+
+        let Operation::Scan(sir) = &operations[0].1 else {
+            panic!("Expected an IR scan");
+        };
+        assert_eq!(operations[0].0, Location::Offset(7));
+        assert_eq!(sir.tdi, bits![u8, Lsb0; 1, 0, 0, 1, 0, 0]);
+        assert_eq!(sir.end_state, JtagState::Idle);
+        assert_eq!(
+            operations[1].1,
+            Operation::RunTest {
+                state: JtagState::Idle,
+                cycles: 0,
+                min_time: Duration::from_millis(1),
+                end_state: JtagState::Idle,
+            }
+        );
+
+        let Operation::Scan(sdr) = &operations[2].1 else {
+            panic!("Expected a DR scan");
+        };
+        assert_eq!(sdr.tdi.load_le::<u16>(), 0x123);
+        let expected = sdr.expected.as_ref().unwrap();
+        assert_eq!(expected.tdo.load_le::<u16>(), 0x456);
+        assert_eq!(expected.mask.count_ones(), 12);
+        assert_eq!(sdr.retries, 3);
+
+        assert_eq!(operations[4].1, Operation::Reset);
+    }
+
+    #[test]
+    fn decode_truncated_xsvf() {
+        assert!(matches!(
+            parse(&[XSDRSIZE, 0, 0, 0, 8, XSDR]),
+            Err(SvfError::Syntax {
+                location: Location::Offset(5),
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+//! Vendor independent management of the readout protection of a device.
+//!
+//! Readout protection prevents the debugger from reading the memory of a device. Most devices
+//! can only be unprotected by erasing all of their memory, and some protection levels can not be
+//! removed at all. Because of this, all operations which change the protection require
+//! [`Permissions`].
+//!
+//! The protection is accessed without attaching to the cores of the device, so the status of a
+//! protected device can be read without unlocking it.
+//!
+//! ```no_run
+//! use probe_rs::probe::list::Lister;
+//! use probe_rs::protection::{ProtectionState, ReadoutProtection};
+//! use probe_rs::Permissions;
+//!
+//! let lister = Lister::new();
+//! let probe = lister.list_all()[0].open()?;
+//!
+//! let mut protection = ReadoutProtection::attach(probe, "nRF52840_xxAA", Permissions::new())?;
+//! for status in protection.status()? {
+//!     assert_ne!(status.state, ProtectionState::Disabled);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::sync::Arc;
+
+use crate::{
+    architecture::arm::{
+        communication_interface::ArmProbeInterface, sequences::ArmDebugSequence, ArmError,
+        FullyQualifiedApAddress,
+    },
+    config::{CoreExt, DebugSequence, TargetSelector},
+    probe::{AttachMethod, Probe},
+    session::{get_target_from_selector, MissingPermissions},
+    Error, Permissions,
+};
+
+/// The state of one readout protection mechanism of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum ProtectionState {
+    /// The memory of the device can be read.
+    Disabled,
+    /// The memory of the device can not be read, but the protection can be removed, usually by
+    /// erasing all memory.
+    Enabled,
+    /// The protection can not be removed anymore.
+    Permanent,
+    /// The state could not be determined, e.g. because the protection blocks reading it.
+    Unknown,
+}
+
+/// The protection level to enable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtectionLevel {
+    /// Protection which can be removed again, usually by erasing all memory.
+    Enabled,
+    /// Protection which can never be removed, like STM32 RDP level 2.
+    Permanent,
+}
+
+/// The readout protection status reported by a device.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ProtectionStatus {
+    /// The name of the protection mechanism, e.g. `RDP` or `APPROTECT`.
+    pub mechanism: String,
+    /// The current state of the mechanism.
+    pub state: ProtectionState,
+    /// Device specific details, e.g. the raw protection level.
+    pub details: String,
+}
+
+/// Access to the readout protection of an ARM device.
+///
+/// The protection specific operations are implemented by the debug sequence of the target, see
+/// [`ArmDebugSequence::protection_status`].
+pub struct ReadoutProtection {
+    interface: Box<dyn ArmProbeInterface>,
+    sequence: Arc<dyn ArmDebugSequence>,
+    default_ap: FullyQualifiedApAddress,
+    permissions: Permissions,
+}
+
+impl ReadoutProtection {
+    /// Connects to the debug port of the target, without unlocking it or attaching to its cores.
+    pub fn attach(
+        probe: Probe,
+        target: impl Into<TargetSelector>,
+        permissions: Permissions,
+    ) -> Result<Self, Error> {
+        let (mut probe, target) =
+            get_target_from_selector(target.into(), AttachMethod::Normal, probe)?;
+
+        let DebugSequence::Arm(sequence) = target.debug_sequence.clone() else {
+            return Err(Error::NotImplemented(
+                "Readout protection is only implemented for ARM targets",
+            ));
+        };
+
+        let default_core = target.default_core();
+        let default_ap = default_core.memory_ap().ok_or_else(|| {
+            Error::Other(format!(
+                "Unable to connect to core {default_core:?}, no memory AP configured"
+            ))
+        })?;
+
+        if let Some(scan_chain) = target
+            .jtag
+            .as_ref()
+            .and_then(|jtag| jtag.scan_chain.clone())
+        {
+            probe.set_scan_chain(scan_chain)?;
+        }
+        probe.attach_to_unspecified()?;
+
+        let interface = probe.try_into_arm_interface().map_err(|(_, err)| err)?;
+        let interface = interface
+            .initialize(sequence.clone(), default_ap.dp())
+            .map_err(|(_interface, err)| err)?;
+
+        Ok(Self {
+            interface,
+            sequence,
+            default_ap,
+            permissions,
+        })
+    }
+
+    /// Reads the state of every readout protection mechanism of the device.
+    pub fn status(&mut self) -> Result<Vec<ProtectionStatus>, Error> {
+        Ok(self
+            .sequence
+            .protection_status(&mut *self.interface, &self.default_ap)?)
+    }
+
+    /// Enables readout protection.
+    ///
+    /// This requires [`Permissions::allow_enable_protection`], or
+    /// [`Permissions::allow_permanent_protection`] for [`ProtectionLevel::Permanent`]. Most
+    /// devices have to be reset before the protection takes effect.
+    pub fn enable(&mut self, level: ProtectionLevel) -> Result<(), Error> {
+        match level {
+            ProtectionLevel::Enabled => self.permissions.enable_protection(),
+            ProtectionLevel::Permanent => self.permissions.permanent_protection(),
+        }
+        .map_err(|MissingPermissions(desc)| Error::MissingPermissions(desc))?;
+
+        self.sequence
+            .enable_protection(&mut *self.interface, &self.default_ap, level)?;
+
+        Ok(())
+    }
+
+    /// Removes readout protection, which erases all memory of most devices.
+    ///
+    /// This requires [`Permissions::allow_erase_all`]. The device may have to be reattached
+    /// afterwards.
+    pub fn disable(&mut self) -> Result<(), Error> {
+        self.permissions
+            .erase_all()
+            .map_err(|MissingPermissions(desc)| Error::MissingPermissions(desc))?;
+
+        match self
+            .sequence
+            .disable_protection(&mut *self.interface, &self.default_ap)
+        {
+            Ok(()) | Err(ArmError::ReAttachRequired) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -17,6 +17,7 @@ use crate::{
     },
     config::{CoreExt, DebugSequence, RegistryError, Target, TargetSelector},
     core::{Architecture, CombinedCoreState},
+    flashing::{configuration, ConfigurationError, FlashProgress},
     probe::{
         fake_probe::FakeProbe, list::Lister, AttachMethod, DebugProbeError, Probe,
         ProbeCreationError,
@@ -50,6 +51,7 @@ pub struct Session {
     interfaces: ArchitectureInterface,
     cores: Vec<CombinedCoreState>,
     configured_trace_sink: Option<TraceSink>,
+    permissions: Permissions,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -267,6 +269,7 @@ impl Session {
                 interfaces: ArchitectureInterface::Arm(interface),
                 cores,
                 configured_trace_sink: None,
+                permissions,
             };
 
             {
@@ -290,6 +293,7 @@ impl Session {
                 interfaces: ArchitectureInterface::Arm(interface),
                 cores,
                 configured_trace_sink: None,
+                permissions,
             })
         }
     }
@@ -298,7 +302,7 @@ impl Session {
         mut probe: Probe,
         target: Target,
         _attach_method: AttachMethod,
-        _permissions: Permissions,
+        permissions: Permissions,
         cores: Vec<CombinedCoreState>,
     ) -> Result<Self, Error> {
         // While we still don't support mixed architectures
@@ -373,6 +377,7 @@ impl Session {
             interfaces,
             cores,
             configured_trace_sink: None,
+            permissions,
         };
 
         // Wait for the cores to be halted.
@@ -688,6 +693,33 @@ impl Session {
         Ok(())
     }
 
+    /// Reads the contents of the configuration region with the given name, e.g. the option bytes.
+    ///
+    /// The values of the fields of the region can be extracted with [`ConfigurationField::extract`].
+    ///
+    /// [`ConfigurationField::extract`]: crate::config::ConfigurationField::extract
+    pub fn read_configuration_region(&mut self, name: &str) -> Result<Vec<u8>, ConfigurationError> {
+        configuration::read_region(self, name)
+    }
+
+    /// Writes fields of the configuration region with the given name, e.g. the option bytes.
+    ///
+    /// `values` contains pairs of field names and their new values. Other fields of the region
+    /// keep their current value. This requires the [`Permissions::allow_configuration_write`]
+    /// permission, as some settings can not be undone.
+    pub fn write_configuration_fields(
+        &mut self,
+        name: &str,
+        values: &[(&str, u64)],
+        progress: Option<FlashProgress>,
+    ) -> Result<(), ConfigurationError> {
+        self.permissions
+            .configuration_write()
+            .map_err(|MissingPermissions(desc)| ConfigurationError::MissingPermissions(desc))?;
+
+        configuration::write_fields(self, name, values, progress)
+    }
+
     /// Reads all the available ARM CoresightComponents of the currently attached target.
     ///
     /// This will recursively parse the Romtable of the attached target
@@ -852,7 +884,7 @@ impl Drop for Session {
 /// If the selector is [TargetSelector::Unspecified], the target will be looked up in the registry.
 /// If it its [TargetSelector::Auto], probe-rs will try to determine the target automatically, based on
 /// information read from the chip.
-fn get_target_from_selector(
+pub(crate) fn get_target_from_selector(
     target: TargetSelector,
     attach_method: AttachMethod,
     mut probe: Probe,
@@ -903,6 +935,12 @@ fn get_target_from_selector(
 pub struct Permissions {
     /// When set to true, all memory of the chip may be erased or reset to factory default
     erase_all: bool,
+    /// When set to true, configuration regions like option bytes or fuses may be written
+    configuration_write: bool,
+    /// When set to true, readout protection may be enabled
+    enable_protection: bool,
+    /// When set to true, readout protection which can never be removed may be enabled
+    permanent_protection: bool,
 }
 
 impl Permissions {
@@ -924,6 +962,42 @@ impl Permissions {
         }
     }
 
+    /// Allow the session to write configuration regions of the chip, like option bytes or fuses.
+    ///
+    /// # Warning
+    /// Some configuration settings, like permanent read protection or blown fuses, can not be undone
+    /// and may render the device unusable.
+    #[must_use]
+    pub fn allow_configuration_write(self) -> Self {
+        Self {
+            configuration_write: true,
+            ..self
+        }
+    }
+
+    /// Allow enabling the readout protection of the chip.
+    ///
+    /// Removing the protection again usually erases all memory of the chip.
+    #[must_use]
+    pub fn allow_enable_protection(self) -> Self {
+        Self {
+            enable_protection: true,
+            ..self
+        }
+    }
+
+    /// Allow enabling readout protection which can never be removed, like STM32 RDP level 2.
+    ///
+    /// # Warning
+    /// The chip can not be debugged or reprogrammed through the debug port anymore afterwards.
+    #[must_use]
+    pub fn allow_permanent_protection(self) -> Self {
+        Self {
+            permanent_protection: true,
+            ..self
+        }
+    }
+
     pub(crate) fn erase_all(&self) -> Result<(), MissingPermissions> {
         if self.erase_all {
             Ok(())
@@ -931,6 +1005,30 @@ impl Permissions {
             Err(MissingPermissions("erase_all".into()))
         }
     }
+
+    pub(crate) fn configuration_write(&self) -> Result<(), MissingPermissions> {
+        if self.configuration_write {
+            Ok(())
+        } else {
+            Err(MissingPermissions("configuration_write".into()))
+        }
+    }
+
+    pub(crate) fn enable_protection(&self) -> Result<(), MissingPermissions> {
+        if self.enable_protection {
+            Ok(())
+        } else {
+            Err(MissingPermissions("enable_protection".into()))
+        }
+    }
+
+    pub(crate) fn permanent_protection(&self) -> Result<(), MissingPermissions> {
+        if self.permanent_protection {
+            Ok(())
+        } else {
+            Err(MissingPermissions("permanent_protection".into()))
+        }
+    }
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
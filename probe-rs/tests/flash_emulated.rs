@@ -0,0 +1,72 @@
+#![cfg(feature = "builtin-targets")]
+use probe_rs::{
+    flashing::DownloadOptions,
+    integration::{EmulatedCore, FakeProbe, Nvmc},
+    probe::Probe,
+    CoreType, MemoryInterface, Permissions,
+};
+
+/// Flash and page size of the nRF51822 xxAC.
+const FLASH_SIZE: u32 = 0x40000;
+const PAGE_SIZE: u32 = 0x400;
+
+fn nrf51_probe() -> Probe {
+    let mut core =
+        EmulatedCore::new(CoreType::Armv6m).with_nvmc(Nvmc::new(0..FLASH_SIZE, PAGE_SIZE));
+
+    // Start with programmed flash, to check that it gets erased.
+    core.load(0, &vec![0; FLASH_SIZE as usize]);
+
+    Probe::from_specific_probe(Box::new(FakeProbe::with_emulated_core(core)))
+}
+
+/// Data spanning multiple pages, starting in the middle of a page.
+fn test_data() -> Vec<u8> {
+    (0..3 * PAGE_SIZE)
+        .map(|i| (i * 7 + i / 256) as u8)
+        .collect()
+}
+
+fn flash_nrf51(options: DownloadOptions) {
+    let mut session = nrf51_probe()
+        .attach("nrf51822_xxAC", Permissions::default())
+        .expect("Failed to attach with emulated core.");
+
+    let data = test_data();
+    let address = 0x1200;
+
+    let mut loader = session.target().flash_loader();
+    loader
+        .add_data(address, &data)
+        .expect("Failed to add flash data.");
+
+    loader
+        .commit(&mut session, options)
+        .expect("Failed to flash the emulated core.");
+
+    let mut core = session.core(0).unwrap();
+
+    let mut flashed = vec![0; data.len()];
+    core.read(address, &mut flashed).unwrap();
+    assert_eq!(flashed, data);
+
+    // The unused parts of the written pages are erased, the other pages are untouched.
+    assert_eq!(core.read_word_32(0x1000).unwrap(), 0xFFFF_FFFF);
+    assert_eq!(core.read_word_32(0x1FFC).unwrap(), 0xFFFF_FFFF);
+    assert_eq!(core.read_word_32(0xFFC).unwrap(), 0);
+    assert_eq!(core.read_word_32(0x2000).unwrap(), 0);
+}
+
+#[test]
+fn flash_emulated_nrf51822_double_buffered() {
+    flash_nrf51(DownloadOptions::default());
+}
+
+#[test]
+fn flash_emulated_nrf51822_single_buffered() {
+    let mut options = DownloadOptions::default();
+    options.disable_double_buffering = true;
+    options.verify = true;
+
+    flash_nrf51(options);
+}
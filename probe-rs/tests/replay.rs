@@ -0,0 +1,58 @@
+#![cfg(feature = "builtin-targets")]
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use probe_rs::{
+    integration::{FakeProbe, RecordingProbe, ReplayLister},
+    probe::{list::Lister, recording::parse_recording, Probe},
+    MemoryInterface, Permissions,
+};
+
+#[derive(Clone, Default)]
+struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn read_session(probe: Probe) -> u32 {
+    let mut session = probe
+        .attach("nrf51822_xxAC", Permissions::default())
+        .expect("Failed to attach.");
+
+    let mut core = session.core(0).expect("Failed to get core.");
+    core.read_word_32(0x2000_0000)
+        .expect("Failed to read memory.")
+}
+
+#[test]
+fn replay_recorded_session() {
+    let sink = SharedSink::default();
+
+    let probe = RecordingProbe::wrap(
+        Probe::from_specific_probe(Box::new(FakeProbe::with_mocked_core())),
+        sink.clone(),
+    );
+    let recorded_value = read_session(probe);
+
+    let recording = String::from_utf8(sink.0.lock().unwrap().clone()).unwrap();
+    let operations = parse_recording(&recording).expect("Failed to parse recording.");
+    assert!(!operations.is_empty());
+
+    let lister = Lister::with_lister(Box::new(ReplayLister::new(operations)));
+    let probes = lister.list_all();
+    assert_eq!(probes.len(), 1);
+
+    let probe = lister
+        .open(&probes[0])
+        .expect("Failed to open replay probe.");
+    assert_eq!(read_session(probe), recorded_value);
+}
@@ -89,6 +89,8 @@ pub fn cmd_elf(
                         debug_base: None,
                         cti_base: None,
                         jtag_tap: None,
+                        dp_id: None,
+                        default_reset_sequence: None,
                     }),
                 }],
                 part: None,
@@ -118,6 +120,9 @@ pub fn cmd_elf(
                 rtt_scan_ranges: None,
                 jtag: None,
                 default_binary_format: None,
+                debug_sequences: vec![],
+                debug_vars: None,
+                configuration_regions: vec![],
             }],
             flash_algorithms: vec![algorithm],
             source: TargetDescriptionSource::BuiltIn,
@@ -386,4 +391,56 @@ mod test {
         let yaml_string = serialize_to_yaml_string(&family).unwrap();
         insta::assert_snapshot!("serialization_cleanup", yaml_string);
     }
+
+    #[test]
+    fn test_serialize_debug_sequences_round_trips() {
+        use probe_rs_target::{
+            SequenceBlock, SequenceControl, SequenceDescription, SequenceElement,
+        };
+
+        let mut chip = Chip::generic_arm("Test Chip", CoreType::Armv7m);
+        chip.memory_map.push(MemoryRegion::Ram(RamRegion {
+            range: 0x20000000..0x20004000,
+            cores: vec!["main".to_owned()],
+            name: Some(String::from("SRAM")),
+            access: None,
+        }));
+        chip.debug_vars = Some("__var Unlock = 0;".to_owned());
+        chip.debug_sequences = vec![SequenceDescription {
+            name: "ResetSystem".to_owned(),
+            pname: None,
+            disable: false,
+            info: None,
+            elements: vec![
+                SequenceElement::Block(SequenceBlock {
+                    atomic: false,
+                    info: None,
+                    code: "__var value = 0;\nWrite32(0xE000ED0C, 0x05FA0004);".to_owned(),
+                }),
+                SequenceElement::Control(SequenceControl {
+                    if_expr: None,
+                    while_expr: Some("(Read32(0x40000000) & 1) == 0".to_owned()),
+                    timeout: Some(1000),
+                    info: None,
+                    elements: vec![],
+                }),
+            ],
+        }];
+
+        let family = ChipFamily {
+            name: "Test Family".to_owned(),
+            manufacturer: None,
+            generated_from_pack: false,
+            chip_detection: vec![],
+            pack_file_release: None,
+            variants: vec![chip.clone()],
+            flash_algorithms: vec![],
+            source: TargetDescriptionSource::BuiltIn,
+        };
+        let yaml_string = serialize_to_yaml_string(&family).unwrap();
+        let parsed: ChipFamily = serde_yaml::from_str(&yaml_string).unwrap();
+
+        assert_eq!(parsed.variants[0].debug_sequences, chip.debug_sequences);
+        assert_eq!(parsed.variants[0].debug_vars, chip.debug_vars);
+    }
 }
@@ -12,6 +12,8 @@ use probe_rs_target::{
 use std::collections::HashMap;
 use std::{fs, io::Read, path::Path};
 
+use crate::parser::DebugDescriptions;
+
 pub enum Kind<'a, T>
 where
     T: std::io::Seek + std::io::Read,
@@ -67,6 +69,7 @@ where
 
 pub(crate) fn extract_families<T>(
     pdsc: Package,
+    debug_descriptions: &DebugDescriptions,
     mut kind: Kind<T>,
     families: &mut Vec<ChipFamily>,
     only_supported_familes: bool,
@@ -160,6 +163,12 @@ where
         let mut memory_map = get_mem_map(&device, &cores);
         patch_memmap(&mut memory_map);
 
+        let debug_description = debug_descriptions
+            .0
+            .get(&device_name)
+            .cloned()
+            .unwrap_or_default();
+
         family.variants.push(Chip {
             name: device_name,
             part: None,
@@ -172,6 +181,9 @@ where
             rtt_scan_ranges: None,
             jtag: None, // TODO, parse scan chain from sdf
             default_binary_format: None,
+            debug_sequences: debug_description.sequences,
+            debug_vars: debug_description.debug_vars,
+            configuration_regions: vec![],
         });
     }
 
@@ -208,6 +220,11 @@ fn create_core(processor: &Processor) -> Result<ProbeCore> {
                 debug_base: None,
                 cti_base: None,
                 jtag_tap: None,
+                dp_id: (processor.dp != 0).then_some(processor.dp),
+                default_reset_sequence: processor
+                    .default_reset_sequence
+                    .clone()
+                    .filter(|sequence| sequence != "ResetSystem"),
             }),
             Architecture::Riscv => CoreAccessOptions::Riscv(RiscvCoreAccessOptions {
                 hart_id: None,
@@ -244,9 +261,16 @@ pub fn visit_dirs(path: &Path, families: &mut Vec<ChipFamily>) -> Result<()> {
 
             let package = Package::from_path(path)
                 .context(format!("Failed to open .pdsc file {}.", path.display()))?;
-
-            extract_families::<fs::File>(package, Kind::Directory(path), families, false)
-                .context(format!("Failed to process .pdsc file {}.", path.display()))?;
+            let debug_descriptions = read_debug_descriptions(DebugDescriptions::from_path(path));
+
+            extract_families::<fs::File>(
+                package,
+                &debug_descriptions,
+                Kind::Directory(path),
+                families,
+                false,
+            )
+            .context(format!("Failed to process .pdsc file {}.", path.display()))?;
         }
 
         Ok(())
@@ -294,7 +318,15 @@ pub fn visit_file(path: &Path, families: &mut Vec<ChipFamily>) -> Result<()> {
 
     drop(pdsc_file);
 
-    extract_families(package, Kind::Archive(&mut archive), families, false)
+    let debug_descriptions = read_debug_descriptions(DebugDescriptions::from_string(&pdsc));
+
+    extract_families(
+        package,
+        &debug_descriptions,
+        Kind::Archive(&mut archive),
+        families,
+        false,
+    )
 }
 
 pub async fn visit_arm_files(families: &mut Vec<ChipFamily>, filter: Option<String>) -> Result<()> {
@@ -407,10 +439,13 @@ pub(crate) async fn visit_arm_file(
 
     drop(pdsc_file);
 
+    let debug_descriptions = read_debug_descriptions(DebugDescriptions::from_string(&pdsc));
+
     let mut families = vec![];
 
     match extract_families(
         package,
+        &debug_descriptions,
         Kind::Archive(&mut archive),
         &mut families,
         only_supported_familes,
@@ -422,6 +457,18 @@ pub(crate) async fn visit_arm_file(
     families
 }
 
+/// Returns the debug sequences of a .pdsc file, or none if they could not be parsed.
+///
+/// The sequences are only an addition to the target description, so failing to parse them
+/// doesn't prevent generating the target.
+fn read_debug_descriptions(descriptions: Result<DebugDescriptions>) -> DebugDescriptions {
+    descriptions.unwrap_or_else(|error| {
+        log::warn!("Failed to parse the debug sequences, skipping them.");
+        log::warn!("Reason: {:?}", error);
+        DebugDescriptions::default()
+    })
+}
+
 /// Extracts the pdsc out of a ZIP archive.
 pub(crate) fn find_pdsc_in_archive<T>(
     archive: &mut zip::ZipArchive<T>,
@@ -1,6 +1,4 @@
-pub mod algorithm_binary;
 pub mod commands;
 pub mod fetch;
-pub mod flash_device;
 pub mod generate;
 pub mod parser;
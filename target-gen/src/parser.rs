@@ -1,155 +1,262 @@
-use crate::flash_device::FlashDevice;
 use anyhow::{anyhow, Context, Result};
-use probe_rs_target::{FlashProperties, MemoryRange, RawFlashAlgorithm, SectorDescription};
-
-/// Extract a chunk of data from an ELF binary.
-///
-/// This does only return the data chunk if it is fully contained in one section.
-/// If it is across two sections, no chunk will be returned.
-pub(crate) fn read_elf_bin_data<'a>(
-    elf: &'a goblin::elf::Elf<'_>,
-    buffer: &'a [u8],
-    address: u32,
-    size: u32,
-) -> Option<&'a [u8]> {
-    log::debug!("Trying to read {} bytes from {:#010x}.", size, address);
-
-    let start = address as u64;
-    let end = (address + size) as u64;
-    let range_to_read = start..end;
-
-    // Iterate all segments.
-    for ph in &elf.program_headers {
-        let segment_address = ph.p_paddr;
-        let segment_size = ph.p_memsz.min(ph.p_filesz);
-
-        log::debug!("Segment address: {:#010x}", segment_address);
-        log::debug!("Segment size:    {} bytes", segment_size);
-
-        let segment = segment_address..segment_address + segment_size;
-        // If the requested data is not fully inside of the current segment, skip the segment.
-        if !segment.contains_range(&range_to_read) {
-            log::debug!("Skipping segment.");
-            continue;
-        }
+use cmsis_pack::utils::FromElem;
+use minidom::Element;
+use probe_rs::flashing::extract_flash_algorithm;
+use probe_rs_target::{
+    RawFlashAlgorithm, SequenceBlock, SequenceControl, SequenceDescription, SequenceElement,
+};
+use std::collections::HashMap;
 
-        let start = ph.p_offset as u32 + address - segment_address as u32;
-        return Some(&buffer[start as usize..][..size as usize]);
-    }
+/// Extracts a position & memory independent flash algorithm blob from the provided ELF file.
+pub fn extract_flash_algo(
+    buffer: &[u8],
+    file_name: &std::path::Path,
+    default: bool,
+    fixed_load_address: bool,
+) -> Result<RawFlashAlgorithm> {
+    let mut algo = extract_flash_algorithm(buffer, fixed_load_address).context(format!(
+        "Failed to extract flash algorithm from ELF file '{}'.",
+        file_name.display()
+    ))?;
+
+    algo.name = file_name
+        .file_stem()
+        .and_then(|f| f.to_str())
+        .unwrap()
+        .to_lowercase();
+    algo.default = default;
 
-    None
+    Ok(algo)
 }
 
-fn extract_flash_device(elf: &goblin::elf::Elf, buffer: &[u8]) -> Result<FlashDevice> {
-    // Extract the flash device info.
-    for sym in elf.syms.iter() {
-        let name = &elf.strtab[sym.st_name];
+/// The debug sequences and debug variables of a device, as described by the `<sequences>` and
+/// `<debugvars>` elements of a .pdsc file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct DebugDescription {
+    pub sequences: Vec<SequenceDescription>,
+    pub debug_vars: Option<String>,
+}
 
-        if name == "FlashDevice" {
-            // This struct contains information about the FLM file structure.
-            let address = sym.st_value as u32;
-            return FlashDevice::new(elf, buffer, address);
+impl DebugDescription {
+    /// Adds the descriptions of the device properties element `e`, which replace the inherited
+    /// descriptions of the same name.
+    fn merge_properties(&mut self, e: &Element) -> Result<()> {
+        for child in e.children() {
+            match child.name() {
+                "sequences" => {
+                    for sequence in child.children().filter(|c| c.name() == "sequence") {
+                        let sequence = parse_sequence(sequence)?;
+                        self.sequences.retain(|existing| {
+                            existing.name != sequence.name || existing.pname != sequence.pname
+                        });
+                        self.sequences.push(sequence);
+                    }
+                }
+                "debugvars" => {
+                    if child.attr("Pname").is_some() {
+                        log::warn!(
+                            "Core specific debug variables are not supported, using them for all cores."
+                        );
+                    }
+                    self.debug_vars = Some(trim_code(&child.text()));
+                }
+                _ => {}
+            }
         }
-    }
 
-    // Failed to find flash device
-    Err(anyhow!("Failed to find 'FlashDevice' symbol in ELF file."))
+        Ok(())
+    }
 }
 
-/// Extracts a position & memory independent flash algorithm blob from the provided ELF file.
-pub fn extract_flash_algo(
-    buffer: &[u8],
-    file_name: &std::path::Path,
-    default: bool,
-    fixed_load_address: bool,
-) -> Result<RawFlashAlgorithm> {
-    let mut algo = RawFlashAlgorithm::default();
+/// The debug descriptions of all devices in a .pdsc file, by device name.
+#[derive(Debug, Default)]
+pub(crate) struct DebugDescriptions(pub HashMap<String, DebugDescription>);
 
-    let elf = goblin::elf::Elf::parse(buffer)?;
+impl DebugDescriptions {
+    fn visit(&mut self, e: &Element, parent: &DebugDescription) -> Result<()> {
+        let mut description = parent.clone();
+        description.merge_properties(e)?;
 
-    let flash_device = extract_flash_device(&elf, buffer).context(format!(
-        "Failed to extract flash information from ELF file '{}'.",
-        file_name.display()
-    ))?;
+        let children = e
+            .children()
+            .filter(|child| matches!(child.name(), "subFamily" | "device" | "variant"))
+            .collect::<Vec<_>>();
 
-    // Extract binary blob.
-    let algorithm_binary = crate::algorithm_binary::AlgorithmBinary::new(&elf, buffer)?;
-    algo.instructions = algorithm_binary.blob();
-
-    let code_section_offset = algorithm_binary.code_section.start;
-
-    // Extract the function pointers,
-    // and check if a RTT symbol is present.
-    for sym in elf.syms.iter() {
-        let name = &elf.strtab[sym.st_name];
-
-        match name {
-            "Init" => algo.pc_init = Some(sym.st_value - code_section_offset as u64),
-            "UnInit" => algo.pc_uninit = Some(sym.st_value - code_section_offset as u64),
-            "EraseChip" => algo.pc_erase_all = Some(sym.st_value - code_section_offset as u64),
-            "EraseSector" => algo.pc_erase_sector = sym.st_value - code_section_offset as u64,
-            "ProgramPage" => algo.pc_program_page = sym.st_value - code_section_offset as u64,
-            "Verify" => algo.pc_verify = Some(sym.st_value - code_section_offset as u64),
-            "ReadFlash" => algo.pc_read = Some(sym.st_value - code_section_offset as u64),
-            "_SEGGER_RTT" => {
-                algo.rtt_location = Some(sym.st_value);
-                log::debug!("Found RTT control block at address {:#010x}", sym.st_value);
+        // Like `cmsis_pack`, only the variants of a device are used, if it has any.
+        if children.is_empty() && matches!(e.name(), "device" | "variant") {
+            if let Some(name) = e.attr("Dname").or_else(|| e.attr("Dvariant")) {
+                self.0.insert(name.to_string(), description);
             }
+            return Ok(());
+        }
 
-            _ => {}
+        for child in children {
+            self.visit(child, &description)?;
         }
+
+        Ok(())
     }
+}
 
-    if fixed_load_address {
-        log::debug!(
-            "Flash algorithm will be loaded at fixed address {:#010x}",
-            algorithm_binary.code_section.load_address
-        );
+impl FromElem for DebugDescriptions {
+    fn from_elem(e: &Element) -> Result<Self> {
+        let mut descriptions = Self::default();
 
-        anyhow::ensure!(
-            algorithm_binary.is_continuous_in_ram(),
-            "If the flash algorithm is not position independent, all sections have to follow each other in RAM. \
-            Please check your linkerscript."
-        );
+        let families = e
+            .children()
+            .filter(|child| child.name() == "devices")
+            .flat_map(|devices| devices.children())
+            .filter(|child| child.name() == "family");
+        for family in families {
+            descriptions.visit(family, &DebugDescription::default())?;
+        }
 
-        algo.load_address = Some(algorithm_binary.code_section.load_address as u64);
+        Ok(descriptions)
     }
+}
 
-    algo.description.clone_from(&flash_device.name);
-    algo.name = file_name
-        .file_stem()
-        .and_then(|f| f.to_str())
-        .unwrap()
-        .to_lowercase();
-    algo.default = default;
-    algo.data_section_offset = algorithm_binary.data_section.start as u64;
-    algo.flash_properties = FlashProperties::from(flash_device);
+fn parse_sequence(e: &Element) -> Result<SequenceDescription> {
+    let name = e
+        .attr("name")
+        .ok_or_else(|| anyhow!("Found a sequence without a name."))?;
 
-    Ok(algo)
+    Ok(SequenceDescription {
+        name: name.to_string(),
+        pname: e.attr("Pname").map(str::to_string),
+        disable: e.attr("disable").is_some_and(parse_bool),
+        info: e.attr("info").map(str::to_string),
+        elements: parse_sequence_elements(e)
+            .with_context(|| format!("Failed to parse sequence {name}."))?,
+    })
 }
 
-impl From<FlashDevice> for FlashProperties {
-    fn from(device: FlashDevice) -> Self {
-        let sectors = device
-            .sectors
-            .iter()
-            .map(|si| SectorDescription {
-                address: si.address.into(),
-                size: si.size.into(),
-            })
-            .collect();
+fn parse_sequence_elements(e: &Element) -> Result<Vec<SequenceElement>> {
+    e.children()
+        .filter_map(|child| match child.name() {
+            "block" => Some(Ok(SequenceElement::Block(SequenceBlock {
+                atomic: child.attr("atomic").is_some_and(parse_bool),
+                info: child.attr("info").map(str::to_string),
+                code: trim_code(&child.text()),
+            }))),
+            "control" => Some(parse_control(child).map(SequenceElement::Control)),
+            _ => None,
+        })
+        .collect()
+}
 
-        FlashProperties {
-            address_range: device.start_address as u64
-                ..(device.start_address as u64 + device.device_size as u64),
+fn parse_control(e: &Element) -> Result<SequenceControl> {
+    let timeout = e
+        .attr("timeout")
+        .map(|timeout| {
+            parse_int::parse::<u64>(timeout)
+                .with_context(|| format!("Invalid control timeout {timeout}."))
+        })
+        .transpose()?;
 
-            page_size: device.page_size,
-            erased_byte_value: device.erased_default_value,
+    Ok(SequenceControl {
+        if_expr: e.attr("if").map(str::to_string),
+        while_expr: e.attr("while").map(str::to_string),
+        timeout,
+        info: e.attr("info").map(str::to_string),
+        elements: parse_sequence_elements(e)?,
+    })
+}
 
-            program_page_timeout: device.program_page_timeout,
-            erase_sector_timeout: device.erase_sector_timeout,
+fn parse_bool(value: &str) -> bool {
+    matches!(value, "1" | "true")
+}
 
-            sectors,
-        }
+/// Removes the indentation of the XML file from the sequence code.
+fn trim_code(code: &str) -> String {
+    code.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PDSC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package schemaVersion="1.7.2" xmlns:xs="http://www.w3.org/2001/XMLSchema-instance">
+  <devices>
+    <family Dfamily="Test Series" Dvendor="Test:0">
+      <debugvars configfile="Debug.dbgconf">
+        __var DbgMCU_CR = 0x00000007;
+      </debugvars>
+      <sequences>
+        <sequence name="DebugDeviceUnlock">
+          <block>
+            Sequence("CheckID");
+          </block>
+        </sequence>
+        <sequence name="ResetSystem" info="System reset">
+          <block atomic="1">
+            WriteDP(0x8, 0);
+            WriteAP(0x4, 1);
+          </block>
+          <control while="(ReadDP(0x4) &amp; 1) == 0" timeout="0x1000">
+            <block>__var x = 1;</block>
+          </control>
+        </sequence>
+      </sequences>
+      <device Dname="TEST1">
+        <sequences>
+          <sequence name="ResetSystem" disable="1"/>
+        </sequences>
+      </device>
+      <device Dname="TEST2">
+        <variant Dvariant="TEST2A"/>
+      </device>
+    </family>
+  </devices>
+</package>"#;
+
+    #[test]
+    fn extract_debug_descriptions() {
+        let descriptions = DebugDescriptions::from_string(PDSC).unwrap().0;
+        assert_eq!(descriptions.len(), 2);
+
+        let test1 = &descriptions["TEST1"];
+        assert_eq!(
+            test1.debug_vars.as_deref(),
+            Some("__var DbgMCU_CR = 0x00000007;")
+        );
+        assert_eq!(test1.sequences.len(), 2);
+        assert_eq!(test1.sequences[0].name, "DebugDeviceUnlock");
+        assert_eq!(test1.sequences[1].name, "ResetSystem");
+        assert!(test1.sequences[1].disable);
+        assert!(test1.sequences[1].elements.is_empty());
+
+        let test2 = &descriptions["TEST2A"];
+        assert_eq!(
+            test2.sequences[1],
+            SequenceDescription {
+                name: "ResetSystem".to_string(),
+                pname: None,
+                disable: false,
+                info: Some("System reset".to_string()),
+                elements: vec![
+                    SequenceElement::Block(SequenceBlock {
+                        atomic: true,
+                        info: None,
+                        code: "WriteDP(0x8, 0);\nWriteAP(0x4, 1);".to_string(),
+                    }),
+                    SequenceElement::Control(SequenceControl {
+                        if_expr: None,
+                        while_expr: Some("(ReadDP(0x4) & 1) == 0".to_string()),
+                        timeout: Some(0x1000),
+                        info: None,
+                        elements: vec![SequenceElement::Block(SequenceBlock {
+                            atomic: false,
+                            info: None,
+                            code: "__var x = 1;".to_string(),
+                        })],
+                    }),
+                ],
+            }
+        );
     }
 }